@@ -0,0 +1,132 @@
+use crate::context::Context;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::{Context as _, Result};
+use clap::Subcommand;
+use shop_client::{instructions as ix, pda};
+use solana_e_commerce::instruction;
+
+/// ID generator of the wallet's merchant
+#[derive(Subcommand)]
+pub enum IdCommand {
+    /// Generate the next product ID from the active chunk
+    Generate {
+        /// Active chunk index, read from the merchant ID account when omitted
+        #[arg(long)]
+        chunk_index: Option<u32>,
+    },
+    /// Generate several IDs from the active chunk
+    BatchGenerate {
+        count: u16,
+        /// Active chunk index, read from the merchant ID account when omitted
+        #[arg(long)]
+        chunk_index: Option<u32>,
+    },
+    /// Check whether an ID is in use (view)
+    Exists {
+        id: u64,
+        /// Chunk holding the ID, looked up from the merchant's chunks when omitted
+        #[arg(long)]
+        chunk_index: Option<u32>,
+    },
+    /// Allocate the chunk after the last one
+    AllocateChunk {
+        /// Last chunk index, read from the merchant ID account when omitted
+        #[arg(long)]
+        last_chunk_index: Option<u32>,
+    },
+    /// Release an ID without a product
+    Release {
+        id: u64,
+        /// Chunk holding the ID, looked up from the merchant's chunks when omitted
+        #[arg(long)]
+        chunk_index: Option<u32>,
+    },
+    /// Close inactive chunks without any ID in use (pass them with --remaining-account PUBKEY:w)
+    Compact,
+    /// Close an ID chunk (admin)
+    CloseChunk {
+        #[arg(long)]
+        merchant: Pubkey,
+        #[arg(long)]
+        chunk_index: u32,
+        #[arg(long)]
+        force: bool,
+    },
+    /// Close a merchant ID account (admin)
+    CloseMerchantAccount {
+        #[arg(long)]
+        merchant: Pubkey,
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+impl IdCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        let merchant = ctx.wallet;
+        let chunk = |chunk_index: Option<u32>| -> Result<Pubkey> {
+            let chunk_index = match chunk_index {
+                Some(chunk_index) => chunk_index,
+                None => ctx.merchant_id_account(&merchant)?.last_chunk_index,
+            };
+            Ok(pda::id_chunk(&merchant, chunk_index).0)
+        };
+        let holding_chunk = |id: u64, chunk_index: Option<u32>| -> Result<u32> {
+            match chunk_index {
+                Some(chunk_index) => Ok(chunk_index),
+                None => ctx
+                    .find_id_chunk(&merchant, id)?
+                    .with_context(|| format!("no ID chunk of the merchant holds ID {}", id)),
+            }
+        };
+
+        Ok(match self {
+            IdCommand::Generate { chunk_index } => {
+                ix::generate_product_id(merchant, chunk(chunk_index)?)
+            }
+            IdCommand::BatchGenerate { count, chunk_index } => ix::batch_generate_ids(
+                merchant,
+                chunk(chunk_index)?,
+                instruction::BatchGenerateIds { count },
+            ),
+            IdCommand::Exists { id, chunk_index } => ix::is_id_exists(
+                merchant,
+                pda::id_chunk(&merchant, holding_chunk(id, chunk_index)?).0,
+                instruction::IsIdExists { id },
+            ),
+            IdCommand::AllocateChunk { last_chunk_index } => {
+                let last_chunk_index = match last_chunk_index {
+                    Some(last_chunk_index) => last_chunk_index,
+                    None => ctx.merchant_id_account(&merchant)?.last_chunk_index,
+                };
+                ix::allocate_new_chunk(merchant, ctx.payer, last_chunk_index)
+            }
+            IdCommand::Release { id, chunk_index } => ix::release_id(
+                merchant,
+                holding_chunk(id, chunk_index)?,
+                instruction::ReleaseId { id },
+            ),
+            IdCommand::Compact => ix::compact_id_chunks(merchant),
+            IdCommand::CloseChunk {
+                merchant,
+                chunk_index,
+                force,
+            } => ix::close_id_chunk(
+                ctx.wallet,
+                instruction::CloseIdChunk {
+                    merchant_key: merchant,
+                    chunk_index,
+                    force,
+                },
+            ),
+            IdCommand::CloseMerchantAccount { merchant, force } => ix::close_merchant_id_account(
+                ctx.wallet,
+                instruction::CloseMerchantIdAccount {
+                    merchant_key: merchant,
+                    force,
+                },
+            ),
+        })
+    }
+}
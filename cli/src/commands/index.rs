@@ -0,0 +1,454 @@
+use crate::context::Context;
+use crate::parse::{RangeArg, ShardKeyArg, SortKeyArg, SortOrderArg};
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use shop_client::{instructions as ix, pda};
+use solana_e_commerce::instruction;
+use solana_e_commerce::utils::SearchFilter;
+
+#[derive(Subcommand)]
+pub enum KeywordCommand {
+    /// Create the root and first shard of a keyword
+    Init { keyword: String },
+    /// Add a product to a keyword
+    Add { keyword: String, product_id: u64 },
+    /// Remove a product from a keyword
+    Remove { keyword: String, product_id: u64 },
+    /// Print the bloom filter summary of a keyword (view)
+    Bloom { keyword: String },
+    /// Create the next shard of a keyword
+    CreateShard { keyword: String, shard_index: u32 },
+    /// Merge a shard into its neighbour
+    Merge {
+        keyword: String,
+        #[arg(long)]
+        source: u32,
+        #[arg(long)]
+        target: u32,
+        /// Shard on the other side of the source shard, when there is one
+        #[arg(long)]
+        outer: Option<u32>,
+    },
+    /// Close a keyword root, the rent goes to the wallet
+    CloseRoot {
+        keyword: String,
+        #[arg(long)]
+        force: bool,
+    },
+    /// Rebuild a legacy keyword root as a counting bloom filter (admin), every shard of the
+    /// keyword goes in --remaining-account in chain order
+    Migrate { keyword: String },
+    /// Close a keyword shard, the rent goes to the wallet
+    CloseShard {
+        keyword: String,
+        shard_index: u32,
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Price index, node ranges are START:END. Ancestors of a node go in --remaining-account PUBKEY:w.
+#[derive(Subcommand)]
+pub enum PriceCommand {
+    /// Add a product to a price node, its price is read from the product
+    Add {
+        product_id: u64,
+        /// Payment token of the product
+        #[arg(long)]
+        mint: Pubkey,
+        #[arg(long)]
+        node: RangeArg<u64>,
+    },
+    /// Remove a product from a price node
+    Remove {
+        product_id: u64,
+        #[arg(long)]
+        mint: Pubkey,
+        #[arg(long)]
+        node: RangeArg<u64>,
+    },
+    /// Split a price node at a price
+    Split {
+        split_price: u64,
+        #[arg(long)]
+        mint: Pubkey,
+        #[arg(long)]
+        node: RangeArg<u64>,
+    },
+    /// Move products of a legacy price node into the node of their payment token
+    Migrate {
+        /// Range of the legacy node
+        #[arg(long)]
+        legacy: RangeArg<u64>,
+        #[arg(long)]
+        mint: Pubkey,
+        /// Leaf receiving the products, a new node must be the bucket root of its range
+        #[arg(long)]
+        node: RangeArg<u64>,
+        /// Product to move, repeat for several
+        #[arg(long = "product")]
+        product_ids: Vec<u64>,
+    },
+    /// Search a price range (view), further leaf nodes go in --remaining-account
+    Search {
+        #[arg(long)]
+        min: u64,
+        #[arg(long)]
+        max: u64,
+        /// Mint of the first leaf node
+        #[arg(long)]
+        mint: Pubkey,
+        /// First leaf node of the range
+        #[arg(long)]
+        node: RangeArg<u64>,
+        /// Search the normalized range of all tokens instead of --mint only
+        #[arg(long)]
+        all_tokens: bool,
+        #[arg(long, value_enum, default_value = "asc")]
+        order: SortOrderArg,
+        #[arg(long, default_value_t = 0)]
+        offset: u32,
+        #[arg(long, default_value_t = 20)]
+        limit: u16,
+    },
+}
+
+/// Sales index, node ranges are START:END
+#[derive(Subcommand)]
+pub enum SalesCommand {
+    /// Create a sales node
+    Init {
+        #[arg(long)]
+        node: RangeArg<u32>,
+    },
+    /// Add a product to a sales node
+    Add {
+        product_id: u64,
+        sales: u32,
+        #[arg(long)]
+        node: RangeArg<u32>,
+    },
+    /// Remove a product from a sales node
+    Remove {
+        product_id: u64,
+        #[arg(long)]
+        node: RangeArg<u32>,
+    },
+    /// Move a product to the node of its new sales (admin)
+    Update {
+        product_id: u64,
+        #[arg(long)]
+        old_sales: u32,
+        #[arg(long)]
+        new_sales: u32,
+        #[arg(long)]
+        old_node: RangeArg<u32>,
+        #[arg(long)]
+        new_node: RangeArg<u32>,
+    },
+}
+
+/// Composite product search (view). Index accounts, then the ProductBase accounts of the
+/// candidates from the cursor, go in --remaining-account. Price bounds and --sort-key price
+/// need price index accounts of one mint.
+#[derive(Args)]
+pub struct SearchArgs {
+    #[arg(long)]
+    price_min: Option<u64>,
+    #[arg(long)]
+    price_max: Option<u64>,
+    #[arg(long)]
+    sales_min: Option<u32>,
+    #[arg(long)]
+    sales_max: Option<u32>,
+    #[arg(long)]
+    merchant: Option<Pubkey>,
+    /// Repeat for several keywords
+    #[arg(long = "keyword")]
+    keywords: Vec<String>,
+    #[arg(long)]
+    include_inactive: bool,
+    #[arg(long, value_enum, default_value = "product-id")]
+    sort_key: SortKeyArg,
+    #[arg(long, value_enum, default_value = "asc")]
+    order: SortOrderArg,
+    /// Leading remaining accounts that are index accounts
+    #[arg(long, default_value_t = 0)]
+    index_accounts: u8,
+    #[arg(long, default_value_t = 0)]
+    cursor: u32,
+    #[arg(long, default_value_t = 20)]
+    limit: u16,
+}
+
+/// Zero-copy index shards, keys are keyword:KEYWORD:SHARD, price:MINT:START:END or
+/// sales:START:END
+#[derive(Subcommand)]
+pub enum IndexShardCommand {
+    /// Create a shard
+    Init { key: ShardKeyArg },
+    /// Add a product, its price or sales are read from the product (merchant or administrator)
+    Add { key: ShardKeyArg, product_id: u64 },
+    /// Remove a product (merchant or administrator, anyone once the product is deleted)
+    Remove { key: ShardKeyArg, product_id: u64 },
+    /// Copy the legacy Borsh shard of the key into its zero-copy shard (admin)
+    Migrate { key: ShardKeyArg },
+}
+
+impl KeywordCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        let payer = ctx.payer;
+        Ok(match self {
+            KeywordCommand::Init { keyword } => {
+                ix::initialize_keyword_index(payer, instruction::InitializeKeywordIndex { keyword })
+            }
+            KeywordCommand::Add {
+                keyword,
+                product_id,
+            } => ix::add_product_to_keyword_index(
+                payer,
+                instruction::AddProductToKeywordIndex {
+                    keyword,
+                    product_id,
+                },
+            ),
+            KeywordCommand::Remove {
+                keyword,
+                product_id,
+            } => {
+                ix::remove_product_from_keyword_index(instruction::RemoveProductFromKeywordIndex {
+                    keyword,
+                    product_id,
+                })
+            }
+            KeywordCommand::Bloom { keyword } => {
+                ix::get_keyword_bloom_summary(instruction::GetKeywordBloomSummary { keyword })
+            }
+            KeywordCommand::CreateShard {
+                keyword,
+                shard_index,
+            } => ix::create_keyword_shard(
+                payer,
+                instruction::CreateKeywordShard {
+                    keyword,
+                    shard_index,
+                },
+            ),
+            KeywordCommand::Merge {
+                keyword,
+                source,
+                target,
+                outer,
+            } => ix::merge_keyword_shards(
+                ctx.wallet,
+                outer,
+                instruction::MergeKeywordShards {
+                    keyword,
+                    source_shard_index: source,
+                    target_shard_index: target,
+                },
+            ),
+            KeywordCommand::CloseRoot { keyword, force } => {
+                ix::close_keyword_root(ctx.wallet, instruction::CloseKeywordRoot { keyword, force })
+            }
+            KeywordCommand::Migrate { keyword } => {
+                ix::migrate_keyword_root(ctx.wallet, instruction::MigrateKeywordRoot { keyword })
+            }
+            KeywordCommand::CloseShard {
+                keyword,
+                shard_index,
+                force,
+            } => ix::close_keyword_shard(
+                ctx.wallet,
+                instruction::CloseKeywordShard {
+                    keyword,
+                    shard_index,
+                    force,
+                },
+            ),
+        })
+    }
+}
+
+impl PriceCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        Ok(match self {
+            PriceCommand::Add {
+                product_id,
+                mint,
+                node,
+            } => ix::add_product_to_price_index(
+                ctx.payer,
+                mint,
+                instruction::AddProductToPriceIndex {
+                    product_id,
+                    price_range_start: node.start,
+                    price_range_end: node.end,
+                },
+            ),
+            PriceCommand::Remove {
+                product_id,
+                mint,
+                node,
+            } => ix::remove_product_from_price_index(
+                mint,
+                node.start,
+                node.end,
+                instruction::RemoveProductFromPriceIndex { product_id },
+            ),
+            PriceCommand::Split {
+                split_price,
+                mint,
+                node,
+            } => ix::split_price_node(
+                ctx.payer,
+                mint,
+                instruction::SplitPriceNode {
+                    price_range_start: node.start,
+                    price_range_end: node.end,
+                    split_price,
+                },
+            ),
+            PriceCommand::Migrate {
+                legacy,
+                mint,
+                node,
+                product_ids,
+            } => ix::with_remaining_accounts(
+                ix::migrate_price_node(
+                    ctx.payer,
+                    mint,
+                    instruction::MigratePriceNode {
+                        legacy_range_start: legacy.start,
+                        legacy_range_end: legacy.end,
+                        price_range_start: node.start,
+                        price_range_end: node.end,
+                    },
+                ),
+                product_ids.iter().map(|&product_id| {
+                    AccountMeta::new_readonly(pda::product(product_id).0, false)
+                }),
+            ),
+            PriceCommand::Search {
+                min,
+                max,
+                mint,
+                node,
+                all_tokens,
+                order,
+                offset,
+                limit,
+            } => ix::search_price_range(
+                pda::price_index(&mint, node.start, node.end).0,
+                instruction::SearchPriceRange {
+                    payment_token: (!all_tokens).then_some(mint),
+                    min_price: min,
+                    max_price: max,
+                    sort_order: order.into(),
+                    offset,
+                    limit,
+                },
+            ),
+        })
+    }
+}
+
+impl SalesCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        Ok(match self {
+            SalesCommand::Init { node } => ix::initialize_sales_index(
+                ctx.payer,
+                instruction::InitializeSalesIndex {
+                    sales_range_start: node.start,
+                    sales_range_end: node.end,
+                },
+            ),
+            SalesCommand::Add {
+                product_id,
+                sales,
+                node,
+            } => ix::add_product_to_sales_index(
+                ctx.payer,
+                instruction::AddProductToSalesIndex {
+                    sales_range_start: node.start,
+                    sales_range_end: node.end,
+                    product_id,
+                    sales,
+                },
+            ),
+            SalesCommand::Remove { product_id, node } => ix::remove_product_from_sales_index(
+                node.start,
+                node.end,
+                instruction::RemoveProductFromSalesIndex { product_id },
+            ),
+            SalesCommand::Update {
+                product_id,
+                old_sales,
+                new_sales,
+                old_node,
+                new_node,
+            } => ix::update_product_sales_index(
+                ctx.wallet,
+                pda::sales_index(old_node.start, old_node.end).0,
+                pda::sales_index(new_node.start, new_node.end).0,
+                instruction::UpdateProductSalesIndex {
+                    product_id,
+                    old_sales,
+                    new_sales,
+                },
+            ),
+        })
+    }
+}
+
+impl SearchArgs {
+    pub fn instruction(self) -> Instruction {
+        ix::search_products(instruction::SearchProducts {
+            filter: SearchFilter {
+                price_min: self.price_min,
+                price_max: self.price_max,
+                sales_min: self.sales_min,
+                sales_max: self.sales_max,
+                merchant: self.merchant,
+                keywords: (!self.keywords.is_empty()).then_some(self.keywords),
+                is_active_only: !self.include_inactive,
+            },
+            sort_key: self.sort_key.into(),
+            sort_order: self.order.into(),
+            index_account_count: self.index_accounts,
+            cursor: self.cursor,
+            limit: self.limit,
+        })
+    }
+}
+
+impl IndexShardCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        Ok(match self {
+            IndexShardCommand::Init { key } => ix::initialize_index_shard(
+                ctx.payer,
+                instruction::InitializeIndexShard { key: key.0 },
+            ),
+            IndexShardCommand::Add { key, product_id } => ix::add_product_to_index_shard(
+                ctx.wallet,
+                ctx.payer,
+                instruction::AddProductToIndexShard {
+                    key: key.0,
+                    product_id,
+                },
+            ),
+            IndexShardCommand::Remove { key, product_id } => ix::remove_product_from_index_shard(
+                ctx.wallet,
+                instruction::RemoveProductFromIndexShard {
+                    key: key.0,
+                    product_id,
+                },
+            ),
+            IndexShardCommand::Migrate { key } => {
+                ix::migrate_index_shard(ctx.wallet, instruction::MigrateIndexShard { key: key.0 })
+            }
+        })
+    }
+}
@@ -0,0 +1,222 @@
+use crate::context::Context;
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_spl::associated_token::get_associated_token_address;
+use anyhow::Result;
+use clap::Subcommand;
+use shop_client::{instructions as ix, pda};
+use solana_e_commerce::instruction;
+
+/// Merchant of the wallet
+#[derive(Subcommand)]
+pub enum MerchantCommand {
+    /// Register the wallet as a merchant with its ID account and catalog
+    Register {
+        #[arg(long)]
+        name: String,
+        #[arg(long, default_value = "")]
+        description: String,
+    },
+    /// Update the merchant name or description
+    Update {
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Print the merchant statistics (view)
+    Stats {
+        /// Payment token to break the orders down by, repeatable
+        #[arg(long = "mint")]
+        mints: Vec<Pubkey>,
+    },
+    /// Close the merchant account
+    Close {
+        /// Receives the rent, defaults to the wallet
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+        #[arg(long)]
+        force: bool,
+    },
+    /// List a page of a merchant catalog (view)
+    Products {
+        /// Merchant owner, defaults to the wallet
+        #[arg(long)]
+        merchant: Option<Pubkey>,
+        #[arg(long)]
+        inactive: bool,
+        #[arg(long, default_value_t = 0)]
+        page: u64,
+    },
+    /// Print a merchant reputation (view)
+    Reputation {
+        /// Merchant owner, defaults to the wallet
+        #[arg(long)]
+        merchant: Option<Pubkey>,
+    },
+    /// Record a dispute the merchant of an order lost (admin)
+    LostDispute {
+        order: Pubkey,
+        #[arg(long)]
+        reason: String,
+    },
+}
+
+/// Merchant deposits, the deposit mint defaults to the one of the system configuration
+#[derive(Subcommand)]
+pub enum DepositCommand {
+    /// Pay or top up the wallet's merchant deposit
+    Pay {
+        amount: u64,
+        /// Source token account, defaults to the wallet's associated token account
+        #[arg(long)]
+        token_account: Option<Pubkey>,
+        #[arg(long)]
+        mint: Option<Pubkey>,
+    },
+    /// Withdraw from a merchant deposit (the merchant or the admin)
+    Withdraw {
+        amount: u64,
+        /// Merchant owner, defaults to the wallet
+        #[arg(long)]
+        merchant: Option<Pubkey>,
+        /// Recipient token account, defaults to the wallet's associated token account
+        #[arg(long)]
+        recipient: Option<Pubkey>,
+        #[arg(long)]
+        mint: Option<Pubkey>,
+    },
+    /// Deduct from a merchant deposit (admin)
+    Deduct {
+        amount: u64,
+        #[arg(long)]
+        merchant: Pubkey,
+        #[arg(long)]
+        reason: String,
+        /// Admin token account, defaults to the wallet's associated token account
+        #[arg(long)]
+        admin_token_account: Option<Pubkey>,
+        #[arg(long)]
+        mint: Option<Pubkey>,
+    },
+    /// Print a merchant deposit (view)
+    Info {
+        /// Merchant owner, defaults to the wallet
+        #[arg(long)]
+        merchant: Option<Pubkey>,
+        #[arg(long)]
+        mint: Option<Pubkey>,
+    },
+}
+
+impl MerchantCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        let owner = ctx.wallet;
+        Ok(match self {
+            MerchantCommand::Register { name, description } => ix::register_merchant_atomic(
+                owner,
+                ctx.payer,
+                instruction::RegisterMerchantAtomic { name, description },
+            ),
+            MerchantCommand::Update { name, description } => ix::update_merchant_info(
+                owner,
+                instruction::UpdateMerchantInfo { name, description },
+            ),
+            MerchantCommand::Stats { mints } => ix::with_remaining_accounts(
+                ix::get_merchant_stats(owner),
+                mints.iter().map(|mint| {
+                    AccountMeta::new_readonly(pda::merchant_order_stats(&owner, mint).0, false)
+                }),
+            ),
+            MerchantCommand::Close { beneficiary, force } => ix::close_merchant(
+                owner,
+                beneficiary.unwrap_or(owner),
+                instruction::CloseMerchant { force },
+            ),
+            MerchantCommand::Products {
+                merchant,
+                inactive,
+                page,
+            } => ix::get_merchant_products(instruction::GetMerchantProducts {
+                merchant: merchant.unwrap_or(owner),
+                is_active: !inactive,
+                page_index: page,
+            }),
+            MerchantCommand::Reputation { merchant } => {
+                ix::get_merchant_reputation(merchant.unwrap_or(owner))
+            }
+            MerchantCommand::LostDispute { order, reason } => ix::record_lost_dispute(
+                owner,
+                order,
+                &ctx.account(&order)?,
+                instruction::RecordLostDispute { reason },
+            ),
+        })
+    }
+}
+
+impl DepositCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        let wallet = ctx.wallet;
+        let mint = |mint: Option<Pubkey>| -> Result<Pubkey> {
+            match mint {
+                Some(mint) => Ok(mint),
+                None => Ok(ctx.system_config()?.deposit_token_mint),
+            }
+        };
+
+        Ok(match self {
+            DepositCommand::Pay {
+                amount,
+                token_account,
+                mint: deposit_mint,
+            } => {
+                let deposit_mint = mint(deposit_mint)?;
+                ix::manage_deposit(
+                    wallet,
+                    token_account
+                        .unwrap_or_else(|| get_associated_token_address(&wallet, &deposit_mint)),
+                    deposit_mint,
+                    instruction::ManageDeposit { amount },
+                )
+            }
+            DepositCommand::Withdraw {
+                amount,
+                merchant,
+                recipient,
+                mint: deposit_mint,
+            } => {
+                let deposit_mint = mint(deposit_mint)?;
+                ix::withdraw_merchant_deposit(
+                    wallet,
+                    merchant.unwrap_or(wallet),
+                    recipient
+                        .unwrap_or_else(|| get_associated_token_address(&wallet, &deposit_mint)),
+                    deposit_mint,
+                    instruction::WithdrawMerchantDeposit { amount },
+                )
+            }
+            DepositCommand::Deduct {
+                amount,
+                merchant,
+                reason,
+                admin_token_account,
+                mint: deposit_mint,
+            } => {
+                let deposit_mint = mint(deposit_mint)?;
+                ix::deduct_merchant_deposit(
+                    wallet,
+                    merchant,
+                    deposit_mint,
+                    admin_token_account
+                        .unwrap_or_else(|| get_associated_token_address(&wallet, &deposit_mint)),
+                    instruction::DeductMerchantDeposit { amount, reason },
+                )
+            }
+            DepositCommand::Info {
+                merchant,
+                mint: deposit_mint,
+            } => ix::get_merchant_deposit_info(merchant.unwrap_or(wallet), mint(deposit_mint)?),
+        })
+    }
+}
@@ -0,0 +1,97 @@
+//! One subcommand per program instruction, grouped by program module
+
+pub mod id;
+pub mod index;
+pub mod merchant;
+pub mod order;
+pub mod payment;
+pub mod product;
+pub mod ranking;
+pub mod review;
+pub mod system;
+
+use crate::context::Context;
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::Result;
+use clap::Subcommand;
+use std::path::PathBuf;
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(flatten)]
+    Program(Box<ProgramCommand>),
+    /// Add the signatures of the given keypairs to a transaction file
+    Sign { file: PathBuf },
+    /// Send a signed transaction file, or simulate it with --dry-run
+    Submit { file: PathBuf },
+}
+
+#[derive(Subcommand)]
+pub enum ProgramCommand {
+    /// System configuration (admin)
+    #[command(subcommand)]
+    System(system::SystemCommand),
+    /// Product ID generator of the wallet's merchant
+    #[command(subcommand)]
+    Id(id::IdCommand),
+    /// Merchant registration and information
+    #[command(subcommand)]
+    Merchant(merchant::MerchantCommand),
+    /// Merchant deposits
+    #[command(subcommand)]
+    Deposit(merchant::DepositCommand),
+    /// Products and recency feeds
+    #[command(subcommand)]
+    Product(product::ProductCommand),
+    /// Payment configuration and escrow purchases
+    #[command(subcommand)]
+    Payment(payment::PaymentCommand),
+    /// Orders
+    #[command(subcommand)]
+    Order(order::OrderCommand),
+    /// Reviews of delivered orders
+    #[command(subcommand)]
+    Review(review::ReviewCommand),
+    /// Keyword index
+    #[command(subcommand)]
+    Keyword(index::KeywordCommand),
+    /// Price index
+    #[command(subcommand)]
+    Price(index::PriceCommand),
+    /// Sales index
+    #[command(subcommand)]
+    Sales(index::SalesCommand),
+    /// Composite product search (view)
+    Search(index::SearchArgs),
+    /// Zero-copy index shards
+    #[command(subcommand)]
+    IndexShard(index::IndexShardCommand),
+    /// Trending products
+    #[command(subcommand)]
+    Trending(ranking::TrendingCommand),
+    /// Bestseller leaderboards
+    #[command(subcommand)]
+    Leaderboard(ranking::LeaderboardCommand),
+}
+
+impl ProgramCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        match self {
+            ProgramCommand::System(command) => command.instruction(ctx),
+            ProgramCommand::Id(command) => command.instruction(ctx),
+            ProgramCommand::Merchant(command) => command.instruction(ctx),
+            ProgramCommand::Deposit(command) => command.instruction(ctx),
+            ProgramCommand::Product(command) => command.instruction(ctx),
+            ProgramCommand::Payment(command) => command.instruction(ctx),
+            ProgramCommand::Order(command) => command.instruction(ctx),
+            ProgramCommand::Review(command) => command.instruction(ctx),
+            ProgramCommand::Keyword(command) => command.instruction(ctx),
+            ProgramCommand::Price(command) => command.instruction(ctx),
+            ProgramCommand::Sales(command) => command.instruction(ctx),
+            ProgramCommand::Search(args) => Ok(args.instruction()),
+            ProgramCommand::IndexShard(command) => command.instruction(ctx),
+            ProgramCommand::Trending(command) => command.instruction(ctx),
+            ProgramCommand::Leaderboard(command) => command.instruction(ctx),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use crate::context::Context;
+use crate::parse::RangeArg;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_spl::associated_token::get_associated_token_address;
+use anyhow::Result;
+use clap::Subcommand;
+use shop_client::{instructions as ix, pda};
+use solana_e_commerce::instruction;
+use solana_e_commerce::state::{MerchantOrderCount, Order, ProductBase, UserPurchaseCount};
+
+/// Orders. Sales nodes are START:END ranges, leaderboards including the product go in
+/// --remaining-account PUBKEY:w.
+#[derive(Subcommand)]
+pub enum OrderCommand {
+    /// Create an order as the buyer
+    Create {
+        product_id: u64,
+        #[arg(long, default_value_t = 1)]
+        quantity: u32,
+        /// Variant ordered, required when the product has variants
+        #[arg(long)]
+        variant: Option<u32>,
+        #[arg(long)]
+        shipping_address: String,
+        #[arg(long, default_value = "")]
+        notes: String,
+        /// Signature of the payment transaction
+        #[arg(long)]
+        transaction_signature: String,
+        /// Merchant owner, read from the product when omitted
+        #[arg(long)]
+        merchant: Option<Pubkey>,
+        /// Payment token mint, read from the product when omitted
+        #[arg(long)]
+        payment_token: Option<Pubkey>,
+        /// Buyer order sequence, read from the purchase count when omitted
+        #[arg(long)]
+        purchase_sequence: Option<u64>,
+        /// Merchant order sequence, read from the merchant order count when omitted
+        #[arg(long)]
+        merchant_order_sequence: Option<u64>,
+        /// Day of the daily order statistics, today by the local clock when omitted
+        #[arg(long)]
+        day: Option<u32>,
+    },
+    /// Ship an order as the merchant
+    Ship {
+        order: Pubkey,
+        #[arg(long)]
+        tracking_number: String,
+    },
+    /// Grow an order created before variants to the current layout
+    Migrate { order: Pubkey },
+    /// Refund an order before delivery as the buyer
+    Refund {
+        order: Pubkey,
+        #[arg(long)]
+        reason: String,
+        /// Defaults to the buyer's associated token account
+        #[arg(long)]
+        buyer_token_account: Option<Pubkey>,
+        /// Day of the daily order statistics, today by the local clock when omitted
+        #[arg(long)]
+        day: Option<u32>,
+    },
+    /// Refund a delivered order as the merchant, reverting the sale
+    RefundDelivered {
+        order: Pubkey,
+        #[arg(long)]
+        reason: String,
+        /// Sales range covering the product's current sales, left out with
+        /// --new-sales-node when the product is not in the sales index
+        #[arg(long, requires = "new_sales_node")]
+        old_sales_node: Option<RangeArg<u32>>,
+        #[arg(long, requires = "old_sales_node")]
+        new_sales_node: Option<RangeArg<u32>>,
+        /// Defaults to the buyer's associated token account
+        #[arg(long)]
+        buyer_token_account: Option<Pubkey>,
+        /// Day of the daily order statistics, today by the local clock when omitted
+        #[arg(long)]
+        day: Option<u32>,
+    },
+    /// Confirm delivery as the buyer
+    Confirm {
+        order: Pubkey,
+        /// Sales range covering the product's current sales, left out with
+        /// --new-sales-node when the product is not in the sales index
+        #[arg(long, requires = "new_sales_node")]
+        old_sales_node: Option<RangeArg<u32>>,
+        #[arg(long, requires = "old_sales_node")]
+        new_sales_node: Option<RangeArg<u32>>,
+        /// Day of the daily order statistics, today by the local clock when omitted
+        #[arg(long)]
+        day: Option<u32>,
+    },
+    /// Confirm delivery after the auto confirm period (admin)
+    AutoConfirm {
+        order: Pubkey,
+        /// Sales range covering the product's current sales, left out with
+        /// --new-sales-node when the product is not in the sales index
+        #[arg(long, requires = "new_sales_node")]
+        old_sales_node: Option<RangeArg<u32>>,
+        #[arg(long, requires = "old_sales_node")]
+        new_sales_node: Option<RangeArg<u32>>,
+        /// Day of the daily order statistics, today by the local clock when omitted
+        #[arg(long)]
+        day: Option<u32>,
+    },
+}
+
+fn sales_node(ctx: &Context, range: Option<RangeArg<u32>>) -> Result<Option<Pubkey>> {
+    range
+        .map(|range| ctx.sales_index_account(range.start, range.end))
+        .transpose()
+}
+
+impl OrderCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        let wallet = ctx.wallet;
+        Ok(match self {
+            OrderCommand::Create {
+                product_id,
+                quantity,
+                variant,
+                shipping_address,
+                notes,
+                transaction_signature,
+                merchant,
+                payment_token,
+                purchase_sequence,
+                merchant_order_sequence,
+                day,
+            } => {
+                let (merchant, payment_token) = match (merchant, payment_token) {
+                    (Some(merchant), Some(payment_token)) => (merchant, payment_token),
+                    _ => {
+                        let product: ProductBase = ctx.account(&pda::product(product_id).0)?;
+                        (
+                            merchant.unwrap_or(product.merchant),
+                            payment_token.unwrap_or(product.payment_token),
+                        )
+                    }
+                };
+                let purchase_sequence = match purchase_sequence {
+                    Some(sequence) => sequence,
+                    None => {
+                        let count = ctx.try_account::<UserPurchaseCount>(
+                            &pda::user_purchase_count(&wallet).0,
+                        )?;
+                        count.map_or(0, |count| count.purchase_count) + 1
+                    }
+                };
+                let merchant_order_sequence = match merchant_order_sequence {
+                    Some(sequence) => sequence,
+                    None => {
+                        let count = ctx.try_account::<MerchantOrderCount>(
+                            &pda::merchant_order_count(&merchant).0,
+                        )?;
+                        count.map_or(0, |count| count.total_orders) + 1
+                    }
+                };
+
+                ix::create_order(
+                    wallet,
+                    merchant,
+                    payment_token,
+                    purchase_sequence,
+                    merchant_order_sequence,
+                    variant,
+                    instruction::CreateOrder {
+                        product_id,
+                        quantity,
+                        shipping_address,
+                        notes,
+                        transaction_signature,
+                        day: day.map_or_else(|| ctx.today(), Ok)?,
+                    },
+                )
+            }
+            OrderCommand::Ship {
+                order,
+                tracking_number,
+            } => ix::ship_order(
+                order,
+                &ctx.account(&order)?,
+                instruction::ShipOrder { tracking_number },
+            ),
+            OrderCommand::Migrate { order } => ix::migrate_order(ctx.payer, order),
+            OrderCommand::Refund {
+                order,
+                reason,
+                buyer_token_account,
+                day,
+            } => {
+                let order_account: Order = ctx.account(&order)?;
+                ix::refund_order(
+                    order,
+                    &order_account,
+                    buyer_token_account.unwrap_or_else(|| buyer_token_address(&order_account)),
+                    instruction::RefundOrder {
+                        refund_reason: reason,
+                        day: day.map_or_else(|| ctx.today(), Ok)?,
+                    },
+                )
+            }
+            OrderCommand::RefundDelivered {
+                order,
+                reason,
+                old_sales_node,
+                new_sales_node,
+                buyer_token_account,
+                day,
+            } => {
+                let order_account: Order = ctx.account(&order)?;
+                ix::refund_delivered_order(
+                    order,
+                    &order_account,
+                    sales_node(ctx, old_sales_node)?,
+                    sales_node(ctx, new_sales_node)?,
+                    buyer_token_account.unwrap_or_else(|| buyer_token_address(&order_account)),
+                    instruction::RefundDeliveredOrder {
+                        refund_reason: reason,
+                        day: day.map_or_else(|| ctx.today(), Ok)?,
+                    },
+                )
+            }
+            OrderCommand::Confirm {
+                order,
+                old_sales_node,
+                new_sales_node,
+                day,
+            } => ix::confirm_delivery(
+                order,
+                &ctx.account(&order)?,
+                &ctx.system_config()?,
+                sales_node(ctx, old_sales_node)?,
+                sales_node(ctx, new_sales_node)?,
+                instruction::ConfirmDelivery {
+                    day: day.map_or_else(|| ctx.today(), Ok)?,
+                },
+            ),
+            OrderCommand::AutoConfirm {
+                order,
+                old_sales_node,
+                new_sales_node,
+                day,
+            } => ix::auto_confirm_delivery(
+                wallet,
+                order,
+                &ctx.account(&order)?,
+                sales_node(ctx, old_sales_node)?,
+                sales_node(ctx, new_sales_node)?,
+                instruction::AutoConfirmDelivery {
+                    day: day.map_or_else(|| ctx.today(), Ok)?,
+                },
+            ),
+        })
+    }
+}
+
+fn buyer_token_address(order: &Order) -> Pubkey {
+    get_associated_token_address(&order.buyer, &order.payment_token)
+}
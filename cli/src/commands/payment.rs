@@ -0,0 +1,127 @@
+use crate::context::Context;
+use crate::parse::TokenArg;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_spl::associated_token::get_associated_token_address;
+use anyhow::Result;
+use clap::Subcommand;
+use shop_client::instructions as ix;
+use solana_e_commerce::instruction;
+
+#[derive(Subcommand)]
+pub enum PaymentCommand {
+    /// Initialize the payment configuration (admin)
+    Init {
+        /// MINT:SYMBOL[:inactive], repeat for several tokens
+        #[arg(long = "token", required = true)]
+        tokens: Vec<TokenArg>,
+        /// Fee rate in basis points
+        #[arg(long, default_value_t = 40)]
+        fee_rate: u16,
+        /// Fee recipient, defaults to the wallet
+        #[arg(long)]
+        fee_recipient: Option<Pubkey>,
+    },
+    /// Replace the supported payment tokens (admin)
+    UpdateTokens {
+        /// MINT:SYMBOL[:inactive], repeat for several tokens
+        #[arg(long = "token", required = true)]
+        tokens: Vec<TokenArg>,
+    },
+    /// Set the fee rate in basis points (admin)
+    UpdateFeeRate { fee_rate: u16 },
+    /// Close the payment configuration (admin)
+    CloseConfig {
+        /// Receives the rent, defaults to the wallet
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+        #[arg(long)]
+        force: bool,
+    },
+    /// Create the program token account of a payment token (admin)
+    InitTokenAccount {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Create the escrow ledger of a payment token, opening at its current balance (admin)
+    InitEscrowLedger {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Compare escrow liabilities of a payment token with its balance (view)
+    VerifySolvency {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Block escrow withdrawals of a payment token while insolvent, unblock once solvent (admin)
+    EnforceSolvency {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Pay for a product into escrow
+    Purchase {
+        product_id: u64,
+        amount: u64,
+        #[arg(long)]
+        mint: Pubkey,
+        /// Variant bought, required when the product has variants
+        #[arg(long)]
+        variant: Option<u32>,
+        /// Paying token account, defaults to the wallet's associated token account
+        #[arg(long)]
+        token_account: Option<Pubkey>,
+    },
+}
+
+impl PaymentCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        let wallet = ctx.wallet;
+        Ok(match self {
+            PaymentCommand::Init {
+                tokens,
+                fee_rate,
+                fee_recipient,
+            } => ix::initialize_payment_system(
+                wallet,
+                instruction::InitializePaymentSystem {
+                    supported_tokens: tokens.into_iter().map(|token| token.0).collect(),
+                    fee_rate,
+                    fee_recipient: fee_recipient.unwrap_or(wallet),
+                },
+            ),
+            PaymentCommand::UpdateTokens { tokens } => ix::update_supported_tokens(
+                wallet,
+                instruction::UpdateSupportedTokens {
+                    supported_tokens: tokens.into_iter().map(|token| token.0).collect(),
+                },
+            ),
+            PaymentCommand::UpdateFeeRate { fee_rate } => {
+                ix::update_fee_rate(wallet, instruction::UpdateFeeRate { fee_rate })
+            }
+            PaymentCommand::CloseConfig { beneficiary, force } => ix::close_payment_config(
+                wallet,
+                beneficiary.unwrap_or(wallet),
+                instruction::ClosePaymentConfig { force },
+            ),
+            PaymentCommand::InitTokenAccount { mint } => {
+                ix::initialize_program_token_account(wallet, mint)
+            }
+            PaymentCommand::InitEscrowLedger { mint } => ix::initialize_escrow_ledger(wallet, mint),
+            PaymentCommand::VerifySolvency { mint } => ix::verify_escrow_solvency(mint),
+            PaymentCommand::EnforceSolvency { mint } => ix::enforce_escrow_solvency(wallet, mint),
+            PaymentCommand::Purchase {
+                product_id,
+                amount,
+                mint,
+                variant,
+                token_account,
+            } => ix::purchase_product_escrow(
+                wallet,
+                token_account.unwrap_or_else(|| get_associated_token_address(&wallet, &mint)),
+                mint,
+                variant,
+                instruction::PurchaseProductEscrow { product_id, amount },
+            ),
+        })
+    }
+}
@@ -0,0 +1,537 @@
+use crate::context::Context;
+use crate::parse::{FeedArg, VariantOptionArg};
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::{Context as _, Result};
+use clap::Subcommand;
+use shop_client::{instructions as ix, pda};
+use solana_e_commerce::instruction;
+use solana_e_commerce::instructions::RecencyCursor;
+use solana_e_commerce::state::{
+    recency_day, IdChunk, MerchantProductList, MerchantProductPage, ProductBase, RecencyBucket,
+    RecencyFeed, RecencyFeedHead,
+};
+
+/// Products of the wallet's merchant. Lookups left out are read from the chain.
+#[derive(Subcommand)]
+pub enum ProductCommand {
+    /// Create a product with the next free ID
+    Create {
+        #[arg(long)]
+        name: String,
+        #[arg(long, default_value = "")]
+        description: String,
+        #[arg(long)]
+        price: u64,
+        /// Repeat for several keywords
+        #[arg(long = "keyword")]
+        keywords: Vec<String>,
+        #[arg(long)]
+        inventory: u64,
+        #[arg(long)]
+        payment_token: Pubkey,
+        #[arg(long, default_value = "")]
+        shipping_location: String,
+        /// ID the program will generate
+        #[arg(long, requires_all = ["chunk_index", "last_chunk_index"])]
+        product_id: Option<u64>,
+        /// Active ID chunk index
+        #[arg(long, requires = "product_id")]
+        chunk_index: Option<u32>,
+        /// Last ID chunk index
+        #[arg(long, requires = "product_id")]
+        last_chunk_index: Option<u32>,
+        /// Active catalog page the product is appended to
+        #[arg(long)]
+        page: Option<u64>,
+        /// Day of the NewArrivals feed, today by the local clock when omitted
+        #[arg(long)]
+        day: Option<u32>,
+    },
+    /// Create the extended information of a product
+    CreateExtended {
+        product_id: u64,
+        #[arg(long = "image-url")]
+        image_video_urls: Vec<String>,
+        #[arg(long = "sales-region")]
+        sales_regions: Vec<String>,
+        #[arg(long = "logistics-method")]
+        logistics_methods: Vec<String>,
+    },
+    /// Update product fields, leaderboards including it go in --remaining-account PUBKEY:w
+    Update {
+        product_id: u64,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long)]
+        price: Option<u64>,
+        /// Replaces all keywords, repeat for several
+        #[arg(long = "keyword")]
+        keywords: Option<Vec<String>>,
+        #[arg(long)]
+        inventory: Option<u64>,
+        #[arg(long)]
+        payment_token: Option<Pubkey>,
+        #[arg(long)]
+        shipping_location: Option<String>,
+        /// Replaces all image and video URLs, repeat for several
+        #[arg(long = "image-url")]
+        image_video_urls: Option<Vec<String>>,
+        /// Replaces all sales regions, repeat for several
+        #[arg(long = "sales-region")]
+        sales_regions: Option<Vec<String>>,
+        /// Replaces all logistics methods, repeat for several
+        #[arg(long = "logistics-method")]
+        logistics_methods: Option<Vec<String>>,
+        /// Day of the RecentlyUpdated feed, today by the local clock when omitted
+        #[arg(long)]
+        day: Option<u32>,
+    },
+    /// Delist a product, or close it and release its ID with --hard
+    Delete {
+        product_id: u64,
+        #[arg(long)]
+        hard: bool,
+        #[arg(long)]
+        force: bool,
+        /// Receives the rent on a hard delete, defaults to the wallet
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+        /// Inactive catalog page the product is appended to
+        #[arg(long)]
+        inactive_page: Option<u64>,
+        /// Catalog page holding the product, in the chain matching its status
+        #[arg(long)]
+        current_page: Option<u64>,
+        /// ID chunk holding the product ID
+        #[arg(long)]
+        chunk_index: Option<u32>,
+    },
+    /// Update a product price, leaderboards including it go in --remaining-account PUBKEY:w
+    UpdatePrice { product_id: u64, price: u64 },
+    /// Add a variant with its own options, SKU, price and inventory to a product
+    CreateVariant {
+        product_id: u64,
+        /// Variant option NAME=VALUE, repeat for several
+        #[arg(long = "option", required = true)]
+        options: Vec<VariantOptionArg>,
+        #[arg(long, default_value = "")]
+        sku: String,
+        /// Price override, the product price when omitted
+        #[arg(long)]
+        price: Option<u64>,
+        #[arg(long)]
+        inventory: u64,
+    },
+    /// Update the SKU, inventory or availability of a variant
+    UpdateVariant {
+        product_id: u64,
+        variant_id: u32,
+        #[arg(long)]
+        sku: Option<String>,
+        #[arg(long)]
+        inventory: Option<u64>,
+        #[arg(long)]
+        active: Option<bool>,
+    },
+    /// Set the price override of a variant, or fall back to the product price with --clear
+    UpdateVariantPrice {
+        product_id: u64,
+        variant_id: u32,
+        #[arg(long, required_unless_present = "clear")]
+        price: Option<u64>,
+        #[arg(long, conflicts_with = "price")]
+        clear: bool,
+    },
+    /// Grow a product created before variants to the current layout
+    Migrate { product_id: u64 },
+    /// Add to the sales count of a product (admin)
+    UpdateSales { product_id: u64, increment: u32 },
+    /// Record a product in a recency feed
+    RecordRecent {
+        product_id: u64,
+        #[arg(long, value_enum)]
+        feed: FeedArg,
+        /// Keyword feed, the global feed when empty
+        #[arg(long, default_value = "")]
+        keyword: String,
+        /// Day of the product timestamp of the feed
+        #[arg(long)]
+        day: Option<u32>,
+        /// Bucket following --day in the feed, looked up when --day is older than the newest bucket
+        #[arg(long)]
+        newer_day: Option<u32>,
+    },
+    /// List a recency feed (view), buckets go in --remaining-account
+    Recent {
+        #[arg(long, value_enum)]
+        feed: FeedArg,
+        #[arg(long, default_value = "")]
+        keyword: String,
+        /// Continue after DAY:TIMESTAMP:PRODUCT_ID of the previous page
+        #[arg(long, value_parser = parse_cursor)]
+        cursor: Option<RecencyCursor>,
+        #[arg(long, default_value_t = 20)]
+        limit: u16,
+    },
+}
+
+fn parse_cursor(value: &str) -> Result<RecencyCursor> {
+    let parts: Vec<&str> = value.split(':').collect();
+    let [day, timestamp, product_id] = parts.as_slice() else {
+        anyhow::bail!("expected DAY:TIMESTAMP:PRODUCT_ID, got `{}`", value);
+    };
+    Ok(RecencyCursor {
+        day: day.parse()?,
+        timestamp: timestamp.parse()?,
+        product_id: product_id.parse()?,
+    })
+}
+
+impl ProductCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        let merchant = ctx.wallet;
+        let product_list = || -> Result<MerchantProductList> {
+            ctx.account(&pda::merchant_product_list(&merchant).0)
+        };
+
+        Ok(match self {
+            ProductCommand::Create {
+                name,
+                description,
+                price,
+                keywords,
+                inventory,
+                payment_token,
+                shipping_location,
+                product_id,
+                chunk_index,
+                last_chunk_index,
+                page,
+                day,
+            } => {
+                let (active_chunk, last_chunk_index, product_id) =
+                    match (product_id, chunk_index, last_chunk_index) {
+                        (Some(product_id), Some(chunk_index), Some(last_chunk_index)) => (
+                            pda::id_chunk(&merchant, chunk_index).0,
+                            last_chunk_index,
+                            product_id,
+                        ),
+                        _ => next_product_id(ctx, &merchant)?,
+                    };
+                let page = match page {
+                    Some(page) => page,
+                    None => product_list()?.next_page_index(true),
+                };
+
+                ix::create_product_base(
+                    merchant,
+                    active_chunk,
+                    last_chunk_index,
+                    product_id,
+                    page,
+                    instruction::CreateProductBase {
+                        name,
+                        description,
+                        price,
+                        keywords,
+                        inventory,
+                        payment_token,
+                        shipping_location,
+                        day: day.map_or_else(|| ctx.today(), Ok)?,
+                    },
+                )
+            }
+            ProductCommand::CreateExtended {
+                product_id,
+                image_video_urls,
+                sales_regions,
+                logistics_methods,
+            } => ix::create_product_extended(
+                merchant,
+                instruction::CreateProductExtended {
+                    product_id,
+                    image_video_urls,
+                    sales_regions,
+                    logistics_methods,
+                },
+            ),
+            ProductCommand::Update {
+                product_id,
+                name,
+                description,
+                price,
+                keywords,
+                inventory,
+                payment_token,
+                shipping_location,
+                image_video_urls,
+                sales_regions,
+                logistics_methods,
+                day,
+            } => {
+                let with_extended = image_video_urls.is_some()
+                    || sales_regions.is_some()
+                    || logistics_methods.is_some();
+                ix::update_product(
+                    merchant,
+                    with_extended,
+                    instruction::UpdateProduct {
+                        product_id,
+                        name,
+                        description,
+                        price,
+                        keywords,
+                        inventory,
+                        payment_token,
+                        image_video_urls,
+                        shipping_location,
+                        sales_regions,
+                        logistics_methods,
+                        day: day.map_or_else(|| ctx.today(), Ok)?,
+                    },
+                )
+            }
+            ProductCommand::Delete {
+                product_id,
+                hard,
+                force,
+                beneficiary,
+                inactive_page,
+                current_page,
+                chunk_index,
+            } => {
+                let inactive_page = match inactive_page {
+                    Some(page) => page,
+                    None => product_list()?.next_page_index(false),
+                };
+                let product = ctx.account::<ProductBase>(&pda::product(product_id).0)?;
+                let is_active = product.is_active;
+                let current_page = match current_page {
+                    Some(page) => Some((is_active, page)),
+                    // Soft deleting an inactive product leaves the catalog alone
+                    None if !hard && !is_active => None,
+                    None => find_current_page(ctx, &merchant, product_id, is_active)?
+                        .map(|page| (is_active, page)),
+                };
+                let chunk_index = match chunk_index {
+                    Some(chunk_index) => Some(chunk_index),
+                    None if hard => ctx.find_id_chunk(&merchant, product_id)?,
+                    None => None,
+                };
+
+                // Hard delete closes the product's variants
+                let variant_count = if hard { product.variant_count } else { 0 };
+                ix::with_remaining_accounts(
+                    ix::delete_product(
+                        merchant,
+                        beneficiary.unwrap_or(merchant),
+                        inactive_page,
+                        current_page,
+                        chunk_index,
+                        instruction::DeleteProduct {
+                            product_id,
+                            hard_delete: hard,
+                            force,
+                        },
+                    ),
+                    (0..variant_count).map(|variant_id| {
+                        AccountMeta::new(pda::product_variant(product_id, variant_id).0, false)
+                    }),
+                )
+            }
+            ProductCommand::UpdatePrice { product_id, price } => ix::update_product_price(
+                merchant,
+                instruction::UpdateProductPrice {
+                    product_id,
+                    new_price: price,
+                },
+            ),
+            ProductCommand::CreateVariant {
+                product_id,
+                options,
+                sku,
+                price,
+                inventory,
+            } => {
+                let product: ProductBase = ctx.account(&pda::product(product_id).0)?;
+                ix::create_product_variant(
+                    merchant,
+                    product.variant_count,
+                    instruction::CreateProductVariant {
+                        product_id,
+                        options: options.into_iter().map(|option| option.0).collect(),
+                        sku,
+                        price,
+                        inventory,
+                    },
+                )
+            }
+            ProductCommand::UpdateVariant {
+                product_id,
+                variant_id,
+                sku,
+                inventory,
+                active,
+            } => ix::update_product_variant(
+                merchant,
+                instruction::UpdateProductVariant {
+                    product_id,
+                    variant_id,
+                    sku,
+                    inventory,
+                    is_active: active,
+                },
+            ),
+            ProductCommand::UpdateVariantPrice {
+                product_id,
+                variant_id,
+                price,
+                clear: _,
+            } => ix::update_product_variant_price(
+                merchant,
+                instruction::UpdateProductVariantPrice {
+                    product_id,
+                    variant_id,
+                    price,
+                },
+            ),
+            ProductCommand::Migrate { product_id } => {
+                ix::migrate_product_base(ctx.payer, instruction::MigrateProductBase { product_id })
+            }
+            ProductCommand::UpdateSales {
+                product_id,
+                increment,
+            } => ix::update_sales_count(
+                ctx.wallet,
+                instruction::UpdateSalesCount {
+                    product_id,
+                    sales_increment: increment,
+                },
+            ),
+            ProductCommand::RecordRecent {
+                product_id,
+                feed,
+                keyword,
+                day,
+                newer_day,
+            } => {
+                let feed = RecencyFeed::from(feed);
+                let day = match day {
+                    Some(day) => day,
+                    None => {
+                        let product: ProductBase = ctx.account(&pda::product(product_id).0)?;
+                        recency_day(match feed {
+                            RecencyFeed::NewArrivals => product.created_at,
+                            RecencyFeed::RecentlyUpdated => product.updated_at,
+                        })
+                    }
+                };
+                let newer_day = match newer_day {
+                    Some(newer_day) => Some(newer_day),
+                    None => find_newer_bucket(ctx, feed, &keyword, day)?,
+                };
+                ix::record_recent_product(
+                    ctx.payer,
+                    newer_day,
+                    instruction::RecordRecentProduct {
+                        product_id,
+                        feed,
+                        keyword,
+                        day,
+                    },
+                )
+            }
+            ProductCommand::Recent {
+                feed,
+                keyword,
+                cursor,
+                limit,
+            } => ix::get_recent_products(instruction::GetRecentProducts {
+                feed: feed.into(),
+                keyword,
+                cursor,
+                limit,
+            }),
+        })
+    }
+}
+
+// Active chunk, last chunk index and the ID the program generates next: the next ID of the
+// active chunk, or the first ID of the next chunk when it is full
+fn next_product_id(ctx: &Context, merchant: &Pubkey) -> Result<(Pubkey, u32, u64)> {
+    let id_account = ctx.merchant_id_account(merchant)?;
+    let mut chunk: IdChunk = ctx.account(&id_account.active_chunk)?;
+    let product_id = match chunk.allocate_next() {
+        Some(product_id) => product_id,
+        // A full active chunk is only left for the preallocated next one
+        None => {
+            let next_chunk = pda::id_chunk(merchant, id_account.last_chunk_index + 1).0;
+            ctx.account::<IdChunk>(&next_chunk)?
+                .allocate_next()
+                .with_context(|| format!("ID chunk {} is full", next_chunk))?
+        }
+    };
+    Ok((
+        id_account.active_chunk,
+        id_account.last_chunk_index,
+        product_id,
+    ))
+}
+
+// Active catalog page holding an active product, None for a delisted one
+fn find_current_page(
+    ctx: &Context,
+    merchant: &Pubkey,
+    product_id: u64,
+    is_active: bool,
+) -> Result<Option<u64>> {
+    let address = pda::merchant_product_list(merchant).0;
+    let Some(list) = ctx.try_account::<MerchantProductList>(&address)? else {
+        return Ok(None);
+    };
+    for page_index in 0..=list.next_page_index(is_active) {
+        let address = pda::merchant_product_page(merchant, is_active, page_index).0;
+        let Some(page) = ctx.try_account::<MerchantProductPage>(&address)? else {
+            continue;
+        };
+        if page.product_ids.contains(&product_id) {
+            return Ok(Some(page_index));
+        }
+    }
+
+    // Products created before the catalog are not on any page
+    let product: ProductBase = ctx.account(&pda::product(product_id).0)?;
+    if product.created_at < list.created_at {
+        return Ok(None);
+    }
+    anyhow::bail!(
+        "product {} is not on a catalog page, pass --current-page",
+        product_id
+    )
+}
+
+// Bucket the previous_day chain passes through right after `day`, None when `day` is not older
+// than the newest bucket
+fn find_newer_bucket(
+    ctx: &Context,
+    feed: RecencyFeed,
+    keyword: &str,
+    day: u32,
+) -> Result<Option<u32>> {
+    let address = pda::recency_head(feed, keyword).0;
+    let Some(head) = ctx.try_account::<RecencyFeedHead>(&address)? else {
+        return Ok(None);
+    };
+    let mut newer_day = None;
+    let mut current = head.latest_day;
+    while let Some(current_day) = current.filter(|&current_day| current_day > day) {
+        let bucket: RecencyBucket =
+            ctx.account(&pda::recency_bucket(feed, keyword, current_day).0)?;
+        newer_day = Some(current_day);
+        current = bucket.previous_day;
+    }
+    Ok(newer_day)
+}
@@ -0,0 +1,66 @@
+use crate::context::Context;
+use crate::parse::ScopeArg;
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::Result;
+use clap::Subcommand;
+use shop_client::instructions as ix;
+use solana_e_commerce::instruction;
+
+#[derive(Subcommand)]
+pub enum TrendingCommand {
+    /// Create the trending list of a keyword
+    InitKeyword { keyword: String },
+    /// List trending products (view)
+    Show {
+        /// Keyword list, the global list when omitted
+        #[arg(long)]
+        keyword: Option<String>,
+        #[arg(long, default_value_t = 20)]
+        limit: u16,
+    },
+}
+
+/// Bestseller leaderboards, scopes are global, category:KEYWORD or token:MINT
+#[derive(Subcommand)]
+pub enum LeaderboardCommand {
+    /// Create a leaderboard
+    Init { scope: ScopeArg },
+    /// List a leaderboard (view)
+    Show {
+        scope: ScopeArg,
+        #[arg(long, default_value_t = 20)]
+        limit: u16,
+    },
+}
+
+impl TrendingCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        Ok(match self {
+            TrendingCommand::InitKeyword { keyword } => ix::initialize_keyword_trending_board(
+                ctx.payer,
+                instruction::InitializeKeywordTrendingBoard { keyword },
+            ),
+            TrendingCommand::Show { keyword, limit } => ix::get_trending_products(
+                keyword.as_deref(),
+                instruction::GetTrendingProducts { limit },
+            ),
+        })
+    }
+}
+
+impl LeaderboardCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        Ok(match self {
+            LeaderboardCommand::Init { scope } => ix::initialize_leaderboard(
+                ctx.payer,
+                instruction::InitializeLeaderboard { scope: scope.0 },
+            ),
+            LeaderboardCommand::Show { scope, limit } => {
+                ix::get_leaderboard(instruction::GetLeaderboard {
+                    scope: scope.0,
+                    limit,
+                })
+            }
+        })
+    }
+}
@@ -0,0 +1,55 @@
+use crate::context::Context;
+use crate::parse::HashArg;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::Result;
+use clap::Subcommand;
+use shop_client::instructions as ix;
+use solana_e_commerce::instruction;
+
+/// Reviews of delivered orders, one per order
+#[derive(Subcommand)]
+pub enum ReviewCommand {
+    /// Review a delivered order as the buyer
+    Create {
+        order: Pubkey,
+        /// 1 to 5
+        #[arg(long)]
+        rating: u8,
+        #[arg(long, default_value = "")]
+        text: String,
+        /// Hash of the review content stored off-chain, 64 hex digits
+        #[arg(long)]
+        content_hash: Option<HashArg>,
+    },
+    /// Reply to the review of an order as the merchant
+    Reply {
+        order: Pubkey,
+        #[arg(long)]
+        reply: String,
+    },
+}
+
+impl ReviewCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        Ok(match self {
+            ReviewCommand::Create {
+                order,
+                rating,
+                text,
+                content_hash,
+            } => ix::create_review(
+                order,
+                &ctx.account(&order)?,
+                instruction::CreateReview {
+                    rating,
+                    content_hash: content_hash.map(|hash| hash.0),
+                    text,
+                },
+            ),
+            ReviewCommand::Reply { order, reply } => {
+                ix::reply_to_review(ctx.wallet, order, instruction::ReplyToReview { reply })
+            }
+        })
+    }
+}
@@ -0,0 +1,162 @@
+use crate::context::Context;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use shop_client::instructions as ix;
+use solana_e_commerce::{instruction, SystemConfig};
+
+#[derive(Subcommand)]
+pub enum SystemCommand {
+    /// Initialize the global ID root and system configuration
+    Init(SystemConfigArgs),
+    /// Initialize the system configuration only
+    InitConfig(SystemConfigArgs),
+    /// Grow a system configuration created before trending scores and index shards
+    MigrateConfig,
+    /// Close the system configuration
+    CloseConfig {
+        /// Receives the rent, defaults to the wallet
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+        #[arg(long)]
+        force: bool,
+    },
+    /// Close a system configuration account that no longer deserializes
+    ForceCloseConfig {
+        /// Receives the rent, defaults to the wallet
+        #[arg(long)]
+        beneficiary: Option<Pubkey>,
+    },
+    /// Set the base merchant deposit requirement (in whole tokens)
+    UpdateDepositRequirement { amount: u64 },
+    /// Set the half-life of trending scores
+    UpdateTrendingHalfLife { seconds: u32 },
+    /// Set the maximum entries of a zero-copy index shard
+    UpdateIndexShardCapacity { capacity: u32 },
+    /// Initialize the order statistics account
+    InitOrderStats,
+    /// Print the order statistics (view)
+    OrderStats,
+}
+
+/// System configuration, unset fields keep the program defaults. The authority is the wallet.
+#[derive(Args)]
+pub struct SystemConfigArgs {
+    #[arg(long)]
+    deposit_mint: Pubkey,
+    /// Base merchant deposit requirement (in whole tokens)
+    #[arg(long)]
+    deposit_requirement: Option<u64>,
+    /// Platform fee rate in basis points
+    #[arg(long)]
+    fee_rate: Option<u16>,
+    /// Platform fee recipient, defaults to the wallet
+    #[arg(long)]
+    fee_recipient: Option<Pubkey>,
+    #[arg(long)]
+    auto_confirm_days: Option<u32>,
+    #[arg(long)]
+    vault_program: Option<Pubkey>,
+    #[arg(long)]
+    vault_account: Option<Pubkey>,
+    #[arg(long)]
+    vault_token_account: Option<Pubkey>,
+    #[arg(long)]
+    platform_token_account: Option<Pubkey>,
+    #[arg(long)]
+    trending_half_life: Option<u32>,
+    #[arg(long)]
+    index_shard_capacity: Option<u32>,
+    #[arg(long)]
+    max_products_per_shard: Option<u16>,
+    #[arg(long)]
+    max_keywords_per_product: Option<u8>,
+    #[arg(long)]
+    chunk_size: Option<u32>,
+    #[arg(long)]
+    bloom_filter_size: Option<u16>,
+}
+
+impl SystemConfigArgs {
+    fn config(self, authority: Pubkey) -> SystemConfig {
+        let defaults = SystemConfig::default();
+        SystemConfig {
+            authority,
+            deposit_token_mint: self.deposit_mint,
+            merchant_deposit_required: self
+                .deposit_requirement
+                .unwrap_or(defaults.merchant_deposit_required),
+            platform_fee_rate: self.fee_rate.unwrap_or(defaults.platform_fee_rate),
+            platform_fee_recipient: self.fee_recipient.unwrap_or(authority),
+            auto_confirm_days: self.auto_confirm_days.unwrap_or(defaults.auto_confirm_days),
+            vault_program_id: self.vault_program.unwrap_or_default(),
+            vault_account: self.vault_account.unwrap_or_default(),
+            vault_token_account: self.vault_token_account.unwrap_or_default(),
+            platform_token_account: self.platform_token_account.unwrap_or_default(),
+            trending_half_life_seconds: self
+                .trending_half_life
+                .unwrap_or(defaults.trending_half_life_seconds),
+            index_shard_capacity: self
+                .index_shard_capacity
+                .unwrap_or(defaults.index_shard_capacity),
+            max_products_per_shard: self
+                .max_products_per_shard
+                .unwrap_or(defaults.max_products_per_shard),
+            max_keywords_per_product: self
+                .max_keywords_per_product
+                .unwrap_or(defaults.max_keywords_per_product),
+            chunk_size: self.chunk_size.unwrap_or(defaults.chunk_size),
+            bloom_filter_size: self.bloom_filter_size.unwrap_or(defaults.bloom_filter_size),
+        }
+    }
+}
+
+impl SystemCommand {
+    pub fn instruction(self, ctx: &Context) -> Result<Instruction> {
+        let authority = ctx.wallet;
+        Ok(match self {
+            SystemCommand::Init(args) => ix::initialize_system(
+                authority,
+                instruction::InitializeSystem {
+                    config: args.config(authority),
+                },
+            ),
+            SystemCommand::InitConfig(args) => ix::initialize_system_config(
+                authority,
+                instruction::InitializeSystemConfig {
+                    config: args.config(authority),
+                },
+            ),
+            SystemCommand::MigrateConfig => ix::migrate_system_config(authority),
+            SystemCommand::CloseConfig { beneficiary, force } => ix::close_system_config(
+                authority,
+                beneficiary.unwrap_or(authority),
+                instruction::CloseSystemConfig { force },
+            ),
+            SystemCommand::ForceCloseConfig { beneficiary } => {
+                ix::force_close_system_config(authority, beneficiary.unwrap_or(authority))
+            }
+            SystemCommand::UpdateDepositRequirement { amount } => ix::update_deposit_requirement(
+                authority,
+                instruction::UpdateDepositRequirement {
+                    new_requirement: amount,
+                },
+            ),
+            SystemCommand::UpdateTrendingHalfLife { seconds } => ix::update_trending_half_life(
+                authority,
+                instruction::UpdateTrendingHalfLife {
+                    half_life_seconds: seconds,
+                },
+            ),
+            SystemCommand::UpdateIndexShardCapacity { capacity } => {
+                ix::update_index_shard_capacity(
+                    authority,
+                    instruction::UpdateIndexShardCapacity { capacity },
+                )
+            }
+            SystemCommand::InitOrderStats => ix::initialize_order_stats(authority),
+            SystemCommand::OrderStats => ix::get_order_stats(),
+        })
+    }
+}
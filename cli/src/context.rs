@@ -0,0 +1,103 @@
+//! What commands need to build their instruction: the acting keys and on-chain lookups
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Space};
+use anyhow::{anyhow, Context as _, Result};
+use shop_client::pda;
+use solana_e_commerce::state::{
+    recency_day, IdChunk, IndexShardKey, MerchantIdAccount, SalesIndexNode,
+};
+use solana_e_commerce::SystemConfig;
+use solana_rpc_client::rpc_client::RpcClient;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct Context {
+    pub rpc: RpcClient,
+    /// Fee payer, also pays the rent of created accounts
+    pub payer: Pubkey,
+    /// Acting authority, merchant or buyer
+    pub wallet: Pubkey,
+}
+
+impl Context {
+    pub fn account<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T> {
+        self.try_account(address)?
+            .with_context(|| format!("account {} does not exist", address))
+    }
+
+    // None when the account does not exist
+    pub fn try_account<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<Option<T>> {
+        self.account_data(address)?
+            .map(|data| {
+                shop_client::accounts::decode(&data)
+                    .map_err(|err| anyhow!("decoding account {}: {}", address, err))
+            })
+            .transpose()
+    }
+
+    // Raw data, None when the account does not exist
+    fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        // Lookups are the only RPC calls of an offline build, say how to avoid them
+        Ok(self
+            .rpc
+            .get_account_with_commitment(address, self.rpc.commitment())
+            .with_context(|| {
+                format!(
+                    "fetching account {} (pass the looked up value as an option to build offline)",
+                    address
+                )
+            })?
+            .value
+            .map(|account| account.data))
+    }
+
+    pub fn system_config(&self) -> Result<SystemConfig> {
+        self.account(&pda::system_config().0)
+    }
+
+    pub fn merchant_id_account(&self, merchant: &Pubkey) -> Result<MerchantIdAccount> {
+        self.account(&pda::merchant_id_account(merchant).0)
+    }
+
+    /// Index of the merchant chunk whose stored range holds an ID, None when no chunk holds it
+    pub fn find_id_chunk(&self, merchant: &Pubkey, id: u64) -> Result<Option<u32>> {
+        let last_chunk_index = self.merchant_id_account(merchant)?.last_chunk_index;
+        for chunk_index in 0..=last_chunk_index {
+            let chunk = self.try_account::<IdChunk>(&pda::id_chunk(merchant, chunk_index).0)?;
+            if chunk.is_some_and(|chunk| chunk.contains_id(id)) {
+                return Ok(Some(chunk_index));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Sales index account of a range settlement writes: the legacy sales node, or the zero-copy
+    /// sales shard once the node has been frozen by its migration
+    pub fn sales_index_account(
+        &self,
+        sales_range_start: u32,
+        sales_range_end: u32,
+    ) -> Result<Pubkey> {
+        let legacy_node = pda::sales_index(sales_range_start, sales_range_end).0;
+        let frozen = self
+            .account_data(&legacy_node)?
+            .is_some_and(|data| data.len() > 8 + SalesIndexNode::INIT_SPACE);
+        Ok(if frozen {
+            pda::index_shard(&IndexShardKey::Sales {
+                sales_range_start,
+                sales_range_end,
+            })
+            .0
+        } else {
+            legacy_node
+        })
+    }
+
+    /// Current day by the local clock, as the daily order statistics count them
+    pub fn today(&self) -> Result<u32> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("local clock is before the unix epoch")?;
+        Ok(recency_day(now.as_secs() as i64))
+    }
+}
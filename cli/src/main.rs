@@ -0,0 +1,199 @@
+//! `shop`: admin, merchant and buyer operations of the solana-e-commerce program
+//!
+//! Every program instruction has a subcommand. A command is sent by default, simulated with
+//! --dry-run, or signed into a file with --sign-only and sent later with `shop submit`.
+
+mod commands;
+mod context;
+mod parse;
+mod signer;
+mod transaction;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use commands::Command;
+use context::Context;
+use parse::RemainingAccount;
+use shop_client::instructions::with_remaining_accounts;
+use signer::{default_keypair_path, SignerSource};
+use solana_commitment_config::CommitmentConfig;
+use solana_hash::Hash;
+use solana_keypair::Keypair;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_system_interface::instruction::advance_nonce_account;
+use solana_transaction::Transaction;
+use std::path::PathBuf;
+use transaction::{Report, TransactionFile};
+
+#[derive(Parser)]
+#[command(
+    name = "shop",
+    version,
+    about = "Admin, merchant and buyer operations of the solana-e-commerce program"
+)]
+struct Cli {
+    /// RPC URL or moniker: localhost, devnet, testnet, mainnet-beta
+    #[arg(
+        long,
+        short = 'u',
+        global = true,
+        env = "SHOP_RPC_URL",
+        default_value = "localhost"
+    )]
+    url: String,
+    /// Fee payer keypair, or its public key with --sign-only [default: ~/.config/solana/id.json]
+    #[arg(long, short = 'k', global = true, env = "SHOP_KEYPAIR")]
+    keypair: Option<String>,
+    /// Acting authority, merchant or buyer: keypair, or public key with --sign-only
+    /// [default: the fee payer]
+    #[arg(long, global = true)]
+    wallet: Option<String>,
+    /// Further keypair signing the transaction, repeat for several
+    #[arg(long = "signer", global = true, value_name = "KEYPAIR")]
+    signers: Vec<String>,
+    /// Simulate instead of sending, signatures are not checked
+    #[arg(long, global = true, conflicts_with = "sign_only")]
+    dry_run: bool,
+    /// Sign with the available keypairs and write the transaction to FILE instead of sending
+    #[arg(long, global = true, value_name = "FILE")]
+    sign_only: Option<PathBuf>,
+    /// Blockhash to sign with (the nonce value with --nonce). With --sign-only and the lookup
+    /// options of a command, no RPC request is made.
+    #[arg(long, global = true)]
+    blockhash: Option<Hash>,
+    /// Durable nonce account, keeps a --sign-only transaction valid until it is submitted
+    #[arg(long, global = true)]
+    nonce: Option<Pubkey>,
+    /// Nonce authority: keypair, or public key with --sign-only [default: the fee payer]
+    #[arg(long, global = true, requires = "nonce")]
+    nonce_authority: Option<String>,
+    /// Account appended to the instruction, PUBKEY or PUBKEY:w for a writable one
+    #[arg(long = "remaining-account", global = true, value_name = "PUBKEY[:w]")]
+    remaining_accounts: Vec<RemainingAccount>,
+    #[arg(long, global = true, value_enum, default_value = "display")]
+    output: OutputFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Display,
+    Json,
+}
+
+fn main() {
+    let matches = Cli::command().get_matches();
+    let command_name = command_name(&matches);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let json = cli.output == OutputFormat::Json;
+
+    let result = run(cli, &command_name).and_then(|report| transaction::print(&report, json));
+    if let Err(err) = result {
+        if json {
+            let error = serde_json::json!({ "status": "error", "error": format!("{:#}", err) });
+            eprintln!("{}", error);
+        } else {
+            eprintln!("Error: {:#}", err);
+        }
+        std::process::exit(1);
+    }
+}
+
+// Subcommand path, e.g. "product create", recorded in transaction files
+fn command_name(matches: &ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut matches = matches;
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        names.push(name);
+        matches = sub_matches;
+    }
+    names.join(" ")
+}
+
+fn rpc_url(url: &str) -> &str {
+    match url {
+        "localhost" | "l" => "http://127.0.0.1:8899",
+        "devnet" | "d" => "https://api.devnet.solana.com",
+        "testnet" | "t" => "https://api.testnet.solana.com",
+        "mainnet-beta" | "m" => "https://api.mainnet-beta.solana.com",
+        url => url,
+    }
+}
+
+fn run(cli: Cli, command_name: &str) -> Result<Report> {
+    let rpc = RpcClient::new_with_commitment(rpc_url(&cli.url), CommitmentConfig::confirmed());
+
+    let payer = SignerSource::parse(&cli.keypair.unwrap_or_else(default_keypair_path))?;
+    let wallet = cli.wallet.as_deref().map(SignerSource::parse).transpose()?;
+    let nonce_authority = cli
+        .nonce_authority
+        .as_deref()
+        .map(SignerSource::parse)
+        .transpose()?;
+    let signers = cli
+        .signers
+        .iter()
+        .map(|signer| SignerSource::parse(signer))
+        .collect::<Result<Vec<_>>>()?;
+    let keypairs: Vec<&Keypair> = [Some(&payer), wallet.as_ref(), nonce_authority.as_ref()]
+        .into_iter()
+        .flatten()
+        .chain(&signers)
+        .filter_map(SignerSource::keypair)
+        .collect();
+
+    let command = match cli.command {
+        Command::Sign { file } => {
+            let (mut transaction_file, mut transaction) = TransactionFile::read(&file)?;
+            transaction::sign(&mut transaction, &keypairs)?;
+            transaction_file.update(&transaction);
+            transaction_file.write(&file)?;
+            return Ok(transaction_file.report(&file));
+        }
+        Command::Submit { file } => {
+            let (transaction_file, transaction) = TransactionFile::read(&file)?;
+            return if cli.dry_run {
+                let nonce = transaction_file.nonce_account.is_some();
+                transaction::simulate(&rpc, &transaction, nonce)
+            } else {
+                transaction::send(&rpc, &transaction)
+            };
+        }
+        Command::Program(command) => *command,
+    };
+
+    let ctx = Context {
+        rpc,
+        payer: payer.pubkey(),
+        wallet: wallet.as_ref().unwrap_or(&payer).pubkey(),
+    };
+    let instruction = with_remaining_accounts(
+        command.instruction(&ctx)?,
+        cli.remaining_accounts.into_iter().map(|account| account.0),
+    );
+
+    // A durable nonce transaction starts by advancing the nonce
+    let mut instructions = Vec::new();
+    if let Some(nonce) = cli.nonce {
+        let authority = nonce_authority.as_ref().unwrap_or(&payer).pubkey();
+        instructions.push(advance_nonce_account(&nonce, &authority));
+    }
+    instructions.push(instruction);
+
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&ctx.payer));
+    transaction.message.recent_blockhash =
+        transaction::blockhash(&ctx.rpc, cli.nonce, cli.blockhash)?;
+    transaction::sign(&mut transaction, &keypairs)?;
+
+    if let Some(file) = cli.sign_only {
+        let transaction_file = TransactionFile::new(command_name, &transaction, cli.nonce);
+        transaction_file.write(&file)?;
+        Ok(transaction_file.report(&file))
+    } else if cli.dry_run {
+        transaction::simulate(&ctx.rpc, &transaction, cli.nonce.is_some())
+    } else {
+        transaction::send(&ctx.rpc, &transaction)
+    }
+}
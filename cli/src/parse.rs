@@ -0,0 +1,280 @@
+//! Value parsers for arguments that map to program types
+
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use solana_e_commerce::state::{
+    IndexShardKey, LeaderboardScope, RecencyFeed, SupportedToken, VariantOption,
+};
+use solana_e_commerce::utils::{SortKey, SortOrder};
+use std::str::FromStr;
+
+fn pubkey(value: &str) -> Result<Pubkey> {
+    Pubkey::from_str(value).map_err(|_| anyhow!("invalid public key `{}`", value))
+}
+
+fn number<T: FromStr>(value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("invalid number `{}`", value))
+}
+
+/// Extra account appended to the instruction, `PUBKEY` or `PUBKEY:w` for a writable one
+#[derive(Clone, Debug)]
+pub struct RemainingAccount(pub AccountMeta);
+
+impl FromStr for RemainingAccount {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let meta = match value.split_once(':') {
+            Some((key, "w")) => AccountMeta::new(pubkey(key)?, false),
+            Some((_, flag)) => bail!("unknown account flag `{}`, expected `w`", flag),
+            None => AccountMeta::new_readonly(pubkey(value)?, false),
+        };
+        Ok(Self(meta))
+    }
+}
+
+/// Supported payment token, `MINT:SYMBOL` or `MINT:SYMBOL:inactive`
+#[derive(Clone, Debug)]
+pub struct TokenArg(pub SupportedToken);
+
+impl FromStr for TokenArg {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let parts: Vec<&str> = value.split(':').collect();
+        let is_active = match parts.as_slice() {
+            [_, _] => true,
+            [_, _, "inactive"] => false,
+            _ => bail!("expected MINT:SYMBOL[:inactive], got `{}`", value),
+        };
+        Ok(Self(SupportedToken {
+            mint: pubkey(parts[0])?,
+            symbol: parts[1].to_string(),
+            is_active,
+        }))
+    }
+}
+
+/// Price or sales index node range, `START:END`
+#[derive(Clone, Copy, Debug)]
+pub struct RangeArg<T> {
+    pub start: T,
+    pub end: T,
+}
+
+impl<T: FromStr> FromStr for RangeArg<T> {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (start, end) = value
+            .split_once(':')
+            .with_context(|| format!("expected START:END, got `{}`", value))?;
+        Ok(Self {
+            start: number(start)?,
+            end: number(end)?,
+        })
+    }
+}
+
+/// Leaderboard scope, `global`, `category:KEYWORD` or `token:MINT`
+#[derive(Clone, Debug)]
+pub struct ScopeArg(pub LeaderboardScope);
+
+impl FromStr for ScopeArg {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let scope = match value.split_once(':') {
+            None if value == "global" => LeaderboardScope::Global,
+            Some(("category", keyword)) => LeaderboardScope::Category(keyword.to_string()),
+            Some(("token", mint)) => LeaderboardScope::PaymentToken(pubkey(mint)?),
+            _ => bail!(
+                "expected global, category:KEYWORD or token:MINT, got `{}`",
+                value
+            ),
+        };
+        Ok(Self(scope))
+    }
+}
+
+/// Zero-copy index shard key, `keyword:KEYWORD:SHARD`, `price:MINT:START:END` or
+/// `sales:START:END`
+#[derive(Clone, Debug)]
+pub struct ShardKeyArg(pub IndexShardKey);
+
+impl FromStr for ShardKeyArg {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let parts: Vec<&str> = value.split(':').collect();
+        let key = match parts.as_slice() {
+            ["keyword", keyword, shard_index] => IndexShardKey::Keyword {
+                keyword: keyword.to_string(),
+                shard_index: number(shard_index)?,
+            },
+            ["price", mint, start, end] => IndexShardKey::Price {
+                payment_token: pubkey(mint)?,
+                price_range_start: number(start)?,
+                price_range_end: number(end)?,
+            },
+            ["sales", start, end] => IndexShardKey::Sales {
+                sales_range_start: number(start)?,
+                sales_range_end: number(end)?,
+            },
+            _ => bail!(
+                "expected keyword:KEYWORD:SHARD, price:MINT:START:END or sales:START:END, got `{}`",
+                value
+            ),
+        };
+        Ok(Self(key))
+    }
+}
+
+/// 32 byte hash, 64 hex digits
+#[derive(Clone, Copy, Debug)]
+pub struct HashArg(pub [u8; 32]);
+
+impl FromStr for HashArg {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        if value.len() != 64 || !value.is_ascii() {
+            bail!("expected 64 hex digits, got `{}`", value);
+        }
+        let mut hash = [0u8; 32];
+        for (byte, digits) in hash.iter_mut().zip(value.as_bytes().chunks(2)) {
+            let digits = std::str::from_utf8(digits)?;
+            *byte = u8::from_str_radix(digits, 16)
+                .map_err(|_| anyhow!("invalid hex digits `{}`", digits))?;
+        }
+        Ok(Self(hash))
+    }
+}
+
+/// Product variant option, `NAME=VALUE`
+#[derive(Clone, Debug)]
+pub struct VariantOptionArg(pub VariantOption);
+
+impl FromStr for VariantOptionArg {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (name, option_value) = value
+            .split_once('=')
+            .with_context(|| format!("expected NAME=VALUE, got `{}`", value))?;
+        Ok(Self(VariantOption {
+            name: name.to_string(),
+            value: option_value.to_string(),
+        }))
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum FeedArg {
+    NewArrivals,
+    RecentlyUpdated,
+}
+
+impl From<FeedArg> for RecencyFeed {
+    fn from(feed: FeedArg) -> Self {
+        match feed {
+            FeedArg::NewArrivals => RecencyFeed::NewArrivals,
+            FeedArg::RecentlyUpdated => RecencyFeed::RecentlyUpdated,
+        }
+    }
+}
+
+// Search sort keys
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SortKeyArg {
+    ProductId,
+    Price,
+}
+
+impl From<SortKeyArg> for SortKey {
+    fn from(key: SortKeyArg) -> Self {
+        match key {
+            SortKeyArg::ProductId => SortKey::ProductId,
+            SortKeyArg::Price => SortKey::Price,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SortOrderArg {
+    Asc,
+    Desc,
+}
+
+impl From<SortOrderArg> for SortOrder {
+    fn from(order: SortOrderArg) -> Self {
+        match order {
+            SortOrderArg::Asc => SortOrder::Ascending,
+            SortOrderArg::Desc => SortOrder::Descending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_composite_arguments() {
+        let mint = Pubkey::new_unique();
+
+        let account: RemainingAccount = format!("{}:w", mint).parse().unwrap();
+        assert!(account.0.is_writable && !account.0.is_signer);
+        assert!(
+            !mint
+                .to_string()
+                .parse::<RemainingAccount>()
+                .unwrap()
+                .0
+                .is_writable
+        );
+        assert!(format!("{}:x", mint).parse::<RemainingAccount>().is_err());
+
+        let token: TokenArg = format!("{}:USDC:inactive", mint).parse().unwrap();
+        assert_eq!(token.0.symbol, "USDC");
+        assert!(!token.0.is_active);
+
+        let range: RangeArg<u32> = "100:199".parse().unwrap();
+        assert_eq!((range.start, range.end), (100, 199));
+        assert!("100".parse::<RangeArg<u32>>().is_err());
+
+        let scope: ScopeArg = format!("token:{}", mint).parse().unwrap();
+        assert!(matches!(scope.0, LeaderboardScope::PaymentToken(key) if key == mint));
+        assert!(matches!(
+            "global".parse::<ScopeArg>().unwrap().0,
+            LeaderboardScope::Global
+        ));
+
+        let key: ShardKeyArg = format!("price:{}:1000:1999", mint).parse().unwrap();
+        assert!(matches!(
+            key.0,
+            IndexShardKey::Price {
+                price_range_start: 1_000,
+                price_range_end: 1_999,
+                ..
+            }
+        ));
+        assert!("keyword:lamp".parse::<ShardKeyArg>().is_err());
+
+        let hash: HashArg = format!("ab{}", "0".repeat(62)).parse().unwrap();
+        assert_eq!(hash.0[0], 0xab);
+        assert!(hash.0[1..].iter().all(|byte| *byte == 0));
+        assert!("abc".parse::<HashArg>().is_err());
+        assert!("zz".repeat(32).parse::<HashArg>().is_err());
+
+        let option: VariantOptionArg = "size=M".parse().unwrap();
+        assert_eq!(
+            (option.0.name.as_str(), option.0.value.as_str()),
+            ("size", "M")
+        );
+        assert!("size".parse::<VariantOptionArg>().is_err());
+    }
+}
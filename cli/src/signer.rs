@@ -0,0 +1,53 @@
+//! Keys given on the command line, as a keypair file or a bare public key
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, Result};
+use solana_keypair::{read_keypair_file, Keypair};
+use solana_signer::Signer;
+use std::str::FromStr;
+
+pub enum SignerSource {
+    Keypair(Keypair),
+    // Signs elsewhere, only usable with --sign-only
+    Pubkey(Pubkey),
+}
+
+impl SignerSource {
+    /// A public key, or the path of a keypair file (`~` expands to the home directory)
+    pub fn parse(value: &str) -> Result<Self> {
+        if let Ok(pubkey) = Pubkey::from_str(value) {
+            return Ok(SignerSource::Pubkey(pubkey));
+        }
+
+        let path = expand_home(value);
+        read_keypair_file(&path)
+            .map(SignerSource::Keypair)
+            .map_err(|err| anyhow!("reading keypair {}: {}", path, err))
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        match self {
+            SignerSource::Keypair(keypair) => keypair.pubkey(),
+            SignerSource::Pubkey(pubkey) => *pubkey,
+        }
+    }
+
+    pub fn keypair(&self) -> Option<&Keypair> {
+        match self {
+            SignerSource::Keypair(keypair) => Some(keypair),
+            SignerSource::Pubkey(_) => None,
+        }
+    }
+}
+
+/// Keypair of the Solana CLI configuration
+pub fn default_keypair_path() -> String {
+    expand_home("~/.config/solana/id.json")
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home, rest),
+        _ => path.to_string(),
+    }
+}
@@ -0,0 +1,258 @@
+//! Building, signing, simulating and sending transactions, and the transaction files of
+//! --sign-only
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{bail, Context as _, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use solana_hash::Hash;
+use solana_keypair::Keypair;
+use solana_nonce::state::State as NonceState;
+use solana_nonce::versions::Versions as NonceVersions;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::config::RpcSimulateTransactionConfig;
+use solana_signature::Signature;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+use std::path::Path;
+
+/// Result of a command, printed as text or JSON
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Report {
+    Sent {
+        signature: String,
+    },
+    Simulated {
+        success: bool,
+        error: Option<String>,
+        units_consumed: Option<u64>,
+        /// Base64 Borsh value returned by the instruction
+        return_data: Option<String>,
+        logs: Vec<String>,
+    },
+    Signed {
+        file: String,
+        blockhash: String,
+        signers: Vec<SignerStatus>,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SignerStatus {
+    pub pubkey: String,
+    pub signed: bool,
+}
+
+/// Transaction written by --sign-only, submitted later with `shop submit`
+///
+/// Fields besides `transaction` are informational, `transaction` is the base64 bincode
+/// transaction with the signatures made so far.
+#[derive(Serialize, Deserialize)]
+pub struct TransactionFile {
+    pub command: String,
+    pub blockhash: String,
+    pub nonce_account: Option<String>,
+    pub signers: Vec<SignerStatus>,
+    pub transaction: String,
+}
+
+impl TransactionFile {
+    pub fn new(command: &str, transaction: &Transaction, nonce_account: Option<Pubkey>) -> Self {
+        let mut file = Self {
+            command: command.to_string(),
+            blockhash: transaction.message.recent_blockhash.to_string(),
+            nonce_account: nonce_account.map(|nonce| nonce.to_string()),
+            signers: Vec::new(),
+            transaction: String::new(),
+        };
+        file.update(transaction);
+        file
+    }
+
+    // Store a transaction with more signatures
+    pub fn update(&mut self, transaction: &Transaction) {
+        self.signers = signer_status(transaction);
+        self.transaction = BASE64.encode(bincode::serialize(transaction).expect("serialize"));
+    }
+
+    pub fn read(path: &Path) -> Result<(Self, Transaction)> {
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let file: Self = serde_json::from_str(&content)
+            .with_context(|| format!("parsing {}", path.display()))?;
+        let bytes = BASE64
+            .decode(&file.transaction)
+            .context("decoding the transaction")?;
+        let transaction = bincode::deserialize(&bytes).context("decoding the transaction")?;
+        Ok((file, transaction))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("writing {}", path.display()))
+    }
+
+    pub fn report(&self, path: &Path) -> Report {
+        Report::Signed {
+            file: path.display().to_string(),
+            blockhash: self.blockhash.clone(),
+            signers: self.signers.clone(),
+        }
+    }
+}
+
+/// Blockhash of a transaction: the stored value of a durable nonce account, or a recent
+/// blockhash. A blockhash given on the command line (the nonce value for a nonce account) is
+/// used as is, so signing needs no RPC.
+pub fn blockhash(
+    rpc: &RpcClient,
+    nonce_account: Option<Pubkey>,
+    given: Option<Hash>,
+) -> Result<Hash> {
+    if let Some(blockhash) = given {
+        return Ok(blockhash);
+    }
+
+    let Some(nonce_account) = nonce_account else {
+        return rpc
+            .get_latest_blockhash()
+            .context("fetching a recent blockhash (pass --blockhash to sign offline)");
+    };
+    let data = rpc
+        .get_account_data(&nonce_account)
+        .with_context(|| format!("fetching nonce account {}", nonce_account))?;
+    let versions: NonceVersions = bincode::deserialize(&data)
+        .with_context(|| format!("{} is not a nonce account", nonce_account))?;
+    match versions.state() {
+        NonceState::Initialized(data) => Ok(data.blockhash()),
+        NonceState::Uninitialized => bail!("nonce account {} is not initialized", nonce_account),
+    }
+}
+
+/// Add the signatures of the given keypairs the transaction requires, others are ignored
+pub fn sign(transaction: &mut Transaction, keypairs: &[&Keypair]) -> Result<()> {
+    let required = required_signers(transaction);
+    let signers: Vec<&Keypair> = keypairs
+        .iter()
+        .copied()
+        .filter(|keypair| required.contains(&keypair.pubkey()))
+        .collect();
+    let blockhash = transaction.message.recent_blockhash;
+    transaction
+        .try_partial_sign(&signers, blockhash)
+        .context("signing the transaction")
+}
+
+pub fn missing_signers(transaction: &Transaction) -> Vec<Pubkey> {
+    required_signers(transaction)
+        .into_iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(pubkey, _)| pubkey)
+        .collect()
+}
+
+fn required_signers(transaction: &Transaction) -> Vec<Pubkey> {
+    let count = transaction.message.header.num_required_signatures as usize;
+    transaction.message.account_keys[..count].to_vec()
+}
+
+fn signer_status(transaction: &Transaction) -> Vec<SignerStatus> {
+    let missing = missing_signers(transaction);
+    required_signers(transaction)
+        .into_iter()
+        .map(|pubkey| SignerStatus {
+            pubkey: pubkey.to_string(),
+            signed: !missing.contains(&pubkey),
+        })
+        .collect()
+}
+
+pub fn send(rpc: &RpcClient, transaction: &Transaction) -> Result<Report> {
+    let missing = missing_signers(transaction);
+    if !missing.is_empty() {
+        let missing: Vec<String> = missing.iter().map(Pubkey::to_string).collect();
+        bail!(
+            "missing signatures of {}, pass their keypairs with --signer",
+            missing.join(", ")
+        );
+    }
+
+    let signature = rpc
+        .send_and_confirm_transaction(transaction)
+        .context("sending the transaction")?;
+    Ok(Report::Sent {
+        signature: signature.to_string(),
+    })
+}
+
+/// Simulate without checking signatures, so a transaction missing signatures can be tried out
+pub fn simulate(rpc: &RpcClient, transaction: &Transaction, nonce: bool) -> Result<Report> {
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        // A nonce value is not a recent blockhash, but the advance instruction checks it
+        replace_recent_blockhash: !nonce,
+        commitment: Some(rpc.commitment()),
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let result = rpc
+        .simulate_transaction_with_config(transaction, config)
+        .context("simulating the transaction")?
+        .value;
+
+    Ok(Report::Simulated {
+        success: result.err.is_none(),
+        error: result.err.map(|err| err.to_string()),
+        units_consumed: result.units_consumed,
+        return_data: result.return_data.map(|data| data.data.0),
+        logs: result.logs.unwrap_or_default(),
+    })
+}
+
+pub fn print(report: &Report, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(report)?);
+        return Ok(());
+    }
+
+    match report {
+        Report::Sent { signature } => println!("Signature: {}", signature),
+        Report::Simulated {
+            error,
+            units_consumed,
+            return_data,
+            logs,
+            ..
+        } => {
+            match error {
+                Some(error) => println!("Simulation failed: {}", error),
+                None => println!("Simulation succeeded"),
+            }
+            if let Some(units) = units_consumed {
+                println!("Compute units: {}", units);
+            }
+            if let Some(data) = return_data {
+                println!("Return data (base64): {}", data);
+            }
+            println!("Logs:");
+            for log in logs {
+                println!("  {}", log);
+            }
+        }
+        Report::Signed {
+            file,
+            blockhash,
+            signers,
+        } => {
+            println!("Wrote {}", file);
+            println!("Blockhash: {}", blockhash);
+            for signer in signers {
+                let status = if signer.signed { "signed" } else { "missing" };
+                println!("  {} {}", signer.pubkey, status);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,320 @@
+//! Account decoders
+//!
+//! Borsh accounts decode through their Anchor discriminator, zero-copy index shards are read
+//! from their raw header and entry region.
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator, Result};
+use solana_e_commerce::state::{
+    DailyOrderStats, EscrowAccount, EscrowLedger, GlobalIdRoot, IdChunk, IndexEntries, IndexEntry,
+    IndexShard, KeywordRoot, KeywordShard, Leaderboard, LegacyOrder, LegacyProductBase, Merchant,
+    MerchantIdAccount, MerchantOrder, MerchantOrderCount, MerchantOrderStats, MerchantProductList,
+    MerchantProductPage, MerchantRating, MerchantReputation, Order, OrderStats, PaymentConfig,
+    PriceIndexNode, ProductBase, ProductExtended, ProductRating, ProductVariant, RecencyBucket,
+    RecencyFeedHead, Review, SalesIndexNode, TokenOrderStats, TrendingBoard, TrendingScore,
+    UserPurchaseCount,
+};
+use solana_e_commerce::{LegacySystemConfig, SystemConfig};
+
+/// Decode a Borsh account of a known type, checking its discriminator
+pub fn decode<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut &data[..])
+}
+
+/// Decode an account whose layout gained trailing fields, reading the legacy layout `L` from
+/// accounts of `legacy_space` bytes or recorded without their unused tail
+pub fn decode_migrated<T, L>(data: &[u8], legacy_space: usize) -> Result<T>
+where
+    T: AccountDeserialize + Discriminator + From<L>,
+    L: AnchorDeserialize,
+{
+    let decode_legacy = || {
+        L::deserialize(&mut &data[T::DISCRIMINATOR.len()..])
+            .map(T::from)
+            .map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+    };
+    if !data.starts_with(T::DISCRIMINATOR) {
+        return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+    }
+    if data.len() == legacy_space {
+        return decode_legacy();
+    }
+    decode::<T>(data).or_else(|_| decode_legacy())
+}
+
+/// Zero-copy index shard with its entries in (value, product_id) order
+#[derive(Clone)]
+pub struct IndexShardAccount {
+    pub header: IndexShard,
+    pub entries: Vec<IndexEntry>,
+}
+
+impl IndexShardAccount {
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        if !data.starts_with(IndexShard::DISCRIMINATOR) {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        if data.len() < IndexShard::ENTRIES_OFFSET {
+            return Err(ErrorCode::AccountDidNotDeserialize.into());
+        }
+
+        // Account data carries no alignment guarantee off-chain
+        let header: IndexShard = bytemuck::pod_read_unaligned(&data[8..IndexShard::ENTRIES_OFFSET]);
+        let entries = &data[IndexShard::ENTRIES_OFFSET..];
+        if header.len as usize > entries.len() / solana_e_commerce::state::INDEX_ENTRY_SIZE {
+            return Err(ErrorCode::AccountDidNotDeserialize.into());
+        }
+
+        let entries = (0..header.len as usize)
+            .map(|index| IndexEntries::get(entries, index))
+            .collect();
+
+        Ok(Self { header, entries })
+    }
+
+    pub fn product_ids(&self) -> Vec<u64> {
+        self.entries.iter().map(|entry| entry.product_id).collect()
+    }
+}
+
+/// Any account owned by the program
+#[derive(Clone)]
+pub enum ShopAccount {
+    SystemConfig(SystemConfig),
+    GlobalIdRoot(GlobalIdRoot),
+    MerchantIdAccount(MerchantIdAccount),
+    IdChunk(IdChunk),
+    Merchant(Merchant),
+    MerchantProductList(MerchantProductList),
+    MerchantProductPage(MerchantProductPage),
+    ProductBase(ProductBase),
+    ProductExtended(ProductExtended),
+    ProductVariant(ProductVariant),
+    PaymentConfig(PaymentConfig),
+    EscrowAccount(EscrowAccount),
+    EscrowLedger(EscrowLedger),
+    Order(Order),
+    OrderStats(OrderStats),
+    TokenOrderStats(TokenOrderStats),
+    MerchantOrderStats(MerchantOrderStats),
+    DailyOrderStats(DailyOrderStats),
+    UserPurchaseCount(UserPurchaseCount),
+    MerchantOrder(MerchantOrder),
+    MerchantOrderCount(MerchantOrderCount),
+    Review(Review),
+    ProductRating(ProductRating),
+    MerchantRating(MerchantRating),
+    MerchantReputation(MerchantReputation),
+    KeywordRoot(KeywordRoot),
+    KeywordShard(KeywordShard),
+    PriceIndexNode(PriceIndexNode),
+    SalesIndexNode(SalesIndexNode),
+    IndexShard(IndexShardAccount),
+    RecencyFeedHead(RecencyFeedHead),
+    RecencyBucket(RecencyBucket),
+    TrendingScore(TrendingScore),
+    TrendingBoard(TrendingBoard),
+    Leaderboard(Leaderboard),
+}
+
+macro_rules! decode_by_discriminator {
+    ($data:expr, [$($ty:ident),* $(,)?]) => {
+        $(
+            if $data.starts_with($ty::DISCRIMINATOR) {
+                return decode::<$ty>($data).map(ShopAccount::$ty);
+            }
+        )*
+    };
+}
+
+impl ShopAccount {
+    /// Decode an account by its discriminator
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        if data.starts_with(IndexShard::DISCRIMINATOR) {
+            return IndexShardAccount::decode(data).map(ShopAccount::IndexShard);
+        }
+        if data.starts_with(SystemConfig::DISCRIMINATOR) {
+            return decode_migrated::<SystemConfig, LegacySystemConfig>(
+                data,
+                LegacySystemConfig::SPACE,
+            )
+            .map(ShopAccount::SystemConfig);
+        }
+        // Products and orders created before product variants
+        if data.starts_with(ProductBase::DISCRIMINATOR) {
+            return decode_migrated::<ProductBase, LegacyProductBase>(
+                data,
+                LegacyProductBase::SPACE,
+            )
+            .map(ShopAccount::ProductBase);
+        }
+        if data.starts_with(Order::DISCRIMINATOR) {
+            return decode_migrated::<Order, LegacyOrder>(data, LegacyOrder::SPACE)
+                .map(ShopAccount::Order);
+        }
+
+        decode_by_discriminator!(
+            data,
+            [
+                GlobalIdRoot,
+                MerchantIdAccount,
+                IdChunk,
+                Merchant,
+                MerchantProductList,
+                MerchantProductPage,
+                ProductExtended,
+                ProductVariant,
+                PaymentConfig,
+                EscrowAccount,
+                EscrowLedger,
+                OrderStats,
+                TokenOrderStats,
+                MerchantOrderStats,
+                DailyOrderStats,
+                UserPurchaseCount,
+                MerchantOrder,
+                MerchantOrderCount,
+                Review,
+                ProductRating,
+                MerchantRating,
+                MerchantReputation,
+                KeywordRoot,
+                KeywordShard,
+                PriceIndexNode,
+                SalesIndexNode,
+                RecencyFeedHead,
+                RecencyBucket,
+                TrendingScore,
+                TrendingBoard,
+                Leaderboard,
+            ]
+        );
+
+        Err(ErrorCode::AccountDiscriminatorNotFound.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+    use anchor_lang::{AccountSerialize, AnchorSerialize};
+
+    #[test]
+    fn test_decode_borsh_and_zero_copy_accounts() {
+        let owner = Pubkey::new_unique();
+        let merchant = Merchant {
+            owner,
+            name: "Shop".to_string(),
+            description: String::new(),
+            product_count: 3,
+            total_sales: 0,
+            is_active: true,
+            created_at: 0,
+            updated_at: 0,
+            deposit_amount: 0,
+            deposit_token_mint: Pubkey::default(),
+            deposit_locked: 0,
+            deposit_updated_at: 0,
+            bump: 255,
+        };
+        let mut data = Vec::new();
+        merchant.try_serialize(&mut data).unwrap();
+        match ShopAccount::decode(&data).unwrap() {
+            ShopAccount::Merchant(decoded) => {
+                assert_eq!(decoded.owner, owner);
+                assert_eq!(decoded.product_count, 3);
+            }
+            _ => panic!("expected a merchant"),
+        }
+
+        // Price shard with capacity 4 holding 2 entries, inserted out of order
+        let mut data = vec![0u8; IndexShard::space(4)];
+        data[..8].copy_from_slice(IndexShard::DISCRIMINATOR);
+        let header = IndexShard {
+            key: [7; 32],
+            range_start: 1_000,
+            range_end: 1_999,
+            shard_index: 0,
+            len: 2,
+            capacity: 4,
+            migrated_entries: 0,
+            kind: 1,
+            key_len: 0,
+            bump: 254,
+            _padding: [0; 5],
+        };
+        data[8..IndexShard::ENTRIES_OFFSET].copy_from_slice(bytemuck::bytes_of(&header));
+        let entries = &mut data[IndexShard::ENTRIES_OFFSET..];
+        IndexEntries::insert(
+            entries,
+            0,
+            IndexEntry {
+                value: 1_500,
+                product_id: 10_002,
+            },
+        );
+        IndexEntries::insert(
+            entries,
+            1,
+            IndexEntry {
+                value: 1_200,
+                product_id: 10_001,
+            },
+        );
+        match ShopAccount::decode(&data).unwrap() {
+            ShopAccount::IndexShard(shard) => {
+                assert_eq!(shard.header.range_end, 1_999);
+                assert_eq!(shard.product_ids(), vec![10_001, 10_002]);
+            }
+            _ => panic!("expected an index shard"),
+        }
+
+        // A header claiming more entries than the account holds is rejected
+        data[8 + 52..8 + 56].copy_from_slice(&5u32.to_le_bytes());
+        assert!(IndexShardAccount::decode(&data).is_err());
+
+        assert!(ShopAccount::decode(&[0u8; 16]).is_err());
+    }
+
+    #[test]
+    fn test_decode_products_created_before_variants() {
+        let legacy = LegacyProductBase {
+            id: 10_000,
+            merchant: Pubkey::new_unique(),
+            name: "Desk lamp".to_string(),
+            description: String::new(),
+            price: 1_000,
+            keywords: "lamp".to_string(),
+            inventory: 5,
+            sales: 2,
+            is_active: true,
+            created_at: 0,
+            updated_at: 0,
+            payment_token: Pubkey::new_unique(),
+            shipping_location: "Lisbon".to_string(),
+            bump: 255,
+        };
+        let mut data = ProductBase::DISCRIMINATOR.to_vec();
+        legacy.serialize(&mut data).unwrap();
+
+        // Recorded without the unused tail
+        let decoded =
+            decode_migrated::<ProductBase, LegacyProductBase>(&data, LegacyProductBase::SPACE)
+                .unwrap();
+        assert_eq!(
+            (decoded.id, decoded.sales, decoded.variant_count),
+            (10_000, 2, 0)
+        );
+
+        // A legacy-sized account ignores stale bytes where variant_count now lives
+        data.resize(LegacyProductBase::SPACE, 0xFF);
+        match ShopAccount::decode(&data).unwrap() {
+            ShopAccount::ProductBase(decoded) => {
+                assert_eq!(decoded.shipping_location, "Lisbon");
+                assert_eq!(decoded.variant_count, 0);
+            }
+            _ => panic!("expected a product"),
+        }
+    }
+}
@@ -0,0 +1,2959 @@
+//! Instruction builders, one per program instruction
+//!
+//! Builders take the program's own instruction data (`solana_e_commerce::instruction::*`) and
+//! fill the program's account structs (`solana_e_commerce::accounts::*`), deriving every PDA
+//! with [`crate::pda`]. Accounts whose address depends on on-chain state (next sequence numbers,
+//! chunk and page indexes, index nodes) are passed by the caller. Accounts a handler reads from
+//! remaining accounts are appended by the caller to `Instruction::accounts`.
+
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use solana_e_commerce::state::{Order, RecencyFeed};
+use solana_e_commerce::{accounts, instruction, SystemConfig};
+
+use crate::pda;
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: solana_e_commerce::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Append remaining accounts to a built instruction
+pub fn with_remaining_accounts(
+    mut instruction: Instruction,
+    remaining_accounts: impl IntoIterator<Item = AccountMeta>,
+) -> Instruction {
+    instruction.accounts.extend(remaining_accounts);
+    instruction
+}
+
+// ==================== System ====================
+
+pub fn initialize_system(payer: Pubkey, args: instruction::InitializeSystem) -> Instruction {
+    build(
+        accounts::InitializeSystem {
+            payer,
+            global_root: pda::global_id_root().0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn initialize_system_config(
+    payer: Pubkey,
+    args: instruction::InitializeSystemConfig,
+) -> Instruction {
+    build(
+        accounts::InitializeSystemConfig {
+            payer,
+            system_config: pda::system_config().0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn migrate_system_config(authority: Pubkey) -> Instruction {
+    build(
+        accounts::MigrateSystemConfig {
+            system_config: pda::system_config().0,
+            authority,
+            system_program: system_program::ID,
+        },
+        instruction::MigrateSystemConfig {},
+    )
+}
+
+pub fn close_system_config(
+    authority: Pubkey,
+    beneficiary: Pubkey,
+    args: instruction::CloseSystemConfig,
+) -> Instruction {
+    build(
+        accounts::CloseSystemConfig {
+            system_config: pda::system_config().0,
+            beneficiary,
+            authority,
+        },
+        args,
+    )
+}
+
+pub fn force_close_system_config(authority: Pubkey, beneficiary: Pubkey) -> Instruction {
+    build(
+        accounts::ForceCloseSystemConfig {
+            system_config: pda::system_config().0,
+            beneficiary,
+            authority,
+            system_program: system_program::ID,
+        },
+        instruction::ForceCloseSystemConfig {},
+    )
+}
+
+pub fn update_deposit_requirement(
+    authority: Pubkey,
+    args: instruction::UpdateDepositRequirement,
+) -> Instruction {
+    build(
+        accounts::UpdateDepositRequirement {
+            authority,
+            system_config: pda::system_config().0,
+        },
+        args,
+    )
+}
+
+pub fn update_trending_half_life(
+    authority: Pubkey,
+    args: instruction::UpdateTrendingHalfLife,
+) -> Instruction {
+    build(
+        accounts::UpdateTrendingHalfLife {
+            authority,
+            system_config: pda::system_config().0,
+        },
+        args,
+    )
+}
+
+pub fn update_index_shard_capacity(
+    authority: Pubkey,
+    args: instruction::UpdateIndexShardCapacity,
+) -> Instruction {
+    build(
+        accounts::UpdateIndexShardCapacity {
+            authority,
+            system_config: pda::system_config().0,
+        },
+        args,
+    )
+}
+
+// ==================== ID generator ====================
+
+/// `active_chunk` is MerchantIdAccount::active_chunk
+pub fn generate_product_id(merchant: Pubkey, active_chunk: Pubkey) -> Instruction {
+    build(
+        accounts::GenerateId {
+            merchant_account: pda::merchant_id_account(&merchant).0,
+            merchant,
+            active_chunk,
+        },
+        instruction::GenerateProductId {},
+    )
+}
+
+pub fn batch_generate_ids(
+    merchant: Pubkey,
+    active_chunk: Pubkey,
+    args: instruction::BatchGenerateIds,
+) -> Instruction {
+    build(
+        accounts::BatchGenerate {
+            merchant_account: pda::merchant_id_account(&merchant).0,
+            merchant,
+            active_chunk,
+        },
+        args,
+    )
+}
+
+pub fn is_id_exists(
+    merchant: Pubkey,
+    id_chunk: Pubkey,
+    args: instruction::IsIdExists,
+) -> Instruction {
+    build(
+        accounts::VerifyId {
+            merchant_account: pda::merchant_id_account(&merchant).0,
+            merchant,
+            id_chunk,
+        },
+        args,
+    )
+}
+
+/// `last_chunk_index` is MerchantIdAccount::last_chunk_index, the new chunk takes the next index
+pub fn allocate_new_chunk(merchant: Pubkey, payer: Pubkey, last_chunk_index: u32) -> Instruction {
+    build(
+        accounts::AllocateChunk {
+            global_root: pda::global_id_root().0,
+            merchant_account: pda::merchant_id_account(&merchant).0,
+            merchant,
+            new_chunk: pda::id_chunk(&merchant, last_chunk_index + 1).0,
+            payer,
+            system_program: system_program::ID,
+        },
+        instruction::AllocateNewChunk {},
+    )
+}
+
+/// `chunk_index` is the index of the chunk holding `args.id`
+pub fn release_id(merchant: Pubkey, chunk_index: u32, args: instruction::ReleaseId) -> Instruction {
+    let product = pda::product(args.id).0;
+    build(
+        accounts::ReleaseId {
+            merchant,
+            merchant_account: pda::merchant_id_account(&merchant).0,
+            id_chunk: pda::id_chunk(&merchant, chunk_index).0,
+            product,
+        },
+        args,
+    )
+}
+
+/// Remaining accounts: the inactive IdChunk accounts to close (writable)
+pub fn compact_id_chunks(merchant: Pubkey) -> Instruction {
+    build(
+        accounts::CompactIdChunks {
+            merchant,
+            merchant_account: pda::merchant_id_account(&merchant).0,
+        },
+        instruction::CompactIdChunks {},
+    )
+}
+
+pub fn close_id_chunk(beneficiary: Pubkey, args: instruction::CloseIdChunk) -> Instruction {
+    build(
+        accounts::CloseIdChunk {
+            id_chunk: pda::id_chunk(&args.merchant_key, args.chunk_index).0,
+            beneficiary,
+        },
+        args,
+    )
+}
+
+pub fn close_merchant_id_account(
+    beneficiary: Pubkey,
+    args: instruction::CloseMerchantIdAccount,
+) -> Instruction {
+    build(
+        accounts::CloseMerchantIdAccount {
+            merchant_id_account: pda::merchant_id_account(&args.merchant_key).0,
+            beneficiary,
+        },
+        args,
+    )
+}
+
+// ==================== Merchant ====================
+
+pub fn register_merchant_atomic(
+    merchant: Pubkey,
+    payer: Pubkey,
+    args: instruction::RegisterMerchantAtomic,
+) -> Instruction {
+    build(
+        accounts::RegisterMerchantAtomic {
+            merchant,
+            payer,
+            global_root: pda::global_id_root().0,
+            merchant_info: pda::merchant_info(&merchant).0,
+            system_config: pda::system_config().0,
+            merchant_id_account: pda::merchant_id_account(&merchant).0,
+            initial_chunk: pda::id_chunk(&merchant, 0).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn update_merchant_info(owner: Pubkey, args: instruction::UpdateMerchantInfo) -> Instruction {
+    build(
+        accounts::UpdateMerchant {
+            merchant_info: pda::merchant_info(&owner).0,
+            owner,
+        },
+        args,
+    )
+}
+
+/// Merchant statistics view, append [`pda::merchant_order_stats`] of each payment token to break
+/// the orders down by
+pub fn get_merchant_stats(owner: Pubkey) -> Instruction {
+    build(
+        accounts::GetMerchantStats {
+            merchant_info: pda::merchant_info(&owner).0,
+            merchant_product_list: pda::merchant_product_list(&owner).0,
+            owner,
+        },
+        instruction::GetMerchantStats {},
+    )
+}
+
+pub fn close_merchant(
+    owner: Pubkey,
+    beneficiary: Pubkey,
+    args: instruction::CloseMerchant,
+) -> Instruction {
+    build(
+        accounts::CloseMerchant {
+            merchant_info: pda::merchant_info(&owner).0,
+            beneficiary,
+            owner,
+        },
+        args,
+    )
+}
+
+pub fn get_merchant_products(args: instruction::GetMerchantProducts) -> Instruction {
+    build(
+        accounts::GetMerchantProducts {
+            merchant_product_list: pda::merchant_product_list(&args.merchant).0,
+            product_page: pda::merchant_product_page(
+                &args.merchant,
+                args.is_active,
+                args.page_index,
+            )
+            .0,
+        },
+        args,
+    )
+}
+
+// ==================== Deposit ====================
+
+pub fn manage_deposit(
+    merchant_owner: Pubkey,
+    merchant_token_account: Pubkey,
+    deposit_token_mint: Pubkey,
+    args: instruction::ManageDeposit,
+) -> Instruction {
+    build(
+        accounts::ManageDeposit {
+            merchant_owner,
+            merchant: pda::merchant_info(&merchant_owner).0,
+            system_config: pda::system_config().0,
+            merchant_token_account,
+            deposit_token_mint,
+            deposit_escrow_account: pda::deposit_escrow(&deposit_token_mint).0,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn withdraw_merchant_deposit(
+    signer: Pubkey,
+    merchant_owner: Pubkey,
+    recipient_token_account: Pubkey,
+    deposit_token_mint: Pubkey,
+    args: instruction::WithdrawMerchantDeposit,
+) -> Instruction {
+    build(
+        accounts::WithdrawMerchantDeposit {
+            signer,
+            merchant: pda::merchant_info(&merchant_owner).0,
+            merchant_owner,
+            system_config: pda::system_config().0,
+            recipient_token_account,
+            deposit_token_mint,
+            deposit_escrow_account: pda::deposit_escrow(&deposit_token_mint).0,
+            token_program: anchor_spl::token::ID,
+        },
+        args,
+    )
+}
+
+pub fn deduct_merchant_deposit(
+    authority: Pubkey,
+    merchant_owner: Pubkey,
+    deposit_token_mint: Pubkey,
+    admin_token_account: Pubkey,
+    args: instruction::DeductMerchantDeposit,
+) -> Instruction {
+    build(
+        accounts::DeductMerchantDeposit {
+            authority,
+            merchant: pda::merchant_info(&merchant_owner).0,
+            merchant_owner,
+            system_config: pda::system_config().0,
+            deposit_token_mint,
+            deposit_escrow_account: pda::deposit_escrow(&deposit_token_mint).0,
+            admin_token_account,
+            merchant_reputation: pda::merchant_reputation(&merchant_owner).0,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn get_merchant_deposit_info(
+    merchant_owner: Pubkey,
+    deposit_token_mint: Pubkey,
+) -> Instruction {
+    build(
+        accounts::GetMerchantDepositInfo {
+            merchant: pda::merchant_info(&merchant_owner).0,
+            merchant_owner,
+            system_config: pda::system_config().0,
+            deposit_token_mint,
+        },
+        instruction::GetMerchantDepositInfo {},
+    )
+}
+
+// ==================== Product ====================
+
+/// Create a product with the ID the program is about to generate
+///
+/// `product_id` is the next free ID of `active_chunk` (or the first ID of the next chunk when the
+/// active one is full), `last_chunk_index` is MerchantIdAccount::last_chunk_index and
+/// `active_page_index` is MerchantProductList::next_page_index(true). `args.day` is the current day
+/// of the global NewArrivals feed.
+pub fn create_product_base(
+    merchant: Pubkey,
+    active_chunk: Pubkey,
+    last_chunk_index: u32,
+    product_id: u64,
+    active_page_index: u64,
+    args: instruction::CreateProductBase,
+) -> Instruction {
+    build(
+        accounts::CreateProductBase {
+            merchant,
+            global_root: pda::global_id_root().0,
+            merchant_id_account: pda::merchant_id_account(&merchant).0,
+            merchant_info: pda::merchant_info(&merchant).0,
+            active_chunk,
+            next_chunk: pda::id_chunk(&merchant, last_chunk_index + 1).0,
+            payment_config: pda::payment_config().0,
+            product_account: pda::product(product_id).0,
+            merchant_product_list: pda::merchant_product_list(&merchant).0,
+            active_product_page: pda::merchant_product_page(&merchant, true, active_page_index).0,
+            new_arrivals_head: pda::recency_head(RecencyFeed::NewArrivals, "").0,
+            new_arrivals_bucket: pda::recency_bucket(RecencyFeed::NewArrivals, "", args.day).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn create_product_extended(
+    merchant: Pubkey,
+    args: instruction::CreateProductExtended,
+) -> Instruction {
+    build(
+        accounts::CreateProductExtended {
+            merchant,
+            product_extended: pda::product_extended(args.product_id).0,
+            product_base: pda::product(args.product_id).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `with_extended` passes the ProductExtended account, required when updating extended fields.
+/// `args.day` is the current day of the global RecentlyUpdated feed.
+pub fn update_product(
+    merchant: Pubkey,
+    with_extended: bool,
+    args: instruction::UpdateProduct,
+) -> Instruction {
+    build(
+        accounts::UpdateProduct {
+            merchant,
+            product: pda::product(args.product_id).0,
+            product_extended: with_extended.then(|| pda::product_extended(args.product_id).0),
+            payment_config: pda::payment_config().0,
+            recently_updated_head: pda::recency_head(RecencyFeed::RecentlyUpdated, "").0,
+            recently_updated_bucket: pda::recency_bucket(
+                RecencyFeed::RecentlyUpdated,
+                "",
+                args.day,
+            )
+            .0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Soft or hard delete a product
+///
+/// `inactive_page_index` is MerchantProductList::next_page_index(false), `current_page` the
+/// (is_active, page_index) of the catalog page holding the product and `chunk_index` the ID chunk
+/// of the product (hard delete releases the ID through it).
+///
+/// Remaining accounts (hard delete): the product's variants, variant IDs 0..variant_count (writable)
+pub fn delete_product(
+    merchant: Pubkey,
+    beneficiary: Pubkey,
+    inactive_page_index: u64,
+    current_page: Option<(bool, u64)>,
+    chunk_index: Option<u32>,
+    args: instruction::DeleteProduct,
+) -> Instruction {
+    build(
+        accounts::DeleteProduct {
+            merchant,
+            merchant_info: pda::merchant_info(&merchant).0,
+            product: pda::product(args.product_id).0,
+            beneficiary,
+            merchant_product_list: pda::merchant_product_list(&merchant).0,
+            current_product_page: current_page.map(|(is_active, page_index)| {
+                pda::merchant_product_page(&merchant, is_active, page_index).0
+            }),
+            inactive_product_page: pda::merchant_product_page(
+                &merchant,
+                false,
+                inactive_page_index,
+            )
+            .0,
+            id_chunk: chunk_index.map(|chunk_index| pda::id_chunk(&merchant, chunk_index).0),
+            product_extended: pda::product_extended(args.product_id).0,
+            product_rating: pda::product_rating(args.product_id).0,
+            trending_score: pda::trending_score(args.product_id).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Remaining accounts: leaderboards including the product (writable)
+pub fn update_product_price(
+    merchant: Pubkey,
+    args: instruction::UpdateProductPrice,
+) -> Instruction {
+    build(
+        accounts::UpdateProductPrice {
+            merchant,
+            product: pda::product(args.product_id).0,
+        },
+        args,
+    )
+}
+
+/// `variant_id` is the product's current `variant_count`, the ID the new variant receives
+pub fn create_product_variant(
+    merchant: Pubkey,
+    variant_id: u32,
+    args: instruction::CreateProductVariant,
+) -> Instruction {
+    build(
+        accounts::CreateProductVariant {
+            merchant,
+            product: pda::product(args.product_id).0,
+            product_variant: pda::product_variant(args.product_id, variant_id).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn update_product_variant(
+    merchant: Pubkey,
+    args: instruction::UpdateProductVariant,
+) -> Instruction {
+    build(
+        accounts::UpdateProductVariant {
+            merchant,
+            product: pda::product(args.product_id).0,
+            product_variant: pda::product_variant(args.product_id, args.variant_id).0,
+        },
+        args,
+    )
+}
+
+pub fn update_product_variant_price(
+    merchant: Pubkey,
+    args: instruction::UpdateProductVariantPrice,
+) -> Instruction {
+    build(
+        accounts::UpdateProductVariant {
+            merchant,
+            product: pda::product(args.product_id).0,
+            product_variant: pda::product_variant(args.product_id, args.variant_id).0,
+        },
+        args,
+    )
+}
+
+/// Remaining accounts: leaderboards including the product (writable)
+pub fn migrate_product_base(payer: Pubkey, args: instruction::MigrateProductBase) -> Instruction {
+    build(
+        accounts::MigrateProductBase {
+            product: pda::product(args.product_id).0,
+            payer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn update_sales_count(authority: Pubkey, args: instruction::UpdateSalesCount) -> Instruction {
+    build(
+        accounts::UpdateSales {
+            authority,
+            system_config: pda::system_config().0,
+            product: pda::product(args.product_id).0,
+        },
+        args,
+    )
+}
+
+// ==================== Recency feeds ====================
+
+/// `newer_day` is the day of the bucket following `args.day` in the previous_day chain, required
+/// when recording in a day older than the newest bucket of the feed
+pub fn record_recent_product(
+    payer: Pubkey,
+    newer_day: Option<u32>,
+    args: instruction::RecordRecentProduct,
+) -> Instruction {
+    build(
+        accounts::RecordRecentProduct {
+            product: pda::product(args.product_id).0,
+            feed_head: pda::recency_head(args.feed, &args.keyword).0,
+            bucket: pda::recency_bucket(args.feed, &args.keyword, args.day).0,
+            newer_bucket: newer_day.map(|day| pda::recency_bucket(args.feed, &args.keyword, day).0),
+            payer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Remaining accounts: RecencyBucket accounts from the cursor day backwards
+pub fn get_recent_products(args: instruction::GetRecentProducts) -> Instruction {
+    build(
+        accounts::GetRecentProducts {
+            feed_head: pda::recency_head(args.feed, &args.keyword).0,
+        },
+        args,
+    )
+}
+
+// ==================== Payment ====================
+
+pub fn initialize_payment_system(
+    authority: Pubkey,
+    args: instruction::InitializePaymentSystem,
+) -> Instruction {
+    build(
+        accounts::InitializePaymentSystem {
+            payment_config: pda::payment_config().0,
+            authority,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn update_supported_tokens(
+    authority: Pubkey,
+    args: instruction::UpdateSupportedTokens,
+) -> Instruction {
+    build(
+        accounts::UpdatePaymentConfig {
+            payment_config: pda::payment_config().0,
+            authority,
+        },
+        args,
+    )
+}
+
+pub fn update_fee_rate(authority: Pubkey, args: instruction::UpdateFeeRate) -> Instruction {
+    build(
+        accounts::UpdatePaymentConfig {
+            payment_config: pda::payment_config().0,
+            authority,
+        },
+        args,
+    )
+}
+
+pub fn close_payment_config(
+    authority: Pubkey,
+    beneficiary: Pubkey,
+    args: instruction::ClosePaymentConfig,
+) -> Instruction {
+    build(
+        accounts::ClosePaymentConfig {
+            payment_config: pda::payment_config().0,
+            beneficiary,
+            authority,
+        },
+        args,
+    )
+}
+
+pub fn initialize_program_token_account(
+    authority: Pubkey,
+    payment_token_mint: Pubkey,
+) -> Instruction {
+    build(
+        accounts::InitializeProgramTokenAccount {
+            program_token_account: pda::program_token_account(&payment_token_mint).0,
+            program_authority: pda::program_authority().0,
+            payment_token_mint,
+            authority,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::InitializeProgramTokenAccount {},
+    )
+}
+
+/// `variant_id` is required when the product has variants
+pub fn purchase_product_escrow(
+    buyer: Pubkey,
+    buyer_token_account: Pubkey,
+    payment_token_mint: Pubkey,
+    variant_id: Option<u32>,
+    args: instruction::PurchaseProductEscrow,
+) -> Instruction {
+    build(
+        accounts::PurchaseProductEscrow {
+            buyer,
+            product: pda::product(args.product_id).0,
+            product_variant: variant_id
+                .map(|variant_id| pda::product_variant(args.product_id, variant_id).0),
+            program_token_account: pda::program_token_account(&payment_token_mint).0,
+            escrow_ledger: pda::escrow_ledger(&payment_token_mint).0,
+            program_authority: pda::program_authority().0,
+            buyer_token_account,
+            payment_token_mint,
+            token_program: anchor_spl::token::ID,
+        },
+        args,
+    )
+}
+
+pub fn initialize_escrow_ledger(authority: Pubkey, payment_token_mint: Pubkey) -> Instruction {
+    build(
+        accounts::InitializeEscrowLedger {
+            escrow_ledger: pda::escrow_ledger(&payment_token_mint).0,
+            program_token_account: pda::program_token_account(&payment_token_mint).0,
+            payment_token_mint,
+            system_config: pda::system_config().0,
+            authority,
+            system_program: system_program::ID,
+        },
+        instruction::InitializeEscrowLedger {},
+    )
+}
+
+pub fn verify_escrow_solvency(payment_token_mint: Pubkey) -> Instruction {
+    build(
+        accounts::VerifyEscrowSolvency {
+            escrow_ledger: pda::escrow_ledger(&payment_token_mint).0,
+            program_token_account: pda::program_token_account(&payment_token_mint).0,
+        },
+        instruction::VerifyEscrowSolvency {},
+    )
+}
+
+pub fn enforce_escrow_solvency(authority: Pubkey, payment_token_mint: Pubkey) -> Instruction {
+    build(
+        accounts::EnforceEscrowSolvency {
+            escrow_ledger: pda::escrow_ledger(&payment_token_mint).0,
+            program_token_account: pda::program_token_account(&payment_token_mint).0,
+            system_config: pda::system_config().0,
+            authority,
+        },
+        instruction::EnforceEscrowSolvency {},
+    )
+}
+
+// ==================== Keyword index ====================
+
+pub fn initialize_keyword_index(
+    payer: Pubkey,
+    args: instruction::InitializeKeywordIndex,
+) -> Instruction {
+    build(
+        accounts::InitializeKeywordIndexIfNeeded {
+            global_root: pda::global_id_root().0,
+            keyword_root: pda::keyword_root(&args.keyword).0,
+            first_shard: pda::keyword_shard(&args.keyword, 0).0,
+            payer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn add_product_to_keyword_index(
+    payer: Pubkey,
+    args: instruction::AddProductToKeywordIndex,
+) -> Instruction {
+    build(
+        accounts::AddProductToKeywordIndexIfNeeded {
+            global_root: pda::global_id_root().0,
+            keyword_root: pda::keyword_root(&args.keyword).0,
+            target_shard: pda::keyword_shard(&args.keyword, 0).0,
+            payer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn remove_product_from_keyword_index(
+    args: instruction::RemoveProductFromKeywordIndex,
+) -> Instruction {
+    build(
+        accounts::RemoveProductFromKeywordIndex {
+            keyword_root: pda::keyword_root(&args.keyword).0,
+            target_shard: pda::keyword_shard(&args.keyword, 0).0,
+        },
+        args,
+    )
+}
+
+pub fn get_keyword_bloom_summary(args: instruction::GetKeywordBloomSummary) -> Instruction {
+    build(
+        accounts::GetKeywordBloomSummary {
+            keyword_root: pda::keyword_root(&args.keyword).0,
+        },
+        args,
+    )
+}
+
+pub fn create_keyword_shard(payer: Pubkey, args: instruction::CreateKeywordShard) -> Instruction {
+    build(
+        accounts::CreateKeywordShard {
+            keyword_root: pda::keyword_root(&args.keyword).0,
+            prev_shard: pda::keyword_shard(&args.keyword, args.shard_index - 1).0,
+            new_shard: pda::keyword_shard(&args.keyword, args.shard_index).0,
+            payer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `outer_shard_index` is the shard on the other side of the source shard, when there is one
+pub fn merge_keyword_shards(
+    beneficiary: Pubkey,
+    outer_shard_index: Option<u32>,
+    args: instruction::MergeKeywordShards,
+) -> Instruction {
+    build(
+        accounts::MergeKeywordShards {
+            keyword_root: pda::keyword_root(&args.keyword).0,
+            source_shard: pda::keyword_shard(&args.keyword, args.source_shard_index).0,
+            target_shard: pda::keyword_shard(&args.keyword, args.target_shard_index).0,
+            outer_shard: outer_shard_index
+                .map(|shard_index| pda::keyword_shard(&args.keyword, shard_index).0),
+            beneficiary,
+        },
+        args,
+    )
+}
+
+pub fn close_keyword_root(beneficiary: Pubkey, args: instruction::CloseKeywordRoot) -> Instruction {
+    build(
+        accounts::CloseKeywordRoot {
+            keyword_root: pda::keyword_root(&args.keyword).0,
+            beneficiary,
+        },
+        args,
+    )
+}
+
+/// The keyword's shards are appended by the caller in chain order
+pub fn migrate_keyword_root(
+    authority: Pubkey,
+    args: instruction::MigrateKeywordRoot,
+) -> Instruction {
+    build(
+        accounts::MigrateKeywordRoot {
+            keyword_root: pda::keyword_root(&args.keyword).0,
+            global_root: pda::global_id_root().0,
+            system_config: pda::system_config().0,
+            authority,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn close_keyword_shard(
+    beneficiary: Pubkey,
+    args: instruction::CloseKeywordShard,
+) -> Instruction {
+    build(
+        accounts::CloseKeywordShard {
+            keyword_shard: pda::keyword_shard(&args.keyword, args.shard_index).0,
+            beneficiary,
+        },
+        args,
+    )
+}
+
+// ==================== Price index ====================
+
+/// `payment_mint` is the payment token of the product
+pub fn add_product_to_price_index(
+    payer: Pubkey,
+    payment_mint: Pubkey,
+    args: instruction::AddProductToPriceIndex,
+) -> Instruction {
+    build(
+        accounts::AddProductToPriceIndex {
+            payer,
+            product: pda::product(args.product_id).0,
+            payment_mint,
+            price_index: pda::price_index(
+                &payment_mint,
+                args.price_range_start,
+                args.price_range_end,
+            )
+            .0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Remaining accounts: ancestors of the node from its parent up to the bucket root (writable)
+pub fn remove_product_from_price_index(
+    payment_token: Pubkey,
+    price_range_start: u64,
+    price_range_end: u64,
+    args: instruction::RemoveProductFromPriceIndex,
+) -> Instruction {
+    build(
+        accounts::RemoveProductFromPriceIndex {
+            price_node: pda::price_index(&payment_token, price_range_start, price_range_end).0,
+        },
+        args,
+    )
+}
+
+/// Remaining accounts: ancestors of the node from its parent up to the bucket root (writable)
+pub fn split_price_node(
+    payer: Pubkey,
+    payment_token: Pubkey,
+    args: instruction::SplitPriceNode,
+) -> Instruction {
+    build(
+        accounts::SplitPriceNode {
+            price_node: pda::price_index(
+                &payment_token,
+                args.price_range_start,
+                args.price_range_end,
+            )
+            .0,
+            left_node: pda::price_index(
+                &payment_token,
+                args.price_range_start,
+                args.split_price.saturating_sub(1),
+            )
+            .0,
+            right_node: pda::price_index(&payment_token, args.split_price, args.price_range_end).0,
+            payer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Remaining accounts: ProductBase accounts of the moved products
+pub fn migrate_price_node(
+    payer: Pubkey,
+    payment_mint: Pubkey,
+    args: instruction::MigratePriceNode,
+) -> Instruction {
+    build(
+        accounts::MigratePriceNode {
+            legacy_node: pda::legacy_price_index(args.legacy_range_start, args.legacy_range_end).0,
+            payment_mint,
+            price_node: pda::price_index(
+                &payment_mint,
+                args.price_range_start,
+                args.price_range_end,
+            )
+            .0,
+            payer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Remaining accounts: further leaf nodes of the range, contiguous in ascending range order. A
+/// bucket root that was never created is passed by its address.
+pub fn search_price_range(price_node: Pubkey, args: instruction::SearchPriceRange) -> Instruction {
+    build(accounts::SearchPriceRange { price_node }, args)
+}
+
+// ==================== Sales index ====================
+
+pub fn initialize_sales_index(
+    payer: Pubkey,
+    args: instruction::InitializeSalesIndex,
+) -> Instruction {
+    build(
+        accounts::InitializeSalesIndexIfNeeded {
+            payer,
+            sales_index: pda::sales_index(args.sales_range_start, args.sales_range_end).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn add_product_to_sales_index(
+    payer: Pubkey,
+    args: instruction::AddProductToSalesIndex,
+) -> Instruction {
+    build(
+        accounts::AddProductToSalesIndexIfNeeded {
+            payer,
+            sales_index: pda::sales_index(args.sales_range_start, args.sales_range_end).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn remove_product_from_sales_index(
+    sales_range_start: u32,
+    sales_range_end: u32,
+    args: instruction::RemoveProductFromSalesIndex,
+) -> Instruction {
+    build(
+        accounts::RemoveProductFromSalesIndex {
+            sales_node: pda::sales_index(sales_range_start, sales_range_end).0,
+        },
+        args,
+    )
+}
+
+pub fn update_product_sales_index(
+    authority: Pubkey,
+    old_sales_node: Pubkey,
+    new_sales_node: Pubkey,
+    args: instruction::UpdateProductSalesIndex,
+) -> Instruction {
+    build(
+        accounts::UpdateProductSalesIndex {
+            old_sales_node,
+            new_sales_node,
+            system_config: pda::system_config().0,
+            authority,
+        },
+        args,
+    )
+}
+
+// ==================== Search ====================
+
+/// Remaining accounts: `index_account_count` index accounts followed by the ProductBase accounts
+/// of the candidates starting at the cursor. Price bounds and SortKey::Price need price index
+/// accounts of a single mint.
+pub fn search_products(args: instruction::SearchProducts) -> Instruction {
+    build(accounts::SearchProducts {}, args)
+}
+
+// ==================== Orders ====================
+
+pub fn initialize_order_stats(authority: Pubkey) -> Instruction {
+    build(
+        accounts::InitializeOrderStats {
+            order_stats: pda::order_stats().0,
+            authority,
+            system_program: system_program::ID,
+        },
+        instruction::InitializeOrderStats {},
+    )
+}
+
+/// Create an order
+///
+/// `purchase_sequence` is UserPurchaseCount::purchase_count + 1 of the buyer and
+/// `merchant_order_sequence` MerchantOrderCount::total_orders + 1 of the merchant (both 1 before
+/// the counters exist). `payment_token` is the product's payment token. `variant_id` is required
+/// when the product has variants.
+pub fn create_order(
+    buyer: Pubkey,
+    merchant_owner: Pubkey,
+    payment_token: Pubkey,
+    purchase_sequence: u64,
+    merchant_order_sequence: u64,
+    variant_id: Option<u32>,
+    args: instruction::CreateOrder,
+) -> Instruction {
+    build(
+        accounts::CreateOrder {
+            user_purchase_count: pda::user_purchase_count(&buyer).0,
+            order: pda::buyer_order(&buyer, purchase_sequence).0,
+            order_stats: pda::order_stats().0,
+            token_order_stats: pda::token_order_stats(&payment_token).0,
+            merchant_order_stats: pda::merchant_order_stats(&merchant_owner, &payment_token).0,
+            daily_order_stats: pda::daily_order_stats(&payment_token, args.day).0,
+            product: pda::product(args.product_id).0,
+            product_variant: variant_id
+                .map(|variant_id| pda::product_variant(args.product_id, variant_id).0),
+            merchant: pda::merchant_info(&merchant_owner).0,
+            merchant_order_count: pda::merchant_order_count(&merchant_owner).0,
+            merchant_order: pda::merchant_order(&merchant_owner, merchant_order_sequence).0,
+            buyer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn migrate_order(payer: Pubkey, order: Pubkey) -> Instruction {
+    build(
+        accounts::MigrateOrder {
+            order,
+            payer,
+            system_program: system_program::ID,
+        },
+        instruction::MigrateOrder {},
+    )
+}
+
+pub fn ship_order(
+    order: Pubkey,
+    order_account: &Order,
+    args: instruction::ShipOrder,
+) -> Instruction {
+    let merchant_owner = order_account.merchant;
+    build(
+        accounts::ShipOrder {
+            order,
+            order_stats: pda::order_stats().0,
+            token_order_stats: pda::token_order_stats(&order_account.payment_token).0,
+            merchant_order_stats: pda::merchant_order_stats(
+                &merchant_owner,
+                &order_account.payment_token,
+            )
+            .0,
+            merchant_reputation: pda::merchant_reputation(&merchant_owner).0,
+            merchant: pda::merchant_info(&merchant_owner).0,
+            authority: merchant_owner,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn refund_order(
+    order: Pubkey,
+    order_account: &Order,
+    buyer_token_account: Pubkey,
+    args: instruction::RefundOrder,
+) -> Instruction {
+    build(
+        accounts::RefundOrder {
+            order,
+            product_variant: order_account
+                .variant_id
+                .map(|variant_id| pda::product_variant(order_account.product_id, variant_id).0),
+            order_stats: pda::order_stats().0,
+            token_order_stats: pda::token_order_stats(&order_account.payment_token).0,
+            merchant_order_stats: pda::merchant_order_stats(
+                &order_account.merchant,
+                &order_account.payment_token,
+            )
+            .0,
+            merchant_reputation: pda::merchant_reputation(&order_account.merchant).0,
+            daily_order_stats: pda::daily_order_stats(&order_account.payment_token, args.day).0,
+            program_token_account: pda::program_token_account(&order_account.payment_token).0,
+            escrow_ledger: pda::escrow_ledger(&order_account.payment_token).0,
+            buyer_token_account,
+            program_authority: pda::program_authority().0,
+            payment_token_mint: order_account.payment_token,
+            buyer: order_account.buyer,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Merchant approved refund of a delivered order
+///
+/// `old_sales_node` and `new_sales_node` cover the product's sales before and after the refund:
+/// the legacy sales nodes, or the zero-copy sales shards of ranges whose node has migrated. The
+/// sales index is left alone when either is None.
+/// Remaining accounts: keyword trending lists, category and payment token leaderboards of the
+/// product (writable).
+pub fn refund_delivered_order(
+    order: Pubkey,
+    order_account: &Order,
+    old_sales_node: Option<Pubkey>,
+    new_sales_node: Option<Pubkey>,
+    buyer_token_account: Pubkey,
+    args: instruction::RefundDeliveredOrder,
+) -> Instruction {
+    build(
+        accounts::RefundDeliveredOrder {
+            order,
+            order_stats: pda::order_stats().0,
+            token_order_stats: pda::token_order_stats(&order_account.payment_token).0,
+            merchant_order_stats: pda::merchant_order_stats(
+                &order_account.merchant,
+                &order_account.payment_token,
+            )
+            .0,
+            merchant_reputation: pda::merchant_reputation(&order_account.merchant).0,
+            daily_order_stats: pda::daily_order_stats(&order_account.payment_token, args.day).0,
+            merchant_info: pda::merchant_info(&order_account.merchant).0,
+            product: pda::product(order_account.product_id).0,
+            old_sales_node,
+            new_sales_node,
+            trending_score: pda::trending_score(order_account.product_id).0,
+            trending_board: pda::trending_global().0,
+            leaderboard: pda::leaderboard(&solana_e_commerce::state::LeaderboardScope::Global).0,
+            system_config: pda::system_config().0,
+            deposit_token_mint: order_account.payment_token,
+            deposit_escrow_account: pda::deposit_escrow(&order_account.payment_token).0,
+            buyer_token_account,
+            merchant_owner: order_account.merchant,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Buyer confirms delivery, settling the order
+///
+/// `old_sales_node` and `new_sales_node` cover the product's sales before and after settlement:
+/// the legacy sales nodes, or the zero-copy sales shards of ranges whose node has migrated. The
+/// sales index is left alone when either is None.
+/// Remaining accounts: keyword trending lists, category and payment token leaderboards of the
+/// product (writable).
+pub fn confirm_delivery(
+    order: Pubkey,
+    order_account: &Order,
+    system_config: &SystemConfig,
+    old_sales_node: Option<Pubkey>,
+    new_sales_node: Option<Pubkey>,
+    args: instruction::ConfirmDelivery,
+) -> Instruction {
+    build(
+        accounts::ConfirmDelivery {
+            order,
+            order_stats: pda::order_stats().0,
+            token_order_stats: pda::token_order_stats(&order_account.payment_token).0,
+            merchant_order_stats: pda::merchant_order_stats(
+                &order_account.merchant,
+                &order_account.payment_token,
+            )
+            .0,
+            merchant_reputation: pda::merchant_reputation(&order_account.merchant).0,
+            daily_order_stats: pda::daily_order_stats(&order_account.payment_token, args.day).0,
+            merchant_info: pda::merchant_info(&order_account.merchant).0,
+            product: pda::product(order_account.product_id).0,
+            old_sales_node,
+            new_sales_node,
+            trending_score: pda::trending_score(order_account.product_id).0,
+            trending_board: pda::trending_global().0,
+            leaderboard: pda::leaderboard(&solana_e_commerce::state::LeaderboardScope::Global).0,
+            system_config: pda::system_config().0,
+            program_token_account: pda::program_token_account(&system_config.deposit_token_mint).0,
+            escrow_ledger: pda::escrow_ledger(&system_config.deposit_token_mint).0,
+            deposit_escrow_account: pda::deposit_escrow(&system_config.deposit_token_mint).0,
+            program_authority: pda::program_authority().0,
+            vault: system_config.vault_account,
+            vault_token_account: system_config.vault_token_account,
+            platform_token_account: system_config.platform_token_account,
+            vault_program: system_config.vault_program_id,
+            buyer: order_account.buyer,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Confirm delivery after the auto confirm period (system call)
+///
+/// Remaining accounts as for [`confirm_delivery`].
+pub fn auto_confirm_delivery(
+    authority: Pubkey,
+    order: Pubkey,
+    order_account: &Order,
+    old_sales_node: Option<Pubkey>,
+    new_sales_node: Option<Pubkey>,
+    args: instruction::AutoConfirmDelivery,
+) -> Instruction {
+    build(
+        accounts::AutoConfirmDelivery {
+            order,
+            order_stats: pda::order_stats().0,
+            token_order_stats: pda::token_order_stats(&order_account.payment_token).0,
+            merchant_order_stats: pda::merchant_order_stats(
+                &order_account.merchant,
+                &order_account.payment_token,
+            )
+            .0,
+            merchant_reputation: pda::merchant_reputation(&order_account.merchant).0,
+            daily_order_stats: pda::daily_order_stats(&order_account.payment_token, args.day).0,
+            merchant: pda::merchant_info(&order_account.merchant).0,
+            product: pda::product(order_account.product_id).0,
+            old_sales_node,
+            new_sales_node,
+            trending_score: pda::trending_score(order_account.product_id).0,
+            trending_board: pda::trending_global().0,
+            leaderboard: pda::leaderboard(&solana_e_commerce::state::LeaderboardScope::Global).0,
+            system_config: pda::system_config().0,
+            authority,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn get_order_stats() -> Instruction {
+    build(
+        accounts::GetOrderStats {
+            order_stats: pda::order_stats().0,
+        },
+        instruction::GetOrderStats {},
+    )
+}
+
+// ==================== Reviews ====================
+
+/// Review a delivered order, signed by its buyer
+pub fn create_review(
+    order: Pubkey,
+    order_account: &Order,
+    args: instruction::CreateReview,
+) -> Instruction {
+    build(
+        accounts::CreateReview {
+            buyer: order_account.buyer,
+            order,
+            review: pda::review(&order).0,
+            product_rating: pda::product_rating(order_account.product_id).0,
+            merchant_rating: pda::merchant_rating(&order_account.merchant).0,
+            merchant_reputation: pda::merchant_reputation(&order_account.merchant).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn reply_to_review(
+    merchant: Pubkey,
+    order: Pubkey,
+    args: instruction::ReplyToReview,
+) -> Instruction {
+    build(
+        accounts::ReplyToReview {
+            merchant,
+            review: pda::review(&order).0,
+        },
+        args,
+    )
+}
+
+// ==================== Reputation ====================
+
+/// Record a dispute the merchant of an order lost (system administrator)
+pub fn record_lost_dispute(
+    authority: Pubkey,
+    order: Pubkey,
+    order_account: &Order,
+    args: instruction::RecordLostDispute,
+) -> Instruction {
+    build(
+        accounts::RecordLostDispute {
+            authority,
+            system_config: pda::system_config().0,
+            order,
+            merchant_reputation: pda::merchant_reputation(&order_account.merchant).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn get_merchant_reputation(owner: Pubkey) -> Instruction {
+    build(
+        accounts::GetMerchantReputation {
+            merchant_reputation: pda::merchant_reputation(&owner).0,
+            owner,
+        },
+        instruction::GetMerchantReputation {},
+    )
+}
+
+// ==================== Rankings ====================
+
+pub fn initialize_keyword_trending_board(
+    payer: Pubkey,
+    args: instruction::InitializeKeywordTrendingBoard,
+) -> Instruction {
+    build(
+        accounts::InitializeKeywordTrendingBoard {
+            trending_board: pda::trending_keyword(&args.keyword).0,
+            payer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `keyword` selects a keyword trending list, the global one without it
+pub fn get_trending_products(
+    keyword: Option<&str>,
+    args: instruction::GetTrendingProducts,
+) -> Instruction {
+    let trending_board = match keyword {
+        Some(keyword) => pda::trending_keyword(keyword).0,
+        None => pda::trending_global().0,
+    };
+    build(
+        accounts::GetTrendingProducts {
+            trending_board,
+            system_config: pda::system_config().0,
+        },
+        args,
+    )
+}
+
+pub fn initialize_leaderboard(
+    payer: Pubkey,
+    args: instruction::InitializeLeaderboard,
+) -> Instruction {
+    build(
+        accounts::InitializeLeaderboard {
+            leaderboard: pda::leaderboard(&args.scope).0,
+            payer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn get_leaderboard(args: instruction::GetLeaderboard) -> Instruction {
+    build(
+        accounts::GetLeaderboard {
+            leaderboard: pda::leaderboard(&args.scope).0,
+        },
+        args,
+    )
+}
+
+// ==================== Zero-copy index shards ====================
+
+pub fn initialize_index_shard(
+    payer: Pubkey,
+    args: instruction::InitializeIndexShard,
+) -> Instruction {
+    build(
+        accounts::InitializeIndexShard {
+            index_shard: pda::index_shard(&args.key).0,
+            payer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `authority` is the merchant of the product or the system administrator
+pub fn add_product_to_index_shard(
+    authority: Pubkey,
+    payer: Pubkey,
+    args: instruction::AddProductToIndexShard,
+) -> Instruction {
+    build(
+        accounts::AddProductToIndexShard {
+            index_shard: pda::index_shard(&args.key).0,
+            product: pda::product(args.product_id).0,
+            system_config: pda::system_config().0,
+            authority,
+            payer,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `authority` is the merchant of the product or the system administrator, any signer once the
+/// product is deleted
+pub fn remove_product_from_index_shard(
+    authority: Pubkey,
+    args: instruction::RemoveProductFromIndexShard,
+) -> Instruction {
+    build(
+        accounts::RemoveProductFromIndexShard {
+            index_shard: pda::index_shard(&args.key).0,
+            product: pda::product(args.product_id).0,
+            system_config: pda::system_config().0,
+            authority,
+        },
+        args,
+    )
+}
+
+pub fn migrate_index_shard(authority: Pubkey, args: instruction::MigrateIndexShard) -> Instruction {
+    build(
+        accounts::MigrateIndexShard {
+            index_shard: pda::index_shard(&args.key).0,
+            legacy_shard: pda::legacy_index_shard(&args.key).0,
+            system_config: pda::system_config().0,
+            authority,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::idl::types::{IdlInstructionAccount, IdlInstructionAccountItem, IdlSeed};
+    use solana_e_commerce::instructions as program;
+    use solana_e_commerce::state::{
+        IndexShardKey, LeaderboardScope, OrderManagementStatus, RecencyFeed, SupportedToken,
+        VariantOption,
+    };
+    use solana_e_commerce::utils::{SearchFilter, SortKey, SortOrder};
+    use std::collections::BTreeMap;
+
+    // Accounts of a program Accounts struct, as generated for the IDL
+    macro_rules! idl_accounts {
+        ($accounts:ident) => {
+            flatten(program::$accounts::__anchor_private_gen_idl_accounts(
+                &mut BTreeMap::new(),
+                &mut BTreeMap::new(),
+            ))
+        };
+    }
+
+    fn flatten(items: Vec<IdlInstructionAccountItem>) -> Vec<IdlInstructionAccount> {
+        items
+            .into_iter()
+            .flat_map(|item| match item {
+                IdlInstructionAccountItem::Single(account) => vec![account],
+                IdlInstructionAccountItem::Composite(accounts) => flatten(accounts.accounts),
+            })
+            .collect()
+    }
+
+    fn address(seeds: &[&[u8]]) -> Pubkey {
+        Pubkey::find_program_address(seeds, &solana_e_commerce::ID).0
+    }
+
+    // Check a builder against the program's Accounts struct: account order, signer and writable
+    // flags, fixed addresses and every PDA the IDL describes. `seed_values` supplies instruction
+    // argument seeds and seeds read from account data, by IDL seed path.
+    fn assert_accounts(
+        ix: &Instruction,
+        expected: Vec<IdlInstructionAccount>,
+        seed_values: &[(&str, &[u8])],
+    ) {
+        assert_eq!(ix.program_id, solana_e_commerce::ID);
+        assert_eq!(
+            ix.accounts.len(),
+            expected.len(),
+            "account count of {:?}",
+            expected
+                .iter()
+                .map(|account| &account.name)
+                .collect::<Vec<_>>()
+        );
+
+        for (meta, account) in ix.accounts.iter().zip(&expected) {
+            if account.optional && meta.pubkey == solana_e_commerce::ID {
+                assert!(!meta.is_signer && !meta.is_writable, "{}", account.name);
+                continue;
+            }
+
+            assert_eq!(meta.is_signer, account.signer, "{} signer", account.name);
+            assert_eq!(
+                meta.is_writable, account.writable,
+                "{} writable",
+                account.name
+            );
+
+            if let Some(fixed) = &account.address {
+                assert_eq!(meta.pubkey.to_string(), *fixed, "{} address", account.name);
+            }
+
+            let Some(pda) = &account.pda else {
+                continue;
+            };
+            let seeds: Vec<Vec<u8>> = pda
+                .seeds
+                .iter()
+                .map(|seed| {
+                    let path = match seed {
+                        IdlSeed::Const(seed) => return seed.value.clone(),
+                        IdlSeed::Arg(seed) => &seed.path,
+                        IdlSeed::Account(seed) => &seed.path,
+                    };
+                    if let Some((_, value)) = seed_values.iter().find(|(name, _)| name == path) {
+                        return value.to_vec();
+                    }
+                    let index = match seed {
+                        IdlSeed::Account(_) => {
+                            expected.iter().position(|other| other.name == *path)
+                        }
+                        _ => None,
+                    };
+                    let index = index.unwrap_or_else(|| {
+                        panic!("no value for seed `{}` of {}", path, account.name)
+                    });
+                    ix.accounts[index].pubkey.to_bytes().to_vec()
+                })
+                .collect();
+            let seeds: Vec<&[u8]> = seeds.iter().map(Vec::as_slice).collect();
+
+            assert_eq!(meta.pubkey, address(&seeds), "{} PDA", account.name);
+        }
+    }
+
+    fn order_account(buyer: Pubkey, merchant: Pubkey, payment_token: Pubkey) -> Order {
+        Order {
+            buyer,
+            merchant,
+            product_id: 10_042,
+            variant_id: None,
+            quantity: 1,
+            price: 1_000,
+            total_amount: 1_000,
+            payment_token,
+            status: OrderManagementStatus::Shipped,
+            shipping_address: String::new(),
+            notes: String::new(),
+            created_at: 0,
+            updated_at: 0,
+            confirmed_at: None,
+            shipped_at: None,
+            delivered_at: None,
+            refunded_at: None,
+            refund_requested_at: None,
+            refund_reason: String::new(),
+            tracking_number: String::new(),
+            transaction_signature: String::new(),
+            merchant_order_pda: Pubkey::default(),
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn test_system_builders_match_accounts() {
+        let authority = Pubkey::new_unique();
+        let beneficiary = Pubkey::new_unique();
+
+        let config = SystemConfig {
+            authority,
+            ..Default::default()
+        };
+        assert_accounts(
+            &initialize_system(
+                authority,
+                instruction::InitializeSystem {
+                    config: config.clone(),
+                },
+            ),
+            idl_accounts!(InitializeSystem),
+            &[],
+        );
+        assert_accounts(
+            &initialize_system_config(authority, instruction::InitializeSystemConfig { config }),
+            idl_accounts!(InitializeSystemConfig),
+            &[],
+        );
+        assert_accounts(
+            &migrate_system_config(authority),
+            idl_accounts!(MigrateSystemConfig),
+            &[],
+        );
+        assert_accounts(
+            &close_system_config(
+                authority,
+                beneficiary,
+                instruction::CloseSystemConfig { force: false },
+            ),
+            idl_accounts!(CloseSystemConfig),
+            &[],
+        );
+        assert_accounts(
+            &force_close_system_config(authority, beneficiary),
+            idl_accounts!(ForceCloseSystemConfig),
+            &[],
+        );
+        assert_accounts(
+            &update_deposit_requirement(
+                authority,
+                instruction::UpdateDepositRequirement {
+                    new_requirement: 500,
+                },
+            ),
+            idl_accounts!(UpdateDepositRequirement),
+            &[],
+        );
+        assert_accounts(
+            &update_trending_half_life(
+                authority,
+                instruction::UpdateTrendingHalfLife {
+                    half_life_seconds: 3_600,
+                },
+            ),
+            idl_accounts!(UpdateTrendingHalfLife),
+            &[],
+        );
+        assert_accounts(
+            &update_index_shard_capacity(
+                authority,
+                instruction::UpdateIndexShardCapacity { capacity: 8_192 },
+            ),
+            idl_accounts!(UpdateIndexShardCapacity),
+            &[],
+        );
+        assert_accounts(
+            &initialize_order_stats(authority),
+            idl_accounts!(InitializeOrderStats),
+            &[],
+        );
+        assert_accounts(&get_order_stats(), idl_accounts!(GetOrderStats), &[]);
+    }
+
+    #[test]
+    fn test_id_generator_builders_match_accounts() {
+        let merchant = Pubkey::new_unique();
+        let beneficiary = Pubkey::new_unique();
+        let active_chunk = pda::id_chunk(&merchant, 2).0;
+
+        assert_accounts(
+            &generate_product_id(merchant, active_chunk),
+            idl_accounts!(GenerateId),
+            &[("merchant_account.last_chunk_index", &2u32.to_le_bytes())],
+        );
+        assert_accounts(
+            &batch_generate_ids(
+                merchant,
+                active_chunk,
+                instruction::BatchGenerateIds { count: 5 },
+            ),
+            idl_accounts!(BatchGenerate),
+            &[],
+        );
+        assert_accounts(
+            &is_id_exists(merchant, active_chunk, instruction::IsIdExists { id: 7 }),
+            idl_accounts!(VerifyId),
+            &[],
+        );
+
+        let ix = allocate_new_chunk(merchant, merchant, 2);
+        assert_accounts(&ix, idl_accounts!(AllocateChunk), &[]);
+        assert_eq!(
+            ix.accounts[3].pubkey,
+            address(&[b"id_chunk", merchant.as_ref(), &3u32.to_le_bytes()])
+        );
+
+        // The initial chunk is derived from a single byte chunk index
+        let ix = release_id(merchant, 0, instruction::ReleaseId { id: 10_007 });
+        assert_accounts(
+            &ix,
+            idl_accounts!(ReleaseId),
+            &[("id", &10_007u64.to_le_bytes())],
+        );
+        assert_eq!(
+            ix.accounts[2].pubkey,
+            address(&[b"id_chunk", merchant.as_ref(), &[0]])
+        );
+
+        assert_accounts(
+            &compact_id_chunks(merchant),
+            idl_accounts!(CompactIdChunks),
+            &[],
+        );
+        assert_accounts(
+            &close_id_chunk(
+                beneficiary,
+                instruction::CloseIdChunk {
+                    merchant_key: merchant,
+                    chunk_index: 3,
+                    force: false,
+                },
+            ),
+            idl_accounts!(CloseIdChunk),
+            &[
+                ("merchant_key", merchant.as_ref()),
+                ("chunk_index", &3u32.to_le_bytes()),
+            ],
+        );
+        assert_accounts(
+            &close_merchant_id_account(
+                beneficiary,
+                instruction::CloseMerchantIdAccount {
+                    merchant_key: merchant,
+                    force: false,
+                },
+            ),
+            idl_accounts!(CloseMerchantIdAccount),
+            &[("merchant_key", merchant.as_ref())],
+        );
+    }
+
+    #[test]
+    fn test_merchant_builders_match_accounts() {
+        let owner = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+
+        let ix = register_merchant_atomic(
+            owner,
+            payer,
+            instruction::RegisterMerchantAtomic {
+                name: "Shop".to_string(),
+                description: "Test shop".to_string(),
+            },
+        );
+        assert_accounts(&ix, idl_accounts!(RegisterMerchantAtomic), &[]);
+        assert_eq!(
+            ix.accounts[6].pubkey,
+            address(&[b"id_chunk", owner.as_ref(), &[0u8]])
+        );
+
+        assert_accounts(
+            &update_merchant_info(
+                owner,
+                instruction::UpdateMerchantInfo {
+                    name: Some("Renamed".to_string()),
+                    description: None,
+                },
+            ),
+            idl_accounts!(UpdateMerchant),
+            &[],
+        );
+        assert_accounts(
+            &get_merchant_stats(owner),
+            idl_accounts!(GetMerchantStats),
+            &[],
+        );
+        assert_accounts(
+            &close_merchant(owner, payer, instruction::CloseMerchant { force: false }),
+            idl_accounts!(CloseMerchant),
+            &[],
+        );
+
+        let ix = get_merchant_products(instruction::GetMerchantProducts {
+            merchant: owner,
+            is_active: false,
+            page_index: 4,
+        });
+        assert_accounts(
+            &ix,
+            idl_accounts!(GetMerchantProducts),
+            &[("merchant", owner.as_ref())],
+        );
+        assert_eq!(
+            ix.accounts[1].pubkey,
+            address(&[
+                b"merchant_inactive_products",
+                owner.as_ref(),
+                &4u64.to_le_bytes()
+            ])
+        );
+
+        assert_accounts(
+            &manage_deposit(
+                owner,
+                token_account,
+                mint,
+                instruction::ManageDeposit { amount: 1_000 },
+            ),
+            idl_accounts!(ManageDeposit),
+            &[],
+        );
+        assert_accounts(
+            &withdraw_merchant_deposit(
+                owner,
+                owner,
+                token_account,
+                mint,
+                instruction::WithdrawMerchantDeposit { amount: 1_000 },
+            ),
+            idl_accounts!(WithdrawMerchantDeposit),
+            &[],
+        );
+        assert_accounts(
+            &deduct_merchant_deposit(
+                payer,
+                owner,
+                mint,
+                token_account,
+                instruction::DeductMerchantDeposit {
+                    amount: 100,
+                    reason: "Late shipment".to_string(),
+                },
+            ),
+            idl_accounts!(DeductMerchantDeposit),
+            &[],
+        );
+        assert_accounts(
+            &get_merchant_deposit_info(owner, mint),
+            idl_accounts!(GetMerchantDepositInfo),
+            &[],
+        );
+    }
+
+    #[test]
+    fn test_product_builders_match_accounts() {
+        let merchant = Pubkey::new_unique();
+        let payment_token = Pubkey::new_unique();
+        let product_id = 10_042u64;
+
+        let ix = create_product_base(
+            merchant,
+            pda::id_chunk(&merchant, 1).0,
+            1,
+            product_id,
+            3,
+            instruction::CreateProductBase {
+                name: "Lamp".to_string(),
+                description: "Desk lamp".to_string(),
+                price: 1_000,
+                keywords: vec!["lamp".to_string()],
+                inventory: 10,
+                payment_token,
+                shipping_location: "Default".to_string(),
+                day: 20_000,
+            },
+        );
+        assert_accounts(
+            &ix,
+            idl_accounts!(CreateProductBase),
+            &[(
+                "merchant_product_list.next_page_index(true)",
+                &3u64.to_le_bytes(),
+            )],
+        );
+        assert_eq!(
+            ix.accounts[5].pubkey,
+            address(&[b"id_chunk", merchant.as_ref(), &2u32.to_le_bytes()])
+        );
+        assert_eq!(
+            ix.accounts[7].pubkey,
+            address(&[b"product", &product_id.to_le_bytes()])
+        );
+
+        let product_seed = product_id.to_le_bytes();
+        assert_accounts(
+            &create_product_extended(
+                merchant,
+                instruction::CreateProductExtended {
+                    product_id,
+                    image_video_urls: vec![],
+                    sales_regions: vec![],
+                    logistics_methods: vec![],
+                },
+            ),
+            idl_accounts!(CreateProductExtended),
+            &[("product_id", &product_seed)],
+        );
+
+        for with_extended in [true, false] {
+            assert_accounts(
+                &update_product(
+                    merchant,
+                    with_extended,
+                    instruction::UpdateProduct {
+                        product_id,
+                        name: None,
+                        description: None,
+                        price: Some(2_000),
+                        keywords: None,
+                        inventory: None,
+                        payment_token: None,
+                        image_video_urls: None,
+                        shipping_location: None,
+                        sales_regions: None,
+                        logistics_methods: None,
+                        day: 20_000,
+                    },
+                ),
+                idl_accounts!(UpdateProduct),
+                &[("product_id", &product_seed)],
+            );
+        }
+
+        let ix = delete_product(
+            merchant,
+            merchant,
+            2,
+            Some((true, 5)),
+            Some(1),
+            instruction::DeleteProduct {
+                product_id,
+                hard_delete: true,
+                force: false,
+            },
+        );
+        assert_accounts(
+            &ix,
+            idl_accounts!(DeleteProduct),
+            &[
+                ("product_id", &product_seed),
+                ("product.merchant", merchant.as_ref()),
+                (
+                    "merchant_product_list.next_page_index(false)",
+                    &2u64.to_le_bytes(),
+                ),
+            ],
+        );
+        assert_eq!(
+            ix.accounts[5].pubkey,
+            address(&[
+                b"merchant_active_products",
+                merchant.as_ref(),
+                &5u64.to_le_bytes()
+            ])
+        );
+        assert_eq!(
+            ix.accounts[7].pubkey,
+            address(&[b"id_chunk", merchant.as_ref(), &1u32.to_le_bytes()])
+        );
+
+        assert_accounts(
+            &delete_product(
+                merchant,
+                merchant,
+                2,
+                None,
+                None,
+                instruction::DeleteProduct {
+                    product_id,
+                    hard_delete: false,
+                    force: false,
+                },
+            ),
+            idl_accounts!(DeleteProduct),
+            &[
+                ("product_id", &product_seed),
+                ("product.merchant", merchant.as_ref()),
+                (
+                    "merchant_product_list.next_page_index(false)",
+                    &2u64.to_le_bytes(),
+                ),
+            ],
+        );
+        assert_accounts(
+            &update_product_price(
+                merchant,
+                instruction::UpdateProductPrice {
+                    product_id,
+                    new_price: 1_500,
+                },
+            ),
+            idl_accounts!(UpdateProductPrice),
+            &[("product_id", &product_seed)],
+        );
+        assert_accounts(
+            &update_sales_count(
+                merchant,
+                instruction::UpdateSalesCount {
+                    product_id,
+                    sales_increment: 1,
+                },
+            ),
+            idl_accounts!(UpdateSales),
+            &[("product_id", &product_seed)],
+        );
+        assert_accounts(
+            &migrate_product_base(merchant, instruction::MigrateProductBase { product_id }),
+            idl_accounts!(MigrateProductBase),
+            &[("product_id", &product_seed)],
+        );
+
+        let ix = record_recent_product(
+            merchant,
+            Some(20_003),
+            instruction::RecordRecentProduct {
+                product_id,
+                feed: RecencyFeed::RecentlyUpdated,
+                keyword: "lamp".to_string(),
+                day: 20_000,
+            },
+        );
+        assert_accounts(
+            &ix,
+            idl_accounts!(RecordRecentProduct),
+            &[("product_id", &product_seed)],
+        );
+        assert_eq!(
+            ix.accounts[1].pubkey,
+            address(&[
+                b"recency_head",
+                &[RecencyFeed::RecentlyUpdated.seed()],
+                b"lamp"
+            ])
+        );
+        assert_eq!(
+            ix.accounts[2].pubkey,
+            address(&[
+                b"recency_bucket",
+                &[RecencyFeed::RecentlyUpdated.seed()],
+                b"lamp",
+                &20_000u32.to_le_bytes()
+            ])
+        );
+        assert_eq!(
+            ix.accounts[3].pubkey,
+            address(&[
+                b"recency_bucket",
+                &[RecencyFeed::RecentlyUpdated.seed()],
+                b"lamp",
+                &20_003u32.to_le_bytes()
+            ])
+        );
+
+        let ix = get_recent_products(instruction::GetRecentProducts {
+            feed: RecencyFeed::NewArrivals,
+            keyword: String::new(),
+            cursor: None,
+            limit: 20,
+        });
+        assert_accounts(&ix, idl_accounts!(GetRecentProducts), &[]);
+        assert_eq!(
+            ix.accounts[0].pubkey,
+            address(&[b"recency_head", &[RecencyFeed::NewArrivals.seed()], b""])
+        );
+    }
+
+    #[test]
+    fn test_payment_builders_match_accounts() {
+        let authority = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+
+        assert_accounts(
+            &initialize_payment_system(
+                authority,
+                instruction::InitializePaymentSystem {
+                    supported_tokens: vec![SupportedToken {
+                        mint,
+                        symbol: "USDC".to_string(),
+                        is_active: true,
+                    }],
+                    fee_rate: 40,
+                    fee_recipient: authority,
+                },
+            ),
+            idl_accounts!(InitializePaymentSystem),
+            &[],
+        );
+        assert_accounts(
+            &update_supported_tokens(
+                authority,
+                instruction::UpdateSupportedTokens {
+                    supported_tokens: vec![],
+                },
+            ),
+            idl_accounts!(UpdatePaymentConfig),
+            &[],
+        );
+        assert_accounts(
+            &update_fee_rate(authority, instruction::UpdateFeeRate { fee_rate: 50 }),
+            idl_accounts!(UpdatePaymentConfig),
+            &[],
+        );
+        assert_accounts(
+            &close_payment_config(
+                authority,
+                authority,
+                instruction::ClosePaymentConfig { force: false },
+            ),
+            idl_accounts!(ClosePaymentConfig),
+            &[],
+        );
+        assert_accounts(
+            &initialize_program_token_account(authority, mint),
+            idl_accounts!(InitializeProgramTokenAccount),
+            &[],
+        );
+        assert_accounts(
+            &purchase_product_escrow(
+                buyer,
+                token_account,
+                mint,
+                None,
+                instruction::PurchaseProductEscrow {
+                    product_id: 10_042,
+                    amount: 1,
+                },
+            ),
+            idl_accounts!(PurchaseProductEscrow),
+            &[("product_id", &10_042u64.to_le_bytes())],
+        );
+        assert_accounts(
+            &initialize_escrow_ledger(authority, mint),
+            idl_accounts!(InitializeEscrowLedger),
+            &[],
+        );
+        let ledger_mint: &[(&str, &[u8])] = &[("escrow_ledger.mint", mint.as_ref())];
+        assert_accounts(
+            &verify_escrow_solvency(mint),
+            idl_accounts!(VerifyEscrowSolvency),
+            ledger_mint,
+        );
+        assert_accounts(
+            &enforce_escrow_solvency(authority, mint),
+            idl_accounts!(EnforceEscrowSolvency),
+            ledger_mint,
+        );
+    }
+
+    #[test]
+    fn test_index_builders_match_accounts() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let keyword = "lamp".to_string();
+        let keyword_seed = keyword.as_bytes();
+
+        assert_accounts(
+            &initialize_keyword_index(
+                payer,
+                instruction::InitializeKeywordIndex {
+                    keyword: keyword.clone(),
+                },
+            ),
+            idl_accounts!(InitializeKeywordIndexIfNeeded),
+            &[("keyword", keyword_seed)],
+        );
+        assert_accounts(
+            &add_product_to_keyword_index(
+                payer,
+                instruction::AddProductToKeywordIndex {
+                    keyword: keyword.clone(),
+                    product_id: 10_042,
+                },
+            ),
+            idl_accounts!(AddProductToKeywordIndexIfNeeded),
+            &[("keyword", keyword_seed)],
+        );
+        assert_accounts(
+            &remove_product_from_keyword_index(instruction::RemoveProductFromKeywordIndex {
+                keyword: keyword.clone(),
+                product_id: 10_042,
+            }),
+            idl_accounts!(RemoveProductFromKeywordIndex),
+            &[("keyword", keyword_seed)],
+        );
+        assert_accounts(
+            &get_keyword_bloom_summary(instruction::GetKeywordBloomSummary {
+                keyword: keyword.clone(),
+            }),
+            idl_accounts!(GetKeywordBloomSummary),
+            &[("keyword", keyword_seed)],
+        );
+
+        let ix = create_keyword_shard(
+            payer,
+            instruction::CreateKeywordShard {
+                keyword: keyword.clone(),
+                shard_index: 2,
+            },
+        );
+        assert_accounts(
+            &ix,
+            idl_accounts!(CreateKeywordShard),
+            &[
+                ("keyword", keyword_seed),
+                ("shard_index", &2u32.to_le_bytes()),
+            ],
+        );
+        assert_eq!(
+            ix.accounts[1].pubkey,
+            address(&[b"keyword_shard", keyword_seed, &1u32.to_le_bytes()])
+        );
+
+        for outer_shard_index in [Some(3), None] {
+            let ix = merge_keyword_shards(
+                payer,
+                outer_shard_index,
+                instruction::MergeKeywordShards {
+                    keyword: keyword.clone(),
+                    source_shard_index: 2,
+                    target_shard_index: 1,
+                },
+            );
+            assert_accounts(
+                &ix,
+                idl_accounts!(MergeKeywordShards),
+                &[
+                    ("keyword", keyword_seed),
+                    ("source_shard_index", &2u32.to_le_bytes()),
+                    ("target_shard_index", &1u32.to_le_bytes()),
+                ],
+            );
+            if outer_shard_index.is_some() {
+                assert_eq!(
+                    ix.accounts[3].pubkey,
+                    address(&[b"keyword_shard", keyword_seed, &3u32.to_le_bytes()])
+                );
+            }
+        }
+
+        assert_accounts(
+            &close_keyword_root(
+                payer,
+                instruction::CloseKeywordRoot {
+                    keyword: keyword.clone(),
+                    force: false,
+                },
+            ),
+            idl_accounts!(CloseKeywordRoot),
+            &[("keyword", keyword_seed)],
+        );
+        assert_accounts(
+            &migrate_keyword_root(
+                payer,
+                instruction::MigrateKeywordRoot {
+                    keyword: keyword.clone(),
+                },
+            ),
+            idl_accounts!(MigrateKeywordRoot),
+            &[("keyword", keyword_seed)],
+        );
+        assert_accounts(
+            &close_keyword_shard(
+                payer,
+                instruction::CloseKeywordShard {
+                    keyword: keyword.clone(),
+                    shard_index: 1,
+                    force: false,
+                },
+            ),
+            idl_accounts!(CloseKeywordShard),
+            &[
+                ("keyword", keyword_seed),
+                ("shard_index", &1u32.to_le_bytes()),
+            ],
+        );
+
+        assert_accounts(
+            &add_product_to_price_index(
+                payer,
+                mint,
+                instruction::AddProductToPriceIndex {
+                    product_id: 10_042,
+                    price_range_start: 1_000,
+                    price_range_end: 1_999,
+                },
+            ),
+            idl_accounts!(AddProductToPriceIndex),
+            &[
+                ("product_id", &10_042u64.to_le_bytes()),
+                ("price_range_start", &1_000u64.to_le_bytes()),
+                ("price_range_end", &1_999u64.to_le_bytes()),
+            ],
+        );
+        assert_accounts(
+            &migrate_price_node(
+                payer,
+                mint,
+                instruction::MigratePriceNode {
+                    legacy_range_start: 1_024,
+                    legacy_range_end: 2_048,
+                    price_range_start: 1_024,
+                    price_range_end: 2_048,
+                },
+            ),
+            idl_accounts!(MigratePriceNode),
+            &[
+                ("legacy_range_start", &1_024u64.to_le_bytes()),
+                ("legacy_range_end", &2_048u64.to_le_bytes()),
+                ("price_range_start", &1_024u64.to_le_bytes()),
+                ("price_range_end", &2_048u64.to_le_bytes()),
+            ],
+        );
+        assert_accounts(
+            &remove_product_from_price_index(
+                mint,
+                1_000,
+                1_999,
+                instruction::RemoveProductFromPriceIndex { product_id: 10_042 },
+            ),
+            idl_accounts!(RemoveProductFromPriceIndex),
+            &[
+                ("price_node.payment_token", mint.as_ref()),
+                ("price_node.price_range_start", &1_000u64.to_le_bytes()),
+                ("price_node.price_range_end", &1_999u64.to_le_bytes()),
+            ],
+        );
+        assert_accounts(
+            &split_price_node(
+                payer,
+                mint,
+                instruction::SplitPriceNode {
+                    price_range_start: 1_000,
+                    price_range_end: 1_999,
+                    split_price: 1_500,
+                },
+            ),
+            idl_accounts!(SplitPriceNode),
+            &[
+                ("price_node.payment_token", mint.as_ref()),
+                ("price_range_start", &1_000u64.to_le_bytes()),
+                ("price_range_end", &1_999u64.to_le_bytes()),
+                ("split_price", &1_500u64.to_le_bytes()),
+                ("split_price.saturating_sub(1)", &1_499u64.to_le_bytes()),
+            ],
+        );
+        assert_accounts(
+            &search_price_range(
+                pda::price_index(&mint, 1_000, 1_999).0,
+                instruction::SearchPriceRange {
+                    payment_token: Some(mint),
+                    min_price: 1_000,
+                    max_price: 5_000,
+                    sort_order: SortOrder::Ascending,
+                    offset: 0,
+                    limit: 20,
+                },
+            ),
+            idl_accounts!(SearchPriceRange),
+            &[],
+        );
+
+        let sales_range = [
+            ("sales_range_start", 0u32.to_le_bytes()),
+            ("sales_range_end", 99u32.to_le_bytes()),
+        ];
+        let sales_range: Vec<(&str, &[u8])> = sales_range
+            .iter()
+            .map(|(path, value)| (*path, value.as_slice()))
+            .collect();
+        assert_accounts(
+            &initialize_sales_index(
+                payer,
+                instruction::InitializeSalesIndex {
+                    sales_range_start: 0,
+                    sales_range_end: 99,
+                },
+            ),
+            idl_accounts!(InitializeSalesIndexIfNeeded),
+            &sales_range,
+        );
+        assert_accounts(
+            &add_product_to_sales_index(
+                payer,
+                instruction::AddProductToSalesIndex {
+                    sales_range_start: 0,
+                    sales_range_end: 99,
+                    product_id: 10_042,
+                    sales: 3,
+                },
+            ),
+            idl_accounts!(AddProductToSalesIndexIfNeeded),
+            &sales_range,
+        );
+        assert_accounts(
+            &remove_product_from_sales_index(
+                0,
+                99,
+                instruction::RemoveProductFromSalesIndex { product_id: 10_042 },
+            ),
+            idl_accounts!(RemoveProductFromSalesIndex),
+            &[
+                ("sales_node.sales_range_start", &0u32.to_le_bytes()),
+                ("sales_node.sales_range_end", &99u32.to_le_bytes()),
+            ],
+        );
+        assert_accounts(
+            &update_product_sales_index(
+                payer,
+                pda::sales_index(0, 99).0,
+                pda::sales_index(100, 199).0,
+                instruction::UpdateProductSalesIndex {
+                    product_id: 10_042,
+                    old_sales: 99,
+                    new_sales: 100,
+                },
+            ),
+            idl_accounts!(UpdateProductSalesIndex),
+            &[],
+        );
+        assert_accounts(
+            &search_products(instruction::SearchProducts {
+                filter: SearchFilter::default(),
+                sort_key: SortKey::Price,
+                sort_order: SortOrder::Descending,
+                index_account_count: 1,
+                cursor: 0,
+                limit: 20,
+            }),
+            idl_accounts!(SearchProducts),
+            &[],
+        );
+
+        let authority = Pubkey::new_unique();
+        let keys = [
+            IndexShardKey::Keyword {
+                keyword: keyword.clone(),
+                shard_index: 0,
+            },
+            IndexShardKey::Price {
+                payment_token: mint,
+                price_range_start: 1_000,
+                price_range_end: 1_999,
+            },
+            IndexShardKey::Sales {
+                sales_range_start: 0,
+                sales_range_end: 99,
+            },
+        ];
+        for key in keys {
+            let shard = address(&[
+                b"index_shard",
+                &[key.kind()],
+                &key.primary_seed(),
+                &key.secondary_seed(),
+            ]);
+
+            let ix = initialize_index_shard(
+                payer,
+                instruction::InitializeIndexShard { key: key.clone() },
+            );
+            assert_accounts(&ix, idl_accounts!(InitializeIndexShard), &[]);
+            assert_eq!(ix.accounts[0].pubkey, shard);
+
+            let product_seed = 10_042u64.to_le_bytes();
+            let ix = add_product_to_index_shard(
+                authority,
+                payer,
+                instruction::AddProductToIndexShard {
+                    key: key.clone(),
+                    product_id: 10_042,
+                },
+            );
+            assert_accounts(
+                &ix,
+                idl_accounts!(AddProductToIndexShard),
+                &[("product_id", &product_seed)],
+            );
+            assert_eq!(ix.accounts[0].pubkey, shard);
+
+            let ix = remove_product_from_index_shard(
+                authority,
+                instruction::RemoveProductFromIndexShard {
+                    key: key.clone(),
+                    product_id: 10_042,
+                },
+            );
+            assert_accounts(
+                &ix,
+                idl_accounts!(RemoveProductFromIndexShard),
+                &[("product_id", &product_seed)],
+            );
+            assert_eq!(ix.accounts[0].pubkey, shard);
+
+            let legacy_shard = match &key {
+                IndexShardKey::Keyword {
+                    keyword,
+                    shard_index,
+                } => address(&[
+                    b"keyword_shard",
+                    keyword.as_bytes(),
+                    &shard_index.to_le_bytes(),
+                ]),
+                IndexShardKey::Price {
+                    payment_token,
+                    price_range_start,
+                    price_range_end,
+                } => address(&[
+                    b"price_index",
+                    payment_token.as_ref(),
+                    &price_range_start.to_le_bytes(),
+                    &price_range_end.to_le_bytes(),
+                ]),
+                IndexShardKey::Sales {
+                    sales_range_start,
+                    sales_range_end,
+                } => address(&[
+                    b"sales_index",
+                    &sales_range_start.to_le_bytes(),
+                    &sales_range_end.to_le_bytes(),
+                ]),
+            };
+            let ix = migrate_index_shard(payer, instruction::MigrateIndexShard { key });
+            assert_accounts(&ix, idl_accounts!(MigrateIndexShard), &[]);
+            assert_eq!(ix.accounts[0].pubkey, shard);
+            assert_eq!(ix.accounts[1].pubkey, legacy_shard);
+        }
+    }
+
+    #[test]
+    fn test_order_builders_match_accounts() {
+        let buyer = Pubkey::new_unique();
+        let merchant = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let order = pda::buyer_order(&buyer, 3).0;
+        let order_data = order_account(buyer, merchant, mint);
+        let product_seed = order_data.product_id.to_le_bytes();
+        let (old_sales_node, new_sales_node) = (
+            Some(pda::sales_index(0, 99).0),
+            Some(pda::sales_index(100, 199).0),
+        );
+        let day = 20_200u32;
+        let day_seed = day.to_le_bytes();
+        let order_seeds: &[(&str, &[u8])] = &[
+            ("order.merchant", merchant.as_ref()),
+            ("order.payment_token", mint.as_ref()),
+            ("order.product_id", &product_seed),
+            ("day", &day_seed),
+        ];
+
+        let ix = create_order(
+            buyer,
+            merchant,
+            mint,
+            3,
+            8,
+            None,
+            instruction::CreateOrder {
+                product_id: order_data.product_id,
+                quantity: 1,
+                shipping_address: "Somewhere".to_string(),
+                notes: String::new(),
+                transaction_signature: "signature".to_string(),
+                day,
+            },
+        );
+        assert_accounts(
+            &ix,
+            idl_accounts!(CreateOrder),
+            &[
+                ("product_id", &product_seed),
+                ("merchant.owner", merchant.as_ref()),
+                ("product.merchant", merchant.as_ref()),
+                ("product.payment_token", mint.as_ref()),
+                ("day", &day_seed),
+            ],
+        );
+        assert_eq!(
+            ix.accounts[1].pubkey,
+            address(&[b"buyer_order", buyer.as_ref(), &3u64.to_le_bytes()])
+        );
+        assert_eq!(
+            ix.accounts[10].pubkey,
+            address(&[b"merchant_order", merchant.as_ref(), &8u64.to_le_bytes()])
+        );
+
+        assert_accounts(
+            &migrate_order(buyer, order),
+            idl_accounts!(MigrateOrder),
+            &[],
+        );
+        assert_accounts(
+            &ship_order(
+                order,
+                &order_data,
+                instruction::ShipOrder {
+                    tracking_number: "TRACK-1".to_string(),
+                },
+            ),
+            idl_accounts!(ShipOrder),
+            &[
+                ("merchant.owner", merchant.as_ref()),
+                ("order.merchant", merchant.as_ref()),
+                ("order.payment_token", mint.as_ref()),
+            ],
+        );
+        assert_accounts(
+            &refund_order(
+                order,
+                &order_data,
+                token_account,
+                instruction::RefundOrder {
+                    refund_reason: "Changed mind".to_string(),
+                    day,
+                },
+            ),
+            idl_accounts!(RefundOrder),
+            order_seeds,
+        );
+        assert_accounts(
+            &refund_delivered_order(
+                order,
+                &order_data,
+                old_sales_node,
+                new_sales_node,
+                token_account,
+                instruction::RefundDeliveredOrder {
+                    refund_reason: "Damaged".to_string(),
+                    day,
+                },
+            ),
+            idl_accounts!(RefundDeliveredOrder),
+            order_seeds,
+        );
+
+        let system_config = SystemConfig {
+            deposit_token_mint: mint,
+            vault_account: Pubkey::new_unique(),
+            vault_token_account: Pubkey::new_unique(),
+            platform_token_account: Pubkey::new_unique(),
+            vault_program_id: Pubkey::new_unique(),
+            ..Default::default()
+        };
+        assert_accounts(
+            &confirm_delivery(
+                order,
+                &order_data,
+                &system_config,
+                old_sales_node,
+                new_sales_node,
+                instruction::ConfirmDelivery { day },
+            ),
+            idl_accounts!(ConfirmDelivery),
+            &[
+                order_seeds,
+                &[("system_config.deposit_token_mint", mint.as_ref())],
+            ]
+            .concat(),
+        );
+        assert_accounts(
+            &auto_confirm_delivery(
+                Pubkey::new_unique(),
+                order,
+                &order_data,
+                None,
+                None,
+                instruction::AutoConfirmDelivery { day },
+            ),
+            idl_accounts!(AutoConfirmDelivery),
+            &[order_seeds, &[("merchant.owner", merchant.as_ref())]].concat(),
+        );
+    }
+
+    #[test]
+    fn test_review_builders_match_accounts() {
+        let buyer = Pubkey::new_unique();
+        let merchant = Pubkey::new_unique();
+        let order = pda::buyer_order(&buyer, 3).0;
+        let order_data = order_account(buyer, merchant, Pubkey::new_unique());
+        let product_seed = order_data.product_id.to_le_bytes();
+
+        assert_accounts(
+            &create_review(
+                order,
+                &order_data,
+                instruction::CreateReview {
+                    rating: 4,
+                    content_hash: None,
+                    text: "Bright and sturdy".to_string(),
+                },
+            ),
+            idl_accounts!(CreateReview),
+            &[
+                ("order.merchant", merchant.as_ref()),
+                ("order.product_id", &product_seed),
+            ],
+        );
+        assert_accounts(
+            &reply_to_review(
+                merchant,
+                order,
+                instruction::ReplyToReview {
+                    reply: "Thank you".to_string(),
+                },
+            ),
+            idl_accounts!(ReplyToReview),
+            &[("review.order", order.as_ref())],
+        );
+    }
+
+    #[test]
+    fn test_reputation_builders_match_accounts() {
+        let authority = Pubkey::new_unique();
+        let merchant = Pubkey::new_unique();
+        let order = Pubkey::new_unique();
+        let order_data = order_account(Pubkey::new_unique(), merchant, Pubkey::new_unique());
+
+        assert_accounts(
+            &record_lost_dispute(
+                authority,
+                order,
+                &order_data,
+                instruction::RecordLostDispute {
+                    reason: "Item not as described".to_string(),
+                },
+            ),
+            idl_accounts!(RecordLostDispute),
+            &[("order.merchant", merchant.as_ref())],
+        );
+        assert_accounts(
+            &get_merchant_reputation(merchant),
+            idl_accounts!(GetMerchantReputation),
+            &[],
+        );
+    }
+
+    #[test]
+    fn test_product_variant_builders_match_accounts() {
+        let merchant = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let product_seed = 10_042u64.to_le_bytes();
+        let variant_seed = 2u32.to_le_bytes();
+        let variant = address(&[b"product_variant", &product_seed, &variant_seed]);
+
+        let ix = create_product_variant(
+            merchant,
+            2,
+            instruction::CreateProductVariant {
+                product_id: 10_042,
+                options: vec![VariantOption {
+                    name: "size".to_string(),
+                    value: "M".to_string(),
+                }],
+                sku: "LAMP-M".to_string(),
+                price: Some(1_200),
+                inventory: 10,
+            },
+        );
+        assert_accounts(
+            &ix,
+            idl_accounts!(CreateProductVariant),
+            &[
+                ("product_id", &product_seed),
+                ("product.variant_count", &variant_seed),
+            ],
+        );
+        assert_eq!(ix.accounts[2].pubkey, variant);
+        let variant_seeds: &[(&str, &[u8])] =
+            &[("product_id", &product_seed), ("variant_id", &variant_seed)];
+        assert_accounts(
+            &update_product_variant(
+                merchant,
+                instruction::UpdateProductVariant {
+                    product_id: 10_042,
+                    variant_id: 2,
+                    sku: None,
+                    inventory: Some(5),
+                    is_active: None,
+                },
+            ),
+            idl_accounts!(UpdateProductVariant),
+            variant_seeds,
+        );
+        assert_accounts(
+            &update_product_variant_price(
+                merchant,
+                instruction::UpdateProductVariantPrice {
+                    product_id: 10_042,
+                    variant_id: 2,
+                    price: None,
+                },
+            ),
+            idl_accounts!(UpdateProductVariant),
+            variant_seeds,
+        );
+
+        // Orders and escrow purchases of a variant pass it, refunds take it from the order
+        let ix = create_order(
+            buyer,
+            merchant,
+            mint,
+            1,
+            1,
+            Some(2),
+            instruction::CreateOrder {
+                product_id: 10_042,
+                quantity: 1,
+                shipping_address: "Somewhere".to_string(),
+                notes: String::new(),
+                transaction_signature: "signature".to_string(),
+                day: 20_200,
+            },
+        );
+        let product_variant = ix.accounts[7].clone();
+        assert_eq!(product_variant.pubkey, variant);
+        assert!(product_variant.is_writable);
+        let ix = purchase_product_escrow(
+            buyer,
+            Pubkey::new_unique(),
+            mint,
+            Some(2),
+            instruction::PurchaseProductEscrow {
+                product_id: 10_042,
+                amount: 1,
+            },
+        );
+        assert_eq!(ix.accounts[2].pubkey, variant);
+        let order_data = Order {
+            variant_id: Some(2),
+            ..order_account(buyer, merchant, mint)
+        };
+        let ix = refund_order(
+            Pubkey::new_unique(),
+            &order_data,
+            Pubkey::new_unique(),
+            instruction::RefundOrder {
+                refund_reason: String::new(),
+                day: 20_200,
+            },
+        );
+        assert_eq!(ix.accounts[1].pubkey, variant);
+        assert!(ix.accounts[1].is_writable);
+    }
+
+    #[test]
+    fn test_ranking_builders_match_accounts() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        assert_accounts(
+            &initialize_keyword_trending_board(
+                payer,
+                instruction::InitializeKeywordTrendingBoard {
+                    keyword: "lamp".to_string(),
+                },
+            ),
+            idl_accounts!(InitializeKeywordTrendingBoard),
+            &[("keyword", b"lamp")],
+        );
+
+        let ix =
+            get_trending_products(Some("lamp"), instruction::GetTrendingProducts { limit: 10 });
+        assert_accounts(&ix, idl_accounts!(GetTrendingProducts), &[]);
+        assert_eq!(
+            ix.accounts[0].pubkey,
+            address(&[b"trending_keyword", b"lamp"])
+        );
+        let ix = get_trending_products(None, instruction::GetTrendingProducts { limit: 10 });
+        assert_eq!(ix.accounts[0].pubkey, address(&[b"trending_global"]));
+
+        let scopes = [
+            LeaderboardScope::Global,
+            LeaderboardScope::Category("lamp".to_string()),
+            LeaderboardScope::PaymentToken(mint),
+        ];
+        for scope in scopes {
+            let leaderboard = address(&[b"leaderboard", &[scope.kind()], &scope.key_bytes()]);
+
+            let ix = initialize_leaderboard(
+                payer,
+                instruction::InitializeLeaderboard {
+                    scope: scope.clone(),
+                },
+            );
+            assert_accounts(&ix, idl_accounts!(InitializeLeaderboard), &[]);
+            assert_eq!(ix.accounts[0].pubkey, leaderboard);
+
+            let ix = get_leaderboard(instruction::GetLeaderboard { scope, limit: 10 });
+            assert_accounts(&ix, idl_accounts!(GetLeaderboard), &[]);
+            assert_eq!(ix.accounts[0].pubkey, leaderboard);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! Rust client for the solana-e-commerce program
+//!
+//! - [`pda`]: typed PDA derivation sharing the program's seed helpers
+//! - [`instructions`]: one builder per program instruction
+//! - [`accounts`]: account decoders
+
+pub mod accounts;
+pub mod instructions;
+pub mod pda;
+
+pub use solana_e_commerce::ID;
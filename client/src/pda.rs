@@ -0,0 +1,241 @@
+//! Program derived addresses of the solana-e-commerce program
+//!
+//! Every helper derives from the seeds defined next to the account type in the program, so the
+//! client cannot drift from the `seeds = [...]` constraints the program checks.
+
+use anchor_lang::prelude::Pubkey;
+use solana_e_commerce::state::{
+    self, DailyOrderStats, EscrowAccount, GlobalIdRoot, IdChunk, IndexShard, IndexShardKey,
+    KeywordRoot, KeywordShard, Leaderboard, LeaderboardScope, LegacyPriceIndexNode, Merchant,
+    MerchantIdAccount, MerchantOrder, MerchantOrderCount, MerchantOrderStats, MerchantProductList,
+    MerchantProductPage, MerchantRating, MerchantReputation, Order, OrderStats, PaymentConfig,
+    PriceIndexNode, ProductBase, ProductExtended, ProductRating, ProductVariant, RecencyBucket,
+    RecencyFeed, RecencyFeedHead, Review, SalesIndexNode, TokenOrderStats, TrendingBoard,
+    TrendingScore, UserPurchaseCount,
+};
+use solana_e_commerce::SystemConfig;
+
+fn find(seeds: &[Vec<u8>]) -> (Pubkey, u8) {
+    let seeds: Vec<&[u8]> = seeds.iter().map(Vec::as_slice).collect();
+    Pubkey::find_program_address(&seeds, &solana_e_commerce::ID)
+}
+
+fn find_static(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &solana_e_commerce::ID)
+}
+
+// ==================== System ====================
+
+pub fn system_config() -> (Pubkey, u8) {
+    find_static(SystemConfig::seeds())
+}
+
+pub fn global_id_root() -> (Pubkey, u8) {
+    find_static(GlobalIdRoot::seeds())
+}
+
+pub fn payment_config() -> (Pubkey, u8) {
+    find_static(PaymentConfig::seeds())
+}
+
+pub fn order_stats() -> (Pubkey, u8) {
+    find(&OrderStats::seeds())
+}
+
+pub fn token_order_stats(mint: &Pubkey) -> (Pubkey, u8) {
+    find(&TokenOrderStats::seeds(mint))
+}
+
+// `merchant` is the merchant owner
+pub fn merchant_order_stats(merchant: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    find(&MerchantOrderStats::seeds(merchant, mint))
+}
+
+// `day` is the recency_day of the event
+pub fn daily_order_stats(mint: &Pubkey, day: u32) -> (Pubkey, u8) {
+    find(&DailyOrderStats::seeds(mint, day))
+}
+
+// ==================== Merchant and ID generator ====================
+
+pub fn merchant_info(owner: &Pubkey) -> (Pubkey, u8) {
+    find(&Merchant::seeds(owner))
+}
+
+pub fn merchant_id_account(merchant: &Pubkey) -> (Pubkey, u8) {
+    find(&MerchantIdAccount::seeds(merchant))
+}
+
+// The initial chunk (index 0) is derived from a single byte seed
+pub fn id_chunk(merchant: &Pubkey, chunk_index: u32) -> (Pubkey, u8) {
+    find(&IdChunk::seeds(merchant, chunk_index))
+}
+
+pub fn merchant_product_list(merchant: &Pubkey) -> (Pubkey, u8) {
+    find(&MerchantProductList::seeds(merchant))
+}
+
+pub fn merchant_product_page(merchant: &Pubkey, is_active: bool, page_index: u64) -> (Pubkey, u8) {
+    find(&MerchantProductPage::seeds(merchant, is_active, page_index))
+}
+
+// ==================== Product ====================
+
+pub fn product(product_id: u64) -> (Pubkey, u8) {
+    find(&ProductBase::seeds_static(product_id))
+}
+
+pub fn product_extended(product_id: u64) -> (Pubkey, u8) {
+    find(&ProductExtended::seeds_static(product_id))
+}
+
+pub fn product_variant(product_id: u64, variant_id: u32) -> (Pubkey, u8) {
+    find(&ProductVariant::seeds(product_id, variant_id))
+}
+
+// ==================== Payment and escrow ====================
+
+pub fn program_authority() -> (Pubkey, u8) {
+    find_static(state::program_authority_seeds())
+}
+
+pub fn program_token_account(mint: &Pubkey) -> (Pubkey, u8) {
+    find(&state::program_token_account_seeds(mint))
+}
+
+pub fn deposit_escrow(mint: &Pubkey) -> (Pubkey, u8) {
+    find(&state::deposit_escrow_seeds(mint))
+}
+
+pub fn escrow_ledger(mint: &Pubkey) -> (Pubkey, u8) {
+    find(&state::escrow_ledger_seeds(mint))
+}
+
+pub fn escrow(buyer: &Pubkey, product_id: u64) -> (Pubkey, u8) {
+    find(&EscrowAccount::seeds(buyer, product_id))
+}
+
+// ==================== Orders ====================
+
+// `purchase_sequence` starts at 1, the order created next uses purchase_count + 1
+pub fn buyer_order(buyer: &Pubkey, purchase_sequence: u64) -> (Pubkey, u8) {
+    find(&Order::seeds(buyer, purchase_sequence))
+}
+
+pub fn user_purchase_count(buyer: &Pubkey) -> (Pubkey, u8) {
+    find(&UserPurchaseCount::seeds(buyer))
+}
+
+// `merchant` is the merchant owner, the order created next uses total_orders + 1
+pub fn merchant_order(merchant: &Pubkey, merchant_order_sequence: u64) -> (Pubkey, u8) {
+    find(&MerchantOrder::seeds(merchant, merchant_order_sequence))
+}
+
+pub fn merchant_order_count(merchant: &Pubkey) -> (Pubkey, u8) {
+    find(&MerchantOrderCount::seeds(merchant))
+}
+
+// ==================== Reviews ====================
+
+pub fn review(order: &Pubkey) -> (Pubkey, u8) {
+    find(&Review::seeds(order))
+}
+
+pub fn product_rating(product_id: u64) -> (Pubkey, u8) {
+    find(&ProductRating::seeds(product_id))
+}
+
+// `merchant` is the merchant owner
+pub fn merchant_rating(merchant: &Pubkey) -> (Pubkey, u8) {
+    find(&MerchantRating::seeds(merchant))
+}
+
+// ==================== Reputation ====================
+
+// `merchant` is the merchant owner
+pub fn merchant_reputation(merchant: &Pubkey) -> (Pubkey, u8) {
+    find(&MerchantReputation::seeds(merchant))
+}
+
+// ==================== Indexes ====================
+
+pub fn keyword_root(keyword: &str) -> (Pubkey, u8) {
+    find(&KeywordRoot::seeds(keyword))
+}
+
+pub fn keyword_shard(keyword: &str, shard_index: u32) -> (Pubkey, u8) {
+    find(&KeywordShard::seeds(keyword, shard_index))
+}
+
+pub fn price_index(
+    payment_token: &Pubkey,
+    price_range_start: u64,
+    price_range_end: u64,
+) -> (Pubkey, u8) {
+    find(&PriceIndexNode::seeds(
+        payment_token,
+        price_range_start,
+        price_range_end,
+    ))
+}
+
+/// Price node created before nodes were namespaced by payment mint
+pub fn legacy_price_index(price_range_start: u64, price_range_end: u64) -> (Pubkey, u8) {
+    find(&LegacyPriceIndexNode::seeds(
+        price_range_start,
+        price_range_end,
+    ))
+}
+
+pub fn sales_index(sales_range_start: u32, sales_range_end: u32) -> (Pubkey, u8) {
+    find(&SalesIndexNode::seeds(sales_range_start, sales_range_end))
+}
+
+pub fn index_shard(key: &IndexShardKey) -> (Pubkey, u8) {
+    find(&IndexShard::seeds(key))
+}
+
+// Borsh keyword shard, price node or sales node a zero-copy shard is migrated from
+pub fn legacy_index_shard(key: &IndexShardKey) -> (Pubkey, u8) {
+    match key {
+        IndexShardKey::Keyword {
+            keyword,
+            shard_index,
+        } => keyword_shard(keyword, *shard_index),
+        IndexShardKey::Price {
+            payment_token,
+            price_range_start,
+            price_range_end,
+        } => price_index(payment_token, *price_range_start, *price_range_end),
+        IndexShardKey::Sales {
+            sales_range_start,
+            sales_range_end,
+        } => sales_index(*sales_range_start, *sales_range_end),
+    }
+}
+
+pub fn recency_head(feed: RecencyFeed, keyword: &str) -> (Pubkey, u8) {
+    find(&RecencyFeedHead::seeds(feed, keyword))
+}
+
+pub fn recency_bucket(feed: RecencyFeed, keyword: &str, day: u32) -> (Pubkey, u8) {
+    find(&RecencyBucket::seeds(feed, keyword, day))
+}
+
+// ==================== Rankings ====================
+
+pub fn trending_score(product_id: u64) -> (Pubkey, u8) {
+    find(&TrendingScore::seeds(product_id))
+}
+
+pub fn trending_global() -> (Pubkey, u8) {
+    find(&TrendingBoard::global_seeds())
+}
+
+pub fn trending_keyword(keyword: &str) -> (Pubkey, u8) {
+    find(&TrendingBoard::keyword_seeds(keyword))
+}
+
+pub fn leaderboard(scope: &LeaderboardScope) -> (Pubkey, u8) {
+    find(&Leaderboard::seeds(scope))
+}
@@ -0,0 +1,338 @@
+//! Program instructions of a confirmed transaction, with the token transfers they made
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Discriminator;
+use anyhow::{anyhow, bail, Context, Result};
+use solana_e_commerce::instruction;
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, UiInnerInstructions, UiInstruction,
+    UiLoadedAddresses, UiTransactionTokenBalance,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub struct DecodedTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub fee_payer: Pubkey,
+    pub error: Option<String>,
+    /// Writable accounts of the program instructions
+    pub writable_accounts: Vec<Pubkey>,
+    pub instructions: Vec<ProgramInstruction>,
+    /// Mint of every token account with a balance in the transaction
+    pub token_mints: HashMap<Pubkey, Pubkey>,
+}
+
+pub struct ProgramInstruction {
+    /// Index of the top-level instruction
+    pub outer_index: usize,
+    /// Invoked by another program rather than by the transaction
+    pub cpi: bool,
+    pub name: Option<&'static str>,
+    pub accounts: Vec<Pubkey>,
+    pub data: Vec<u8>,
+    /// Token transfers made through CPI, only known for top-level instructions
+    pub transfers: Vec<TokenTransfer>,
+}
+
+struct InnerInstruction {
+    program: Pubkey,
+    accounts: Vec<Pubkey>,
+    data: Vec<u8>,
+}
+
+pub struct TokenTransfer {
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+impl ProgramInstruction {
+    pub fn account(&self, position: usize) -> Result<Pubkey> {
+        self.accounts.get(position).copied().ok_or_else(|| {
+            anyhow!(
+                "{} has no account at position {}",
+                self.name.unwrap_or("instruction"),
+                position
+            )
+        })
+    }
+
+    /// Instruction arguments, after the discriminator
+    pub fn args<T: anchor_lang::AnchorDeserialize>(&self) -> Result<T> {
+        let data = self.data.get(8..).unwrap_or_default();
+        T::try_from_slice(data).with_context(|| {
+            format!(
+                "decoding the arguments of {}",
+                self.name.unwrap_or("instruction")
+            )
+        })
+    }
+
+    pub fn transferred_to(&self, account: &Pubkey) -> u64 {
+        self.transfers
+            .iter()
+            .filter(|transfer| transfer.destination == *account)
+            .map(|transfer| transfer.amount)
+            .sum()
+    }
+
+    pub fn transferred_from(&self, account: &Pubkey) -> u64 {
+        self.transfers
+            .iter()
+            .filter(|transfer| transfer.source == *account)
+            .map(|transfer| transfer.amount)
+            .sum()
+    }
+}
+
+macro_rules! instruction_names {
+    ($($name:ident => $ty:ident),* $(,)?) => {
+        /// Name of a program instruction from its Anchor discriminator
+        pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+            $(
+                if data.starts_with(instruction::$ty::DISCRIMINATOR) {
+                    return Some(stringify!($name));
+                }
+            )*
+            None
+        }
+    };
+}
+
+instruction_names! {
+    initialize_system => InitializeSystem,
+    initialize_system_config => InitializeSystemConfig,
+    migrate_system_config => MigrateSystemConfig,
+    close_system_config => CloseSystemConfig,
+    force_close_system_config => ForceCloseSystemConfig,
+    generate_product_id => GenerateProductId,
+    batch_generate_ids => BatchGenerateIds,
+    is_id_exists => IsIdExists,
+    allocate_new_chunk => AllocateNewChunk,
+    release_id => ReleaseId,
+    compact_id_chunks => CompactIdChunks,
+    register_merchant_atomic => RegisterMerchantAtomic,
+    update_merchant_info => UpdateMerchantInfo,
+    get_merchant_stats => GetMerchantStats,
+    close_merchant => CloseMerchant,
+    create_product_base => CreateProductBase,
+    create_product_extended => CreateProductExtended,
+    update_product => UpdateProduct,
+    delete_product => DeleteProduct,
+    get_merchant_products => GetMerchantProducts,
+    update_product_price => UpdateProductPrice,
+    create_product_variant => CreateProductVariant,
+    update_product_variant => UpdateProductVariant,
+    update_product_variant_price => UpdateProductVariantPrice,
+    migrate_product_base => MigrateProductBase,
+    update_sales_count => UpdateSalesCount,
+    record_recent_product => RecordRecentProduct,
+    get_recent_products => GetRecentProducts,
+    initialize_payment_system => InitializePaymentSystem,
+    update_supported_tokens => UpdateSupportedTokens,
+    update_fee_rate => UpdateFeeRate,
+    close_payment_config => ClosePaymentConfig,
+    initialize_program_token_account => InitializeProgramTokenAccount,
+    purchase_product_escrow => PurchaseProductEscrow,
+    initialize_escrow_ledger => InitializeEscrowLedger,
+    verify_escrow_solvency => VerifyEscrowSolvency,
+    enforce_escrow_solvency => EnforceEscrowSolvency,
+    remove_product_from_keyword_index => RemoveProductFromKeywordIndex,
+    initialize_keyword_index => InitializeKeywordIndex,
+    add_product_to_keyword_index => AddProductToKeywordIndex,
+    get_keyword_bloom_summary => GetKeywordBloomSummary,
+    create_keyword_shard => CreateKeywordShard,
+    merge_keyword_shards => MergeKeywordShards,
+    add_product_to_price_index => AddProductToPriceIndex,
+    remove_product_from_price_index => RemoveProductFromPriceIndex,
+    split_price_node => SplitPriceNode,
+    migrate_price_node => MigratePriceNode,
+    search_price_range => SearchPriceRange,
+    search_products => SearchProducts,
+    initialize_sales_index => InitializeSalesIndex,
+    add_product_to_sales_index => AddProductToSalesIndex,
+    remove_product_from_sales_index => RemoveProductFromSalesIndex,
+    update_product_sales_index => UpdateProductSalesIndex,
+    close_keyword_root => CloseKeywordRoot,
+    migrate_keyword_root => MigrateKeywordRoot,
+    close_keyword_shard => CloseKeywordShard,
+    close_id_chunk => CloseIdChunk,
+    close_merchant_id_account => CloseMerchantIdAccount,
+    initialize_order_stats => InitializeOrderStats,
+    create_order => CreateOrder,
+    ship_order => ShipOrder,
+    refund_order => RefundOrder,
+    refund_delivered_order => RefundDeliveredOrder,
+    migrate_order => MigrateOrder,
+    get_order_stats => GetOrderStats,
+    confirm_delivery => ConfirmDelivery,
+    auto_confirm_delivery => AutoConfirmDelivery,
+    create_review => CreateReview,
+    reply_to_review => ReplyToReview,
+    record_lost_dispute => RecordLostDispute,
+    get_merchant_reputation => GetMerchantReputation,
+    manage_deposit => ManageDeposit,
+    withdraw_merchant_deposit => WithdrawMerchantDeposit,
+    deduct_merchant_deposit => DeductMerchantDeposit,
+    get_merchant_deposit_info => GetMerchantDepositInfo,
+    update_deposit_requirement => UpdateDepositRequirement,
+    initialize_keyword_trending_board => InitializeKeywordTrendingBoard,
+    get_trending_products => GetTrendingProducts,
+    update_trending_half_life => UpdateTrendingHalfLife,
+    initialize_leaderboard => InitializeLeaderboard,
+    get_leaderboard => GetLeaderboard,
+    initialize_index_shard => InitializeIndexShard,
+    add_product_to_index_shard => AddProductToIndexShard,
+    remove_product_from_index_shard => RemoveProductFromIndexShard,
+    migrate_index_shard => MigrateIndexShard,
+    update_index_shard_capacity => UpdateIndexShardCapacity,
+}
+
+// SPL Token instruction tags
+const TOKEN_TRANSFER: u8 = 3;
+const TOKEN_TRANSFER_CHECKED: u8 = 12;
+
+/// Decode a transaction fetched with base64 encoding
+pub fn decode_transaction(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<DecodedTransaction> {
+    let versioned = transaction
+        .transaction
+        .transaction
+        .decode()
+        .context("decoding the transaction (it must be fetched with base64 encoding)")?;
+    let meta = transaction
+        .transaction
+        .meta
+        .as_ref()
+        .context("the transaction has no status meta")?;
+
+    // Static keys, then the writable and readonly keys loaded from lookup tables
+    let mut keys = versioned.message.static_account_keys().to_vec();
+    let loaded: Option<&UiLoadedAddresses> = Option::from(meta.loaded_addresses.as_ref());
+    if let Some(loaded) = loaded {
+        for address in loaded.writable.iter().chain(&loaded.readonly) {
+            keys.push(parse_pubkey(address)?);
+        }
+    }
+    let key = |index: u8| {
+        keys.get(index as usize)
+            .copied()
+            .ok_or_else(|| anyhow!("account index {} out of range", index))
+    };
+    let keys_of = |indexes: &[u8]| -> Result<Vec<Pubkey>> {
+        indexes.iter().map(|index| key(*index)).collect()
+    };
+
+    // Instructions invoked through CPI, by top-level instruction
+    let mut inner_instructions: HashMap<usize, Vec<InnerInstruction>> = HashMap::new();
+    let inner: Option<&Vec<UiInnerInstructions>> = Option::from(meta.inner_instructions.as_ref());
+    for inner in inner.into_iter().flatten() {
+        let instructions = inner_instructions.entry(inner.index as usize).or_default();
+        for instruction in &inner.instructions {
+            let UiInstruction::Compiled(compiled) = instruction else {
+                bail!("inner instructions must not be parsed");
+            };
+            instructions.push(InnerInstruction {
+                program: key(compiled.program_id_index)?,
+                accounts: keys_of(&compiled.accounts)?,
+                data: bs58::decode(&compiled.data)
+                    .into_vec()
+                    .context("decoding inner instruction data")?,
+            });
+        }
+    }
+
+    let message = &versioned.message;
+    let mut instructions = Vec::new();
+    let mut writable_accounts = Vec::new();
+    for (outer_index, compiled) in message.instructions().iter().enumerate() {
+        let inner = inner_instructions
+            .get(&outer_index)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        if key(compiled.program_id_index)? == shop_client::ID {
+            for &index in &compiled.accounts {
+                if message.is_maybe_writable(index as usize, None) {
+                    writable_accounts.push(key(index)?);
+                }
+            }
+            instructions.push(ProgramInstruction {
+                outer_index,
+                cpi: false,
+                name: instruction_name(&compiled.data),
+                accounts: keys_of(&compiled.accounts)?,
+                data: compiled.data.clone(),
+                transfers: inner.iter().filter_map(token_transfer).collect(),
+            });
+        }
+
+        for instruction in inner {
+            if instruction.program == shop_client::ID {
+                instructions.push(ProgramInstruction {
+                    outer_index,
+                    cpi: true,
+                    name: instruction_name(&instruction.data),
+                    accounts: instruction.accounts.clone(),
+                    data: instruction.data.clone(),
+                    transfers: Vec::new(),
+                });
+            }
+        }
+    }
+
+    let mut token_mints = HashMap::new();
+    for balances in [&meta.pre_token_balances, &meta.post_token_balances] {
+        let balances: Option<&Vec<UiTransactionTokenBalance>> = Option::from(balances.as_ref());
+        for balance in balances.into_iter().flatten() {
+            token_mints.insert(key(balance.account_index)?, parse_pubkey(&balance.mint)?);
+        }
+    }
+
+    writable_accounts.sort();
+    writable_accounts.dedup();
+
+    Ok(DecodedTransaction {
+        signature: versioned
+            .signatures
+            .first()
+            .context("the transaction has no signature")?
+            .to_string(),
+        slot: transaction.slot,
+        block_time: transaction.block_time,
+        fee_payer: key(0)?,
+        error: meta.err.as_ref().map(ToString::to_string),
+        writable_accounts,
+        instructions,
+        token_mints,
+    })
+}
+
+fn token_transfer(instruction: &InnerInstruction) -> Option<TokenTransfer> {
+    let InnerInstruction {
+        program,
+        accounts,
+        data,
+    } = instruction;
+    if *program != anchor_spl::token::ID || data.len() < 9 {
+        return None;
+    }
+    let amount = u64::from_le_bytes(data[1..9].try_into().ok()?);
+    let (source, destination) = match data[0] {
+        TOKEN_TRANSFER => (accounts.first()?, accounts.get(1)?),
+        TOKEN_TRANSFER_CHECKED => (accounts.first()?, accounts.get(2)?),
+        _ => return None,
+    };
+    Some(TokenTransfer {
+        source: *source,
+        destination: *destination,
+        amount,
+    })
+}
+
+pub fn parse_pubkey(value: &str) -> Result<Pubkey> {
+    Pubkey::from_str(value).map_err(|err| anyhow!("invalid public key {}: {}", value, err))
+}
@@ -0,0 +1,399 @@
+//! Applying transactions to the database
+//!
+//! Each transaction is written in one SQLite transaction together with the checkpoint, and
+//! transactions already in the `transactions` table are skipped, so a sync can be stopped
+//! and rerun at any point. Failed transactions are recorded without their effects.
+//!
+//! Account tables come from the account snapshots of an entry. Deposits and fee flows come
+//! from top-level program instructions: their arguments and the token transfers they made.
+
+use crate::decode::{decode_transaction, DecodedTransaction, ProgramInstruction};
+use crate::ledger::{LedgerEntry, RpcLedger};
+use crate::store::{self, DepositRow, FeeFlowRow, InstructionRow};
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rusqlite::Connection;
+use shop_client::accounts::ShopAccount;
+use solana_e_commerce::instruction;
+
+// Positions in the instruction accounts, the field order of the program's Accounts structs
+const DEPOSIT_MERCHANT: usize = 1;
+const MANAGE_DEPOSIT_MINT: usize = 4;
+const WITHDRAW_DEPOSIT_MINT: usize = 5;
+const DEDUCT_DEPOSIT_MINT: usize = 4;
+const SETTLEMENT_ORDER: usize = 0;
+const SETTLEMENT_MERCHANT: usize = 2;
+const CONFIRM_DELIVERY_DEPOSIT_ESCROW: usize = 11;
+const CONFIRM_DELIVERY_VAULT_TOKEN_ACCOUNT: usize = 14;
+const REFUND_DELIVERED_MINT: usize = 10;
+const REFUND_DELIVERED_DEPOSIT_ESCROW: usize = 11;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub indexed: usize,
+    pub skipped: usize,
+}
+
+impl Summary {
+    fn add(&mut self, indexed: bool) {
+        if indexed {
+            self.indexed += 1;
+        } else {
+            self.skipped += 1;
+        }
+    }
+}
+
+pub fn sync_ledger(conn: &mut Connection, entries: &[LedgerEntry]) -> Result<Summary> {
+    let mut summary = Summary::default();
+    for entry in entries {
+        summary.add(index_entry(conn, entry)?);
+    }
+    Ok(summary)
+}
+
+/// Index the program's transactions after the checkpoint
+pub fn sync_rpc(conn: &mut Connection, ledger: &RpcLedger) -> Result<Summary> {
+    let checkpoint = store::checkpoint(conn)?;
+    let signatures =
+        ledger.signatures_after(checkpoint.as_ref().map(|checkpoint| &*checkpoint.signature))?;
+
+    let mut summary = Summary::default();
+    for signature in &signatures {
+        if store::is_indexed(conn, &signature.to_string())? {
+            summary.add(false);
+            continue;
+        }
+        summary.add(index_entry(conn, &ledger.entry(signature)?)?);
+    }
+    Ok(summary)
+}
+
+/// Index one transaction, false when it was already indexed
+pub fn index_entry(conn: &mut Connection, entry: &LedgerEntry) -> Result<bool> {
+    let transaction = decode_transaction(&entry.transaction)?;
+    if store::is_indexed(conn, &transaction.signature)? {
+        return Ok(false);
+    }
+
+    let db = conn.transaction()?;
+    store::insert_transaction(
+        &db,
+        &transaction.signature,
+        transaction.slot,
+        transaction.block_time,
+        &transaction.fee_payer,
+        transaction.error.as_deref(),
+    )?;
+    for (seq, instruction) in transaction.instructions.iter().enumerate() {
+        let accounts: Vec<String> = instruction
+            .accounts
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        store::insert_instruction(
+            &db,
+            &InstructionRow {
+                signature: &transaction.signature,
+                seq,
+                outer_index: instruction.outer_index,
+                cpi: instruction.cpi,
+                name: instruction.name,
+                accounts: serde_json::to_string(&accounts)?,
+                data: BASE64.encode(&instruction.data),
+            },
+        )?;
+    }
+
+    if transaction.error.is_none() {
+        // Accounts first, fee flows read the settled order
+        for snapshot in &entry.accounts {
+            let address = crate::decode::parse_pubkey(&snapshot.pubkey)?;
+            match snapshot.data()? {
+                Some(data) => apply_account(&db, &address, &data, transaction.slot)?,
+                None => store::mark_closed(&db, &address, transaction.slot)?,
+            }
+        }
+        for (seq, instruction) in transaction.instructions.iter().enumerate() {
+            if !instruction.cpi {
+                apply_instruction(&db, &transaction, seq, instruction).with_context(|| {
+                    format!("indexing instruction {} of {}", seq, transaction.signature)
+                })?;
+            }
+        }
+    }
+
+    store::set_checkpoint(&db, transaction.slot, &transaction.signature)?;
+    db.commit()?;
+    Ok(true)
+}
+
+fn apply_account(
+    db: &Connection,
+    address: &anchor_lang::prelude::Pubkey,
+    data: &[u8],
+    slot: u64,
+) -> Result<()> {
+    // Accounts of other types are not mirrored
+    match ShopAccount::decode(data) {
+        Ok(ShopAccount::Merchant(merchant)) => store::upsert_merchant(db, address, &merchant, slot),
+        Ok(ShopAccount::ProductBase(product)) => store::upsert_product(db, address, &product, slot),
+        Ok(ShopAccount::Order(order)) => store::upsert_order(db, address, &order, slot),
+        _ => Ok(()),
+    }
+}
+
+fn apply_instruction(
+    db: &Connection,
+    transaction: &DecodedTransaction,
+    seq: usize,
+    instruction: &ProgramInstruction,
+) -> Result<()> {
+    let deposit = |kind, merchant, mint, amount| {
+        store::insert_deposit(
+            db,
+            &DepositRow {
+                signature: &transaction.signature,
+                seq,
+                slot: transaction.slot,
+                block_time: transaction.block_time,
+                merchant,
+                kind,
+                mint,
+                amount,
+            },
+        )
+    };
+
+    match instruction.name {
+        Some("manage_deposit") => {
+            let args: instruction::ManageDeposit = instruction.args()?;
+            deposit(
+                "deposit",
+                instruction.account(DEPOSIT_MERCHANT)?,
+                Some(instruction.account(MANAGE_DEPOSIT_MINT)?),
+                args.amount,
+            )
+        }
+        Some("withdraw_merchant_deposit") => {
+            let args: instruction::WithdrawMerchantDeposit = instruction.args()?;
+            deposit(
+                "withdraw",
+                instruction.account(DEPOSIT_MERCHANT)?,
+                Some(instruction.account(WITHDRAW_DEPOSIT_MINT)?),
+                args.amount,
+            )
+        }
+        Some("deduct_merchant_deposit") => {
+            let args: instruction::DeductMerchantDeposit = instruction.args()?;
+            deposit(
+                "deduct",
+                instruction.account(DEPOSIT_MERCHANT)?,
+                Some(instruction.account(DEDUCT_DEPOSIT_MINT)?),
+                args.amount,
+            )
+        }
+        Some("refund_delivered_order") => {
+            let escrow = instruction.account(REFUND_DELIVERED_DEPOSIT_ESCROW)?;
+            deposit(
+                "refund",
+                instruction.account(SETTLEMENT_MERCHANT)?,
+                Some(instruction.account(REFUND_DELIVERED_MINT)?),
+                instruction.transferred_from(&escrow),
+            )
+        }
+        Some("confirm_delivery") => {
+            let order = instruction.account(SETTLEMENT_ORDER)?;
+            let merchant = instruction.account(SETTLEMENT_MERCHANT)?;
+            let escrow = instruction.account(CONFIRM_DELIVERY_DEPOSIT_ESCROW)?;
+            let vault = instruction.account(CONFIRM_DELIVERY_VAULT_TOKEN_ACCOUNT)?;
+            let mint = transaction.token_mints.get(&escrow).copied();
+
+            // The merchant amount is credited to the merchant's deposit
+            let merchant_amount = instruction.transferred_to(&escrow);
+            deposit("settlement", merchant, mint, merchant_amount)?;
+
+            let platform_fee =
+                store::order_total(db, &order)?.map(|total| total.saturating_sub(merchant_amount));
+            store::insert_fee_flow(
+                db,
+                &FeeFlowRow {
+                    signature: &transaction.signature,
+                    seq,
+                    slot: transaction.slot,
+                    block_time: transaction.block_time,
+                    order,
+                    merchant,
+                    mint,
+                    merchant_amount,
+                    platform_fee,
+                    vault_fee: instruction.transferred_to(&vault),
+                },
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::read_ledger;
+    use std::path::Path;
+
+    fn fixture() -> Vec<LedgerEntry> {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/ledger.jsonl");
+        read_ledger(Path::new(path)).unwrap()
+    }
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        store::init(&conn).unwrap();
+        conn
+    }
+
+    // Every row of every table, to compare databases
+    fn dump(conn: &Connection) -> Vec<String> {
+        let mut rows = Vec::new();
+        for table in [
+            "checkpoint",
+            "transactions",
+            "instructions",
+            "merchants",
+            "products",
+            "orders",
+            "deposits",
+            "fee_flows",
+        ] {
+            let mut statement = conn
+                .prepare(&format!("SELECT * FROM {table} ORDER BY 1, 2"))
+                .unwrap();
+            let columns = statement.column_count();
+            let table_rows = statement
+                .query_map([], |row| {
+                    let values = (0..columns)
+                        .map(|index| Ok(format!("{:?}", row.get_ref(index)?)))
+                        .collect::<rusqlite::Result<Vec<_>>>()?;
+                    Ok(format!("{table}: {}", values.join(", ")))
+                })
+                .unwrap()
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .unwrap();
+            rows.extend(table_rows);
+        }
+        rows
+    }
+
+    #[test]
+    fn test_index_recorded_ledger() {
+        let entries = fixture();
+        let mut conn = database();
+        let summary = sync_ledger(&mut conn, &entries).unwrap();
+        assert_eq!(
+            summary,
+            Summary {
+                indexed: 9,
+                skipped: 0
+            }
+        );
+
+        let merchant: (String, u64, u64, bool) = conn
+            .query_row(
+                "SELECT name, deposit_amount, total_sales, closed FROM merchants",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            merchant,
+            ("Harbor Goods".to_string(), 599_600_000, 2, false)
+        );
+
+        // The hard deleted product is kept, marked closed
+        let product: (u64, u32, bool) = conn
+            .query_row("SELECT id, sales, closed FROM products", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(product, (10_000, 2, true));
+
+        let order: (String, u64, String) = conn
+            .query_row(
+                "SELECT status, total_amount, tracking_number FROM orders",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            order,
+            (
+                "Delivered".to_string(),
+                100_000_000,
+                "PT123456789".to_string()
+            )
+        );
+
+        // The failed withdrawal is recorded without effects
+        let deposits: Vec<(String, u64)> = conn
+            .prepare("SELECT kind, amount FROM deposits ORDER BY slot")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            deposits,
+            vec![
+                ("deposit".to_string(), 1_000_000_000),
+                ("settlement".to_string(), 99_600_000),
+                ("withdraw".to_string(), 500_000_000),
+            ]
+        );
+        let failed: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM transactions WHERE error IS NOT NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(failed, 1);
+
+        let fee: (u64, u64, u64) = conn
+            .query_row(
+                "SELECT merchant_amount, platform_fee, vault_fee FROM fee_flows",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(fee, (99_600_000, 400_000, 400_000));
+
+        let checkpoint = store::checkpoint(&conn).unwrap().unwrap();
+        assert_eq!(checkpoint.slot, 108);
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint_is_idempotent() {
+        let entries = fixture();
+        let mut full = database();
+        sync_ledger(&mut full, &entries).unwrap();
+
+        // Stopped after four transactions, then rerun over the whole ledger
+        let mut resumed = database();
+        sync_ledger(&mut resumed, &entries[..4]).unwrap();
+        assert_eq!(store::checkpoint(&resumed).unwrap().unwrap().slot, 103);
+        let summary = sync_ledger(&mut resumed, &entries).unwrap();
+        assert_eq!(
+            summary,
+            Summary {
+                indexed: 5,
+                skipped: 4
+            }
+        );
+        assert_eq!(dump(&resumed), dump(&full));
+
+        let summary = sync_ledger(&mut resumed, &entries).unwrap();
+        assert_eq!(summary.indexed, 0);
+        assert_eq!(dump(&resumed), dump(&full));
+    }
+}
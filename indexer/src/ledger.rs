@@ -0,0 +1,178 @@
+//! Sources of confirmed transactions: a validator over RPC, or a recorded ledger dump
+//!
+//! A dump is a JSON Lines file of [`LedgerEntry`], oldest first, as written by
+//! `shop-indexer record`.
+
+use crate::decode::decode_transaction;
+use anchor_lang::prelude::Pubkey;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use solana_commitment_config::CommitmentConfig;
+use solana_rpc_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_rpc_client_api::config::RpcTransactionConfig;
+use solana_signature::Signature;
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding,
+};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A transaction invoking the program, with the program accounts it wrote
+#[derive(Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub transaction: EncodedConfirmedTransactionWithStatusMeta,
+    #[serde(default)]
+    pub accounts: Vec<AccountSnapshot>,
+}
+
+/// Data of a program account after the transaction, None once the account is closed
+#[derive(Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    pub pubkey: String,
+    /// Base64 account data
+    pub data: Option<String>,
+}
+
+impl AccountSnapshot {
+    pub fn data(&self) -> Result<Option<Vec<u8>>> {
+        self.data
+            .as_ref()
+            .map(|data| BASE64.decode(data))
+            .transpose()
+            .with_context(|| format!("decoding the data of account {}", self.pubkey))
+    }
+}
+
+pub fn read_ledger(path: &Path) -> Result<Vec<LedgerEntry>> {
+    let file = std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut entries = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("reading {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .with_context(|| format!("parsing {} line {}", path.display(), number + 1))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+pub fn write_entry(writer: &mut impl Write, entry: &LedgerEntry) -> Result<()> {
+    serde_json::to_writer(&mut *writer, entry)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+// getSignaturesForAddress page size limit
+const SIGNATURE_PAGE: usize = 1_000;
+// getMultipleAccounts request size limit
+const ACCOUNT_BATCH: usize = 100;
+
+pub struct RpcLedger {
+    rpc: RpcClient,
+}
+
+impl RpcLedger {
+    pub fn new(url: &str) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(url, CommitmentConfig::confirmed()),
+        }
+    }
+
+    /// Signatures of the program's transactions after `until`, oldest first
+    pub fn signatures_after(&self, until: Option<&str>) -> Result<Vec<Signature>> {
+        let until = until
+            .map(Signature::from_str)
+            .transpose()
+            .context("invalid checkpoint signature")?;
+
+        // Pages run newest to oldest
+        let mut signatures = Vec::new();
+        let mut before = None;
+        loop {
+            let page = self
+                .rpc
+                .get_signatures_for_address_with_config(
+                    &shop_client::ID,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until,
+                        limit: Some(SIGNATURE_PAGE),
+                        commitment: Some(self.rpc.commitment()),
+                    },
+                )
+                .context("fetching the program's signatures")?;
+            let last_page = page.len() < SIGNATURE_PAGE;
+            for status in page {
+                signatures
+                    .push(Signature::from_str(&status.signature).context("invalid signature")?);
+            }
+            if last_page {
+                break;
+            }
+            before = signatures.last().copied();
+        }
+
+        signatures.reverse();
+        Ok(signatures)
+    }
+
+    /// A transaction with the current data of the program accounts it wrote
+    ///
+    /// Accounts are fetched now rather than at the transaction's slot, so replaying old
+    /// transactions fills the account tables with their latest state.
+    pub fn entry(&self, signature: &Signature) -> Result<LedgerEntry> {
+        let transaction = self
+            .rpc
+            .get_transaction_with_config(
+                signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(self.rpc.commitment()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .with_context(|| format!("fetching transaction {}", signature))?;
+
+        let decoded = decode_transaction(&transaction)?;
+        let accounts = if decoded.error.is_none() {
+            self.snapshots(&decoded.writable_accounts)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(LedgerEntry {
+            transaction,
+            accounts,
+        })
+    }
+
+    fn snapshots(&self, addresses: &[Pubkey]) -> Result<Vec<AccountSnapshot>> {
+        let mut snapshots = Vec::new();
+        for batch in addresses.chunks(ACCOUNT_BATCH) {
+            let accounts = self
+                .rpc
+                .get_multiple_accounts(batch)
+                .context("fetching the written accounts")?;
+            for (address, account) in batch.iter().zip(accounts) {
+                // Missing accounts were closed, other owners are not indexed
+                let data = match account {
+                    Some(account) if account.owner == shop_client::ID => {
+                        Some(BASE64.encode(&account.data))
+                    }
+                    Some(_) => continue,
+                    None => None,
+                };
+                snapshots.push(AccountSnapshot {
+                    pubkey: address.to_string(),
+                    data,
+                });
+            }
+        }
+        Ok(snapshots)
+    }
+}
@@ -0,0 +1,147 @@
+//! `shop-indexer`: a queryable SQLite mirror of the solana-e-commerce program
+//!
+//! Transactions are read from a validator over RPC or from a recorded ledger dump, and
+//! indexed into tables of merchants, products, orders, deposit movements and fee flows.
+//! Syncs resume from the checkpoint of the database and skip indexed transactions.
+
+mod decode;
+mod indexer;
+mod ledger;
+mod store;
+
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+use ledger::RpcLedger;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(
+    name = "shop-indexer",
+    version,
+    about = "Index solana-e-commerce transactions into SQLite"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Index new transactions into the database
+    Sync {
+        /// SQLite database, created if missing
+        #[arg(long, env = "SHOP_INDEXER_DB", default_value = "shop.db")]
+        db: PathBuf,
+        #[command(flatten)]
+        source: Source,
+        /// Keep polling the validator for new transactions
+        #[arg(long, conflicts_with = "ledger")]
+        follow: bool,
+        /// Seconds between polls with --follow
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+    },
+    /// Write the program's transactions to a ledger dump, appending to FILE
+    Record {
+        /// RPC URL or moniker: localhost, devnet, testnet, mainnet-beta
+        #[arg(long, short = 'u', env = "SHOP_RPC_URL", default_value = "localhost")]
+        url: String,
+        #[arg(long, value_name = "FILE")]
+        out: PathBuf,
+        /// Only record transactions after this signature
+        #[arg(long)]
+        after: Option<String>,
+    },
+}
+
+#[derive(Args)]
+#[group(multiple = false)]
+struct Source {
+    /// RPC URL or moniker: localhost, devnet, testnet, mainnet-beta
+    #[arg(long, short = 'u', env = "SHOP_RPC_URL", default_value = "localhost")]
+    url: String,
+    /// Ledger dump to index instead of the validator
+    #[arg(long, value_name = "FILE")]
+    ledger: Option<PathBuf>,
+}
+
+fn rpc_url(url: &str) -> &str {
+    match url {
+        "localhost" | "l" => "http://127.0.0.1:8899",
+        "devnet" | "d" => "https://api.devnet.solana.com",
+        "testnet" | "t" => "https://api.testnet.solana.com",
+        "mainnet-beta" | "m" => "https://api.mainnet-beta.solana.com",
+        url => url,
+    }
+}
+
+fn main() {
+    if let Err(err) = run(Cli::parse()) {
+        eprintln!("Error: {:#}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Command::Sync {
+            db,
+            source,
+            follow,
+            interval,
+        } => {
+            let mut conn = store::open(&db)?;
+            if let Some(path) = source.ledger {
+                let entries = ledger::read_ledger(&path)?;
+                let summary = indexer::sync_ledger(&mut conn, &entries)?;
+                return report(&conn, &summary);
+            }
+
+            let rpc = RpcLedger::new(rpc_url(&source.url));
+            loop {
+                let summary = indexer::sync_rpc(&mut conn, &rpc)?;
+                if !follow {
+                    return report(&conn, &summary);
+                }
+                if summary.indexed > 0 {
+                    report(&conn, &summary)?;
+                }
+                std::thread::sleep(Duration::from_secs(interval));
+            }
+        }
+        Command::Record { url, out, after } => {
+            let rpc = RpcLedger::new(rpc_url(&url));
+            let signatures = rpc.signatures_after(after.as_deref())?;
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&out)
+                .with_context(|| format!("opening {}", out.display()))?;
+            let mut writer = std::io::BufWriter::new(file);
+            for signature in &signatures {
+                ledger::write_entry(&mut writer, &rpc.entry(signature)?)?;
+            }
+            writer.flush()?;
+            println!(
+                "Recorded {} transactions to {}",
+                signatures.len(),
+                out.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+fn report(conn: &rusqlite::Connection, summary: &indexer::Summary) -> Result<()> {
+    print!(
+        "Indexed {} transactions, skipped {} already indexed",
+        summary.indexed, summary.skipped
+    );
+    match store::checkpoint(conn)? {
+        Some(checkpoint) => println!(", checkpoint slot {}", checkpoint.slot),
+        None => println!(),
+    }
+    Ok(())
+}
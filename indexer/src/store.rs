@@ -0,0 +1,437 @@
+//! SQLite schema and row writers
+//!
+//! Account tables hold the latest decoded state of each account, rows are only replaced by
+//! a snapshot from the same or a later slot. Event tables are keyed by (signature, seq),
+//! `seq` being the position of the program instruction in the transaction.
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use solana_e_commerce::state::{Merchant, Order, ProductBase};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS checkpoint (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    slot INTEGER NOT NULL,
+    signature TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS transactions (
+    signature TEXT PRIMARY KEY,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    fee_payer TEXT NOT NULL,
+    error TEXT
+);
+
+CREATE TABLE IF NOT EXISTS instructions (
+    signature TEXT NOT NULL REFERENCES transactions (signature),
+    seq INTEGER NOT NULL,
+    outer_index INTEGER NOT NULL,
+    cpi INTEGER NOT NULL,
+    name TEXT,
+    accounts TEXT NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (signature, seq)
+);
+CREATE INDEX IF NOT EXISTS instructions_name ON instructions (name);
+
+CREATE TABLE IF NOT EXISTS merchants (
+    address TEXT PRIMARY KEY,
+    owner TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL,
+    product_count INTEGER NOT NULL,
+    total_sales INTEGER NOT NULL,
+    is_active INTEGER NOT NULL,
+    deposit_amount INTEGER NOT NULL,
+    deposit_token_mint TEXT NOT NULL,
+    deposit_locked INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    closed INTEGER NOT NULL DEFAULT 0,
+    slot INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS merchants_owner ON merchants (owner);
+
+CREATE TABLE IF NOT EXISTS products (
+    address TEXT PRIMARY KEY,
+    id INTEGER NOT NULL,
+    merchant TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL,
+    price INTEGER NOT NULL,
+    keywords TEXT NOT NULL,
+    inventory INTEGER NOT NULL,
+    sales INTEGER NOT NULL,
+    is_active INTEGER NOT NULL,
+    payment_token TEXT NOT NULL,
+    shipping_location TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    closed INTEGER NOT NULL DEFAULT 0,
+    slot INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS products_merchant ON products (merchant);
+
+CREATE TABLE IF NOT EXISTS orders (
+    address TEXT PRIMARY KEY,
+    buyer TEXT NOT NULL,
+    merchant TEXT NOT NULL,
+    product_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    price INTEGER NOT NULL,
+    total_amount INTEGER NOT NULL,
+    payment_token TEXT NOT NULL,
+    status TEXT NOT NULL,
+    shipping_address TEXT NOT NULL,
+    notes TEXT NOT NULL,
+    tracking_number TEXT NOT NULL,
+    refund_reason TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    confirmed_at INTEGER,
+    shipped_at INTEGER,
+    delivered_at INTEGER,
+    refunded_at INTEGER,
+    closed INTEGER NOT NULL DEFAULT 0,
+    slot INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS orders_buyer ON orders (buyer);
+CREATE INDEX IF NOT EXISTS orders_merchant ON orders (merchant);
+
+-- Movements of merchant deposits: deposit, withdraw, deduct, settlement, refund
+CREATE TABLE IF NOT EXISTS deposits (
+    signature TEXT NOT NULL REFERENCES transactions (signature),
+    seq INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    merchant TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    mint TEXT,
+    amount INTEGER NOT NULL,
+    PRIMARY KEY (signature, seq)
+);
+CREATE INDEX IF NOT EXISTS deposits_merchant ON deposits (merchant);
+
+-- Platform fees of settled orders; the fee not sent to the vault stays in escrow
+CREATE TABLE IF NOT EXISTS fee_flows (
+    signature TEXT NOT NULL REFERENCES transactions (signature),
+    seq INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    order_address TEXT NOT NULL,
+    merchant TEXT NOT NULL,
+    mint TEXT,
+    merchant_amount INTEGER NOT NULL,
+    platform_fee INTEGER,
+    vault_fee INTEGER NOT NULL,
+    PRIMARY KEY (signature, seq)
+);
+";
+
+pub fn open(path: &Path) -> Result<Connection> {
+    let conn =
+        Connection::open(path).with_context(|| format!("opening database {}", path.display()))?;
+    init(&conn)?;
+    Ok(conn)
+}
+
+pub fn init(conn: &Connection) -> Result<()> {
+    conn.execute_batch(SCHEMA).context("creating the schema")
+}
+
+pub struct Checkpoint {
+    pub slot: u64,
+    pub signature: String,
+}
+
+pub fn checkpoint(conn: &Connection) -> Result<Option<Checkpoint>> {
+    conn.query_row(
+        "SELECT slot, signature FROM checkpoint WHERE id = 1",
+        [],
+        |row| {
+            Ok(Checkpoint {
+                slot: row.get(0)?,
+                signature: row.get(1)?,
+            })
+        },
+    )
+    .optional()
+    .context("reading the checkpoint")
+}
+
+// Only moves forward, so an older transaction indexed late keeps the checkpoint in place
+pub fn set_checkpoint(conn: &Connection, slot: u64, signature: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO checkpoint (id, slot, signature) VALUES (1, ?1, ?2)
+         ON CONFLICT (id) DO UPDATE SET slot = excluded.slot, signature = excluded.signature
+         WHERE excluded.slot >= checkpoint.slot",
+        params![slot, signature],
+    )?;
+    Ok(())
+}
+
+pub fn is_indexed(conn: &Connection, signature: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM transactions WHERE signature = ?1",
+        [signature],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+pub fn insert_transaction(
+    conn: &Connection,
+    signature: &str,
+    slot: u64,
+    block_time: Option<i64>,
+    fee_payer: &Pubkey,
+    error: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO transactions (signature, slot, block_time, fee_payer, error)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![signature, slot, block_time, fee_payer.to_string(), error],
+    )?;
+    Ok(())
+}
+
+pub struct InstructionRow<'a> {
+    pub signature: &'a str,
+    pub seq: usize,
+    pub outer_index: usize,
+    pub cpi: bool,
+    pub name: Option<&'a str>,
+    /// JSON array of the account addresses
+    pub accounts: String,
+    /// Base64 instruction data
+    pub data: String,
+}
+
+pub fn insert_instruction(conn: &Connection, row: &InstructionRow) -> Result<()> {
+    conn.execute(
+        "INSERT INTO instructions (signature, seq, outer_index, cpi, name, accounts, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            row.signature,
+            row.seq,
+            row.outer_index,
+            row.cpi,
+            row.name,
+            row.accounts,
+            row.data
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn upsert_merchant(
+    conn: &Connection,
+    address: &Pubkey,
+    merchant: &Merchant,
+    slot: u64,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO merchants (address, owner, name, description, product_count, total_sales,
+             is_active, deposit_amount, deposit_token_mint, deposit_locked, created_at,
+             updated_at, closed, slot)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 0, ?13)
+         ON CONFLICT (address) DO UPDATE SET owner = excluded.owner, name = excluded.name,
+             description = excluded.description, product_count = excluded.product_count,
+             total_sales = excluded.total_sales, is_active = excluded.is_active,
+             deposit_amount = excluded.deposit_amount,
+             deposit_token_mint = excluded.deposit_token_mint,
+             deposit_locked = excluded.deposit_locked, created_at = excluded.created_at,
+             updated_at = excluded.updated_at, closed = 0, slot = excluded.slot
+         WHERE excluded.slot >= merchants.slot",
+        params![
+            address.to_string(),
+            merchant.owner.to_string(),
+            merchant.name,
+            merchant.description,
+            merchant.product_count,
+            merchant.total_sales,
+            merchant.is_active,
+            merchant.deposit_amount,
+            merchant.deposit_token_mint.to_string(),
+            merchant.deposit_locked,
+            merchant.created_at,
+            merchant.updated_at,
+            slot
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn upsert_product(
+    conn: &Connection,
+    address: &Pubkey,
+    product: &ProductBase,
+    slot: u64,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO products (address, id, merchant, name, description, price, keywords,
+             inventory, sales, is_active, payment_token, shipping_location, created_at,
+             updated_at, closed, slot)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, 0, ?15)
+         ON CONFLICT (address) DO UPDATE SET id = excluded.id, merchant = excluded.merchant,
+             name = excluded.name, description = excluded.description,
+             price = excluded.price, keywords = excluded.keywords,
+             inventory = excluded.inventory, sales = excluded.sales,
+             is_active = excluded.is_active, payment_token = excluded.payment_token,
+             shipping_location = excluded.shipping_location,
+             created_at = excluded.created_at, updated_at = excluded.updated_at, closed = 0,
+             slot = excluded.slot
+         WHERE excluded.slot >= products.slot",
+        params![
+            address.to_string(),
+            product.id,
+            product.merchant.to_string(),
+            product.name,
+            product.description,
+            product.price,
+            product.keywords,
+            product.inventory,
+            product.sales,
+            product.is_active,
+            product.payment_token.to_string(),
+            product.shipping_location,
+            product.created_at,
+            product.updated_at,
+            slot
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn upsert_order(conn: &Connection, address: &Pubkey, order: &Order, slot: u64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO orders (address, buyer, merchant, product_id, quantity, price,
+             total_amount, payment_token, status, shipping_address, notes, tracking_number,
+             refund_reason, created_at, updated_at, confirmed_at, shipped_at, delivered_at,
+             refunded_at, closed, slot)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+             ?18, ?19, 0, ?20)
+         ON CONFLICT (address) DO UPDATE SET buyer = excluded.buyer,
+             merchant = excluded.merchant, product_id = excluded.product_id,
+             quantity = excluded.quantity, price = excluded.price,
+             total_amount = excluded.total_amount, payment_token = excluded.payment_token,
+             status = excluded.status, shipping_address = excluded.shipping_address,
+             notes = excluded.notes, tracking_number = excluded.tracking_number,
+             refund_reason = excluded.refund_reason, created_at = excluded.created_at,
+             updated_at = excluded.updated_at, confirmed_at = excluded.confirmed_at,
+             shipped_at = excluded.shipped_at, delivered_at = excluded.delivered_at,
+             refunded_at = excluded.refunded_at, closed = 0, slot = excluded.slot
+         WHERE excluded.slot >= orders.slot",
+        params![
+            address.to_string(),
+            order.buyer.to_string(),
+            order.merchant.to_string(),
+            order.product_id,
+            order.quantity,
+            order.price,
+            order.total_amount,
+            order.payment_token.to_string(),
+            format!("{:?}", order.status),
+            order.shipping_address,
+            order.notes,
+            order.tracking_number,
+            order.refund_reason,
+            order.created_at,
+            order.updated_at,
+            order.confirmed_at,
+            order.shipped_at,
+            order.delivered_at,
+            order.refunded_at,
+            slot
+        ],
+    )?;
+    Ok(())
+}
+
+/// Mark a closed account in whichever account table holds it
+pub fn mark_closed(conn: &Connection, address: &Pubkey, slot: u64) -> Result<()> {
+    for table in ["merchants", "products", "orders"] {
+        conn.execute(
+            &format!("UPDATE {table} SET closed = 1, slot = ?2 WHERE address = ?1 AND slot <= ?2"),
+            params![address.to_string(), slot],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn order_total(conn: &Connection, address: &Pubkey) -> Result<Option<u64>> {
+    conn.query_row(
+        "SELECT total_amount FROM orders WHERE address = ?1",
+        [address.to_string()],
+        |row| row.get(0),
+    )
+    .optional()
+    .context("reading an order")
+}
+
+pub struct DepositRow<'a> {
+    pub signature: &'a str,
+    pub seq: usize,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub merchant: Pubkey,
+    pub kind: &'static str,
+    pub mint: Option<Pubkey>,
+    pub amount: u64,
+}
+
+pub fn insert_deposit(conn: &Connection, row: &DepositRow) -> Result<()> {
+    conn.execute(
+        "INSERT INTO deposits (signature, seq, slot, block_time, merchant, kind, mint, amount)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            row.signature,
+            row.seq,
+            row.slot,
+            row.block_time,
+            row.merchant.to_string(),
+            row.kind,
+            row.mint.map(|mint| mint.to_string()),
+            row.amount
+        ],
+    )?;
+    Ok(())
+}
+
+pub struct FeeFlowRow<'a> {
+    pub signature: &'a str,
+    pub seq: usize,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub order: Pubkey,
+    pub merchant: Pubkey,
+    pub mint: Option<Pubkey>,
+    pub merchant_amount: u64,
+    /// Order total less the merchant amount, None when the order was never indexed
+    pub platform_fee: Option<u64>,
+    pub vault_fee: u64,
+}
+
+pub fn insert_fee_flow(conn: &Connection, row: &FeeFlowRow) -> Result<()> {
+    conn.execute(
+        "INSERT INTO fee_flows (signature, seq, slot, block_time, order_address, merchant, mint,
+             merchant_amount, platform_fee, vault_fee)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            row.signature,
+            row.seq,
+            row.slot,
+            row.block_time,
+            row.order.to_string(),
+            row.merchant.to_string(),
+            row.mint.map(|mint| mint.to_string()),
+            row.merchant_amount,
+            row.platform_fee,
+            row.vault_fee
+        ],
+    )?;
+    Ok(())
+}
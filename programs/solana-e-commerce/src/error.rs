@@ -225,4 +225,98 @@ pub enum ErrorCode {
     InvalidVaultTokenAccount,
     #[msg("Invalid platform token account")]
     InvalidPlatformTokenAccount,
+
+    // Keyword shard merge related errors
+    #[msg("Shard does not need merging")]
+    ShardMergeNotNeeded,
+    #[msg("Shards are not linked neighbors")]
+    InvalidShardLink,
+
+    // Price index tree related errors
+    #[msg("Price index node cannot be split")]
+    PriceNodeCannotSplit,
+    #[msg("Price index node is not a leaf")]
+    PriceNodeNotLeaf,
+    #[msg("Invalid price index node link")]
+    InvalidPriceNodeLink,
+
+    // Composite search related errors
+    #[msg("Product account does not match the search cursor")]
+    SearchCursorMismatch,
+
+    // Merchant product catalog related errors
+    #[msg("Invalid merchant product catalog page")]
+    InvalidProductCatalogPage,
+
+    // Recency feed related errors
+    #[msg("Invalid recency bucket")]
+    InvalidRecencyBucket,
+
+    // Trending ranking related errors
+    #[msg("Invalid trending half-life")]
+    InvalidTrendingHalfLife,
+
+    // Bestseller leaderboard related errors
+    #[msg("Leaderboard scope does not include the product")]
+    InvalidLeaderboard,
+
+    // Zero-copy index shard related errors
+    #[msg("Invalid index shard capacity")]
+    InvalidIndexShardCapacity,
+
+    // ID recycling related errors
+    #[msg("ID is still used by a product")]
+    IdStillInUse,
+
+    // Escrow reconciliation related errors
+    #[msg("Escrow withdrawals are blocked until the escrow account is solvent")]
+    EscrowWithdrawalsBlocked,
+
+    // Order statistics related errors
+    #[msg("Order statistics day does not match the current day")]
+    InvalidOrderStatsDay,
+    #[msg("Order statistics do not belong to the merchant or are listed twice")]
+    InvalidMerchantOrderStats,
+
+    // Review related errors
+    #[msg("Only delivered orders can be reviewed")]
+    OrderNotDelivered,
+    #[msg("Rating must be between 1 and 5")]
+    InvalidRating,
+    #[msg("Invalid review text length")]
+    InvalidReviewTextLength,
+    #[msg("Invalid review reply length")]
+    InvalidReviewReplyLength,
+    #[msg("Review already has a reply")]
+    ReviewAlreadyReplied,
+
+    // Reputation related errors
+    #[msg("A lost dispute needs a reason")]
+    InvalidDisputeReason,
+
+    // Product variant related errors
+    #[msg("Variant needs 1 to 3 options with distinct, non-empty names and values")]
+    InvalidVariantOptions,
+    #[msg("Invalid SKU length")]
+    InvalidSkuLength,
+    #[msg("Product has variants, the order must name one")]
+    ProductVariantRequired,
+    #[msg("Variant does not belong to the product or order")]
+    InvalidProductVariant,
+    #[msg("Product variant is not active")]
+    ProductVariantInactive,
+    #[msg("Insufficient variant inventory")]
+    InsufficientVariantInventory,
+
+    // Migration related errors
+    #[msg("Account already uses the current layout")]
+    AccountAlreadyMigrated,
+    #[msg("Account does not hold the legacy layout being migrated")]
+    InvalidLegacyAccount,
+    #[msg("Account still uses a legacy layout, migrate it first")]
+    AccountNotMigrated,
+
+    // Search related errors
+    #[msg("Price filters and price sorting need price index accounts of a single payment token")]
+    InvalidPriceIndexAccounts,
 }
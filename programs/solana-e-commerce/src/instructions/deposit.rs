@@ -1,5 +1,6 @@
 use crate::error::ErrorCode;
 use crate::state::merchant::Merchant;
+use crate::state::{MerchantReputation, ReputationEvent};
 use crate::SystemConfig;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
@@ -399,7 +400,18 @@ pub struct DeductMerchantDeposit<'info> {
     )]
     pub admin_token_account: Account<'info, TokenAccount>,
 
+    // Reputation of the merchant
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MerchantReputation::INIT_SPACE,
+        seeds = [b"merchant_reputation", merchant_owner.key().as_ref()],
+        bump
+    )]
+    pub merchant_reputation: Account<'info, MerchantReputation>,
+
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 /// Administrator deduct merchant deposit
@@ -429,7 +441,12 @@ pub fn deduct_merchant_deposit(
 
     // Execute token transfer from system escrow account to administrator account
     let deposit_escrow_bump = ctx.bumps.deposit_escrow_account;
-    let seeds = &[b"deposit_escrow".as_ref(), &[deposit_escrow_bump]];
+    let token_mint_key = ctx.accounts.deposit_token_mint.key();
+    let seeds = &[
+        b"deposit_escrow".as_ref(),
+        token_mint_key.as_ref(),
+        &[deposit_escrow_bump],
+    ];
     let signer_seeds = &[&seeds[..]];
 
     let cpi_accounts = Transfer {
@@ -445,6 +462,11 @@ pub fn deduct_merchant_deposit(
     // Update merchant deposit balance
     merchant.deduct_deposit(amount)?;
 
+    let now = Clock::get()?.unix_timestamp;
+    let reputation = &mut ctx.accounts.merchant_reputation;
+    reputation.open(merchant.owner, now, ctx.bumps.merchant_reputation);
+    reputation.record(ReputationEvent::DepositSlashed, now);
+
     msg!(
         "Administrator {} deducted merchant {} deposit {} tokens, reason: {}, remaining deposit: {}",
         ctx.accounts.authority.key(),
@@ -1,8 +1,9 @@
 use crate::error::ErrorCode;
 use crate::state::{
-    GlobalIdRoot, IdChunk, MerchantIdAccount, DEFAULT_CHUNK_SIZE, ID_CHUNK_BITMAP_SIZE,
+    GlobalIdRoot, IdChunk, MerchantIdAccount, CHUNK_PREALLOCATE_THRESHOLD, ID_CHUNK_BITMAP_SIZE,
     MAX_CHUNKS_PER_MERCHANT,
 };
+use crate::utils::close_program_account;
 use anchor_lang::prelude::*;
 
 // ID generator functionality
@@ -34,35 +35,21 @@ pub fn generate_product_id(ctx: Context<GenerateId>) -> Result<u64> {
     let merchant_account = &mut ctx.accounts.merchant_account;
     let active_chunk = &mut ctx.accounts.active_chunk;
 
-    // Check if current chunk has available IDs
-    if active_chunk.is_full() {
-        return Err(ErrorCode::NoAvailableId.into());
-    }
-
-    // Find next available ID
-    let mut local_id = active_chunk.next_available;
-    while local_id < active_chunk.capacity() {
-        if !active_chunk.is_id_used(local_id) {
-            // Allocate this ID
-            active_chunk.mark_id_used(local_id);
-            active_chunk.next_available = local_id + 1;
-            merchant_account.last_local_id = local_id;
-
-            let global_id = active_chunk.start_id + local_id;
-
-            msg!(
-                "Product ID generation successful, merchant: {}, local ID: {}, global ID: {}",
-                merchant_account.merchant_id,
-                local_id,
-                global_id
-            );
+    // Allocate the lowest available ID (released IDs are reused first)
+    let global_id = active_chunk
+        .allocate_next()
+        .ok_or(ErrorCode::NoAvailableId)?;
+    let local_id = global_id - active_chunk.start_id;
+    merchant_account.last_local_id = local_id;
 
-            return Ok(global_id);
-        }
-        local_id += 1;
-    }
+    msg!(
+        "Product ID generation successful, merchant: {}, local ID: {}, global ID: {}",
+        merchant_account.merchant_id,
+        local_id,
+        global_id
+    );
 
-    Err(ErrorCode::NoAvailableId.into())
+    Ok(global_id)
 }
 
 // 3. Allocate new chunk
@@ -119,20 +106,16 @@ pub fn allocate_new_chunk(ctx: Context<AllocateChunk>) -> Result<Pubkey> {
         ErrorCode::InvalidShardIndex
     );
 
-    // Initialize new chunk - use merchant ID based range
-    let merchant_start_id = merchant_account.merchant_id as u64 * 10000; // Reserve 10000 IDs per merchant
-    let chunk_start_id = merchant_start_id + (chunk_index as u64 * global_root.chunk_size as u64);
+    // Initialize new chunk - reserve its range from the global pool
+    let start_id = global_root.reserve_chunk_range();
     let new_chunk = &mut ctx.accounts.new_chunk;
-    new_chunk.merchant_id = merchant_account.merchant_id;
-    new_chunk.chunk_index = chunk_index;
-    new_chunk.start_id = chunk_start_id;
-    new_chunk.end_id = chunk_start_id + global_root.chunk_size as u64 - 1;
-    new_chunk.next_available = 0;
-    new_chunk.initialize_bitmap(); // Use safe initialization method
-    new_chunk.bump = ctx.bumps.new_chunk;
-
-    // Update global ID counter
-    global_root.last_global_id = new_chunk.end_id + 1;
+    new_chunk.initialize(
+        merchant_account.merchant_id,
+        chunk_index,
+        start_id,
+        global_root.chunk_size,
+        ctx.bumps.new_chunk,
+    );
 
     // Add old active chunk to unused queue
     let old_active_chunk = merchant_account.active_chunk;
@@ -164,45 +147,22 @@ pub struct VerifyId<'info> {
 
     pub merchant: Signer<'info>,
 
-    /// CHECK: Will verify this is the correct ID chunk in the instruction
-    pub id_chunk: AccountInfo<'info>,
+    // Any chunk of the merchant, the ID is looked up in its stored range
+    #[account(
+        seeds = [
+            b"id_chunk",
+            merchant.key().as_ref(),
+            &IdChunk::chunk_index_seed(id_chunk.chunk_index)
+        ],
+        bump = id_chunk.bump
+    )]
+    pub id_chunk: Account<'info, IdChunk>,
 }
 
 pub fn is_id_exists(ctx: Context<VerifyId>, id: u64) -> Result<bool> {
-    let merchant_account = &ctx.accounts.merchant_account;
-
-    // Calculate which chunk the ID should be in
-    let chunk_size = DEFAULT_CHUNK_SIZE as u64;
-    let merchant_start_id = merchant_account.merchant_id as u64 * 10000; // Reserve 10000 IDs per merchant
-
-    if id < merchant_start_id {
-        return Ok(false);
-    }
+    let chunk = &ctx.accounts.id_chunk;
 
-    let chunk_index = (id - merchant_start_id) / chunk_size;
-
-    // Verify ID chunk account
-    let merchant_key_bytes = ctx.accounts.merchant.key().to_bytes();
-    let chunk_index_bytes = (chunk_index as u32).to_le_bytes();
-    let expected_chunk_seeds = [
-        b"id_chunk".as_ref(),
-        merchant_key_bytes.as_ref(),
-        chunk_index_bytes.as_ref(),
-    ];
-
-    let (expected_chunk_key, _) =
-        Pubkey::find_program_address(&expected_chunk_seeds, &ctx.program_id);
-
-    require!(
-        ctx.accounts.id_chunk.key() == expected_chunk_key,
-        ErrorCode::InvalidPda
-    );
-
-    // Deserialize ID chunk and check
-    let chunk_account_data = ctx.accounts.id_chunk.data.borrow();
-    let chunk = IdChunk::try_deserialize(&mut &chunk_account_data[8..])?;
-
-    if id < chunk.start_id || id > chunk.end_id {
+    if !chunk.contains_id(id) {
         return Ok(false);
     }
 
@@ -237,22 +197,15 @@ pub fn batch_generate_ids(ctx: Context<BatchGenerate>, count: u16) -> Result<Vec
     require!(count > 0 && count <= 100, ErrorCode::InvalidId); // Limit batch quantity
 
     let mut ids = Vec::new();
-    let mut local_id = active_chunk.next_available;
     let mut allocated = 0u16;
 
-    while allocated < count && local_id < active_chunk.capacity() {
-        if !active_chunk.is_id_used(local_id) {
-            active_chunk.mark_id_used(local_id);
-            let global_id = active_chunk.start_id + local_id as u64;
-            ids.push(global_id);
-            allocated += 1;
-        }
-        local_id += 1;
-    }
-
-    if allocated > 0 {
-        active_chunk.next_available = local_id;
-        merchant_account.last_local_id = local_id - 1;
+    while allocated < count {
+        let Some(global_id) = active_chunk.allocate_next() else {
+            break;
+        };
+        merchant_account.last_local_id = global_id - active_chunk.start_id;
+        ids.push(global_id);
+        allocated += 1;
     }
 
     require!(allocated == count, ErrorCode::NoAvailableId);
@@ -268,21 +221,64 @@ pub fn batch_generate_ids(ctx: Context<BatchGenerate>, count: u16) -> Result<Vec
 
 // 6. ID recycling
 #[derive(Accounts)]
+#[instruction(id: u64)]
 pub struct ReleaseId<'info> {
-    #[account(mut)]
+    pub merchant: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"merchant_id", merchant.key().as_ref()],
+        bump = merchant_account.bump
+    )]
+    pub merchant_account: Account<'info, MerchantIdAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"id_chunk",
+            merchant.key().as_ref(),
+            &IdChunk::chunk_index_seed(id_chunk.chunk_index)
+        ],
+        bump = id_chunk.bump
+    )]
     pub id_chunk: Account<'info, IdChunk>,
+
+    /// CHECK: product account of the ID, must not exist
+    #[account(
+        seeds = [b"product", id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub product: UncheckedAccount<'info>,
 }
 
+/// Release an ID whose product no longer exists so it is handed out again
+///
+/// Hard deleted products release their ID in delete_product, this covers IDs generated without
+/// a product (generate_product_id, batch_generate_ids).
 pub fn release_id(ctx: Context<ReleaseId>, id: u64) -> Result<()> {
-    let chunk = &mut ctx.accounts.id_chunk;
     require!(
-        id >= chunk.start_id && id <= chunk.end_id,
-        ErrorCode::InvalidId
+        ctx.accounts.product.data_is_empty(),
+        ErrorCode::IdStillInUse
     );
-    let offset = id - chunk.start_id;
-    let byte_index = (offset / 8) as usize;
-    let bit_index = (offset % 8) as u8;
-    chunk.bitmap[byte_index] &= !(1 << bit_index);
+
+    let released = release_id_in_chunk(&mut ctx.accounts.id_chunk, id)?;
+
+    // IDs of inactive chunks are reused when the chunk becomes active again
+    let chunk_key = ctx.accounts.id_chunk.key();
+    let merchant_account = &mut ctx.accounts.merchant_account;
+    if released
+        && merchant_account.active_chunk != chunk_key
+        && !merchant_account.unused_chunks.contains(&chunk_key)
+    {
+        require!(
+            merchant_account.unused_chunks.len() < MAX_CHUNKS_PER_MERCHANT as usize,
+            ErrorCode::InvalidShardIndex
+        );
+        merchant_account.unused_chunks.push(chunk_key);
+    }
+
+    msg!("ID {} released: {}", id, released);
+
     Ok(())
 }
 
@@ -352,17 +348,9 @@ pub fn allocate_id_in_chunk(
     Ok(global_id)
 }
 
-// Pure function: recycle ID
-pub fn release_id_in_chunk(chunk: &mut Account<IdChunk>, id: u64) -> Result<()> {
-    require!(
-        id >= chunk.start_id && id <= chunk.end_id,
-        ErrorCode::InvalidId
-    );
-    let offset = id - chunk.start_id;
-    let byte_index = (offset / 8) as usize;
-    let bit_index = (offset % 8) as u8;
-    chunk.bitmap[byte_index] &= !(1 << bit_index);
-    Ok(())
+// Pure function: recycle ID, returns false when the ID was not in use
+pub fn release_id_in_chunk(chunk: &mut Account<IdChunk>, id: u64) -> Result<bool> {
+    chunk.release(id)
 }
 
 // Check chunk utilization
@@ -372,7 +360,71 @@ pub fn check_chunk_utilization(chunk: &Account<IdChunk>) -> f32 {
 
 // Pre-allocate next chunk (performance optimization)
 pub fn should_preallocate_chunk(chunk: &Account<IdChunk>) -> bool {
-    chunk.utilization_rate() > 0.8 // Pre-allocate when utilization exceeds 80%
+    chunk.utilization_rate() > CHUNK_PREALLOCATE_THRESHOLD
+}
+
+// Reclaim fully released chunks
+#[derive(Accounts)]
+pub struct CompactIdChunks<'info> {
+    #[account(mut)]
+    pub merchant: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"merchant_id", merchant.key().as_ref()],
+        bump = merchant_account.bump
+    )]
+    pub merchant_account: Account<'info, MerchantIdAccount>,
+    // remaining_accounts: inactive IdChunk accounts of the merchant (listed in unused_chunks)
+}
+
+/// Close inactive chunks without any ID in use and return their rent to the merchant
+///
+/// The IDs of a closed chunk are not handed out again. Chunks that still hold IDs are skipped.
+pub fn compact_id_chunks<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CompactIdChunks<'info>>,
+) -> Result<u32> {
+    let merchant = &ctx.accounts.merchant;
+    let merchant_account = &mut ctx.accounts.merchant_account;
+    let mut closed = 0u32;
+
+    for chunk_info in ctx.remaining_accounts {
+        require!(
+            chunk_info.owner == ctx.program_id,
+            ErrorCode::InvalidAccountOwner
+        );
+        require!(
+            merchant_account.unused_chunks.contains(chunk_info.key),
+            ErrorCode::InvalidActiveChunk
+        );
+        let chunk_data = chunk_info.try_borrow_data()?;
+        let chunk = IdChunk::try_deserialize(&mut &chunk_data[..])?;
+        drop(chunk_data);
+
+        if chunk.used_count() > 0 {
+            msg!(
+                "ID chunk {} still holds {} IDs, skipped",
+                chunk.chunk_index,
+                chunk.used_count()
+            );
+            continue;
+        }
+
+        close_program_account(chunk_info, merchant)?;
+
+        merchant_account
+            .unused_chunks
+            .retain(|key| key != chunk_info.key);
+        closed += 1;
+    }
+
+    msg!(
+        "ID chunk compaction completed, merchant: {}, chunks closed: {}",
+        merchant_account.merchant_id,
+        closed
+    );
+
+    Ok(closed)
 }
 
 // Close ID chunk account
@@ -461,3 +513,76 @@ pub fn close_merchant_id_account(
     // Account will be automatically closed and rent reclaimed through close constraint
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{CHUNK_POOL_START, MERCHANT_ID_RANGE};
+
+    fn empty_chunk() -> IdChunk {
+        IdChunk {
+            merchant_id: 0,
+            chunk_index: 0,
+            start_id: 0,
+            end_id: 0,
+            next_available: 0,
+            bitmap: Vec::new(),
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_later_chunks_come_from_the_pool() {
+        let mut root = GlobalIdRoot {
+            last_merchant_id: 2,
+            last_global_id: 0,
+            chunk_size: 16,
+            merchants: Vec::new(),
+            max_products_per_shard: 100,
+            max_keywords_per_product: 10,
+            bloom_filter_size: 256,
+            bump: 255,
+        };
+
+        // Initial chunks keep the merchant ID based range
+        let mut merchant_1 = empty_chunk();
+        merchant_1.initialize(1, 0, IdChunk::initial_start_id(1), 16, 255);
+        assert_eq!((merchant_1.start_id, merchant_1.end_id), (10_000, 10_015));
+
+        // Later chunks of any merchant are reserved one after another above every initial chunk
+        let mut merchant_0_next = empty_chunk();
+        merchant_0_next.initialize(0, 1, root.reserve_chunk_range(), 16, 255);
+        let mut merchant_1_next = empty_chunk();
+        merchant_1_next.initialize(1, 1, root.reserve_chunk_range(), 16, 255);
+        assert_eq!(merchant_0_next.start_id, CHUNK_POOL_START);
+        assert_eq!(merchant_1_next.start_id, CHUNK_POOL_START + 16);
+        assert_eq!(root.next_chunk_start(), CHUNK_POOL_START + 32);
+        assert!(IdChunk::initial_start_id(u32::MAX) + MERCHANT_ID_RANGE <= CHUNK_POOL_START);
+    }
+
+    #[test]
+    fn test_released_ids_are_reused_first() {
+        let mut chunk = empty_chunk();
+        let start = CHUNK_POOL_START;
+        chunk.initialize(2, 1, start, 16, 255);
+
+        let ids: Vec<u64> = (0..4).filter_map(|_| chunk.allocate_next()).collect();
+        assert_eq!(ids, vec![start, start + 1, start + 2, start + 3]);
+
+        // The lowest released ID is handed out first, then allocation continues after the used IDs
+        assert!(chunk.release(start + 1).unwrap());
+        assert!(!chunk.release(start + 1).unwrap());
+        assert_eq!(chunk.start_id + chunk.next_available, start + 1);
+        assert_eq!(chunk.used_count(), 3);
+        assert_eq!(chunk.allocate_next(), Some(start + 1));
+        assert_eq!(chunk.allocate_next(), Some(start + 4));
+        assert!(chunk.release(start + 16).is_err());
+
+        for id in start..=start + 4 {
+            chunk.release(id).unwrap();
+        }
+        assert_eq!(chunk.used_count(), 0);
+        assert_eq!(chunk.utilization_rate(), 0.0);
+        assert_eq!(chunk.allocate_next(), Some(start));
+    }
+}
@@ -0,0 +1,676 @@
+use crate::error::ErrorCode;
+use crate::state::*;
+use crate::utils::resize_account;
+use crate::SystemConfig;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_instruction::MAX_PERMITTED_DATA_LENGTH;
+
+/// Create a zero-copy keyword, price or sales index shard
+#[derive(Accounts)]
+#[instruction(key: IndexShardKey)]
+pub struct InitializeIndexShard<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = IndexShard::space(INDEX_SHARD_INITIAL_CAPACITY),
+        seeds = [
+            b"index_shard",
+            &[key.kind()][..],
+            key.primary_seed().as_ref(),
+            key.secondary_seed().as_ref()
+        ],
+        bump
+    )]
+    pub index_shard: AccountLoader<'info, IndexShard>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create a zero-copy index shard with INDEX_SHARD_INITIAL_CAPACITY entries
+pub fn initialize_index_shard(
+    ctx: Context<InitializeIndexShard>,
+    key: IndexShardKey,
+) -> Result<()> {
+    let mut index_shard = ctx.accounts.index_shard.load_init()?;
+    index_shard.initialize(&key, INDEX_SHARD_INITIAL_CAPACITY, ctx.bumps.index_shard)?;
+
+    msg!("Index shard initialized: {:?}", key);
+
+    Ok(())
+}
+
+/// Add a product to a zero-copy index shard, growing it when full
+#[derive(Accounts)]
+#[instruction(key: IndexShardKey, product_id: u64)]
+pub struct AddProductToIndexShard<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"index_shard",
+            &[key.kind()][..],
+            key.primary_seed().as_ref(),
+            key.secondary_seed().as_ref()
+        ],
+        bump = index_shard.load()?.bump
+    )]
+    pub index_shard: AccountLoader<'info, IndexShard>,
+
+    // Product being indexed, provides the entry value
+    #[account(
+        seeds = [b"product", product_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = ProductBase::has_current_layout(&product.to_account_info()) @ ErrorCode::AccountNotMigrated
+    )]
+    pub product: Account<'info, ProductBase>,
+
+    #[account(
+        seeds = [b"system_config"],
+        bump
+    )]
+    pub system_config: Account<'info, SystemConfig>,
+
+    // Merchant of the product or the system administrator
+    #[account(
+        constraint = authority.key() == product.merchant
+            || authority.key() == system_config.authority @ ErrorCode::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    // Pays the rent of the shard growth
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Add a product to a zero-copy index shard
+///
+/// The entry value is the product price for price shards (the product must use the shard's
+/// payment token) and its current sales for sales shards; keyword shards only take products
+/// listing the keyword. A full shard is reallocated by INDEX_SHARD_GROWTH entries up to the
+/// configured index_shard_capacity.
+pub fn add_product_to_index_shard(
+    ctx: Context<AddProductToIndexShard>,
+    key: IndexShardKey,
+    product_id: u64,
+) -> Result<()> {
+    let value = key.entry_value(&ctx.accounts.product)?;
+    let len = {
+        let index_shard = ctx.accounts.index_shard.load()?;
+        require!(
+            index_shard.contains_value(value),
+            if index_shard.kind == 2 {
+                ErrorCode::InvalidSalesRange
+            } else {
+                ErrorCode::InvalidPriceRange
+            }
+        );
+        index_shard.len
+    };
+
+    let shard_info = ctx.accounts.index_shard.to_account_info();
+    let exists = {
+        let data = shard_info.try_borrow_data()?;
+        IndexEntries::position(
+            &data[IndexShard::ENTRIES_OFFSET..],
+            len as usize,
+            product_id,
+        )
+        .is_some()
+    };
+    if exists {
+        msg!(
+            "Product {} already exists in index shard, skipping addition",
+            product_id
+        );
+        return Ok(());
+    }
+
+    ensure_index_shard_capacity(
+        &ctx.accounts.index_shard.to_account_info(),
+        len + 1,
+        ctx.accounts.system_config.index_shard_capacity,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    {
+        let mut data = shard_info.try_borrow_mut_data()?;
+        IndexEntries::insert(
+            &mut data[IndexShard::ENTRIES_OFFSET..],
+            len as usize,
+            IndexEntry { value, product_id },
+        );
+    }
+    ctx.accounts.index_shard.load_mut()?.len = len + 1;
+
+    msg!(
+        "Product {} added to index shard, current product count: {}",
+        product_id,
+        len + 1
+    );
+
+    Ok(())
+}
+
+/// Remove a product from a zero-copy index shard
+#[derive(Accounts)]
+#[instruction(key: IndexShardKey, product_id: u64)]
+pub struct RemoveProductFromIndexShard<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"index_shard",
+            &[key.kind()][..],
+            key.primary_seed().as_ref(),
+            key.secondary_seed().as_ref()
+        ],
+        bump = index_shard.load()?.bump
+    )]
+    pub index_shard: AccountLoader<'info, IndexShard>,
+
+    /// CHECK: product account of the entry, read in the instruction when it still exists
+    #[account(
+        seeds = [b"product", product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub product: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"system_config"],
+        bump
+    )]
+    pub system_config: Account<'info, SystemConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Remove a product from a zero-copy index shard, the allocated capacity is kept
+///
+/// The merchant of the product or the system administrator may remove an existing product;
+/// entries of deleted products can be removed by anyone.
+pub fn remove_product_from_index_shard(
+    ctx: Context<RemoveProductFromIndexShard>,
+    _key: IndexShardKey,
+    product_id: u64,
+) -> Result<()> {
+    let product_info = &ctx.accounts.product;
+    if !product_info.data_is_empty() {
+        require!(
+            product_info.owner == ctx.program_id,
+            ErrorCode::InvalidAccountOwner
+        );
+        let data = product_info.try_borrow_data()?;
+        let product = ProductBase::try_deserialize(&mut &data[..])?;
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == product.merchant || authority == ctx.accounts.system_config.authority,
+            ErrorCode::Unauthorized
+        );
+    }
+
+    let len = ctx.accounts.index_shard.load()?.len;
+
+    let shard_info = ctx.accounts.index_shard.to_account_info();
+    let removed = {
+        let mut data = shard_info.try_borrow_mut_data()?;
+        IndexEntries::remove(
+            &mut data[IndexShard::ENTRIES_OFFSET..],
+            len as usize,
+            product_id,
+        )
+    };
+
+    if removed {
+        ctx.accounts.index_shard.load_mut()?.len = len - 1;
+        msg!("Product {} removed from index shard", product_id);
+    } else {
+        msg!("Product {} not found in index shard", product_id);
+    }
+
+    Ok(())
+}
+
+/// Copy a legacy Borsh index shard into its zero-copy shard (system administrator)
+#[derive(Accounts)]
+#[instruction(key: IndexShardKey)]
+pub struct MigrateIndexShard<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"index_shard",
+            &[key.kind()][..],
+            key.primary_seed().as_ref(),
+            key.secondary_seed().as_ref()
+        ],
+        bump = index_shard.load()?.bump
+    )]
+    pub index_shard: AccountLoader<'info, IndexShard>,
+
+    /// CHECK: KeywordShard, PriceIndexNode or SalesIndexNode matching `key`, owner and address
+    /// are verified in the instruction
+    #[account(mut)]
+    pub legacy_shard: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"system_config"],
+        bump,
+        constraint = system_config.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub system_config: Account<'info, SystemConfig>,
+
+    // System administrator, pays the rent of the shard growth
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Copy the next INDEX_SHARD_GROWTH entries of a legacy shard into its zero-copy shard
+///
+/// Call repeatedly until the migration reports completion. Sales node entries take their sales
+/// from the node's top items when cached, otherwise the range start. The first call freezes the
+/// legacy shard: it stays readable, legacy instructions no longer write it, so the copied
+/// entries cannot go stale. It can be closed once the zero-copy shard is in use.
+pub fn migrate_index_shard(ctx: Context<MigrateIndexShard>, key: IndexShardKey) -> Result<()> {
+    let legacy_entries = load_legacy_entries(&ctx.accounts.legacy_shard, &key, ctx.program_id)?;
+
+    let legacy_info = ctx.accounts.legacy_shard.to_account_info();
+    let legacy_space = legacy_shard_space(&key);
+    if legacy_info.data_len() <= legacy_space {
+        resize_account(
+            &legacy_info,
+            legacy_space + LEGACY_SHARD_FROZEN_BYTES,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        msg!("Legacy shard {} frozen", legacy_info.key());
+    }
+
+    let (len, migrated) = {
+        let index_shard = ctx.accounts.index_shard.load()?;
+        (index_shard.len, index_shard.migrated_entries as usize)
+    };
+    let end = legacy_entries
+        .len()
+        .min(migrated + INDEX_SHARD_GROWTH as usize);
+    if migrated >= end {
+        msg!("Index shard migration already complete");
+        return Ok(());
+    }
+    let batch = &legacy_entries[migrated..end];
+
+    ensure_index_shard_capacity(
+        &ctx.accounts.index_shard.to_account_info(),
+        len + batch.len() as u32,
+        ctx.accounts.system_config.index_shard_capacity,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    let mut new_len = len as usize;
+    {
+        let shard_info = ctx.accounts.index_shard.to_account_info();
+        let mut data = shard_info.try_borrow_mut_data()?;
+        for entry in batch {
+            if IndexEntries::insert(&mut data[IndexShard::ENTRIES_OFFSET..], new_len, *entry) {
+                new_len += 1;
+            }
+        }
+    }
+
+    let mut index_shard = ctx.accounts.index_shard.load_mut()?;
+    index_shard.len = new_len as u32;
+    index_shard.migrated_entries = end as u32;
+
+    msg!(
+        "Index shard migration: {}/{} legacy entries copied{}",
+        end,
+        legacy_entries.len(),
+        if end == legacy_entries.len() {
+            ", complete"
+        } else {
+            ""
+        }
+    );
+
+    Ok(())
+}
+
+/// Update the maximum capacity of zero-copy index shards (system administrator)
+#[derive(Accounts)]
+pub struct UpdateIndexShardCapacity<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"system_config"],
+        bump,
+        constraint = system_config.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub system_config: Account<'info, SystemConfig>,
+}
+
+/// Update the maximum capacity of zero-copy index shards, shards already larger keep their entries
+pub fn update_index_shard_capacity(
+    ctx: Context<UpdateIndexShardCapacity>,
+    capacity: u32,
+) -> Result<()> {
+    require!(
+        capacity >= INDEX_SHARD_INITIAL_CAPACITY
+            && IndexShard::space(capacity) as u64 <= MAX_PERMITTED_DATA_LENGTH,
+        ErrorCode::InvalidIndexShardCapacity
+    );
+
+    let system_config = &mut ctx.accounts.system_config;
+    let old_capacity = system_config.index_shard_capacity;
+    system_config.index_shard_capacity = capacity;
+
+    msg!(
+        "Index shard capacity updated: {} -> {} entries",
+        old_capacity,
+        capacity
+    );
+
+    Ok(())
+}
+
+// Grow a zero-copy index shard so it can hold `required` entries
+//
+// Capacity grows by at least INDEX_SHARD_GROWTH entries, capped at `max_capacity`; the payer
+// funds the additional rent.
+pub(crate) fn ensure_index_shard_capacity<'info>(
+    index_shard: &AccountInfo<'info>,
+    required: u32,
+    max_capacity: u32,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let capacity = shard_header(&index_shard.try_borrow_data()?).capacity;
+    if required <= capacity {
+        return Ok(());
+    }
+    require!(required <= max_capacity, ErrorCode::ShardIsFull);
+
+    let new_capacity = capacity
+        .saturating_add(INDEX_SHARD_GROWTH)
+        .max(required)
+        .min(max_capacity);
+    let new_space = IndexShard::space(new_capacity);
+
+    resize_account(index_shard, new_space, payer, system_program)?;
+    shard_header_mut(&mut index_shard.try_borrow_mut_data()?).capacity = new_capacity;
+
+    msg!(
+        "Index shard grown: {} -> {} entries",
+        capacity,
+        new_capacity
+    );
+
+    Ok(())
+}
+
+// Header of an account holding a zero-copy index shard, None for any other account
+pub(crate) fn index_shard_header(info: &AccountInfo) -> Result<Option<IndexShard>> {
+    let data = info.try_borrow_data()?;
+    if data.len() < IndexShard::ENTRIES_OFFSET || data[..8] != *IndexShard::DISCRIMINATOR {
+        return Ok(None);
+    }
+    Ok(Some(*shard_header(&data)))
+}
+
+// Header of account data known to hold a zero-copy index shard
+fn shard_header(data: &[u8]) -> &IndexShard {
+    bytemuck::from_bytes(&data[8..IndexShard::ENTRIES_OFFSET])
+}
+
+fn shard_header_mut(data: &mut [u8]) -> &mut IndexShard {
+    bytemuck::from_bytes_mut(&mut data[8..IndexShard::ENTRIES_OFFSET])
+}
+
+// Entries in use of the zero-copy sales shard held by `info`, which must cover `sales`
+fn sales_shard_len(info: &AccountInfo, sales: u32) -> Result<u32> {
+    let header = index_shard_header(info)?.ok_or(ErrorCode::InvalidSalesRange)?;
+    require!(
+        header.kind == 2 && header.contains_value(sales as u64),
+        ErrorCode::InvalidSalesRange
+    );
+    Ok(header.len)
+}
+
+// Remove a product from the zero-copy sales shard covering `sales`, returns false when absent
+pub(crate) fn remove_from_sales_shard(
+    info: &AccountInfo,
+    product_id: u64,
+    sales: u32,
+) -> Result<bool> {
+    let len = sales_shard_len(info, sales)?;
+    let mut data = info.try_borrow_mut_data()?;
+    let removed = IndexEntries::remove(
+        &mut data[IndexShard::ENTRIES_OFFSET..],
+        len as usize,
+        product_id,
+    );
+    if removed {
+        shard_header_mut(&mut data).len = len - 1;
+    }
+    Ok(removed)
+}
+
+// Index a product with its sales in the zero-copy sales shard covering them, replacing any entry
+// the product already has there and growing the shard when full
+pub(crate) fn add_to_sales_shard<'info>(
+    info: &AccountInfo<'info>,
+    product_id: u64,
+    sales: u32,
+    max_capacity: u32,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    sales_shard_len(info, sales)?;
+    let len = {
+        let mut data = info.try_borrow_mut_data()?;
+        let len = shard_header(&data).len;
+        if IndexEntries::remove(
+            &mut data[IndexShard::ENTRIES_OFFSET..],
+            len as usize,
+            product_id,
+        ) {
+            shard_header_mut(&mut data).len = len - 1;
+            len - 1
+        } else {
+            len
+        }
+    };
+
+    ensure_index_shard_capacity(info, len + 1, max_capacity, payer, system_program)?;
+
+    let mut data = info.try_borrow_mut_data()?;
+    IndexEntries::insert(
+        &mut data[IndexShard::ENTRIES_OFFSET..],
+        len as usize,
+        IndexEntry {
+            value: sales as u64,
+            product_id,
+        },
+    );
+    shard_header_mut(&mut data).len = len + 1;
+    Ok(())
+}
+
+// Space legacy Borsh shards of `key`'s index are created with
+fn legacy_shard_space(key: &IndexShardKey) -> usize {
+    8 + match key {
+        IndexShardKey::Keyword { .. } => KeywordShard::INIT_SPACE,
+        IndexShardKey::Price { .. } => PriceIndexNode::INIT_SPACE,
+        IndexShardKey::Sales { .. } => SalesIndexNode::INIT_SPACE,
+    }
+}
+
+// Entries of the legacy Borsh shard identified by `key`
+fn load_legacy_entries(
+    legacy_info: &AccountInfo,
+    key: &IndexShardKey,
+    program_id: &Pubkey,
+) -> Result<Vec<IndexEntry>> {
+    require!(
+        legacy_info.owner == program_id,
+        ErrorCode::InvalidAccountOwner
+    );
+    let data = legacy_info.try_borrow_data()?;
+
+    let (entries, expected_address) = match key {
+        IndexShardKey::Keyword {
+            keyword,
+            shard_index,
+        } => {
+            let shard = KeywordShard::try_deserialize(&mut &data[..])?;
+            let address = Pubkey::create_program_address(
+                &[
+                    b"keyword_shard",
+                    keyword.as_bytes(),
+                    &shard_index.to_le_bytes(),
+                    &[shard.bump],
+                ],
+                program_id,
+            );
+            let entries = shard
+                .product_ids
+                .iter()
+                .map(|&product_id| IndexEntry {
+                    value: 0,
+                    product_id,
+                })
+                .collect();
+            (entries, address)
+        }
+        IndexShardKey::Price {
+            payment_token,
+            price_range_start,
+            price_range_end,
+        } => {
+            let node = PriceIndexNode::try_deserialize(&mut &data[..])?;
+            let address = Pubkey::create_program_address(
+                &[
+                    b"price_index",
+                    payment_token.as_ref(),
+                    &price_range_start.to_le_bytes(),
+                    &price_range_end.to_le_bytes(),
+                    &[node.bump],
+                ],
+                program_id,
+            );
+            let entries = node
+                .entries
+                .iter()
+                .map(|entry| IndexEntry {
+                    value: entry.price,
+                    product_id: entry.product_id,
+                })
+                .collect();
+            (entries, address)
+        }
+        IndexShardKey::Sales {
+            sales_range_start,
+            sales_range_end,
+        } => {
+            let node = SalesIndexNode::try_deserialize(&mut &data[..])?;
+            let address = Pubkey::create_program_address(
+                &[
+                    b"sales_index",
+                    &sales_range_start.to_le_bytes(),
+                    &sales_range_end.to_le_bytes(),
+                    &[node.bump],
+                ],
+                program_id,
+            );
+            let entries = node
+                .product_ids
+                .iter()
+                .map(|&product_id| IndexEntry {
+                    value: node
+                        .top_items
+                        .iter()
+                        .find(|item| item.product_id == product_id)
+                        .map_or(node.sales_range_start, |item| item.sales)
+                        as u64,
+                    product_id,
+                })
+                .collect();
+            (entries, address)
+        }
+    };
+
+    let expected_address = expected_address.map_err(|_| ErrorCode::InvalidPda)?;
+    require_keys_eq!(
+        expected_address,
+        legacy_info.key(),
+        ErrorCode::InvalidAccountSeeds
+    );
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(entries: &[u8], len: usize) -> Vec<u64> {
+        IndexEntries::product_ids(entries, len)
+    }
+
+    #[test]
+    fn test_index_entries_in_place() {
+        let mut entries = vec![0u8; 4 * INDEX_ENTRY_SIZE];
+        let mut len = 0;
+
+        for (value, product_id) in [(300, 7), (100, 9), (300, 3)] {
+            assert!(IndexEntries::insert(
+                &mut entries,
+                len,
+                IndexEntry { value, product_id }
+            ));
+            len += 1;
+        }
+        assert_eq!(ids(&entries, len), vec![9, 3, 7]);
+
+        // Products are unique whatever value they are added with
+        assert!(!IndexEntries::insert(
+            &mut entries,
+            len,
+            IndexEntry {
+                value: 50,
+                product_id: 7
+            }
+        ));
+
+        let in_range = IndexEntries::in_range(&entries, len, 200, 300);
+        assert_eq!(
+            in_range,
+            vec![
+                IndexEntry {
+                    value: 300,
+                    product_id: 3
+                },
+                IndexEntry {
+                    value: 300,
+                    product_id: 7
+                }
+            ]
+        );
+
+        assert!(IndexEntries::remove(&mut entries, len, 3));
+        len -= 1;
+        assert!(!IndexEntries::remove(&mut entries, len, 3));
+        assert_eq!(ids(&entries, len), vec![9, 7]);
+
+        assert_eq!(IndexShard::ENTRIES_OFFSET, 8 + 72);
+        assert_eq!(
+            IndexShard::space(INDEX_SHARD_INITIAL_CAPACITY),
+            80 + INDEX_SHARD_INITIAL_CAPACITY as usize * INDEX_ENTRY_SIZE
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::state::*;
-use crate::SystemConfig;
+use crate::utils::migrate_account;
+use crate::{LegacySystemConfig, SystemConfig};
 use anchor_lang::prelude::*;
 
 #[derive(Accounts)]
@@ -48,7 +49,7 @@ pub struct InitializeSystemConfig<'info> {
     #[account(
         init,
         payer = payer,
-        space = 8 + std::mem::size_of::<SystemConfig>(),
+        space = SystemConfig::SPACE,
         seeds = [b"system_config"],
         bump
     )]
@@ -78,6 +79,17 @@ pub fn initialize_system_config(
     system_config.vault_account = config.vault_account;
     system_config.vault_token_account = config.vault_token_account;
     system_config.platform_token_account = config.platform_token_account;
+    system_config.trending_half_life_seconds = if config.trending_half_life_seconds > 0 {
+        config.trending_half_life_seconds
+    } else {
+        crate::state::DEFAULT_TRENDING_HALF_LIFE_SECONDS
+    };
+    system_config.index_shard_capacity =
+        if config.index_shard_capacity >= crate::state::INDEX_SHARD_INITIAL_CAPACITY {
+            config.index_shard_capacity
+        } else {
+            crate::state::DEFAULT_INDEX_SHARD_CAPACITY
+        };
 
     msg!(
         "系统配置初始化成功，管理员: {}, 保证金要求: {} tokens",
@@ -88,6 +100,50 @@ pub fn initialize_system_config(
     Ok(())
 }
 
+/// 迁移旧布局的系统配置账户（系统管理员）
+#[derive(Accounts)]
+pub struct MigrateSystemConfig<'info> {
+    /// CHECK: 旧布局的系统配置，在指令中验证所有者和布局
+    #[account(
+        mut,
+        seeds = [b"system_config"],
+        bump
+    )]
+    pub system_config: UncheckedAccount<'info>,
+
+    // 支付扩容租金
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 将热度排行和零拷贝索引上线前的系统配置扩展到当前布局
+///
+/// 新增字段使用默认值：热度半衰期为DEFAULT_TRENDING_HALF_LIFE_SECONDS，
+/// 索引分片容量上限为DEFAULT_INDEX_SHARD_CAPACITY
+pub fn migrate_system_config(ctx: Context<MigrateSystemConfig>) -> Result<()> {
+    let system_config: SystemConfig = migrate_account::<LegacySystemConfig, _>(
+        &ctx.accounts.system_config.to_account_info(),
+        LegacySystemConfig::SPACE,
+        SystemConfig::SPACE,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    require!(
+        system_config.authority == ctx.accounts.authority.key(),
+        crate::error::ErrorCode::Unauthorized
+    );
+
+    msg!(
+        "系统配置迁移成功，热度半衰期: {} 秒, 索引分片容量上限: {}",
+        system_config.trending_half_life_seconds,
+        system_config.index_shard_capacity
+    );
+
+    Ok(())
+}
+
 /// 关闭系统配置账户
 #[derive(Accounts)]
 pub struct CloseSystemConfig<'info> {
@@ -1,5 +1,7 @@
 use crate::error::ErrorCode;
 use crate::state::*;
+use crate::utils::{resize_account, BloomSummary, CountingBloomFilter};
+use crate::SystemConfig;
 use anchor_lang::prelude::*;
 
 #[derive(Accounts)]
@@ -15,7 +17,8 @@ pub struct RemoveProductFromKeywordIndex<'info> {
     #[account(
         mut,
         seeds = [b"keyword_shard", keyword.as_bytes(), 0u32.to_le_bytes().as_ref()],
-        bump
+        bump,
+        constraint = !is_frozen_legacy_shard::<KeywordShard>(&target_shard.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
     )]
     pub target_shard: Account<'info, KeywordShard>,
     // 移除authority账户 - 在函数实现中完全未使用，权限验证通过PDA种子机制实现
@@ -34,7 +37,8 @@ pub struct CreateKeywordShard<'info> {
     #[account(
         mut,
         seeds = [b"keyword_shard", keyword.as_bytes(), (shard_index - 1).to_le_bytes().as_ref()],
-        bump
+        bump,
+        constraint = !is_frozen_legacy_shard::<KeywordShard>(&prev_shard.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
     )]
     pub prev_shard: Account<'info, KeywordShard>,
 
@@ -97,6 +101,48 @@ pub struct CloseKeywordShard<'info> {
     // 移除authority账户 - 在函数实现中完全未使用，权限验证通过PDA种子机制实现
 }
 
+// Merge a sparse keyword shard into its neighbor (permissionless compaction)
+#[derive(Accounts)]
+#[instruction(keyword: String, source_shard_index: u32, target_shard_index: u32)]
+pub struct MergeKeywordShards<'info> {
+    #[account(
+        mut,
+        seeds = [b"keyword_root", keyword.as_bytes()],
+        bump
+    )]
+    pub keyword_root: Account<'info, KeywordRoot>,
+
+    // Sparse shard, closed after its products are moved
+    #[account(
+        mut,
+        close = beneficiary,
+        seeds = [b"keyword_shard", keyword.as_bytes(), source_shard_index.to_le_bytes().as_ref()],
+        bump,
+        constraint = !is_frozen_legacy_shard::<KeywordShard>(&source_shard.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
+    )]
+    pub source_shard: Account<'info, KeywordShard>,
+
+    // Neighbor shard receiving the products
+    #[account(
+        mut,
+        seeds = [b"keyword_shard", keyword.as_bytes(), target_shard_index.to_le_bytes().as_ref()],
+        bump,
+        constraint = !is_frozen_legacy_shard::<KeywordShard>(&target_shard.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
+    )]
+    pub target_shard: Account<'info, KeywordShard>,
+
+    // Shard on the other side of the source shard, required when it exists so its link can be updated
+    #[account(
+        mut,
+        constraint = !is_frozen_legacy_shard::<KeywordShard>(&outer_shard.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
+    )]
+    pub outer_shard: Option<Account<'info, KeywordShard>>,
+
+    // Receives the closed shard's rent
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+}
+
 pub fn remove_product_from_keyword_index(
     ctx: Context<RemoveProductFromKeywordIndex>,
     keyword: String,
@@ -119,8 +165,9 @@ pub fn remove_product_from_keyword_index(
     let found = target_shard.remove_product(product_id)?;
 
     if found {
-        // 更新根的统计（注意：布隆过滤器不支持删除，保持原样）
+        // 更新根的统计，计数布隆过滤器支持删除
         keyword_root.total_products = keyword_root.total_products.saturating_sub(1);
+        keyword_root.update_bloom_filter(product_id, false);
 
         msg!("产品ID {} 成功从关键词 {} 索引中移除", product_id, keyword);
     } else {
@@ -149,6 +196,13 @@ pub fn create_keyword_shard(
         ErrorCode::InvalidShardIndex
     );
 
+    // Only the tail of the chain is extended, a shard left in the middle by a merge keeps its
+    // successor
+    require!(
+        prev_shard.next_shard.is_none() && prev_shard.key() == keyword_root.last_shard,
+        ErrorCode::InvalidShardLink
+    );
+
     // Initialize new shard
     new_shard.initialize(
         keyword.clone(),
@@ -201,6 +255,41 @@ pub fn search_keyword_index(
     Ok(results)
 }
 
+// Query the bloom filter statistics of a keyword
+#[derive(Accounts)]
+#[instruction(keyword: String)]
+pub struct GetKeywordBloomSummary<'info> {
+    #[account(
+        seeds = [b"keyword_root", keyword.as_bytes()],
+        bump = keyword_root.bump
+    )]
+    pub keyword_root: Account<'info, KeywordRoot>,
+}
+
+/// Keyword bloom filter statistics structure
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct KeywordBloomStats {
+    pub total_products: u32,  // Products indexed under the keyword
+    pub slot_count: u32,      // Counter slots in the bloom filter
+    pub saturated_slots: u32, // Slots whose counter saturated (never decremented)
+    pub summary: BloomSummary,
+}
+
+pub fn get_keyword_bloom_summary(
+    ctx: Context<GetKeywordBloomSummary>,
+    _keyword: String,
+) -> Result<KeywordBloomStats> {
+    let keyword_root = &ctx.accounts.keyword_root;
+    let filter = &keyword_root.bloom_filter;
+
+    Ok(KeywordBloomStats {
+        total_products: keyword_root.total_products,
+        slot_count: CountingBloomFilter::slot_count(filter) as u32,
+        saturated_slots: CountingBloomFilter::saturated_slots(filter),
+        summary: BloomSummary::from_counting_filter(filter),
+    })
+}
+
 // Check if shard needs splitting
 pub fn check_shard_split_needed(shard: &Account<KeywordShard>) -> bool {
     shard.needs_split()
@@ -211,6 +300,91 @@ pub fn check_shard_merge_needed(shard: &Account<KeywordShard>) -> bool {
     shard.needs_merge()
 }
 
+// Merge a sparse shard into its prev or next neighbor and close it
+pub fn merge_keyword_shards(
+    ctx: Context<MergeKeywordShards>,
+    keyword: String,
+    source_shard_index: u32,
+    target_shard_index: u32,
+) -> Result<()> {
+    let keyword_root = &mut ctx.accounts.keyword_root;
+    let source_shard = &ctx.accounts.source_shard;
+    let target_shard = &mut ctx.accounts.target_shard;
+
+    // Verify keyword match
+    require!(keyword_root.keyword == keyword, ErrorCode::InvalidKeyword);
+    require!(source_shard.keyword == keyword, ErrorCode::InvalidKeyword);
+    require!(target_shard.keyword == keyword, ErrorCode::InvalidKeyword);
+
+    // Shard 0 is the fixed entry point of the add/remove instructions, it is never closed
+    require!(source_shard_index != 0, ErrorCode::InvalidShardIndex);
+    require!(
+        source_shard_index != target_shard_index,
+        ErrorCode::InvalidShardIndex
+    );
+
+    require!(
+        check_shard_merge_needed(source_shard),
+        ErrorCode::ShardMergeNotNeeded
+    );
+    require!(
+        target_shard.can_absorb(source_shard),
+        ErrorCode::ShardIsFull
+    );
+
+    let source_key = source_shard.key();
+    let target_key = target_shard.key();
+
+    // Relink the chain around the source shard
+    if source_shard.prev_shard == target_key {
+        target_shard.next_shard = source_shard.next_shard;
+
+        if let Some(next_key) = source_shard.next_shard {
+            let outer_shard = ctx
+                .accounts
+                .outer_shard
+                .as_mut()
+                .ok_or(ErrorCode::InsufficientAccounts)?;
+            require!(outer_shard.key() == next_key, ErrorCode::InvalidShardLink);
+            outer_shard.prev_shard = target_key;
+        }
+    } else if source_shard.next_shard == Some(target_key) {
+        target_shard.prev_shard = source_shard.prev_shard;
+
+        let outer_shard = ctx
+            .accounts
+            .outer_shard
+            .as_mut()
+            .ok_or(ErrorCode::InsufficientAccounts)?;
+        require!(
+            outer_shard.key() == source_shard.prev_shard,
+            ErrorCode::InvalidShardLink
+        );
+        outer_shard.next_shard = Some(target_key);
+    } else {
+        return Err(ErrorCode::InvalidShardLink.into());
+    }
+
+    // Move products and rebuild min/max and bloom summary
+    let moved_products = source_shard.product_ids.len();
+    target_shard.absorb(source_shard)?;
+
+    // Update root shard links and count
+    keyword_root.remove_shard(source_key, target_key);
+
+    msg!(
+        "Keyword {} shard {} merged into shard {}, moved {} products, remaining shards: {}",
+        keyword,
+        source_shard_index,
+        target_shard_index,
+        moved_products,
+        keyword_root.total_shards
+    );
+
+    // Source shard will be closed and rent reclaimed to beneficiary through close constraint
+    Ok(())
+}
+
 // Close keyword root account
 pub fn close_keyword_root(
     ctx: Context<CloseKeywordRoot>,
@@ -275,6 +449,120 @@ pub fn close_keyword_shard(
     Ok(())
 }
 
+/// Rewrite a keyword root created before the counting bloom filter (system administrator)
+#[derive(Accounts)]
+#[instruction(keyword: String)]
+pub struct MigrateKeywordRoot<'info> {
+    /// CHECK: legacy KeywordRoot, owner and layout are verified in the instruction
+    #[account(
+        mut,
+        seeds = [b"keyword_root", keyword.as_bytes()],
+        bump
+    )]
+    pub keyword_root: UncheckedAccount<'info>,
+
+    // Global root, provides the configured bloom filter size
+    #[account(
+        seeds = [b"global_id_root"],
+        bump
+    )]
+    pub global_root: Account<'info, GlobalIdRoot>,
+
+    #[account(
+        seeds = [b"system_config"],
+        bump,
+        constraint = system_config.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub system_config: Account<'info, SystemConfig>,
+
+    // Pays the rent of the larger filter
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts: every KeywordShard of the keyword, in chain order from first_shard
+}
+
+/// Convert a legacy keyword root to the counting bloom filter layout
+///
+/// The legacy bit filter cannot be converted to counters, so the filter is rebuilt from the
+/// product IDs of every shard. Shard links and counts are kept.
+pub fn migrate_keyword_root<'info>(
+    ctx: Context<'_, '_, 'info, 'info, MigrateKeywordRoot<'info>>,
+    keyword: String,
+) -> Result<()> {
+    let root_info = ctx.accounts.keyword_root.to_account_info();
+    require!(
+        root_info.owner == ctx.program_id,
+        ErrorCode::InvalidAccountOwner
+    );
+    let legacy = {
+        let data = root_info.try_borrow_data()?;
+        LegacyKeywordRoot::try_from_data(&data)?
+    };
+    require!(legacy.keyword == keyword, ErrorCode::InvalidKeyword);
+    require!(
+        ctx.remaining_accounts.len() == legacy.total_shards as usize,
+        ErrorCode::InsufficientAccounts
+    );
+
+    let bloom_filter_size = ctx.accounts.global_root.bloom_filter_size;
+    let mut bloom_filter = vec![0u8; CountingBloomFilter::clamp_size(bloom_filter_size)];
+    let mut expected_shard = Some(legacy.first_shard);
+    let mut last_shard = Pubkey::default();
+    for shard_info in ctx.remaining_accounts {
+        require!(
+            expected_shard == Some(shard_info.key()),
+            ErrorCode::InvalidShardLink
+        );
+        require!(
+            shard_info.owner == ctx.program_id,
+            ErrorCode::InvalidAccountOwner
+        );
+        let data = shard_info.try_borrow_data()?;
+        let shard = KeywordShard::try_deserialize(&mut &data[..])?;
+        require!(shard.keyword == keyword, ErrorCode::InvalidKeyword);
+
+        for &product_id in &shard.product_ids {
+            CountingBloomFilter::add(&mut bloom_filter, product_id);
+        }
+        expected_shard = shard.next_shard;
+        last_shard = shard_info.key();
+    }
+    if legacy.total_shards > 0 {
+        require!(
+            expected_shard.is_none() && last_shard == legacy.last_shard,
+            ErrorCode::InvalidShardLink
+        );
+    }
+
+    let keyword_root = KeywordRoot {
+        keyword: legacy.keyword,
+        total_shards: legacy.total_shards,
+        first_shard: legacy.first_shard,
+        last_shard: legacy.last_shard,
+        total_products: legacy.total_products,
+        bloom_filter,
+        bump: legacy.bump,
+    };
+    resize_account(
+        &root_info,
+        KeywordRoot::space(bloom_filter_size),
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    keyword_root.try_serialize(&mut &mut root_info.try_borrow_mut_data()?[..])?;
+
+    msg!(
+        "Keyword root '{}' migrated to a {}-byte counting bloom filter, {} shards scanned",
+        keyword,
+        keyword_root.bloom_filter.len(),
+        keyword_root.total_shards
+    );
+
+    Ok(())
+}
+
 // ============================================================================
 // init_if_needed 版本的指令：供product.rs模块使用
 // ============================================================================
@@ -283,10 +571,17 @@ pub fn close_keyword_shard(
 #[derive(Accounts)]
 #[instruction(keyword: String)]
 pub struct InitializeKeywordIndexIfNeeded<'info> {
+    // Global root, provides the configured bloom filter size
+    #[account(
+        seeds = [b"global_id_root"],
+        bump
+    )]
+    pub global_root: Account<'info, GlobalIdRoot>,
+
     #[account(
         init_if_needed,
         payer = payer,
-        space = 8 + KeywordRoot::INIT_SPACE,
+        space = KeywordRoot::space(global_root.bloom_filter_size),
         seeds = [b"keyword_root", keyword.as_bytes()],
         bump
     )]
@@ -312,6 +607,8 @@ pub fn initialize_keyword_index_if_needed(
     ctx: Context<InitializeKeywordIndexIfNeeded>,
     keyword: String,
 ) -> Result<()> {
+    let bloom_filter_size =
+        CountingBloomFilter::clamp_size(ctx.accounts.global_root.bloom_filter_size);
     let keyword_root = &mut ctx.accounts.keyword_root;
     let first_shard = &mut ctx.accounts.first_shard;
 
@@ -322,7 +619,7 @@ pub fn initialize_keyword_index_if_needed(
         keyword_root.total_shards = 1;
         keyword_root.first_shard = first_shard.key();
         keyword_root.last_shard = first_shard.key();
-        keyword_root.bloom_filter = [0u8; 256];
+        keyword_root.bloom_filter = vec![0u8; bloom_filter_size];
         keyword_root.bump = ctx.bumps.keyword_root;
 
         msg!(
@@ -353,10 +650,17 @@ pub fn initialize_keyword_index_if_needed(
 #[derive(Accounts)]
 #[instruction(keyword: String, product_id: u64)]
 pub struct AddProductToKeywordIndexIfNeeded<'info> {
+    // Global root, provides the configured bloom filter size
+    #[account(
+        seeds = [b"global_id_root"],
+        bump
+    )]
+    pub global_root: Account<'info, GlobalIdRoot>,
+
     #[account(
         init_if_needed,
         payer = payer,
-        space = 8 + KeywordRoot::INIT_SPACE,
+        space = KeywordRoot::space(global_root.bloom_filter_size),
         seeds = [b"keyword_root", keyword.as_bytes()],
         bump
     )]
@@ -367,7 +671,8 @@ pub struct AddProductToKeywordIndexIfNeeded<'info> {
         payer = payer,
         space = 8 + KeywordShard::INIT_SPACE,
         seeds = [b"keyword_shard", keyword.as_bytes(), 0u32.to_le_bytes().as_ref()],
-        bump
+        bump,
+        constraint = !is_frozen_legacy_shard::<KeywordShard>(&target_shard.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
     )]
     pub target_shard: Account<'info, KeywordShard>,
 
@@ -383,6 +688,8 @@ pub fn add_product_to_keyword_index_if_needed(
     keyword: String,
     product_id: u64,
 ) -> Result<()> {
+    let bloom_filter_size =
+        CountingBloomFilter::clamp_size(ctx.accounts.global_root.bloom_filter_size);
     let keyword_root = &mut ctx.accounts.keyword_root;
     let target_shard = &mut ctx.accounts.target_shard;
 
@@ -393,7 +700,7 @@ pub fn add_product_to_keyword_index_if_needed(
         keyword_root.total_shards = 1;
         keyword_root.first_shard = target_shard.key();
         keyword_root.last_shard = target_shard.key();
-        keyword_root.bloom_filter = [0u8; 256];
+        keyword_root.bloom_filter = vec![0u8; bloom_filter_size];
         keyword_root.bump = ctx.bumps.keyword_root;
 
         msg!(
@@ -430,6 +737,7 @@ pub fn add_product_to_keyword_index_if_needed(
     // 添加产品ID
     target_shard.product_ids.push(product_id);
     keyword_root.total_products += 1;
+    keyword_root.update_bloom_filter(product_id, true);
 
     msg!("产品 {} 已添加到关键词索引 '{}'", product_id, keyword);
 
@@ -0,0 +1,211 @@
+use crate::error::ErrorCode;
+use crate::state::*;
+use crate::utils::MAX_PAGE_SIZE;
+use anchor_lang::prelude::*;
+
+/// Create a bestseller leaderboard
+#[derive(Accounts)]
+#[instruction(scope: LeaderboardScope)]
+pub struct InitializeLeaderboard<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Leaderboard::INIT_SPACE,
+        seeds = [b"leaderboard", &[scope.kind()][..], scope.key_bytes().as_ref()],
+        bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create a global, category or payment token leaderboard
+///
+/// The global leaderboard is also created on the first settled order; category and payment
+/// token leaderboards are updated when passed in remaining accounts of sales changes.
+pub fn initialize_leaderboard(
+    ctx: Context<InitializeLeaderboard>,
+    scope: LeaderboardScope,
+) -> Result<()> {
+    ctx.accounts
+        .leaderboard
+        .initialize(&scope, ctx.bumps.leaderboard)?;
+
+    msg!("Leaderboard initialized, scope: {:?}", scope);
+
+    Ok(())
+}
+
+/// Query a bestseller leaderboard
+#[derive(Accounts)]
+#[instruction(scope: LeaderboardScope)]
+pub struct GetLeaderboard<'info> {
+    #[account(
+        seeds = [b"leaderboard", &[scope.kind()][..], scope.key_bytes().as_ref()],
+        bump = leaderboard.bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+}
+
+/// Bestselling products of a leaderboard, highest sales first
+pub fn get_leaderboard(
+    ctx: Context<GetLeaderboard>,
+    _scope: LeaderboardScope,
+    limit: u16,
+) -> Result<Vec<ProductSales>> {
+    let leaderboard = &ctx.accounts.leaderboard;
+    let limit = limit.min(MAX_PAGE_SIZE) as usize;
+
+    Ok(leaderboard.entries.iter().take(limit).cloned().collect())
+}
+
+// Apply a product's current sales to the leaderboards passed in `leaderboards`
+//
+// Every leaderboard must include the product (see LeaderboardScope::includes). When the product
+// has been hard deleted it is removed from the leaderboards instead.
+pub(crate) fn update_leaderboards<'info>(
+    leaderboards: &[AccountInfo<'info>],
+    product: Option<&ProductBase>,
+    product_id: u64,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+
+    for leaderboard_info in leaderboards {
+        require!(
+            leaderboard_info.owner == program_id,
+            ErrorCode::InvalidAccountOwner
+        );
+        let leaderboard_data = leaderboard_info.try_borrow_data()?;
+        let mut leaderboard = Leaderboard::try_deserialize(&mut &leaderboard_data[..])?;
+        drop(leaderboard_data);
+
+        match product {
+            Some(product) => {
+                require!(
+                    leaderboard.scope().includes(product),
+                    ErrorCode::InvalidLeaderboard
+                );
+                leaderboard.update_product(product, now);
+            }
+            None => {
+                leaderboard.remove_product(product_id);
+            }
+        }
+
+        let mut leaderboard_data = leaderboard_info.try_borrow_mut_data()?;
+        let mut cursor = std::io::Cursor::new(&mut leaderboard_data[..]);
+        leaderboard.try_serialize(&mut cursor)?;
+    }
+
+    Ok(())
+}
+
+// Apply a product's current sales to the global leaderboard, created on first use
+pub(crate) fn update_global_leaderboard(
+    leaderboard: &mut Leaderboard,
+    leaderboard_bump: u8,
+    product: Option<&ProductBase>,
+    product_id: u64,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+
+    if leaderboard.updated_at == 0 {
+        leaderboard.initialize(&LeaderboardScope::Global, leaderboard_bump)?;
+    }
+    match product {
+        Some(product) => {
+            leaderboard.update_product(product, now);
+        }
+        None => {
+            leaderboard.remove_product(product_id);
+        }
+    }
+
+    Ok(())
+}
+
+// Split the ranking accounts passed to a sales change into trending boards and leaderboards
+pub(crate) fn split_ranking_accounts<'info>(
+    accounts: &[AccountInfo<'info>],
+) -> Result<(Vec<AccountInfo<'info>>, Vec<AccountInfo<'info>>)> {
+    let mut trending_boards = Vec::new();
+    let mut leaderboards = Vec::new();
+
+    for account_info in accounts {
+        let data = account_info.try_borrow_data()?;
+        if data.starts_with(TrendingBoard::DISCRIMINATOR) {
+            trending_boards.push(account_info.clone());
+        } else if data.starts_with(Leaderboard::DISCRIMINATOR) {
+            leaderboards.push(account_info.clone());
+        } else {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+    }
+
+    Ok((trending_boards, leaderboards))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product(id: u64, sales: u32) -> ProductBase {
+        ProductBase {
+            id,
+            merchant: Pubkey::default(),
+            name: "这是一个名字非常非常长的测试商品名称".to_string(),
+            description: String::new(),
+            price: 1_000,
+            keywords: "phone,case".to_string(),
+            inventory: 10,
+            sales,
+            is_active: true,
+            created_at: 0,
+            updated_at: 0,
+            payment_token: Pubkey::default(),
+            shipping_location: String::new(),
+            variant_count: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_leaderboard_incremental_update() {
+        let mut leaderboard = Leaderboard {
+            kind: LeaderboardScope::Global.kind(),
+            category: String::new(),
+            payment_token: Pubkey::default(),
+            entries: Vec::new(),
+            updated_at: 0,
+            bump: 255,
+        };
+
+        leaderboard.update_product(&product(1, 5), 1);
+        leaderboard.update_product(&product(2, 9), 1);
+        leaderboard.update_product(&product(3, 5), 1);
+        let ids: Vec<u64> = leaderboard
+            .entries
+            .iter()
+            .map(|item| item.product_id)
+            .collect();
+        assert_eq!(ids, vec![2, 1, 3]);
+        assert!(leaderboard.entries[0].name.len() <= LEADERBOARD_NAME_LENGTH);
+
+        // A refund moves the product down, zero sales drop it
+        leaderboard.update_product(&product(2, 4), 2);
+        leaderboard.update_product(&product(1, 0), 2);
+        let ids: Vec<u64> = leaderboard
+            .entries
+            .iter()
+            .map(|item| item.product_id)
+            .collect();
+        assert_eq!(ids, vec![3, 2]);
+
+        assert!(LeaderboardScope::Category("case".to_string()).includes(&product(4, 1)));
+        assert!(!LeaderboardScope::Category("laptop".to_string()).includes(&product(4, 1)));
+    }
+}
@@ -1,5 +1,8 @@
 use crate::error::ErrorCode;
-use crate::state::{GlobalIdRoot, IdChunk, Merchant, MerchantIdAccount, MerchantStats};
+use crate::state::{
+    GlobalIdRoot, IdChunk, Merchant, MerchantIdAccount, MerchantOrderStats, MerchantProductList,
+    MerchantStats, MAX_MERCHANT_STATS_TOKENS,
+};
 use anchor_lang::prelude::*;
 // Remove unused token imports as deposit management has been unified to deposit.rs module
 
@@ -64,20 +67,56 @@ pub struct GetMerchantStats<'info> {
     )]
     pub merchant_info: Account<'info, Merchant>,
 
+    /// CHECK: merchant product catalog, does not exist before the first product; read when it does
+    #[account(
+        seeds = [b"merchant_product_list", owner.key().as_ref()],
+        bump
+    )]
+    pub merchant_product_list: UncheckedAccount<'info>,
+
     pub owner: Signer<'info>,
+    // remaining_accounts: MerchantOrderStats of the merchant, one per payment token
 }
 
-pub fn get_merchant_stats(ctx: Context<GetMerchantStats>) -> Result<MerchantStats> {
+pub fn get_merchant_stats<'info>(
+    ctx: Context<'_, '_, 'info, 'info, GetMerchantStats<'info>>,
+) -> Result<MerchantStats> {
     let merchant_info = &ctx.accounts.merchant_info;
 
-    Ok(MerchantStats {
-        product_count: merchant_info.product_count,
-        total_sales: merchant_info.total_sales,
-        active_products: merchant_info.product_count, // Simplified processing
-        total_keywords: 0,                            // Need to get from elsewhere
-        avg_product_price: 0,                         // Need to calculate
-        last_updated: merchant_info.updated_at,
-    })
+    let catalog_info = &ctx.accounts.merchant_product_list;
+    let catalog = if catalog_info.owner == ctx.program_id && !catalog_info.data_is_empty() {
+        let data = catalog_info.try_borrow_data()?;
+        Some(MerchantProductList::try_deserialize(&mut &data[..])?)
+    } else {
+        None
+    };
+
+    require!(
+        ctx.remaining_accounts.len() <= MAX_MERCHANT_STATS_TOKENS,
+        ErrorCode::TooManyTokens
+    );
+    let mut order_stats: Vec<MerchantOrderStats> = Vec::with_capacity(ctx.remaining_accounts.len());
+    for stats_info in ctx.remaining_accounts {
+        require!(
+            stats_info.owner == ctx.program_id,
+            ErrorCode::InvalidAccountOwner
+        );
+        let data = stats_info.try_borrow_data()?;
+        let stats = MerchantOrderStats::try_deserialize(&mut &data[..])?;
+        // A mint listed twice would be counted twice
+        require!(
+            stats.merchant == merchant_info.owner
+                && order_stats.iter().all(|listed| listed.mint != stats.mint),
+            ErrorCode::InvalidMerchantOrderStats
+        );
+        order_stats.push(stats);
+    }
+
+    Ok(MerchantStats::new(
+        merchant_info,
+        catalog.as_ref(),
+        &order_stats,
+    ))
 }
 
 // Set merchant status
@@ -88,14 +127,6 @@ pub fn set_merchant_status(ctx: Context<UpdateMerchant>, is_active: bool) -> Res
     Ok(())
 }
 
-// Increase merchant sales
-pub fn add_merchant_sales(ctx: Context<UpdateMerchant>, sales_amount: u64) -> Result<()> {
-    let merchant_info = &mut ctx.accounts.merchant_info;
-    merchant_info.add_sales(sales_amount)?;
-
-    Ok(())
-}
-
 // Close merchant account
 #[derive(Accounts)]
 pub struct CloseMerchant<'info> {
@@ -234,16 +265,13 @@ pub fn register_merchant_atomic(
     )?;
 
     // 3. Initialize the first ID chunk - use merchant ID based range
-    let merchant_start_id = merchant_id as u64 * 10000; // Reserve 10000 IDs per merchant
-    initial_chunk.merchant_id = merchant_id;
-    initial_chunk.chunk_index = 0;
-    initial_chunk.start_id = merchant_start_id;
-    initial_chunk.end_id = merchant_start_id + global_root.chunk_size as u64 - 1;
-    initial_chunk.next_available = 0;
-
-    // Safely initialize bitmap (use Vec<u8> to avoid stack overflow)
-    initial_chunk.initialize_bitmap();
-    initial_chunk.bump = ctx.bumps.initial_chunk;
+    initial_chunk.initialize(
+        merchant_id,
+        0,
+        IdChunk::initial_start_id(merchant_id),
+        global_root.chunk_size,
+        ctx.bumps.initial_chunk,
+    );
 
     // 4. Initialize merchant ID allocation account
     merchant_id_account.merchant_id = merchant_id;
@@ -253,8 +281,7 @@ pub fn register_merchant_atomic(
     merchant_id_account.unused_chunks = Vec::new();
     merchant_id_account.bump = ctx.bumps.merchant_id_account;
 
-    // 5. Update global state
-    global_root.last_global_id = initial_chunk.end_id + 1;
+    // 5. Update global state (last_global_id only tracks the pool of later chunks)
     // Remove merchants vector operations - use other methods to track merchant count
 
     // Emit event
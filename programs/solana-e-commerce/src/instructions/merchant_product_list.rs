@@ -0,0 +1,103 @@
+use crate::error::ErrorCode;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+/// Query one page of a merchant's product catalog
+#[derive(Accounts)]
+#[instruction(merchant: Pubkey)]
+pub struct GetMerchantProducts<'info> {
+    #[account(
+        seeds = [b"merchant_product_list", merchant.as_ref()],
+        bump = merchant_product_list.bump
+    )]
+    pub merchant_product_list: Account<'info, MerchantProductList>,
+
+    #[account(
+        constraint = product_page.merchant == merchant @ ErrorCode::InvalidProductCatalogPage
+    )]
+    pub product_page: Account<'info, MerchantProductPage>,
+}
+
+/// Query one page of a merchant's active or inactive products
+pub fn get_merchant_products(
+    ctx: Context<GetMerchantProducts>,
+    _merchant: Pubkey,
+    is_active: bool,
+    page_index: u64,
+) -> Result<MerchantProductPageInfo> {
+    let catalog = &ctx.accounts.merchant_product_list;
+    let page = &ctx.accounts.product_page;
+
+    require!(
+        page.is_active == is_active && page.page_index == page_index,
+        ErrorCode::InvalidProductCatalogPage
+    );
+
+    let (total_count, appended) = if is_active {
+        (catalog.active_count, catalog.active_appended)
+    } else {
+        (catalog.inactive_count, catalog.inactive_appended)
+    };
+    let last_page_index = appended.saturating_sub(1) / MERCHANT_PRODUCT_PAGE_SIZE as u64;
+
+    Ok(MerchantProductPageInfo {
+        product_ids: page.product_ids.clone(),
+        page_index,
+        total_count,
+        has_more: appended > 0 && page_index < last_page_index,
+    })
+}
+
+/// Merchant product catalog page structure
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MerchantProductPageInfo {
+    pub product_ids: Vec<u64>, // Product ids in this page
+    pub page_index: u64,       // Page index
+    pub total_count: u64,      // Products in this chain (active or inactive)
+    pub has_more: bool,        // Whether a later page exists
+}
+
+// Append a product to the active or inactive chain of the merchant catalog
+//
+// `page` must be the page at `catalog.next_page_index(is_active)`, which the accounts
+// constraints derive from the catalog before it is updated.
+pub(crate) fn append_to_catalog(
+    catalog: &mut MerchantProductList,
+    catalog_bump: u8,
+    page: &mut MerchantProductPage,
+    page_bump: u8,
+    merchant: Pubkey,
+    is_active: bool,
+    product_id: u64,
+) -> Result<()> {
+    if catalog.merchant == Pubkey::default() {
+        catalog.initialize(merchant, catalog_bump)?;
+    }
+
+    let page_index = catalog.next_page_index(is_active);
+    page.initialize_if_needed(merchant, is_active, page_index, page_bump);
+    require!(
+        page.merchant == merchant && page.is_active == is_active && page.page_index == page_index,
+        ErrorCode::InvalidProductCatalogPage
+    );
+
+    page.add_product(product_id)?;
+    catalog.record_append(is_active)?;
+
+    Ok(())
+}
+
+// Remove a product from the catalog page holding it
+pub(crate) fn remove_from_catalog(
+    catalog: &mut MerchantProductList,
+    page: &mut MerchantProductPage,
+    product_id: u64,
+) -> Result<()> {
+    require!(
+        page.remove_product(product_id),
+        ErrorCode::InvalidProductCatalogPage
+    );
+    catalog.record_remove(page.is_active)?;
+
+    Ok(())
+}
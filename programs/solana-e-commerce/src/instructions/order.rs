@@ -1,11 +1,24 @@
 use crate::error::ErrorCode;
+use crate::instructions::leaderboard::{
+    split_ranking_accounts, update_global_leaderboard, update_leaderboards,
+};
+use crate::instructions::product_variant::resolve_variant_price;
+use crate::instructions::trending::record_trending_sale;
 use crate::state::*;
+use crate::utils::migrate_account;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
 
 // Create order (original - for backward compatibility)
 #[derive(Accounts)]
-#[instruction(product_id: u64)]
+#[instruction(
+    product_id: u64,
+    quantity: u32,
+    shipping_address: String,
+    notes: String,
+    transaction_signature: String,
+    day: u32
+)]
 pub struct CreateOrder<'info> {
     #[account(
         init_if_needed,
@@ -39,12 +52,49 @@ pub struct CreateOrder<'info> {
     )]
     pub order_stats: Account<'info, OrderStats>,
 
+    // Order statistics of the payment token and of the merchant in it
     #[account(
-        seeds = [b"product", product_id.to_le_bytes().as_ref()],
+        init_if_needed,
+        payer = buyer,
+        space = 8 + TokenOrderStats::INIT_SPACE,
+        seeds = [b"token_order_stats", product.payment_token.as_ref()],
+        bump
+    )]
+    pub token_order_stats: Account<'info, TokenOrderStats>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + MerchantOrderStats::INIT_SPACE,
+        seeds = [b"merchant_order_stats", product.merchant.as_ref(), product.payment_token.as_ref()],
         bump
     )]
+    pub merchant_order_stats: Account<'info, MerchantOrderStats>,
+
+    // Daily order statistics of the payment token, `day` is the current day
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + DailyOrderStats::INIT_SPACE,
+        seeds = [b"daily_order_stats", product.payment_token.as_ref(), day.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_order_stats: Account<'info, DailyOrderStats>,
+
+    #[account(
+        seeds = [b"product", product_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = ProductBase::has_current_layout(&product.to_account_info()) @ ErrorCode::AccountNotMigrated
+    )]
     pub product: Account<'info, ProductBase>,
 
+    // Variant being ordered, required when the product has variants (its stock is reserved)
+    #[account(
+        mut,
+        constraint = product_variant.product_id == product_id @ ErrorCode::InvalidProductVariant
+    )]
+    pub product_variant: Option<Account<'info, ProductVariant>>,
+
     #[account(
         seeds = [b"merchant_info", merchant.owner.as_ref()],
         bump = merchant.bump
@@ -96,6 +146,35 @@ pub struct ShipOrder<'info> {
     )]
     pub order_stats: Account<'info, OrderStats>,
 
+    // Order statistics of the payment token and of the merchant in it
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + TokenOrderStats::INIT_SPACE,
+        seeds = [b"token_order_stats", order.payment_token.as_ref()],
+        bump
+    )]
+    pub token_order_stats: Account<'info, TokenOrderStats>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MerchantOrderStats::INIT_SPACE,
+        seeds = [b"merchant_order_stats", order.merchant.as_ref(), order.payment_token.as_ref()],
+        bump
+    )]
+    pub merchant_order_stats: Account<'info, MerchantOrderStats>,
+
+    // Reputation of the merchant
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MerchantReputation::INIT_SPACE,
+        seeds = [b"merchant_reputation", order.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_reputation: Account<'info, MerchantReputation>,
+
     #[account(
         seeds = [b"merchant_info", merchant.owner.as_ref()],
         bump = merchant.bump,
@@ -103,11 +182,15 @@ pub struct ShipOrder<'info> {
     )]
     pub merchant: Account<'info, Merchant>,
 
+    #[account(mut)]
     pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 // Buyer requests refund (direct refund)
 #[derive(Accounts)]
+#[instruction(refund_reason: String, day: u32)]
 pub struct RefundOrder<'info> {
     #[account(
         mut,
@@ -115,7 +198,59 @@ pub struct RefundOrder<'info> {
     )]
     pub order: Account<'info, Order>,
 
-    // Remove order_stats account - statistics functionality is not core, can be obtained through other methods
+    // Variant of the order, required when the order names one (its stock is released)
+    #[account(
+        mut,
+        constraint = order.variant_id == Some(product_variant.variant_id) @ ErrorCode::InvalidProductVariant,
+        constraint = product_variant.product_id == order.product_id @ ErrorCode::InvalidProductVariant
+    )]
+    pub product_variant: Option<Account<'info, ProductVariant>>,
+
+    #[account(
+        mut,
+        seeds = [b"order_stats"],
+        bump
+    )]
+    pub order_stats: Account<'info, OrderStats>,
+
+    // Order statistics of the payment token and of the merchant in it
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + TokenOrderStats::INIT_SPACE,
+        seeds = [b"token_order_stats", order.payment_token.as_ref()],
+        bump
+    )]
+    pub token_order_stats: Account<'info, TokenOrderStats>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + MerchantOrderStats::INIT_SPACE,
+        seeds = [b"merchant_order_stats", order.merchant.as_ref(), order.payment_token.as_ref()],
+        bump
+    )]
+    pub merchant_order_stats: Account<'info, MerchantOrderStats>,
+
+    // Reputation of the merchant
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + MerchantReputation::INIT_SPACE,
+        seeds = [b"merchant_reputation", order.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_reputation: Account<'info, MerchantReputation>,
+
+    // Daily order statistics of the payment token, `day` is the current day
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + DailyOrderStats::INIT_SPACE,
+        seeds = [b"daily_order_stats", order.payment_token.as_ref(), day.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_order_stats: Account<'info, DailyOrderStats>,
 
     // Main program unified escrow account (refund source)
     #[account(
@@ -125,6 +260,14 @@ pub struct RefundOrder<'info> {
     )]
     pub program_token_account: Account<'info, TokenAccount>,
 
+    // Escrow liabilities of the payment token
+    #[account(
+        mut,
+        seeds = [b"escrow_ledger", payment_token_mint.key().as_ref()],
+        bump = escrow_ledger.bump
+    )]
+    pub escrow_ledger: Account<'info, EscrowLedger>,
+
     #[account(mut)]
     pub buyer_token_account: Account<'info, TokenAccount>,
 
@@ -135,14 +278,166 @@ pub struct RefundOrder<'info> {
     )]
     pub program_authority: AccountInfo<'info>,
 
+    #[account(
+        constraint = payment_token_mint.key() == order.payment_token @ ErrorCode::UnsupportedToken
+    )]
     pub payment_token_mint: Account<'info, Mint>,
 
+    #[account(mut)]
     pub buyer: Signer<'info>,
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 // Merchant approval refund instruction has been removed, buyer can refund directly
 
+// Merchant approved refund of a delivered order (paid back from the merchant deposit)
+#[derive(Accounts)]
+#[instruction(refund_reason: String, day: u32)]
+pub struct RefundDeliveredOrder<'info> {
+    #[account(
+        mut,
+        constraint = order.merchant == merchant_owner.key() @ ErrorCode::Unauthorized
+    )]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        mut,
+        seeds = [b"order_stats"],
+        bump
+    )]
+    pub order_stats: Account<'info, OrderStats>,
+
+    // Order statistics of the payment token and of the merchant in it
+    #[account(
+        init_if_needed,
+        payer = merchant_owner,
+        space = 8 + TokenOrderStats::INIT_SPACE,
+        seeds = [b"token_order_stats", order.payment_token.as_ref()],
+        bump
+    )]
+    pub token_order_stats: Account<'info, TokenOrderStats>,
+
+    #[account(
+        init_if_needed,
+        payer = merchant_owner,
+        space = 8 + MerchantOrderStats::INIT_SPACE,
+        seeds = [b"merchant_order_stats", order.merchant.as_ref(), order.payment_token.as_ref()],
+        bump
+    )]
+    pub merchant_order_stats: Account<'info, MerchantOrderStats>,
+
+    // Reputation of the merchant
+    #[account(
+        init_if_needed,
+        payer = merchant_owner,
+        space = 8 + MerchantReputation::INIT_SPACE,
+        seeds = [b"merchant_reputation", order.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_reputation: Account<'info, MerchantReputation>,
+
+    // Daily order statistics of the payment token, `day` is the current day
+    #[account(
+        init_if_needed,
+        payer = merchant_owner,
+        space = 8 + DailyOrderStats::INIT_SPACE,
+        seeds = [b"daily_order_stats", order.payment_token.as_ref(), day.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_order_stats: Account<'info, DailyOrderStats>,
+
+    #[account(
+        mut,
+        seeds = [b"merchant_info", merchant_owner.key().as_ref()],
+        bump
+    )]
+    pub merchant_info: Account<'info, Merchant>,
+
+    /// CHECK: Product of the order, verified in instruction (may have been hard deleted)
+    #[account(
+        mut,
+        seeds = [b"product", order.product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub product: UncheckedAccount<'info>,
+
+    /// CHECK: Sales index node covering the product's current sales, or the zero-copy sales shard
+    /// of a migrated range, verified in instruction (None when the product is not indexed)
+    #[account(mut)]
+    pub old_sales_node: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Sales index node covering the product's sales after settlement, or the zero-copy
+    /// sales shard of a migrated range, verified in instruction (None when the product is not
+    /// indexed)
+    #[account(mut)]
+    pub new_sales_node: Option<UncheckedAccount<'info>>,
+
+    // Trending score of the order's product
+    #[account(
+        init_if_needed,
+        payer = merchant_owner,
+        space = 8 + TrendingScore::INIT_SPACE,
+        seeds = [b"trending_score", order.product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub trending_score: Account<'info, TrendingScore>,
+
+    // Global trending leaderboard
+    #[account(
+        init_if_needed,
+        payer = merchant_owner,
+        space = 8 + TrendingBoard::INIT_SPACE,
+        seeds = [b"trending_global"],
+        bump
+    )]
+    pub trending_board: Account<'info, TrendingBoard>,
+
+    // Global bestseller leaderboard (keyword trending lists, category and payment token
+    // leaderboards of the product go in remaining accounts)
+    #[account(
+        init_if_needed,
+        payer = merchant_owner,
+        space = 8 + Leaderboard::INIT_SPACE,
+        seeds = [b"leaderboard", [LeaderboardScope::Global.kind()].as_ref()],
+        bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+
+    #[account(
+        seeds = [b"system_config"],
+        bump
+    )]
+    pub system_config: Account<'info, crate::SystemConfig>,
+
+    #[account(
+        constraint = deposit_token_mint.key() == system_config.deposit_token_mint @ ErrorCode::InvalidDepositToken,
+        constraint = deposit_token_mint.key() == order.payment_token @ ErrorCode::InvalidDepositToken
+    )]
+    pub deposit_token_mint: Account<'info, Mint>,
+
+    // Deposit escrow account (refund source)
+    #[account(
+        mut,
+        seeds = [b"deposit_escrow", deposit_token_mint.key().as_ref()],
+        bump,
+        constraint = deposit_escrow_account.mint == deposit_token_mint.key() @ ErrorCode::InvalidDepositToken
+    )]
+    pub deposit_escrow_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == order.buyer @ ErrorCode::Unauthorized,
+        constraint = buyer_token_account.mint == order.payment_token @ ErrorCode::InvalidDepositToken
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub merchant_owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
 // Initialize order statistics
 #[derive(Accounts)]
 pub struct InitializeOrderStats<'info> {
@@ -163,6 +458,7 @@ pub struct InitializeOrderStats<'info> {
 
 // Confirm delivery
 #[derive(Accounts)]
+#[instruction(day: u32)]
 pub struct ConfirmDelivery<'info> {
     #[account(
         mut,
@@ -177,6 +473,45 @@ pub struct ConfirmDelivery<'info> {
     )]
     pub order_stats: Account<'info, OrderStats>,
 
+    // Order statistics of the payment token and of the merchant in it
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + TokenOrderStats::INIT_SPACE,
+        seeds = [b"token_order_stats", order.payment_token.as_ref()],
+        bump
+    )]
+    pub token_order_stats: Account<'info, TokenOrderStats>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + MerchantOrderStats::INIT_SPACE,
+        seeds = [b"merchant_order_stats", order.merchant.as_ref(), order.payment_token.as_ref()],
+        bump
+    )]
+    pub merchant_order_stats: Account<'info, MerchantOrderStats>,
+
+    // Reputation of the merchant
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + MerchantReputation::INIT_SPACE,
+        seeds = [b"merchant_reputation", order.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_reputation: Account<'info, MerchantReputation>,
+
+    // Daily order statistics of the payment token, `day` is the current day
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + DailyOrderStats::INIT_SPACE,
+        seeds = [b"daily_order_stats", order.payment_token.as_ref(), day.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_order_stats: Account<'info, DailyOrderStats>,
+
     // Merchant info account (for updating deposit balance)
     #[account(
         mut,
@@ -185,6 +520,56 @@ pub struct ConfirmDelivery<'info> {
     )]
     pub merchant_info: Account<'info, crate::state::Merchant>,
 
+    /// CHECK: Product of the order, verified in instruction (may have been hard deleted)
+    #[account(
+        mut,
+        seeds = [b"product", order.product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub product: UncheckedAccount<'info>,
+
+    /// CHECK: Sales index node covering the product's current sales, or the zero-copy sales shard
+    /// of a migrated range, verified in instruction (None when the product is not indexed)
+    #[account(mut)]
+    pub old_sales_node: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Sales index node covering the product's sales after settlement, or the zero-copy
+    /// sales shard of a migrated range, verified in instruction (None when the product is not
+    /// indexed)
+    #[account(mut)]
+    pub new_sales_node: Option<UncheckedAccount<'info>>,
+
+    // Trending score of the order's product
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + TrendingScore::INIT_SPACE,
+        seeds = [b"trending_score", order.product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub trending_score: Account<'info, TrendingScore>,
+
+    // Global trending leaderboard
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + TrendingBoard::INIT_SPACE,
+        seeds = [b"trending_global"],
+        bump
+    )]
+    pub trending_board: Account<'info, TrendingBoard>,
+
+    // Global bestseller leaderboard (keyword trending lists, category and payment token
+    // leaderboards of the product go in remaining accounts)
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + Leaderboard::INIT_SPACE,
+        seeds = [b"leaderboard", [LeaderboardScope::Global.kind()].as_ref()],
+        bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+
     // System config account (get deposit token mint and platform fee configuration)
     #[account(
         seeds = [b"system_config"],
@@ -199,6 +584,14 @@ pub struct ConfirmDelivery<'info> {
     )]
     pub program_token_account: Account<'info, TokenAccount>,
 
+    // Escrow liabilities of the deposit token
+    #[account(
+        mut,
+        seeds = [b"escrow_ledger", system_config.deposit_token_mint.as_ref()],
+        bump = escrow_ledger.bump
+    )]
+    pub escrow_ledger: Account<'info, EscrowLedger>,
+
     // Deposit escrow account (receive funds from confirmed delivery)
     #[account(
         mut,
@@ -243,6 +636,7 @@ pub struct ConfirmDelivery<'info> {
     )]
     pub vault_program: UncheckedAccount<'info>,
 
+    #[account(mut)]
     pub buyer: Signer<'info>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
@@ -282,6 +676,7 @@ pub fn create_order(
     shipping_address: String,
     notes: String,
     transaction_signature: String,
+    day: u32,
 ) -> Result<()> {
     let order = &mut ctx.accounts.order;
     let merchant_order = &mut ctx.accounts.merchant_order;
@@ -304,6 +699,13 @@ pub fn create_order(
         ErrorCode::InvalidMerchant
     );
 
+    // A product with variants is ordered as one of them, at its price and from its stock
+    let (variant_id, price) =
+        resolve_variant_price(product, ctx.accounts.product_variant.as_deref())?;
+    if let Some(variant) = ctx.accounts.product_variant.as_mut() {
+        variant.reserve(quantity)?;
+    }
+
     // Initialize or update user purchase count
     if user_purchase_count.buyer == Pubkey::default() {
         user_purchase_count.initialize(buyer.key(), ctx.bumps.user_purchase_count)?;
@@ -322,9 +724,10 @@ pub fn create_order(
     order.buyer = buyer.key();
     order.merchant = merchant.owner;
     order.product_id = product_id;
+    order.variant_id = variant_id;
     order.quantity = quantity;
-    order.price = product.price;
-    order.total_amount = product.price.checked_mul(quantity as u64).unwrap();
+    order.price = price;
+    order.total_amount = price.checked_mul(quantity as u64).unwrap();
     order.payment_token = product.payment_token;
     order.status = OrderManagementStatus::Pending;
     order.shipping_address = shipping_address;
@@ -355,6 +758,23 @@ pub fn create_order(
 
     // Update order statistics
     order_stats.update_for_new_order(order);
+    record_order_stats(
+        &mut ctx.accounts.token_order_stats,
+        ctx.bumps.token_order_stats,
+        &mut ctx.accounts.merchant_order_stats,
+        ctx.bumps.merchant_order_stats,
+        order,
+        None,
+        current_timestamp,
+    );
+    let daily_order_stats = &mut ctx.accounts.daily_order_stats;
+    daily_order_stats.open(
+        order.payment_token,
+        day,
+        current_timestamp,
+        ctx.bumps.daily_order_stats,
+    )?;
+    daily_order_stats.record_created(order.total_amount);
 
     msg!(
         "Dual order creation successful: Buyer order PDA: {}, Merchant order PDA: {}, Buyer: {}, Merchant: {}, Product: {}, Quantity: {}, Total amount: {} lamports, Merchant order sequence: {}",
@@ -400,6 +820,25 @@ pub fn ship_order(ctx: Context<ShipOrder>, tracking_number: String) -> Result<()
         &OrderManagementStatus::Shipped,
         order.total_amount,
     );
+    record_order_stats(
+        &mut ctx.accounts.token_order_stats,
+        ctx.bumps.token_order_stats,
+        &mut ctx.accounts.merchant_order_stats,
+        ctx.bumps.merchant_order_stats,
+        order,
+        Some(&old_status),
+        current_time,
+    );
+    ctx.accounts
+        .merchant_order_stats
+        .record_shipment(order, current_time);
+
+    // Shipping after the deadline counts against the merchant
+    let reputation = &mut ctx.accounts.merchant_reputation;
+    reputation.open(order.merchant, current_time, ctx.bumps.merchant_reputation);
+    if order.is_past_ship_deadline(current_time) {
+        reputation.record(ReputationEvent::MissedShipDeadline, current_time);
+    }
 
     msg!("Merchant shipping successful: Tracking number: {}", tracking_number);
 
@@ -407,9 +846,8 @@ pub fn ship_order(ctx: Context<ShipOrder>, tracking_number: String) -> Result<()
 }
 
 // Buyer direct refund
-pub fn refund_order(ctx: Context<RefundOrder>, refund_reason: String) -> Result<()> {
+pub fn refund_order(ctx: Context<RefundOrder>, refund_reason: String, day: u32) -> Result<()> {
     let order = &mut ctx.accounts.order;
-    // Remove order_stats reference - statistics functionality has been simplified
 
     // Verify order status must be shipped
     require!(order.can_request_refund(), ErrorCode::OrderCannotBeRefunded);
@@ -420,6 +858,8 @@ pub fn refund_order(ctx: Context<RefundOrder>, refund_reason: String) -> Result<
         ErrorCode::InvalidOrderNotesLength
     );
 
+    ctx.accounts.escrow_ledger.check_withdrawals_allowed()?;
+
     // Execute token refund: transfer directly from main program escrow account to buyer
     let program_authority_bump = ctx.bumps.program_authority;
     let program_signer_seeds = &[b"program_authority".as_ref(), &[program_authority_bump]];
@@ -434,14 +874,57 @@ pub fn refund_order(ctx: Context<RefundOrder>, refund_reason: String) -> Result<
     let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, program_signer);
 
     transfer(cpi_ctx, order.total_amount)?;
+    ctx.accounts
+        .escrow_ledger
+        .record_release(order.total_amount);
 
     let current_time = Clock::get()?.unix_timestamp;
+    let old_status = order.status.clone();
+
+    // Cancelling an order before the ship deadline is up to the buyer, after it the merchant
+    // missed the deadline
+    let reputation = &mut ctx.accounts.merchant_reputation;
+    reputation.open(order.merchant, current_time, ctx.bumps.merchant_reputation);
+    if order.is_past_ship_deadline(current_time) {
+        reputation.record(ReputationEvent::MissedShipDeadline, current_time);
+    }
+
+    // The order was never shipped, its variant stock goes back on sale
+    if order.variant_id.is_some() {
+        ctx.accounts
+            .product_variant
+            .as_mut()
+            .ok_or(ErrorCode::ProductVariantRequired)?
+            .release(order.quantity)?;
+    }
 
     // Update order status to refunded
     order.update_status(OrderManagementStatus::Refunded, current_time)?;
     order.refund_reason = refund_reason.clone();
 
-    // Statistics update removed - can get statistics by querying on-chain order accounts
+    // Update statistics
+    ctx.accounts.order_stats.update_for_status_change(
+        &old_status,
+        &OrderManagementStatus::Refunded,
+        order.total_amount,
+    );
+    record_order_stats(
+        &mut ctx.accounts.token_order_stats,
+        ctx.bumps.token_order_stats,
+        &mut ctx.accounts.merchant_order_stats,
+        ctx.bumps.merchant_order_stats,
+        order,
+        Some(&old_status),
+        current_time,
+    );
+    let daily_order_stats = &mut ctx.accounts.daily_order_stats;
+    daily_order_stats.open(
+        order.payment_token,
+        day,
+        current_time,
+        ctx.bumps.daily_order_stats,
+    )?;
+    daily_order_stats.record_refunded(order.total_amount);
 
     msg!(
         "Buyer direct refund successful: Buyer: {}, Refund amount: {} tokens, Refund reason: {}",
@@ -455,6 +938,291 @@ pub fn refund_order(ctx: Context<RefundOrder>, refund_reason: String) -> Result<
 
 // Merchant approval refund function has been removed, buyer can refund directly
 
+// Merchant approved refund after delivery (the goods were shipped, variant stock is not restored)
+pub fn refund_delivered_order(
+    ctx: Context<RefundDeliveredOrder>,
+    refund_reason: String,
+    day: u32,
+) -> Result<()> {
+    let order = &mut ctx.accounts.order;
+    let order_stats = &mut ctx.accounts.order_stats;
+    let merchant_info = &mut ctx.accounts.merchant_info;
+
+    require!(
+        order.status == OrderManagementStatus::Delivered,
+        ErrorCode::OrderCannotBeRefunded
+    );
+    require!(
+        refund_reason.len() <= 200,
+        ErrorCode::InvalidOrderNotesLength
+    );
+
+    // The settled amount was credited to the merchant deposit, refund from there
+    let refund_amount = order.total_amount;
+    require!(
+        merchant_info.deposit_amount >= refund_amount,
+        ErrorCode::InsufficientDeposit
+    );
+    require!(
+        ctx.accounts.deposit_escrow_account.amount >= refund_amount,
+        ErrorCode::InsufficientFunds
+    );
+
+    let deposit_escrow_bump = ctx.bumps.deposit_escrow_account;
+    let token_mint_key = ctx.accounts.deposit_token_mint.key();
+    let seeds = &[
+        b"deposit_escrow".as_ref(),
+        token_mint_key.as_ref(),
+        &[deposit_escrow_bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.deposit_escrow_account.to_account_info(),
+        to: ctx.accounts.buyer_token_account.to_account_info(),
+        authority: ctx.accounts.deposit_escrow_account.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+
+    transfer(cpi_ctx, refund_amount)?;
+
+    merchant_info.deduct_deposit(refund_amount)?;
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let old_status = order.status.clone();
+    let delivered_at = order.delivered_at.unwrap_or(current_time);
+
+    order.refund_after_delivery(current_time)?;
+    order.refund_reason = refund_reason.clone();
+
+    let reputation = &mut ctx.accounts.merchant_reputation;
+    reputation.open(order.merchant, current_time, ctx.bumps.merchant_reputation);
+    reputation.record(ReputationEvent::Refunded, current_time);
+
+    order_stats.update_for_status_change(
+        &old_status,
+        &OrderManagementStatus::Refunded,
+        order.total_amount,
+    );
+    record_order_stats(
+        &mut ctx.accounts.token_order_stats,
+        ctx.bumps.token_order_stats,
+        &mut ctx.accounts.merchant_order_stats,
+        ctx.bumps.merchant_order_stats,
+        order,
+        Some(&old_status),
+        current_time,
+    );
+    let daily_order_stats = &mut ctx.accounts.daily_order_stats;
+    daily_order_stats.open(
+        order.payment_token,
+        day,
+        current_time,
+        ctx.bumps.daily_order_stats,
+    )?;
+    daily_order_stats.record_refunded(refund_amount);
+
+    // Revert the sale counted at delivery
+    let product = record_settled_sales(
+        &ctx.accounts.product,
+        ctx.accounts.old_sales_node.as_deref(),
+        ctx.accounts.new_sales_node.as_deref(),
+        merchant_info,
+        order.product_id,
+        order.quantity,
+        false,
+        ctx.accounts.system_config.index_shard_capacity,
+        &ctx.accounts.merchant_owner.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.program_id,
+    )?;
+    record_rankings(
+        &mut ctx.accounts.trending_score,
+        ctx.bumps.trending_score,
+        &mut ctx.accounts.trending_board,
+        ctx.bumps.trending_board,
+        &mut ctx.accounts.leaderboard,
+        ctx.bumps.leaderboard,
+        ctx.remaining_accounts,
+        product.as_ref(),
+        order.product_id,
+        order.quantity,
+        Some(delivered_at),
+        ctx.accounts.system_config.trending_half_life_seconds,
+        ctx.program_id,
+    )?;
+
+    msg!(
+        "Post-delivery refund successful: Buyer: {}, Merchant: {}, Refund amount: {} tokens, Refund reason: {}",
+        order.buyer,
+        order.merchant,
+        refund_amount,
+        refund_reason
+    );
+
+    Ok(())
+}
+
+// Apply settled sales of an order to the product, its sales index and the merchant
+//
+// The product PDA is enforced by the accounts constraint; if the product has been hard deleted
+// only the merchant total is updated. Sales index accounts are legacy nodes or, for migrated
+// ranges, zero-copy sales shards grown at the payer's expense; the sales index is left alone when
+// either is missing or not created yet. Returns the updated product when it still exists.
+#[allow(clippy::too_many_arguments)]
+fn record_settled_sales<'info>(
+    product_info: &AccountInfo<'info>,
+    old_sales_node: Option<&AccountInfo<'info>>,
+    new_sales_node: Option<&AccountInfo<'info>>,
+    merchant: &mut Merchant,
+    product_id: u64,
+    quantity: u32,
+    is_sale: bool,
+    shard_capacity: u32,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> Result<Option<ProductBase>> {
+    if is_sale {
+        merchant.add_sales(quantity as u64)?;
+    } else {
+        merchant.remove_sales(quantity as u64)?;
+    }
+
+    if product_info.data_is_empty() {
+        msg!(
+            "Product {} no longer exists, skipping product sales update",
+            product_id
+        );
+        return Ok(None);
+    }
+    require!(
+        product_info.owner == program_id,
+        ErrorCode::InvalidAccountOwner
+    );
+
+    let product_data = product_info.try_borrow_data()?;
+    let mut product = ProductBase::try_deserialize(&mut &product_data[..])?;
+    drop(product_data);
+
+    let old_sales = product.sales;
+    if is_sale {
+        product.update_sales(quantity)?;
+    } else {
+        product.decrease_sales(quantity)?;
+    }
+    let new_sales = product.sales;
+
+    {
+        let mut product_data = product_info.try_borrow_mut_data()?;
+        let mut cursor = std::io::Cursor::new(&mut product_data[..]);
+        product.try_serialize(&mut cursor)?;
+    }
+
+    match (old_sales_node, new_sales_node) {
+        (Some(old_sales_node), Some(new_sales_node))
+            if !old_sales_node.data_is_empty() && !new_sales_node.data_is_empty() =>
+        {
+            crate::instructions::sales_index::settle_product_sales_index(
+                old_sales_node,
+                new_sales_node,
+                product_id,
+                old_sales,
+                new_sales,
+                shard_capacity,
+                payer,
+                system_program,
+                program_id,
+            )?
+        }
+        _ => msg!(
+            "Sales index node missing, product {} sales index not updated",
+            product_id
+        ),
+    }
+
+    msg!(
+        "Product {} sales updated from {} to {}",
+        product_id,
+        old_sales,
+        new_sales
+    );
+
+    Ok(Some(product))
+}
+
+// Apply a settled sales change to the trending score and the leaderboards of the product
+//
+// Remaining accounts may hold keyword trending lists and category or payment token leaderboards
+// of the product, told apart by discriminator.
+#[allow(clippy::too_many_arguments)]
+fn record_rankings<'info>(
+    trending_score: &mut TrendingScore,
+    trending_score_bump: u8,
+    trending_board: &mut TrendingBoard,
+    trending_board_bump: u8,
+    leaderboard: &mut Leaderboard,
+    leaderboard_bump: u8,
+    ranking_accounts: &[AccountInfo<'info>],
+    product: Option<&ProductBase>,
+    product_id: u64,
+    quantity: u32,
+    sold_at: Option<i64>,
+    half_life_seconds: u32,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let (keyword_boards, leaderboards) = split_ranking_accounts(ranking_accounts)?;
+
+    record_trending_sale(
+        trending_score,
+        trending_score_bump,
+        trending_board,
+        trending_board_bump,
+        &keyword_boards,
+        product,
+        product_id,
+        quantity,
+        sold_at,
+        half_life_seconds,
+        program_id,
+    )?;
+
+    update_global_leaderboard(leaderboard, leaderboard_bump, product, product_id)?;
+    update_leaderboards(&leaderboards, product, product_id, program_id)
+}
+
+// Apply a new order (`old_status` None) or a status change to the order statistics of its payment
+// token and of its merchant in that token
+//
+// Statistics accounts are opened by the first order instruction that passes them; orders created
+// before that are not counted in them.
+fn record_order_stats(
+    token_stats: &mut TokenOrderStats,
+    token_stats_bump: u8,
+    merchant_stats: &mut MerchantOrderStats,
+    merchant_stats_bump: u8,
+    order: &Order,
+    old_status: Option<&OrderManagementStatus>,
+    now: i64,
+) {
+    token_stats.open(order.payment_token, now, token_stats_bump);
+    merchant_stats.open(order.merchant, order.payment_token, now, merchant_stats_bump);
+
+    let counters = [
+        token_stats.counters_for(order),
+        merchant_stats.counters_for(order),
+    ];
+    for counters in counters.into_iter().flatten() {
+        match old_status {
+            Some(old_status) => {
+                counters.record_status_change(old_status, &order.status, order.total_amount)
+            }
+            None => counters.record_new_order(&order.status, order.total_amount),
+        }
+    }
+}
+
 pub fn get_order_stats(ctx: Context<GetOrderStats>) -> Result<()> {
     let order_stats = &ctx.accounts.order_stats;
 
@@ -469,7 +1237,7 @@ pub fn get_order_stats(ctx: Context<GetOrderStats>) -> Result<()> {
     Ok(())
 }
 
-pub fn confirm_delivery(ctx: Context<ConfirmDelivery>) -> Result<()> {
+pub fn confirm_delivery(ctx: Context<ConfirmDelivery>, day: u32) -> Result<()> {
     let order = &mut ctx.accounts.order;
     let order_stats = &mut ctx.accounts.order_stats;
     let merchant_info = &mut ctx.accounts.merchant_info;
@@ -514,6 +1282,8 @@ pub fn confirm_delivery(ctx: Context<ConfirmDelivery>) -> Result<()> {
         );
     }
 
+    ctx.accounts.escrow_ledger.check_withdrawals_allowed()?;
+
     // Calculate platform fee
     let total_amount = order.total_amount;
     let platform_fee_rate = ctx.accounts.system_config.platform_fee_rate as u64;
@@ -531,6 +1301,7 @@ pub fn confirm_delivery(ctx: Context<ConfirmDelivery>) -> Result<()> {
     let program_signer = &[&program_signer_seeds[..]];
 
     // 1. Process platform fee through CPI call to external vault program
+    let mut fee_forwarded = false;
     if platform_fee > 0 {
         msg!(
             "Start processing platform fee: {} lamports, calling vault program for distribution",
@@ -612,6 +1383,7 @@ pub fn confirm_delivery(ctx: Context<ConfirmDelivery>) -> Result<()> {
                 program_signer,
             ) {
                 Ok(_) => {
+                    fee_forwarded = true;
                     msg!(
                         "External vault program call successful, platform fee: {} lamports",
                         platform_fee
@@ -647,6 +1419,13 @@ pub fn confirm_delivery(ctx: Context<ConfirmDelivery>) -> Result<()> {
     );
     transfer(merchant_cpi_ctx, merchant_amount)?;
 
+    // The order leaves escrow, a fee the vault did not take stays behind as a liability
+    let escrow_ledger = &mut ctx.accounts.escrow_ledger;
+    escrow_ledger.record_release(total_amount);
+    if !fee_forwarded {
+        escrow_ledger.record_fee(platform_fee)?;
+    }
+
     // Update merchant deposit balance (only add merchant's actual received amount, excluding platform fees)
     merchant_info.add_deposit(merchant_amount)?;
 
@@ -656,12 +1435,63 @@ pub fn confirm_delivery(ctx: Context<ConfirmDelivery>) -> Result<()> {
     // Update to delivered status
     order.update_status(OrderManagementStatus::Delivered, current_time)?;
 
+    let reputation = &mut ctx.accounts.merchant_reputation;
+    reputation.open(order.merchant, current_time, ctx.bumps.merchant_reputation);
+    reputation.record(ReputationEvent::Settled, current_time);
+
     // Update statistics
     order_stats.update_for_status_change(
         &old_status,
         &OrderManagementStatus::Delivered,
         order.total_amount,
     );
+    record_order_stats(
+        &mut ctx.accounts.token_order_stats,
+        ctx.bumps.token_order_stats,
+        &mut ctx.accounts.merchant_order_stats,
+        ctx.bumps.merchant_order_stats,
+        order,
+        Some(&old_status),
+        current_time,
+    );
+    let daily_order_stats = &mut ctx.accounts.daily_order_stats;
+    daily_order_stats.open(
+        order.payment_token,
+        day,
+        current_time,
+        ctx.bumps.daily_order_stats,
+    )?;
+    daily_order_stats.record_delivered(total_amount);
+
+    // Count the sale now that the order is settled
+    let product = record_settled_sales(
+        &ctx.accounts.product,
+        ctx.accounts.old_sales_node.as_deref(),
+        ctx.accounts.new_sales_node.as_deref(),
+        merchant_info,
+        order.product_id,
+        order.quantity,
+        true,
+        ctx.accounts.system_config.index_shard_capacity,
+        &ctx.accounts.buyer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.program_id,
+    )?;
+    record_rankings(
+        &mut ctx.accounts.trending_score,
+        ctx.bumps.trending_score,
+        &mut ctx.accounts.trending_board,
+        ctx.bumps.trending_board,
+        &mut ctx.accounts.leaderboard,
+        ctx.bumps.leaderboard,
+        ctx.remaining_accounts,
+        product.as_ref(),
+        order.product_id,
+        order.quantity,
+        None,
+        system_config.trending_half_life_seconds,
+        ctx.program_id,
+    )?;
 
     // Verify token transfer success
     let deposit_balance_after = ctx.accounts.deposit_escrow_account.amount;
@@ -698,6 +1528,7 @@ pub fn confirm_delivery(ctx: Context<ConfirmDelivery>) -> Result<()> {
 
 /// Auto confirm delivery (called by merchant or administrator)
 #[derive(Accounts)]
+#[instruction(day: u32)]
 pub struct AutoConfirmDelivery<'info> {
     #[account(mut)]
     pub order: Account<'info, Order>,
@@ -709,13 +1540,104 @@ pub struct AutoConfirmDelivery<'info> {
     )]
     pub order_stats: Account<'info, OrderStats>,
 
-    // Merchant account (for permission verification)
+    // Order statistics of the payment token and of the merchant in it
     #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + TokenOrderStats::INIT_SPACE,
+        seeds = [b"token_order_stats", order.payment_token.as_ref()],
+        bump
+    )]
+    pub token_order_stats: Account<'info, TokenOrderStats>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MerchantOrderStats::INIT_SPACE,
+        seeds = [b"merchant_order_stats", order.merchant.as_ref(), order.payment_token.as_ref()],
+        bump
+    )]
+    pub merchant_order_stats: Account<'info, MerchantOrderStats>,
+
+    // Reputation of the merchant
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MerchantReputation::INIT_SPACE,
+        seeds = [b"merchant_reputation", order.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_reputation: Account<'info, MerchantReputation>,
+
+    // Daily order statistics of the payment token, `day` is the current day
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + DailyOrderStats::INIT_SPACE,
+        seeds = [b"daily_order_stats", order.payment_token.as_ref(), day.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_order_stats: Account<'info, DailyOrderStats>,
+
+    // Merchant account (for permission verification and sales statistics)
+    #[account(
+        mut,
         seeds = [b"merchant_info", merchant.owner.as_ref()],
-        bump = merchant.bump
+        bump = merchant.bump,
+        constraint = merchant.owner == order.merchant @ ErrorCode::Unauthorized
     )]
     pub merchant: Account<'info, Merchant>,
 
+    /// CHECK: Product of the order, verified in instruction (may have been hard deleted)
+    #[account(
+        mut,
+        seeds = [b"product", order.product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub product: UncheckedAccount<'info>,
+
+    /// CHECK: Sales index node covering the product's current sales, or the zero-copy sales shard
+    /// of a migrated range, verified in instruction (None when the product is not indexed)
+    #[account(mut)]
+    pub old_sales_node: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Sales index node covering the product's sales after settlement, or the zero-copy
+    /// sales shard of a migrated range, verified in instruction (None when the product is not
+    /// indexed)
+    #[account(mut)]
+    pub new_sales_node: Option<UncheckedAccount<'info>>,
+
+    // Trending score of the order's product
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + TrendingScore::INIT_SPACE,
+        seeds = [b"trending_score", order.product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub trending_score: Account<'info, TrendingScore>,
+
+    // Global trending leaderboard
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + TrendingBoard::INIT_SPACE,
+        seeds = [b"trending_global"],
+        bump
+    )]
+    pub trending_board: Account<'info, TrendingBoard>,
+
+    // Global bestseller leaderboard (keyword trending lists, category and payment token
+    // leaderboards of the product go in remaining accounts)
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Leaderboard::INIT_SPACE,
+        seeds = [b"leaderboard", [LeaderboardScope::Global.kind()].as_ref()],
+        bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+
     // 系统配置账户（获取自动确认天数）
     #[account(
         seeds = [b"system_config"],
@@ -725,12 +1647,15 @@ pub struct AutoConfirmDelivery<'info> {
 
     // 调用者（商户或系统管理员）
     #[account(
+        mut,
         constraint = authority.key() == merchant.owner || authority.key() == system_config.authority @ ErrorCode::Unauthorized
     )]
     pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
-pub fn auto_confirm_delivery(ctx: Context<AutoConfirmDelivery>) -> Result<()> {
+pub fn auto_confirm_delivery(ctx: Context<AutoConfirmDelivery>, day: u32) -> Result<()> {
     let order = &mut ctx.accounts.order;
     let order_stats = &mut ctx.accounts.order_stats;
     let system_config = &ctx.accounts.system_config;
@@ -745,15 +1670,66 @@ pub fn auto_confirm_delivery(ctx: Context<AutoConfirmDelivery>) -> Result<()> {
 
     let old_status = order.status.clone();
 
-    // 执行自动确认
+    // 执行自动确认（不转移资金，订单金额仍计入托管账本）
     order.auto_confirm_delivery(current_time)?;
 
+    let reputation = &mut ctx.accounts.merchant_reputation;
+    reputation.open(order.merchant, current_time, ctx.bumps.merchant_reputation);
+    reputation.record(ReputationEvent::Settled, current_time);
+
     // 更新统计信息
     order_stats.update_for_status_change(
         &old_status,
         &OrderManagementStatus::Delivered,
         order.total_amount,
     );
+    record_order_stats(
+        &mut ctx.accounts.token_order_stats,
+        ctx.bumps.token_order_stats,
+        &mut ctx.accounts.merchant_order_stats,
+        ctx.bumps.merchant_order_stats,
+        order,
+        Some(&old_status),
+        current_time,
+    );
+    let daily_order_stats = &mut ctx.accounts.daily_order_stats;
+    daily_order_stats.open(
+        order.payment_token,
+        day,
+        current_time,
+        ctx.bumps.daily_order_stats,
+    )?;
+    daily_order_stats.record_delivered(order.total_amount);
+
+    // 记录已结算的销量与热度
+    let product = record_settled_sales(
+        &ctx.accounts.product,
+        ctx.accounts.old_sales_node.as_deref(),
+        ctx.accounts.new_sales_node.as_deref(),
+        &mut ctx.accounts.merchant,
+        order.product_id,
+        order.quantity,
+        true,
+        ctx.accounts.system_config.index_shard_capacity,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.program_id,
+    )?;
+    record_rankings(
+        &mut ctx.accounts.trending_score,
+        ctx.bumps.trending_score,
+        &mut ctx.accounts.trending_board,
+        ctx.bumps.trending_board,
+        &mut ctx.accounts.leaderboard,
+        ctx.bumps.leaderboard,
+        ctx.remaining_accounts,
+        product.as_ref(),
+        order.product_id,
+        order.quantity,
+        None,
+        system_config.trending_half_life_seconds,
+        ctx.program_id,
+    )?;
 
     let caller_type = if ctx.accounts.authority.key() == ctx.accounts.system_config.authority {
         "系统管理员"
@@ -774,3 +1750,39 @@ pub fn auto_confirm_delivery(ctx: Context<AutoConfirmDelivery>) -> Result<()> {
 
     Ok(())
 }
+
+// Grow an order created before product variants to the current layout
+#[derive(Accounts)]
+pub struct MigrateOrder<'info> {
+    /// CHECK: legacy Order, owner and layout are verified in the instruction
+    #[account(mut)]
+    pub order: UncheckedAccount<'info>,
+
+    // Pays the rent of the larger account, anyone may migrate
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Rewrite a legacy order with `variant_id` appended as None
+//
+// Order strings are written once, so legacy orders already read `variant_id` as None from their
+// zeroed tail. Only orders whose strings fill the account cannot be read before migrating.
+pub fn migrate_order(ctx: Context<MigrateOrder>) -> Result<()> {
+    let order: Order = migrate_account::<LegacyOrder, _>(
+        &ctx.accounts.order.to_account_info(),
+        LegacyOrder::SPACE,
+        Order::SPACE,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    msg!(
+        "Order migrated to the current layout, buyer: {}, product ID: {}",
+        order.buyer,
+        order.product_id
+    );
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use crate::error::ErrorCode;
+use crate::instructions::product_variant::resolve_variant_price;
 use crate::state::*;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
@@ -127,14 +128,20 @@ pub struct PurchaseProductEscrow<'info> {
     // 产品账户 - 验证产品存在和价格
     #[account(
         seeds = [b"product", product_id.to_le_bytes().as_ref()],
-        bump
+        bump,
+        constraint = ProductBase::has_current_layout(&product.to_account_info()) @ ErrorCode::AccountNotMigrated
     )]
     pub product: Account<'info, ProductBase>,
 
-    // 主程序统一托管代币账户（使用产品指定的支付代币）
+    // 商品规格 - 商品有规格时必填，按规格价格付款
+    #[account(
+        constraint = product_variant.product_id == product_id @ ErrorCode::InvalidProductVariant
+    )]
+    pub product_variant: Option<Account<'info, ProductVariant>>,
+
+    // 主程序统一托管代币账户（使用产品指定的支付代币，由管理员创建）
     #[account(
-        init_if_needed,
-        payer = buyer,
+        mut,
         token::mint = payment_token_mint,
         token::authority = program_authority,
         seeds = [b"program_token_account", payment_token_mint.key().as_ref()],
@@ -143,6 +150,14 @@ pub struct PurchaseProductEscrow<'info> {
     )]
     pub program_token_account: Account<'info, TokenAccount>,
 
+    // 托管对账账本（记录托管金额，由管理员以托管账户余额初始化）
+    #[account(
+        mut,
+        seeds = [b"escrow_ledger", payment_token_mint.key().as_ref()],
+        bump = escrow_ledger.bump
+    )]
+    pub escrow_ledger: Account<'info, EscrowLedger>,
+
     /// CHECK: 程序权限账户，用于控制Token转账
     #[account(
         seeds = [b"program_authority"],
@@ -158,7 +173,6 @@ pub struct PurchaseProductEscrow<'info> {
     pub payment_token_mint: Account<'info, Mint>,
 
     pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
 }
 
 pub fn purchase_product_escrow(
@@ -177,9 +191,9 @@ pub fn purchase_product_escrow(
     // 验证购买数量
     require!(amount > 0, ErrorCode::InvalidAmount);
 
-    // 计算总价格
-    let total_price = product
-        .price
+    // 计算总价格（商品有规格时使用规格价格）
+    let (_, price) = resolve_variant_price(product, ctx.accounts.product_variant.as_deref())?;
+    let total_price = price
         .checked_mul(amount)
         .ok_or(ErrorCode::IntegerOverflow)?;
 
@@ -196,6 +210,9 @@ pub fn purchase_product_escrow(
         total_price,
     )?;
 
+    // 记录托管金额
+    ctx.accounts.escrow_ledger.record_payment(total_price)?;
+
     msg!(
         "购买成功: 买家: {}, 产品ID: {}, 数量: {}, 总价: {} tokens",
         ctx.accounts.buyer.key(),
@@ -246,3 +263,133 @@ pub fn initialize_program_token_account(ctx: Context<InitializeProgramTokenAccou
 
     Ok(())
 }
+
+/// 初始化托管对账账本（系统管理员）
+///
+/// 已有托管资金的代币以当前余额作为期初托管金额
+#[derive(Accounts)]
+pub struct InitializeEscrowLedger<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + EscrowLedger::INIT_SPACE,
+        seeds = [b"escrow_ledger", payment_token_mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_ledger: Account<'info, EscrowLedger>,
+
+    #[account(
+        seeds = [b"program_token_account", payment_token_mint.key().as_ref()],
+        bump
+    )]
+    pub program_token_account: Account<'info, TokenAccount>,
+
+    pub payment_token_mint: Account<'info, Mint>,
+
+    #[account(
+        seeds = [b"system_config"],
+        bump,
+        constraint = system_config.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub system_config: Account<'info, crate::SystemConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn initialize_escrow_ledger(ctx: Context<InitializeEscrowLedger>) -> Result<()> {
+    let opening_balance = ctx.accounts.program_token_account.amount;
+    ctx.accounts.escrow_ledger.initialize(
+        ctx.accounts.payment_token_mint.key(),
+        opening_balance,
+        ctx.bumps.escrow_ledger,
+    );
+
+    msg!(
+        "托管对账账本初始化成功: Token Mint: {}, 期初托管金额: {}",
+        ctx.accounts.payment_token_mint.key(),
+        opening_balance
+    );
+    Ok(())
+}
+
+/// 托管对账（查询）
+#[derive(Accounts)]
+pub struct VerifyEscrowSolvency<'info> {
+    #[account(
+        seeds = [b"escrow_ledger", escrow_ledger.mint.as_ref()],
+        bump = escrow_ledger.bump
+    )]
+    pub escrow_ledger: Account<'info, EscrowLedger>,
+
+    #[account(
+        seeds = [b"program_token_account", escrow_ledger.mint.as_ref()],
+        bump
+    )]
+    pub program_token_account: Account<'info, TokenAccount>,
+}
+
+pub fn verify_escrow_solvency(ctx: Context<VerifyEscrowSolvency>) -> Result<EscrowSolvency> {
+    let solvency = ctx
+        .accounts
+        .escrow_ledger
+        .solvency(ctx.accounts.program_token_account.amount);
+
+    msg!(
+        "托管对账: 余额: {}, 托管金额: {}, 手续费: {}, 盈余: {}, 缺口: {}",
+        solvency.token_balance,
+        solvency.escrowed_amount,
+        solvency.accrued_fees,
+        solvency.surplus,
+        solvency.deficit
+    );
+    Ok(solvency)
+}
+
+/// 按对账结果冻结或恢复托管账户转出（系统管理员）
+#[derive(Accounts)]
+pub struct EnforceEscrowSolvency<'info> {
+    #[account(
+        mut,
+        seeds = [b"escrow_ledger", escrow_ledger.mint.as_ref()],
+        bump = escrow_ledger.bump
+    )]
+    pub escrow_ledger: Account<'info, EscrowLedger>,
+
+    #[account(
+        seeds = [b"program_token_account", escrow_ledger.mint.as_ref()],
+        bump
+    )]
+    pub program_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"system_config"],
+        bump,
+        constraint = system_config.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub system_config: Account<'info, crate::SystemConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+pub fn enforce_escrow_solvency(ctx: Context<EnforceEscrowSolvency>) -> Result<EscrowSolvency> {
+    let escrow_ledger = &mut ctx.accounts.escrow_ledger;
+    let solvency = escrow_ledger.solvency(ctx.accounts.program_token_account.amount);
+    escrow_ledger.withdrawals_blocked = !solvency.is_solvent();
+
+    msg!(
+        "托管转出状态: {}, 缺口: {}",
+        if escrow_ledger.withdrawals_blocked {
+            "已冻结"
+        } else {
+            "正常"
+        },
+        solvency.deficit
+    );
+    Ok(EscrowSolvency {
+        withdrawals_blocked: escrow_ledger.withdrawals_blocked,
+        ..solvency
+    })
+}
@@ -1,6 +1,8 @@
 use crate::error::ErrorCode;
 use crate::state::*;
+use crate::utils::{SortOrder, MAX_PAGE_SIZE};
 use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
 
 /// Calculate the starting value of the price range
 /// Using logarithmic algorithm: given price P, find n such that 2^n ≤ P < 2^(n+1)
@@ -55,54 +57,78 @@ pub struct RemoveProductFromPriceIndex<'info> {
         mut,
         seeds = [
             b"price_index",
+            price_node.payment_token.as_ref(),
             price_node.price_range_start.to_le_bytes().as_ref(),
             price_node.price_range_end.to_le_bytes().as_ref()
         ],
-        bump
+        bump,
+        constraint = !is_frozen_legacy_shard::<PriceIndexNode>(&price_node.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
     )]
     pub price_node: Account<'info, PriceIndexNode>,
     // Remove authority account - completely unused in function implementation, permission verification through PDA seed mechanism
 }
 
 #[derive(Accounts)]
-#[instruction(min_price: u64, max_price: u64)]
+#[instruction(payment_token: Option<Pubkey>, min_price: u64, max_price: u64)]
 pub struct SearchPriceRange<'info> {
     /// CHECK: will verify the correct price index node in the instruction
     #[account()]
     pub price_node: AccountInfo<'info>,
+    // remaining_accounts: further leaf nodes of the range, contiguous in ascending range order
+    // (in any order when searching across payment tokens)
 }
 
 #[derive(Accounts)]
-#[instruction(price_range_start: u64, price_range_end: u64)]
+#[instruction(price_range_start: u64, price_range_end: u64, split_price: u64)]
 pub struct SplitPriceNode<'info> {
     #[account(
         mut,
         seeds = [
             b"price_index",
+            price_node.payment_token.as_ref(),
             price_range_start.to_le_bytes().as_ref(),
             price_range_end.to_le_bytes().as_ref()
         ],
-        bump
+        bump,
+        constraint = !is_frozen_legacy_shard::<PriceIndexNode>(&price_node.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
     )]
     pub price_node: Account<'info, PriceIndexNode>,
 
+    // Lower child covering [price_range_start, split_price - 1]
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PriceIndexNode::INIT_SPACE,
+        seeds = [
+            b"price_index",
+            price_node.payment_token.as_ref(),
+            price_range_start.to_le_bytes().as_ref(),
+            split_price.saturating_sub(1).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub left_node: Account<'info, PriceIndexNode>,
+
+    // Upper child covering [split_price, price_range_end]
     #[account(
         init,
         payer = payer,
         space = 8 + PriceIndexNode::INIT_SPACE,
         seeds = [
             b"price_index",
-            ((price_range_start + price_range_end) / 2 + 1).to_le_bytes().as_ref(),
+            price_node.payment_token.as_ref(),
+            split_price.to_le_bytes().as_ref(),
             price_range_end.to_le_bytes().as_ref()
         ],
         bump
     )]
-    pub new_price_node: Account<'info, PriceIndexNode>,
+    pub right_node: Account<'info, PriceIndexNode>,
 
     #[account(mut)]
     pub payer: Signer<'info>,
 
     pub system_program: Program<'info, System>,
+    // remaining_accounts: ancestors of price_node from its parent up to the bucket root (mut)
 }
 
 pub fn remove_product_from_price_index(
@@ -122,32 +148,135 @@ pub fn remove_product_from_price_index(
     Ok(())
 }
 
-pub fn search_price_range(
-    ctx: Context<SearchPriceRange>,
+/// Search products by price across one or more adjacent leaf nodes
+///
+/// With a payment token, `price_node` is the leaf of that token covering `min_price` and the
+/// following leaves are passed in remaining accounts in ascending range order, each starting
+/// where the previous one ends (the address of a bucket root that was never created stands in
+/// for it); prices are in the token's own units. When the leaves end before `max_price`, the
+/// page reports where the next search has to start. Without a payment token, leaves of any
+/// token may be passed and the range is expressed in NORMALIZED_PRICE_DECIMALS, so products
+/// priced in different tokens can be browsed together.
+pub fn search_price_range<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SearchPriceRange<'info>>,
+    payment_token: Option<Pubkey>,
     min_price: u64,
     max_price: u64,
+    sort_order: SortOrder,
     offset: u32,
     limit: u16,
-) -> Result<Vec<u64>> {
+) -> Result<PriceRangePage> {
     // Verify price range
     require!(min_price <= max_price, ErrorCode::InvalidPriceRange);
 
-    // Deserialize price index node
-    let node_data = ctx.accounts.price_node.data.borrow();
-    let price_node = PriceIndexNode::try_deserialize(&mut &node_data[8..])?;
+    let node_infos = std::iter::once(&ctx.accounts.price_node).chain(ctx.remaining_accounts);
+
+    // Collect matching (comparable price, product id) pairs
+    let mut matched: Vec<(u128, u64)> = Vec::new();
+    let mut visited: Vec<Pubkey> = Vec::new();
+    let mut next_range_start: Option<u64> = None;
+    let mut reached_final_leaf = false;
+    for node_info in node_infos {
+        require!(
+            !visited.contains(node_info.key) && !reached_final_leaf,
+            ErrorCode::InvalidPriceNodeLink
+        );
+        visited.push(node_info.key());
+
+        // A bucket that was never created holds no products, its address stands in for it
+        if let (Some(mint), true) = (payment_token, node_info.data_is_empty()) {
+            // The first bucket is the one holding min_price
+            let start = next_range_start.unwrap_or_else(|| calculate_price_range_start(min_price));
+            let (bucket_start, bucket_end) = find_price_node_for_price(start);
+            require!(bucket_start == start, ErrorCode::InvalidPriceNodeLink);
+            let (bucket_address, _) = Pubkey::find_program_address(
+                &[
+                    b"price_index",
+                    mint.as_ref(),
+                    bucket_start.to_le_bytes().as_ref(),
+                    bucket_end.to_le_bytes().as_ref(),
+                ],
+                ctx.program_id,
+            );
+            require_keys_eq!(
+                node_info.key(),
+                bucket_address,
+                ErrorCode::InvalidPriceNodeLink
+            );
+            next_range_start = Some(following_leaf_start(bucket_start, bucket_end));
+            continue;
+        }
 
-    // Get products within price range
-    let all_products = price_node.get_products_in_range(min_price, max_price);
+        let price_node = load_price_node(node_info, ctx.program_id)?;
+        require!(price_node.is_leaf(), ErrorCode::PriceNodeNotLeaf);
+
+        match payment_token {
+            Some(mint) => {
+                require!(
+                    price_node.payment_token == mint,
+                    ErrorCode::UnsupportedToken
+                );
+
+                // Leaves must follow each other without gaps so no product of the range is
+                // skipped, and in range order so the concatenation stays sorted. The first leaf
+                // must cover min_price, the lowest leaf starts at 0 and covers any range.
+                match next_range_start {
+                    Some(next_start) => require!(
+                        price_node.price_range_start == next_start,
+                        ErrorCode::InvalidPriceNodeLink
+                    ),
+                    None => require!(
+                        price_node.price_range_start <= min_price,
+                        ErrorCode::InvalidPriceNodeLink
+                    ),
+                }
+                reached_final_leaf = price_node.price_range_end == u64::MAX;
+                if !reached_final_leaf {
+                    next_range_start = Some(following_leaf_start(
+                        price_node.price_range_start,
+                        price_node.price_range_end,
+                    ));
+                }
+
+                matched.extend(
+                    price_node
+                        .entries_in_range(min_price, max_price)
+                        .iter()
+                        .map(|entry| (entry.price as u128, entry.product_id)),
+                );
+            }
+            None => {
+                matched.extend(price_node.entries.iter().filter_map(|entry| {
+                    let price = price_node.normalized_price(entry.price);
+                    (price >= min_price as u128 && price <= max_price as u128)
+                        .then_some((price, entry.product_id))
+                }));
+            }
+        }
+    }
 
-    // Pagination processing
-    let start_index = offset as usize;
-    let end_index = (start_index + limit as usize).min(all_products.len());
+    // Leaves of a single token stopping before max_price leave the rest of the range unsearched,
+    // the coverage of leaves across tokens is not tracked
+    let next_range_start =
+        next_range_start.filter(|&start| !reached_final_leaf && start <= max_price);
 
-    let results = if start_index < all_products.len() {
-        all_products[start_index..end_index].to_vec()
-    } else {
-        Vec::new()
-    };
+    // Entries from different tokens are merged by normalized price
+    if payment_token.is_none() {
+        matched.sort_unstable();
+    }
+
+    if sort_order == SortOrder::Descending {
+        matched.reverse();
+    }
+
+    // Pagination processing
+    let limit = limit.min(MAX_PAGE_SIZE) as usize;
+    let results: Vec<u64> = matched
+        .iter()
+        .skip(offset as usize)
+        .take(limit)
+        .map(|&(_, product_id)| product_id)
+        .collect();
 
     msg!(
         "Price range search completed, range: {} - {}, found {} results",
@@ -156,67 +285,183 @@ pub fn search_price_range(
         results.len()
     );
 
-    Ok(results)
+    Ok(PriceRangePage {
+        product_ids: results,
+        total_count: matched.len() as u32,
+        has_more: (offset as usize).saturating_add(limit) < matched.len(),
+        next_range_start,
+    })
+}
+
+/// Price range search page structure
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PriceRangePage {
+    pub product_ids: Vec<u64>, // Product ids of this page, in the requested price order
+    pub total_count: u32,      // Products of the range found in the passed leaves
+    pub has_more: bool,        // Whether a later page of the found products exists
+    // Start of the first leaf left unsearched when the passed leaves end before max_price, the
+    // search continues from there with min_price set to it (None when the whole range was
+    // searched or leaves of several tokens were passed)
+    pub next_range_start: Option<u64>,
+}
+
+// Start of the leaf following the leaf [price_range_start, price_range_end] in price order
+//
+// Split children cover [start, split - 1] and [split, end], while the last leaf of a bucket
+// [2^n, 2^(n+1)] shares its end price with the first leaf of the next bucket.
+fn following_leaf_start(price_range_start: u64, price_range_end: u64) -> u64 {
+    let bucket_end = calculate_price_range_end(price_range_start);
+    if price_range_end == bucket_end && bucket_end > 1 {
+        bucket_end
+    } else {
+        price_range_end.saturating_add(1)
+    }
+}
+
+fn load_price_node(node_info: &AccountInfo, program_id: &Pubkey) -> Result<PriceIndexNode> {
+    require!(
+        node_info.owner == program_id,
+        ErrorCode::InvalidAccountOwner
+    );
+
+    let node_data = node_info.try_borrow_data()?;
+    PriceIndexNode::try_deserialize(&mut &node_data[..])
 }
 
+/// Split a full leaf node on the median stored price
+///
+/// The node keeps its range and becomes an internal node, its entries are moved into two new
+/// children, and the heights of all ancestors passed in remaining accounts are refreshed.
 pub fn split_price_node(
     ctx: Context<SplitPriceNode>,
     price_range_start: u64,
     price_range_end: u64,
+    split_price: u64,
 ) -> Result<()> {
+    let price_node_key = ctx.accounts.price_node.key();
+    let left_node_key = ctx.accounts.left_node.key();
+    let right_node_key = ctx.accounts.right_node.key();
+
     let price_node = &mut ctx.accounts.price_node;
-    let new_price_node = &mut ctx.accounts.new_price_node;
+    let left_node = &mut ctx.accounts.left_node;
+    let right_node = &mut ctx.accounts.right_node;
+
+    require!(price_node.is_leaf(), ErrorCode::PriceNodeNotLeaf);
+    require!(price_node.needs_split(), ErrorCode::PriceNodeCannotSplit);
+
+    // Split point must be the real median of the stored prices
+    let median_price = price_node
+        .median_split_price()
+        .ok_or(ErrorCode::PriceNodeCannotSplit)?;
+    require!(split_price == median_price, ErrorCode::InvalidPriceRange);
+
+    // Initialize children
+    left_node.initialize(
+        price_node.payment_token,
+        price_node.token_decimals,
+        price_range_start,
+        split_price - 1,
+        ctx.bumps.left_node,
+    )?;
+    left_node.parent = Some(price_node_key);
+    right_node.initialize(
+        price_node.payment_token,
+        price_node.token_decimals,
+        split_price,
+        price_range_end,
+        ctx.bumps.right_node,
+    )?;
+    right_node.parent = Some(price_node_key);
+
+    // Move entries by their stored price
+    price_node.split_entries_into(split_price, left_node, right_node)?;
+
+    // Link children and update height
+    price_node.left_child = Some(left_node_key);
+    price_node.right_child = Some(right_node_key);
+    price_node.update_height(left_node.height, right_node.height);
+
+    update_ancestor_heights(
+        price_node_key,
+        price_node.parent,
+        price_node.height,
+        ctx.remaining_accounts,
+        ctx.program_id,
+    )?;
+
+    msg!(
+        "Price index node split successful, range: {} - {}, left: {} - {} ({} products), right: {} - {} ({} products)",
+        price_range_start,
+        price_range_end,
+        price_range_start,
+        split_price - 1,
+        left_node.entries.len(),
+        split_price,
+        price_range_end,
+        right_node.entries.len()
+    );
+
+    Ok(())
+}
+
+// Walk up the parent links and raise ancestor heights after a split
+fn update_ancestor_heights(
+    mut child_key: Pubkey,
+    mut parent_key: Option<Pubkey>,
+    mut child_height: u8,
+    ancestors: &[AccountInfo],
+    program_id: &Pubkey,
+) -> Result<()> {
+    let mut ancestors = ancestors.iter();
 
-    // Calculate split point
-    let split_point = (price_range_start + price_range_end) / 2;
+    while let Some(expected_key) = parent_key {
+        let ancestor_info = ancestors.next().ok_or(ErrorCode::InsufficientAccounts)?;
+        require!(
+            ancestor_info.key() == expected_key,
+            ErrorCode::InvalidPriceNodeLink
+        );
+        require!(
+            ancestor_info.owner == program_id,
+            ErrorCode::InvalidAccountOwner
+        );
+        require!(ancestor_info.is_writable, ErrorCode::InvalidPriceNodeLink);
 
-    // Adjust original node range
-    price_node.price_range_end = split_point;
+        let node_data = ancestor_info.try_borrow_data()?;
+        let mut ancestor = PriceIndexNode::try_deserialize(&mut &node_data[..])?;
+        drop(node_data);
 
-    // Initialize new node
-    new_price_node.initialize(split_point + 1, price_range_end, ctx.bumps.new_price_node)?;
+        require!(
+            ancestor.left_child == Some(child_key) || ancestor.right_child == Some(child_key),
+            ErrorCode::InvalidPriceNodeLink
+        );
 
-    // Reallocate products to corresponding nodes
-    let mut products_to_move = Vec::new();
-    for &product_id in &price_node.product_ids.clone() {
-        // Here we need to get the actual price of the product to determine which node it should be allocated to
-        // Simplified implementation: assume the last few digits of product ID represent price range
-        let estimated_price = (product_id % 1000) + price_range_start;
-        if estimated_price > split_point {
-            products_to_move.push(product_id);
-        }
-    }
+        ancestor.height = ancestor.height.max(child_height.saturating_add(1));
 
-    // Move products to new node
-    for product_id in products_to_move {
-        price_node.product_ids.retain(|&x| x != product_id);
-        new_price_node.product_ids.push(product_id);
-    }
+        let mut node_data = ancestor_info.try_borrow_mut_data()?;
+        let mut cursor = std::io::Cursor::new(&mut node_data[..]);
+        ancestor.try_serialize(&mut cursor)?;
 
-    msg!(
-        "Price index node split successful, original range: {} - {}, new range: {} - {}",
-        price_range_start,
-        split_point,
-        split_point + 1,
-        price_range_end
-    );
+        child_key = expected_key;
+        parent_key = ancestor.parent;
+        child_height = ancestor.height;
+    }
 
     Ok(())
 }
 
-// Find appropriate price index node
+// Find the price index node range for a price
+// Returns the power-of-two bucket, which is the root of the sub-tree for this price.
+// After splits, descend through left_child/right_child to the leaf containing the price.
 pub fn find_price_node_for_price(price: u64) -> (u64, u64) {
-    // Simplified implementation: use fixed price range division
-    // Actual implementation should traverse the price index tree to find appropriate leaf nodes
-    let interval = 1000u64; // Every 1000 units as one range
-    let range_start = (price / interval) * interval;
-    let range_end = range_start + interval - 1;
-    (range_start, range_end)
+    (
+        calculate_price_range_start(price),
+        calculate_price_range_end(price),
+    )
 }
 
 // Get price index node utilization
 pub fn get_price_node_utilization(node: &Account<PriceIndexNode>) -> f32 {
-    node.product_ids.len() as f32 / MAX_PRODUCTS_PER_SHARD as f32
+    node.entries.len() as f32 / MAX_PRICE_NODE_ENTRIES as f32
 }
 
 // Check if price index tree needs rebalancing
@@ -278,25 +523,134 @@ mod tests {
         assert_eq!(calculate_price_range_start(50), 32);
         assert_eq!(calculate_price_range_end(50), 64);
     }
+
+    fn test_node(start: u64, end: u64, prices: &[(u64, u64)]) -> PriceIndexNode {
+        let mut node = PriceIndexNode {
+            payment_token: Pubkey::default(),
+            token_decimals: 0,
+            price_range_start: 0,
+            price_range_end: 0,
+            entries: Vec::new(),
+            left_child: None,
+            right_child: None,
+            parent: None,
+            height: 0,
+            bump: 0,
+        };
+        node.initialize(Pubkey::default(), 6, start, end, 255)
+            .unwrap();
+        for &(product_id, price) in prices {
+            node.add_product(product_id, price).unwrap();
+        }
+        node
+    }
+
+    #[test]
+    fn test_split_on_median_stored_price() {
+        // Product ids deliberately unrelated to prices
+        let mut node = test_node(
+            8,
+            16,
+            &[(1999, 15), (2001, 9), (3005, 12), (4010, 8), (5000, 15)],
+        );
+        assert_eq!(find_price_node_for_price(15), (8, 16));
+
+        let split_price = node.median_split_price().unwrap();
+        assert_eq!(split_price, 12);
+
+        let mut left = test_node(8, split_price - 1, &[]);
+        let mut right = test_node(split_price, 16, &[]);
+        node.split_entries_into(split_price, &mut left, &mut right)
+            .unwrap();
+
+        assert!(node.entries.is_empty());
+        assert_eq!(left.product_ids(), vec![4010, 2001]);
+        assert_eq!(right.product_ids(), vec![3005, 1999, 5000]);
+        assert!(left.entries.iter().all(|e| left.contains_price(e.price)));
+        assert!(right.entries.iter().all(|e| right.contains_price(e.price)));
+
+        // Lower half sharing the median price splits above it
+        let node = test_node(8, 16, &[(1, 10), (2, 10), (3, 10), (4, 14)]);
+        assert_eq!(node.median_split_price(), Some(14));
+
+        // A single distinct price cannot be split
+        let node = test_node(8, 16, &[(1, 10), (2, 10)]);
+        assert_eq!(node.median_split_price(), None);
+    }
+
+    #[test]
+    fn test_exact_price_filter_in_node() {
+        let node = test_node(8, 16, &[(7, 15), (3, 9), (5, 12), (1, 8), (9, 12)]);
+
+        // Entries stay sorted by price regardless of insertion order
+        assert_eq!(node.product_ids(), vec![1, 3, 5, 9, 7]);
+
+        // Overlapping the node range no longer returns every product
+        assert_eq!(node.get_products_in_range(10, 12), vec![5, 9]);
+        assert_eq!(node.get_products_in_range(13, 14), Vec::<u64>::new());
+        assert_eq!(node.get_products_in_range(0, 8), vec![1]);
+        assert_eq!(node.get_products_in_range(16, 100), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn test_following_leaf_start() {
+        // Split children continue right after their sibling
+        assert_eq!(following_leaf_start(8, 11), 12);
+
+        // The last leaf of a bucket hands over to the next bucket at the shared boundary
+        assert_eq!(following_leaf_start(12, 16), 16);
+        assert_eq!(following_leaf_start(8, 16), 16);
+
+        // A leaf ending on a power of two inside its bucket is not the last one
+        assert_eq!(following_leaf_start(16, 16), 17);
+
+        assert_eq!(following_leaf_start(0, 0), 1);
+        assert_eq!(following_leaf_start(1, 1), 2);
+    }
+
+    #[test]
+    fn test_normalized_price_across_decimals() {
+        // 100 units of a 6-decimal token and of a 9-decimal token
+        assert_eq!(normalize_price(100_000_000, 6), 100_000_000_000);
+        assert_eq!(normalize_price(100_000_000_000, 9), 100_000_000_000);
+        assert_eq!(normalize_price(1_000_000_000_000, 12), 1_000_000_000);
+        assert_ne!(normalize_price(100, 6), normalize_price(100, 9));
+    }
 }
 
 /// Smart price index account structure (using Anchor standard methods)
 #[derive(Accounts)]
-#[instruction(product_id: u64, price: u64, price_range_start: u64, price_range_end: u64)]
+#[instruction(product_id: u64, price_range_start: u64, price_range_end: u64)]
 pub struct AddProductToPriceIndex<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
+    // Price and payment token of the entry are read from the product
+    #[account(
+        seeds = [b"product", product_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = ProductBase::has_current_layout(&product.to_account_info()) @ ErrorCode::AccountNotMigrated
+    )]
+    pub product: Account<'info, ProductBase>,
+
+    // Payment token of the product, each mint has its own price index
+    #[account(
+        constraint = payment_mint.key() == product.payment_token @ ErrorCode::UnsupportedToken
+    )]
+    pub payment_mint: Account<'info, Mint>,
+
     #[account(
         init_if_needed,
         payer = payer,
         space = 8 + PriceIndexNode::INIT_SPACE,
         seeds = [
             b"price_index",
+            payment_mint.key().as_ref(),
             price_range_start.to_le_bytes().as_ref(),
             price_range_end.to_le_bytes().as_ref()
         ],
-        bump
+        bump,
+        constraint = !is_frozen_legacy_shard::<PriceIndexNode>(&price_index.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
     )]
     pub price_index: Account<'info, PriceIndexNode>,
 
@@ -304,69 +658,205 @@ pub struct AddProductToPriceIndex<'info> {
 }
 
 /// Smart add product to price index
+///
+/// The entry is keyed by the product's current price, in its payment token.
 pub fn add_product_to_price_index(
     ctx: Context<AddProductToPriceIndex>,
     product_id: u64,
-    price: u64,
     price_range_start: u64,
     price_range_end: u64,
 ) -> Result<()> {
+    let price = ctx.accounts.product.price;
     let price_index = &mut ctx.accounts.price_index;
 
-    // Verify that the passed price range is consistent with the range calculated based on price
-    let expected_start = calculate_price_range_start(price);
-    let expected_end = calculate_price_range_end(price);
-
-    require!(
-        price_range_start == expected_start && price_range_end == expected_end,
-        ErrorCode::InvalidPriceRange
-    );
+    // If it's a newly created account, it must be the power-of-two bucket root for this price
+    if price_index.height == 0 {
+        let (expected_start, expected_end) = find_price_node_for_price(price);
+        require!(
+            price_range_start == expected_start && price_range_end == expected_end,
+            ErrorCode::InvalidPriceRange
+        );
 
-    // If it's a newly created account, initialize first
-    if price_index.price_range_start == 0 && price_index.price_range_end == 0 {
-        price_index.price_range_start = price_range_start;
-        price_index.price_range_end = price_range_end;
-        price_index.product_ids = Vec::new();
-        price_index.left_child = None;
-        price_index.right_child = None;
-        price_index.parent = None;
-        price_index.height = 0;
-        price_index.bump = ctx.bumps.price_index;
+        price_index.initialize(
+            ctx.accounts.payment_mint.key(),
+            ctx.accounts.payment_mint.decimals,
+            price_range_start,
+            price_range_end,
+            ctx.bumps.price_index,
+        )?;
 
         msg!(
-            "✅ New price index automatically created: price {} → range [{}, {}]",
+            "✅ New price index automatically created: price {} → range [{}, {}], payment token: {}",
             price,
             price_range_start,
-            price_range_end
+            price_range_end,
+            ctx.accounts.payment_mint.key()
         );
     }
 
+    // Products are only stored in leaf nodes, split nodes must be descended through their children
+    require!(price_index.is_leaf(), ErrorCode::PriceNodeNotLeaf);
+
     // Verify price is within range
     require!(
-        price >= price_index.price_range_start && price <= price_index.price_range_end,
+        price_index.contains_price(price),
         ErrorCode::InvalidPriceRange
     );
 
     // Check if product already exists
-    if price_index.product_ids.contains(&product_id) {
+    if price_index.contains_product(product_id) {
         msg!("Product {} already exists in price index, skipping addition", product_id);
         return Ok(());
     }
 
-    // Check index capacity
-    if price_index.product_ids.len() >= 1000 {
-        return Err(ErrorCode::ShardIsFull.into());
-    }
-
-    // Add product ID
-    price_index.product_ids.push(product_id);
+    // Add product entry (fails when the node is full and needs to be split)
+    price_index.add_product(product_id, price)?;
 
     msg!(
         "✅ Product {} added to price index [{}, {}], current product count: {}",
         product_id,
         price_range_start,
         price_range_end,
-        price_index.product_ids.len()
+        price_index.entries.len()
+    );
+
+    Ok(())
+}
+
+/// Move products of a legacy price node into the price index of their payment mint
+#[derive(Accounts)]
+#[instruction(
+    legacy_range_start: u64,
+    legacy_range_end: u64,
+    price_range_start: u64,
+    price_range_end: u64
+)]
+pub struct MigratePriceNode<'info> {
+    /// CHECK: legacy node, owner and layout are verified in the instruction
+    #[account(
+        mut,
+        seeds = [
+            b"price_index",
+            legacy_range_start.to_le_bytes().as_ref(),
+            legacy_range_end.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub legacy_node: UncheckedAccount<'info>,
+
+    pub payment_mint: Account<'info, Mint>,
+
+    // Leaf receiving the products, a new node must be the bucket root of its range
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PriceIndexNode::INIT_SPACE,
+        seeds = [
+            b"price_index",
+            payment_mint.key().as_ref(),
+            price_range_start.to_le_bytes().as_ref(),
+            price_range_end.to_le_bytes().as_ref()
+        ],
+        bump,
+        constraint = !is_frozen_legacy_shard::<PriceIndexNode>(&price_node.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
+    )]
+    pub price_node: Account<'info, PriceIndexNode>,
+
+    // Pays the rent of a new node, anyone may migrate
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts: ProductBase accounts (current layout) of legacy entries priced in
+    // `payment_mint` within the range of `price_node`
+}
+
+/// Move legacy price index entries into the node of their payment mint
+///
+/// Legacy nodes were shared by all payment tokens and stored no prices, so each moved product is
+/// passed in remaining accounts and its entry is keyed by the product's price and mint. Moved
+/// products are removed from the legacy node; products of other mints or ranges stay until
+/// migrated with their own node.
+pub fn migrate_price_node<'info>(
+    ctx: Context<'_, '_, 'info, 'info, MigratePriceNode<'info>>,
+    legacy_range_start: u64,
+    legacy_range_end: u64,
+    price_range_start: u64,
+    price_range_end: u64,
+) -> Result<()> {
+    let legacy_info = ctx.accounts.legacy_node.to_account_info();
+    require!(
+        legacy_info.owner == ctx.program_id,
+        ErrorCode::InvalidAccountOwner
+    );
+    let mut legacy = {
+        let data = legacy_info.try_borrow_data()?;
+        require!(
+            data.len() == LegacyPriceIndexNode::SPACE
+                && data[..8] == *PriceIndexNode::DISCRIMINATOR,
+            ErrorCode::InvalidLegacyAccount
+        );
+        LegacyPriceIndexNode::deserialize(&mut &data[8..])
+            .map_err(|_| ErrorCode::InvalidLegacyAccount)?
+    };
+
+    let payment_mint = &ctx.accounts.payment_mint;
+    let price_node = &mut ctx.accounts.price_node;
+    if price_node.height == 0 {
+        require!(
+            find_price_node_for_price(price_range_start) == (price_range_start, price_range_end),
+            ErrorCode::InvalidPriceRange
+        );
+        price_node.initialize(
+            payment_mint.key(),
+            payment_mint.decimals,
+            price_range_start,
+            price_range_end,
+            ctx.bumps.price_node,
+        )?;
+    }
+    require!(price_node.is_leaf(), ErrorCode::PriceNodeNotLeaf);
+
+    for product_info in ctx.remaining_accounts {
+        require!(
+            product_info.owner == ctx.program_id,
+            ErrorCode::InvalidAccountOwner
+        );
+        require!(
+            ProductBase::has_current_layout(product_info),
+            ErrorCode::AccountNotMigrated
+        );
+        let product = {
+            let data = product_info.try_borrow_data()?;
+            ProductBase::try_deserialize(&mut &data[..])?
+        };
+        require_keys_eq!(
+            product.payment_token,
+            payment_mint.key(),
+            ErrorCode::UnsupportedToken
+        );
+
+        let index = legacy
+            .product_ids
+            .iter()
+            .position(|&product_id| product_id == product.id)
+            .ok_or(ErrorCode::ProductNotFound)?;
+        price_node.add_product(product.id, product.price)?;
+        legacy.product_ids.remove(index);
+    }
+
+    let mut data = legacy_info.try_borrow_mut_data()?;
+    legacy.serialize(&mut &mut data[8..])?;
+
+    msg!(
+        "Migrated {} products from legacy price node [{}, {}] to [{}, {}], payment token: {}, {} left",
+        ctx.remaining_accounts.len(),
+        legacy_range_start,
+        legacy_range_end,
+        price_range_start,
+        price_range_end,
+        payment_mint.key(),
+        legacy.product_ids.len()
     );
 
     Ok(())
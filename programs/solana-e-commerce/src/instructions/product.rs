@@ -1,5 +1,9 @@
 use crate::error::ErrorCode;
+use crate::instructions::id_generator::{release_id_in_chunk, should_preallocate_chunk};
+use crate::instructions::merchant_product_list::{append_to_catalog, remove_from_catalog};
+use crate::instructions::recency_index::record_in_global_feed;
 use crate::state::*;
+use crate::utils::{close_program_account, migrate_account, verify_pda};
 use anchor_lang::prelude::*;
 
 #[derive(Accounts)]
@@ -20,28 +24,107 @@ pub struct DeleteProduct<'info> {
         mut,
         seeds = [b"product", product_id.to_le_bytes().as_ref()],
         bump,
-        constraint = force || product.merchant == merchant.key() @ ErrorCode::Unauthorized,
-        close = beneficiary
+        constraint = force || product.merchant == merchant.key() @ ErrorCode::Unauthorized
     )]
     pub product: Account<'info, ProductBase>,
 
     #[account(mut)]
     pub beneficiary: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = merchant,
+        space = 8 + MerchantProductList::INIT_SPACE,
+        seeds = [b"merchant_product_list", product.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_product_list: Account<'info, MerchantProductList>,
+
+    // Catalog page currently holding the product, only None for products created before the catalog
+    #[account(
+        mut,
+        constraint = current_product_page.merchant == product.merchant @ ErrorCode::InvalidProductCatalogPage,
+        constraint = current_product_page.is_active == product.is_active @ ErrorCode::InvalidProductCatalogPage
+    )]
+    pub current_product_page: Option<Account<'info, MerchantProductPage>>,
+
+    // Next append page of the inactive chain (used by soft delete)
+    #[account(
+        init_if_needed,
+        payer = merchant,
+        space = 8 + MerchantProductPage::INIT_SPACE,
+        seeds = [
+            b"merchant_inactive_products",
+            product.merchant.as_ref(),
+            merchant_product_list.next_page_index(false).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub inactive_product_page: Account<'info, MerchantProductPage>,
+
+    // ID chunk holding the product ID, hard delete releases the ID for reuse
+    #[account(
+        mut,
+        seeds = [
+            b"id_chunk",
+            product.merchant.as_ref(),
+            &IdChunk::chunk_index_seed(id_chunk.chunk_index)
+        ],
+        bump = id_chunk.bump,
+        constraint = id_chunk.contains_id(product_id) @ ErrorCode::InvalidId
+    )]
+    pub id_chunk: Option<Account<'info, IdChunk>>,
+
+    // Accounts keyed by the product ID, hard delete closes the ones that exist
+    /// CHECK: PDA of the product ID, closed on hard delete when it exists
+    #[account(
+        mut,
+        seeds = [b"product_extended", product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub product_extended: UncheckedAccount<'info>,
+
+    /// CHECK: PDA of the product ID, closed on hard delete when it exists
+    #[account(
+        mut,
+        seeds = [b"product_rating", product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub product_rating: UncheckedAccount<'info>,
+
+    /// CHECK: PDA of the product ID, closed on hard delete when it exists
+    #[account(
+        mut,
+        seeds = [b"trending_score", product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub trending_score: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts (hard delete): the product's variant accounts, variant IDs 0..variant_count
 }
 
 #[derive(Accounts)]
 #[instruction(product_id: u64, sales_increment: u32)]
 pub struct UpdateSales<'info> {
-    /// CHECK: This can be any signer as sales updates might come from various sources
+    // Sales are counted by order settlement, manual updates are reserved for data migration
     #[account(mut)]
     pub authority: Signer<'info>,
 
+    #[account(
+        seeds = [b"system_config"],
+        bump,
+        constraint = system_config.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub system_config: Account<'info, crate::SystemConfig>,
+
     #[account(
         mut,
         seeds = [b"product", product_id.to_le_bytes().as_ref()],
         bump
     )]
     pub product: Account<'info, ProductBase>,
+    // remaining_accounts: leaderboards including the product (global, category, payment token)
 }
 
 #[derive(Accounts)]
@@ -69,7 +152,8 @@ pub struct UpdateProductPrice<'info> {
     keywords: Vec<String>,
     inventory: u64,
     payment_token: Pubkey,
-    shipping_location: String
+    shipping_location: String,
+    day: u32
 )]
 pub struct CreateProductBase<'info> {
     #[account(mut)]
@@ -103,6 +187,19 @@ pub struct CreateProductBase<'info> {
     )]
     pub active_chunk: Account<'info, IdChunk>,
 
+    /// CHECK: chunk following the active one, preallocated once the active chunk crosses
+    /// CHUNK_PREALLOCATE_THRESHOLD and switched to when the active chunk is full
+    #[account(
+        mut,
+        seeds = [
+            b"id_chunk",
+            merchant.key().as_ref(),
+            (merchant_id_account.last_chunk_index + 1).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub next_chunk: UncheckedAccount<'info>,
+
     #[account(
         seeds = [b"payment_config"],
         bump
@@ -113,6 +210,48 @@ pub struct CreateProductBase<'info> {
     #[account(mut)]
     pub product_account: UncheckedAccount<'info>,
 
+    #[account(
+        init_if_needed,
+        payer = merchant,
+        space = 8 + MerchantProductList::INIT_SPACE,
+        seeds = [b"merchant_product_list", merchant.key().as_ref()],
+        bump
+    )]
+    pub merchant_product_list: Account<'info, MerchantProductList>,
+
+    // Next append page of the active chain
+    #[account(
+        init_if_needed,
+        payer = merchant,
+        space = 8 + MerchantProductPage::INIT_SPACE,
+        seeds = [
+            b"merchant_active_products",
+            merchant.key().as_ref(),
+            merchant_product_list.next_page_index(true).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub active_product_page: Account<'info, MerchantProductPage>,
+
+    // Global NewArrivals feed (empty keyword seed), `day` is the current day
+    #[account(
+        init_if_needed,
+        payer = merchant,
+        space = 8 + RecencyFeedHead::INIT_SPACE,
+        seeds = [b"recency_head", &[RecencyFeed::NewArrivals.seed()][..]],
+        bump
+    )]
+    pub new_arrivals_head: Account<'info, RecencyFeedHead>,
+
+    #[account(
+        init_if_needed,
+        payer = merchant,
+        space = 8 + RecencyBucket::INIT_SPACE,
+        seeds = [b"recency_bucket", &[RecencyFeed::NewArrivals.seed()][..], day.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub new_arrivals_bucket: Account<'info, RecencyBucket>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -157,6 +296,7 @@ pub fn create_product_base(
     inventory: u64,
     payment_token: Pubkey,
     shipping_location: String,
+    day: u32,
 ) -> Result<u64> {
     // Validate input parameters - limit to 3 keywords during creation (considering instruction account size)
     require!(
@@ -173,12 +313,39 @@ pub fn create_product_base(
             .is_token_supported(&payment_token),
         ErrorCode::UnsupportedToken
     );
+    let now = Clock::get()?.unix_timestamp;
+    require!(recency_day(now) == day, ErrorCode::InvalidRecencyBucket);
+
+    // 1. Generate product ID, switching to the preallocated chunk when the active one is full
+    let product_id =
+        if ctx.accounts.active_chunk.is_full() && !ctx.accounts.next_chunk.data_is_empty() {
+            switch_to_next_chunk(
+                &mut ctx.accounts.merchant_id_account,
+                &ctx.accounts.next_chunk,
+                ctx.program_id,
+            )?
+        } else {
+            let product_id = generate_next_product_id(
+                &mut ctx.accounts.merchant_id_account,
+                &mut ctx.accounts.active_chunk,
+            )?;
 
-    // 1. Generate product ID
-    let product_id = generate_next_product_id(
-        &mut ctx.accounts.merchant_id_account,
-        &mut ctx.accounts.active_chunk,
-    )?;
+            if should_preallocate_chunk(&ctx.accounts.active_chunk)
+                && ctx.accounts.next_chunk.data_is_empty()
+            {
+                preallocate_next_chunk(
+                    &ctx.accounts.merchant,
+                    &ctx.accounts.merchant_id_account,
+                    &mut ctx.accounts.global_root,
+                    &ctx.accounts.next_chunk,
+                    ctx.bumps.next_chunk,
+                    &ctx.accounts.system_program,
+                    ctx.program_id,
+                )?;
+            }
+
+            product_id
+        };
 
     // 2. Create product account
     create_product_account(
@@ -201,9 +368,10 @@ pub fn create_product_base(
         payment_token,
         sales: 0,
         is_active: true,
-        created_at: Clock::get()?.unix_timestamp,
-        updated_at: Clock::get()?.unix_timestamp,
+        created_at: now,
+        updated_at: now,
         shipping_location,
+        variant_count: 0,
         bump: 0, // Will be set later
     };
 
@@ -216,6 +384,28 @@ pub fn create_product_base(
     // 5. Update merchant product count
     ctx.accounts.merchant_info.increment_product_count()?;
 
+    // 6. Add product to the merchant catalog
+    append_to_catalog(
+        &mut ctx.accounts.merchant_product_list,
+        ctx.bumps.merchant_product_list,
+        &mut ctx.accounts.active_product_page,
+        ctx.bumps.active_product_page,
+        ctx.accounts.merchant.key(),
+        true,
+        product_id,
+    )?;
+
+    // 7. Record the product in the global NewArrivals feed
+    record_in_global_feed(
+        &mut ctx.accounts.new_arrivals_head,
+        ctx.bumps.new_arrivals_head,
+        &mut ctx.accounts.new_arrivals_bucket,
+        ctx.bumps.new_arrivals_bucket,
+        RecencyFeed::NewArrivals,
+        product_id,
+        now,
+    )?;
+
     msg!(
         "Atomic product creation successful, ID: {}, Name: {}, Keyword count: {}",
         product_id,
@@ -256,7 +446,10 @@ pub fn create_product_extended(
         .product_extended
         .set_inner(product_extended_data);
 
-    msg!("ProductExtended created successfully, Product ID: {}", product_id);
+    msg!(
+        "ProductExtended created successfully, Product ID: {}",
+        product_id
+    );
 
     Ok(())
 }
@@ -339,36 +532,125 @@ fn generate_next_product_id(
     merchant_account: &mut Account<MerchantIdAccount>,
     active_chunk: &mut Account<IdChunk>,
 ) -> Result<u64> {
-    // Check if current chunk has available IDs
-    if active_chunk.is_full() {
-        return Err(ErrorCode::NoAvailableId.into());
-    }
+    // Allocate the lowest available ID (released IDs are reused first)
+    let product_id = active_chunk
+        .allocate_next()
+        .ok_or(ErrorCode::NoAvailableId)?;
+    let local_id = product_id - active_chunk.start_id;
+    merchant_account.last_local_id = local_id;
 
-    // Find next available ID
-    let mut local_id = active_chunk.next_available;
-    while local_id < active_chunk.capacity() {
-        if !active_chunk.is_id_used(local_id) {
-            // Allocate this ID
-            active_chunk.mark_id_used(local_id);
-            active_chunk.next_available = local_id + 1;
-            merchant_account.last_local_id = local_id;
-
-            // Use activeChunk.startId + localId to calculate product ID
-            let product_id = active_chunk.start_id + local_id;
-
-            msg!(
-                "Generated product ID: startId {} + local ID {} = {}",
-                active_chunk.start_id,
-                local_id,
-                product_id
-            );
-
-            return Ok(product_id);
-        }
-        local_id += 1;
+    msg!(
+        "Generated product ID: startId {} + local ID {} = {}",
+        active_chunk.start_id,
+        local_id,
+        product_id
+    );
+
+    Ok(product_id)
+}
+
+// Helper function: create the chunk following the active one before the active chunk runs out
+fn preallocate_next_chunk<'info>(
+    merchant: &Signer<'info>,
+    merchant_account: &Account<'info, MerchantIdAccount>,
+    global_root: &mut Account<'info, GlobalIdRoot>,
+    next_chunk: &AccountInfo<'info>,
+    next_chunk_bump: u8,
+    system_program: &Program<'info, System>,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let chunk_index = merchant_account.last_chunk_index + 1;
+    if chunk_index > MAX_CHUNKS_PER_MERCHANT {
+        return Ok(());
     }
 
-    Err(ErrorCode::NoAvailableId.into())
+    let space = 8 + IdChunk::INIT_SPACE;
+    let lamports = Rent::get()?.minimum_balance(space);
+    let merchant_key = merchant.key();
+    let chunk_index_bytes = chunk_index.to_le_bytes();
+
+    anchor_lang::system_program::create_account(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            anchor_lang::system_program::CreateAccount {
+                from: merchant.to_account_info(),
+                to: next_chunk.to_account_info(),
+            },
+            &[&[
+                b"id_chunk",
+                merchant_key.as_ref(),
+                chunk_index_bytes.as_ref(),
+                &[next_chunk_bump],
+            ]],
+        ),
+        lamports,
+        space as u64,
+        program_id,
+    )?;
+
+    let mut chunk = IdChunk {
+        merchant_id: 0,
+        chunk_index: 0,
+        start_id: 0,
+        end_id: 0,
+        next_available: 0,
+        bitmap: Vec::new(),
+        bump: 0,
+    };
+    chunk.initialize(
+        merchant_account.merchant_id,
+        chunk_index,
+        global_root.reserve_chunk_range(),
+        global_root.chunk_size,
+        next_chunk_bump,
+    );
+
+    let mut data = next_chunk.try_borrow_mut_data()?;
+    let mut cursor = std::io::Cursor::new(&mut data[..]);
+    chunk.try_serialize(&mut cursor)?;
+
+    msg!(
+        "ID chunk preallocated, merchant: {}, chunk index: {}, ID range: {} - {}",
+        merchant_account.merchant_id,
+        chunk_index,
+        chunk.start_id,
+        chunk.end_id
+    );
+
+    Ok(())
+}
+
+// Helper function: make the preallocated chunk active and generate the product ID from it
+fn switch_to_next_chunk<'info>(
+    merchant_account: &mut Account<'info, MerchantIdAccount>,
+    next_chunk: &AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> Result<u64> {
+    require!(
+        next_chunk.owner == program_id,
+        ErrorCode::InvalidAccountOwner
+    );
+    let chunk_data = next_chunk.try_borrow_data()?;
+    let mut chunk = IdChunk::try_deserialize(&mut &chunk_data[..])?;
+    drop(chunk_data);
+
+    merchant_account.last_chunk_index += 1;
+    merchant_account.activate_chunk(next_chunk.key())?;
+
+    let product_id = chunk.allocate_next().ok_or(ErrorCode::NoAvailableId)?;
+    merchant_account.last_local_id = product_id - chunk.start_id;
+
+    let mut chunk_data = next_chunk.try_borrow_mut_data()?;
+    let mut cursor = std::io::Cursor::new(&mut chunk_data[..]);
+    chunk.try_serialize(&mut cursor)?;
+
+    msg!(
+        "Switched to ID chunk {}, generated product ID: {}",
+        chunk.chunk_index,
+        product_id
+    );
+
+    Ok(product_id)
 }
 
 #[event]
@@ -385,8 +667,13 @@ pub struct ProductEvent {
     pub event_type: String, // "created", "updated", "deleted", "sold"
 }
 
-pub fn delete_product(
-    ctx: Context<DeleteProduct>,
+/// Deactivate a product, or close it and release its ID when `hard_delete` is set
+///
+/// A released ID is handed out to the next product, so hard delete also closes the accounts
+/// keyed by the product ID (extended info, rating, trending score and variants). Index entries
+/// must be removed through the index instructions before a hard delete.
+pub fn delete_product<'info>(
+    ctx: Context<'_, '_, 'info, 'info, DeleteProduct<'info>>,
     _product_id: u64,
     hard_delete: bool,
     force: bool,
@@ -407,12 +694,61 @@ pub fn delete_product(
     let keywords = product.parse_keywords();
     let price = product.price;
     let sales = product.sales;
+    let product_merchant = product.merchant;
+    let was_active = product.is_active;
+
+    // Update merchant catalog (soft deleting an inactive product leaves it in place)
+    if hard_delete || was_active {
+        let catalog = &mut ctx.accounts.merchant_product_list;
+        // Products created after the catalog are always tracked by it
+        let untracked = if catalog.merchant == Pubkey::default() {
+            catalog.initialize(product_merchant, ctx.bumps.merchant_product_list)?;
+            true
+        } else {
+            product.created_at < catalog.created_at
+        };
+
+        match ctx.accounts.current_product_page.as_mut() {
+            Some(page) => remove_from_catalog(catalog, page, product_id)?,
+            None => {
+                require!(untracked, ErrorCode::InvalidProductCatalogPage);
+                msg!(
+                    "Product {} is not tracked in the merchant catalog",
+                    product_id
+                );
+            }
+        }
+
+        if !hard_delete {
+            append_to_catalog(
+                catalog,
+                ctx.bumps.merchant_product_list,
+                &mut ctx.accounts.inactive_product_page,
+                ctx.bumps.inactive_product_page,
+                product_merchant,
+                false,
+                product_id,
+            )?;
+        }
+    }
 
     if hard_delete {
-        // Hard delete: account will be automatically closed and rent reclaimed to beneficiary through close constraint
+        close_product_accounts(&ctx, product_id)?;
+
+        // Hard delete: close the account and reclaim the rent to beneficiary
+        ctx.accounts
+            .product
+            .close(ctx.accounts.beneficiary.to_account_info())?;
+
         // Update merchant product count
         ctx.accounts.merchant_info.decrement_product_count()?;
 
+        // Release the product ID so the merchant's next product can reuse it
+        if let Some(id_chunk) = ctx.accounts.id_chunk.as_mut() {
+            release_id_in_chunk(id_chunk, product_id)?;
+            msg!("Product ID {} released", product_id);
+        }
+
         msg!(
             "Product hard deleted, ID: {}, force delete: {}, rent reclaimed to beneficiary",
             product_id,
@@ -441,6 +777,52 @@ pub fn delete_product(
     Ok(())
 }
 
+// Close the accounts keyed by a hard deleted product, rent goes to the beneficiary
+fn close_product_accounts<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, DeleteProduct<'info>>,
+    product_id: u64,
+) -> Result<()> {
+    let beneficiary = ctx.accounts.beneficiary.to_account_info();
+    for account in [
+        &ctx.accounts.product_extended,
+        &ctx.accounts.product_rating,
+        &ctx.accounts.trending_score,
+    ] {
+        if !account.data_is_empty() {
+            close_program_account(account, &beneficiary)?;
+        }
+    }
+
+    // Every variant has to be closed, legacy products have none
+    let product = &ctx.accounts.product;
+    let variant_count = if ProductBase::has_current_layout(&product.to_account_info()) {
+        product.variant_count
+    } else {
+        0
+    };
+    require!(
+        ctx.remaining_accounts.len() == variant_count as usize,
+        ErrorCode::InvalidProductVariant
+    );
+    for (variant_id, variant) in (0u32..).zip(ctx.remaining_accounts) {
+        verify_pda(
+            variant.key,
+            &[
+                b"product_variant",
+                product_id.to_le_bytes().as_ref(),
+                variant_id.to_le_bytes().as_ref(),
+            ],
+            ctx.program_id,
+        )?;
+        close_program_account(variant, &beneficiary)?;
+    }
+
+    Ok(())
+}
+
+/// Administrator migration: adjust the sales counter of a product
+///
+/// Regular sales are recorded when an order is delivered and reverted on post-delivery refunds.
 pub fn update_sales_count(
     ctx: Context<UpdateSales>,
     _product_id: u64,
@@ -450,6 +832,13 @@ pub fn update_sales_count(
 
     product.update_sales(sales_increment)?;
 
+    crate::instructions::leaderboard::update_leaderboards(
+        ctx.remaining_accounts,
+        Some(product),
+        product.id,
+        ctx.program_id,
+    )?;
+
     msg!(
         "Product sales update successful, ID: {}, increment: {}",
         product.id,
@@ -491,7 +880,20 @@ pub fn update_product_price(
 
 // Update product information
 #[derive(Accounts)]
-#[instruction(product_id: u64)]
+#[instruction(
+    product_id: u64,
+    name: Option<String>,
+    description: Option<String>,
+    price: Option<u64>,
+    keywords: Option<Vec<String>>,
+    inventory: Option<u64>,
+    payment_token: Option<Pubkey>,
+    image_video_urls: Option<Vec<String>>,
+    shipping_location: Option<String>,
+    sales_regions: Option<Vec<String>>,
+    logistics_methods: Option<Vec<String>>,
+    day: u32
+)]
 pub struct UpdateProduct<'info> {
     #[account(mut)]
     pub merchant: Signer<'info>,
@@ -517,6 +919,25 @@ pub struct UpdateProduct<'info> {
     )]
     pub payment_config: Account<'info, PaymentConfig>,
 
+    // Global RecentlyUpdated feed (empty keyword seed), `day` is the current day
+    #[account(
+        init_if_needed,
+        payer = merchant,
+        space = 8 + RecencyFeedHead::INIT_SPACE,
+        seeds = [b"recency_head", &[RecencyFeed::RecentlyUpdated.seed()][..]],
+        bump
+    )]
+    pub recently_updated_head: Account<'info, RecencyFeedHead>,
+
+    #[account(
+        init_if_needed,
+        payer = merchant,
+        space = 8 + RecencyBucket::INIT_SPACE,
+        seeds = [b"recency_bucket", &[RecencyFeed::RecentlyUpdated.seed()][..], day.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub recently_updated_bucket: Account<'info, RecencyBucket>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -533,7 +954,10 @@ pub fn update_product(
     shipping_location: Option<String>,
     sales_regions: Option<Vec<String>>,
     logistics_methods: Option<Vec<String>>,
+    day: u32,
 ) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    require!(recency_day(now) == day, ErrorCode::InvalidRecencyBucket);
     let product = &mut ctx.accounts.product;
 
     // Update name
@@ -611,7 +1035,9 @@ pub fn update_product(
                 msg!("Attempted to update image video URLs, but ProductExtended account does not exist");
             }
             if sales_regions.is_some() {
-                msg!("Attempted to update sales regions, but ProductExtended account does not exist");
+                msg!(
+                    "Attempted to update sales regions, but ProductExtended account does not exist"
+                );
             }
             if logistics_methods.is_some() {
                 msg!("Attempted to update logistics methods, but ProductExtended account does not exist");
@@ -620,9 +1046,62 @@ pub fn update_product(
     }
 
     // Update timestamp
-    product.updated_at = Clock::get()?.unix_timestamp;
+    product.updated_at = now;
+
+    msg!(
+        "Product information updated successfully, ID: {}",
+        product.id
+    );
+
+    // Record the update in the global RecentlyUpdated feed
+    let product_id = product.id;
+    record_in_global_feed(
+        &mut ctx.accounts.recently_updated_head,
+        ctx.bumps.recently_updated_head,
+        &mut ctx.accounts.recently_updated_bucket,
+        ctx.bumps.recently_updated_bucket,
+        RecencyFeed::RecentlyUpdated,
+        product_id,
+        now,
+    )?;
+
+    Ok(())
+}
+
+/// Grow a product created before product variants to the current layout
+#[derive(Accounts)]
+#[instruction(product_id: u64)]
+pub struct MigrateProductBase<'info> {
+    /// CHECK: legacy ProductBase, owner and layout are verified in the instruction
+    #[account(
+        mut,
+        seeds = [b"product", product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub product: UncheckedAccount<'info>,
+
+    // Pays the rent of the larger account, anyone may migrate
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Rewrite a legacy product with `variant_count` appended as 0
+///
+/// Legacy products may carry stale bytes where `variant_count` now lives, so orders and new
+/// variants require the current layout.
+pub fn migrate_product_base(ctx: Context<MigrateProductBase>, product_id: u64) -> Result<()> {
+    let product: ProductBase = migrate_account::<LegacyProductBase, _>(
+        &ctx.accounts.product.to_account_info(),
+        LegacyProductBase::SPACE,
+        ProductBase::SPACE,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    require!(product.id == product_id, ErrorCode::InvalidProduct);
 
-    msg!("Product information updated successfully, ID: {}", product.id);
+    msg!("Product {} migrated to the current layout", product_id);
 
     Ok(())
 }
@@ -0,0 +1,190 @@
+use crate::error::ErrorCode;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+/// Add a variant to a product (product owner)
+///
+/// Variant IDs are assigned in order from 0; the variant account is derived from the product ID
+/// and the product's current variant count.
+#[derive(Accounts)]
+#[instruction(product_id: u64)]
+pub struct CreateProductVariant<'info> {
+    #[account(mut)]
+    pub merchant: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"product", product_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = product.merchant == merchant.key() @ ErrorCode::Unauthorized,
+        constraint = ProductBase::has_current_layout(&product.to_account_info()) @ ErrorCode::AccountNotMigrated
+    )]
+    pub product: Account<'info, ProductBase>,
+
+    #[account(
+        init,
+        payer = merchant,
+        space = 8 + ProductVariant::INIT_SPACE,
+        seeds = [
+            b"product_variant",
+            product_id.to_le_bytes().as_ref(),
+            product.variant_count.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub product_variant: Account<'info, ProductVariant>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create a variant with its own options, SKU, price override and inventory
+///
+/// Once a product has variants every order must name one. Search keeps indexing the parent
+/// product only.
+pub fn create_product_variant(
+    ctx: Context<CreateProductVariant>,
+    product_id: u64,
+    options: Vec<VariantOption>,
+    sku: String,
+    price: Option<u64>,
+    inventory: u64,
+) -> Result<()> {
+    let product = &mut ctx.accounts.product;
+    let variant_id = product.variant_count;
+
+    ctx.accounts.product_variant.initialize(
+        product_id,
+        variant_id,
+        options,
+        sku,
+        price,
+        inventory,
+        ctx.bumps.product_variant,
+    )?;
+
+    product.variant_count = product
+        .variant_count
+        .checked_add(1)
+        .ok_or(ErrorCode::IntegerOverflow)?;
+    product.updated_at = Clock::get()?.unix_timestamp;
+
+    msg!(
+        "Product variant created: Product ID: {}, Variant ID: {}, Price: {}",
+        product_id,
+        variant_id,
+        ctx.accounts.product_variant.effective_price(product)
+    );
+
+    Ok(())
+}
+
+/// Update a variant of a product (product owner)
+#[derive(Accounts)]
+#[instruction(product_id: u64, variant_id: u32)]
+pub struct UpdateProductVariant<'info> {
+    pub merchant: Signer<'info>,
+
+    #[account(
+        seeds = [b"product", product_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = product.merchant == merchant.key() @ ErrorCode::Unauthorized
+    )]
+    pub product: Account<'info, ProductBase>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"product_variant",
+            product_id.to_le_bytes().as_ref(),
+            variant_id.to_le_bytes().as_ref()
+        ],
+        bump = product_variant.bump
+    )]
+    pub product_variant: Account<'info, ProductVariant>,
+}
+
+/// Update the SKU, inventory or availability of a variant, `None` leaves a field unchanged
+///
+/// Deactivating a variant stops new orders for it; existing orders are unaffected.
+pub fn update_product_variant(
+    ctx: Context<UpdateProductVariant>,
+    _product_id: u64,
+    _variant_id: u32,
+    sku: Option<String>,
+    inventory: Option<u64>,
+    is_active: Option<bool>,
+) -> Result<()> {
+    let variant = &mut ctx.accounts.product_variant;
+
+    if let Some(sku) = sku {
+        validate_sku(&sku)?;
+        variant.sku = sku;
+    }
+    if let Some(inventory) = inventory {
+        variant.inventory = inventory;
+    }
+    if let Some(is_active) = is_active {
+        variant.is_active = is_active;
+    }
+    variant.updated_at = Clock::get()?.unix_timestamp;
+
+    msg!(
+        "Product variant updated: Product ID: {}, Variant ID: {}, Inventory: {}, Active: {}",
+        variant.product_id,
+        variant.variant_id,
+        variant.inventory,
+        variant.is_active
+    );
+
+    Ok(())
+}
+
+/// Set or clear the price override of a variant, `None` falls back to the product price
+pub fn update_product_variant_price(
+    ctx: Context<UpdateProductVariant>,
+    _product_id: u64,
+    _variant_id: u32,
+    price: Option<u64>,
+) -> Result<()> {
+    require!(price != Some(0), ErrorCode::InvalidPrice);
+
+    let variant = &mut ctx.accounts.product_variant;
+    variant.price = price;
+    variant.updated_at = Clock::get()?.unix_timestamp;
+
+    msg!(
+        "Product variant price updated: Product ID: {}, Variant ID: {}, Price: {}",
+        variant.product_id,
+        variant.variant_id,
+        variant.effective_price(&ctx.accounts.product)
+    );
+
+    Ok(())
+}
+
+/// Variant an order or purchase of `product` is for, and the unit price it pays
+///
+/// A product with variants must be bought as one of them; a product without variants is bought
+/// at its own price.
+pub fn resolve_variant_price(
+    product: &ProductBase,
+    variant: Option<&ProductVariant>,
+) -> Result<(Option<u32>, u64)> {
+    match variant {
+        Some(variant) => {
+            require!(
+                variant.product_id == product.id,
+                ErrorCode::InvalidProductVariant
+            );
+            require!(variant.is_active, ErrorCode::ProductVariantInactive);
+            Ok((Some(variant.variant_id), variant.effective_price(product)))
+        }
+        None => {
+            require!(
+                product.variant_count == 0,
+                ErrorCode::ProductVariantRequired
+            );
+            Ok((None, product.price))
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use crate::error::ErrorCode;
+use crate::state::*;
+use crate::utils::MAX_PAGE_SIZE;
+use anchor_lang::prelude::*;
+
+/// Record a product in the daily bucket of a recency feed
+#[derive(Accounts)]
+#[instruction(product_id: u64, feed: RecencyFeed, keyword: String, day: u32)]
+pub struct RecordRecentProduct<'info> {
+    #[account(
+        seeds = [b"product", product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub product: Account<'info, ProductBase>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + RecencyFeedHead::INIT_SPACE,
+        seeds = [b"recency_head", &[feed.seed()][..], keyword.as_bytes()],
+        bump
+    )]
+    pub feed_head: Account<'info, RecencyFeedHead>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + RecencyBucket::INIT_SPACE,
+        seeds = [b"recency_bucket", &[feed.seed()][..], keyword.as_bytes(), day.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub bucket: Account<'info, RecencyBucket>,
+
+    // Bucket following `day` in the previous_day chain, required when opening a bucket older than
+    // the newest one
+    #[account(mut)]
+    pub newer_bucket: Option<Account<'info, RecencyBucket>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Record a product in a recency feed
+///
+/// NewArrivals records the product at `created_at`, RecentlyUpdated at `updated_at`; `day` must be
+/// the bucket of that timestamp. An empty keyword records in the global feed, otherwise the
+/// product must carry the keyword. A bucket older than the newest one of the feed is inserted
+/// before `newer_bucket` so the backwards chain stays ordered.
+pub fn record_recent_product(
+    ctx: Context<RecordRecentProduct>,
+    product_id: u64,
+    feed: RecencyFeed,
+    keyword: String,
+    day: u32,
+) -> Result<()> {
+    let product = &ctx.accounts.product;
+    let feed_head = &mut ctx.accounts.feed_head;
+    let bucket = &mut ctx.accounts.bucket;
+
+    if !keyword.is_empty() {
+        require!(
+            product.parse_keywords().contains(&keyword),
+            ErrorCode::InvalidKeyword
+        );
+    }
+
+    let timestamp = match feed {
+        RecencyFeed::NewArrivals => product.created_at,
+        RecencyFeed::RecentlyUpdated => product.updated_at,
+    };
+    require!(
+        recency_day(timestamp) == day,
+        ErrorCode::InvalidRecencyBucket
+    );
+
+    let opened = open_recency_bucket(
+        feed_head,
+        ctx.bumps.feed_head,
+        bucket,
+        ctx.bumps.bucket,
+        feed,
+        &keyword,
+        day,
+    )?;
+    let newer_bucket = ctx.accounts.newer_bucket.as_deref_mut();
+    if feed_head.record(bucket, opened, newer_bucket, product_id, timestamp)? {
+        msg!(
+            "产品 {} 已记录到时间索引 {:?}, 关键词: '{}', 日期: {}",
+            product_id,
+            feed,
+            keyword,
+            day
+        );
+    }
+
+    Ok(())
+}
+
+/// Initialize a feed head and a bucket that were just created, returns whether the bucket is new
+pub fn open_recency_bucket(
+    feed_head: &mut RecencyFeedHead,
+    feed_head_bump: u8,
+    bucket: &mut RecencyBucket,
+    bucket_bump: u8,
+    feed: RecencyFeed,
+    keyword: &str,
+    day: u32,
+) -> Result<bool> {
+    if feed_head.latest_day.is_none() {
+        feed_head.initialize(feed, keyword.to_string(), feed_head_bump)?;
+    }
+
+    // A bucket that was just created has not recorded its day yet
+    let opened = bucket.day != day;
+    if opened {
+        bucket.initialize(feed, keyword.to_string(), day, bucket_bump);
+    }
+    Ok(opened)
+}
+
+/// Record a product in a global feed as part of the product lifecycle
+///
+/// `timestamp` is the current time, so the bucket is never older than the newest one. A full
+/// bucket skips the product instead of failing the product instruction.
+pub fn record_in_global_feed(
+    feed_head: &mut RecencyFeedHead,
+    feed_head_bump: u8,
+    bucket: &mut RecencyBucket,
+    bucket_bump: u8,
+    feed: RecencyFeed,
+    product_id: u64,
+    timestamp: i64,
+) -> Result<()> {
+    let day = recency_day(timestamp);
+    let opened = open_recency_bucket(
+        feed_head,
+        feed_head_bump,
+        bucket,
+        bucket_bump,
+        feed,
+        "",
+        day,
+    )?;
+    if bucket.is_full() && !bucket.contains_product(product_id) {
+        msg!(
+            "时间桶已满, 产品 {} 未记录到时间索引 {:?}, 日期: {}",
+            product_id,
+            feed,
+            day
+        );
+        return Ok(());
+    }
+    feed_head.record(bucket, opened, None, product_id, timestamp)?;
+
+    Ok(())
+}
+
+/// Query the newest products of a recency feed
+#[derive(Accounts)]
+#[instruction(feed: RecencyFeed, keyword: String)]
+pub struct GetRecentProducts<'info> {
+    #[account(
+        seeds = [b"recency_head", &[feed.seed()][..], keyword.as_bytes()],
+        bump = feed_head.bump
+    )]
+    pub feed_head: Account<'info, RecencyFeedHead>,
+    // remaining_accounts: RecencyBucket accounts starting at the cursor day (or the newest day),
+    // following the previous_day links
+}
+
+/// Page backwards through a recency feed, newest first
+///
+/// Products recorded in the RecentlyUpdated feed more than once are only deduplicated within a
+/// page. Pass `next_cursor` of the previous page to continue.
+pub fn get_recent_products<'info>(
+    ctx: Context<'_, '_, 'info, 'info, GetRecentProducts>,
+    feed: RecencyFeed,
+    keyword: String,
+    cursor: Option<RecencyCursor>,
+    limit: u16,
+) -> Result<RecentProductsPage> {
+    let feed_head = &ctx.accounts.feed_head;
+    let limit = limit.min(MAX_PAGE_SIZE) as usize;
+
+    let mut buckets = Vec::with_capacity(ctx.remaining_accounts.len());
+    for bucket_info in ctx.remaining_accounts {
+        require!(
+            bucket_info.owner == ctx.program_id,
+            ErrorCode::InvalidAccountOwner
+        );
+        let data = bucket_info.try_borrow_data()?;
+        let bucket = RecencyBucket::try_deserialize(&mut &data[..])?;
+        require!(
+            bucket.feed == feed && bucket.keyword == keyword,
+            ErrorCode::InvalidRecencyBucket
+        );
+        buckets.push(bucket);
+    }
+
+    let start = match cursor {
+        Some(cursor) => Some(cursor),
+        None => feed_head.latest_day.map(RecencyCursor::start_of),
+    };
+    let (product_ids, next_cursor) = match start {
+        Some(start) => collect_recent_products(&buckets, start, limit)?,
+        None => (Vec::new(), None),
+    };
+
+    Ok(RecentProductsPage {
+        product_ids,
+        next_cursor,
+        total_entries: feed_head.total_entries,
+    })
+}
+
+/// Position in a recency feed, the next page starts strictly before (timestamp, product_id) of `day`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RecencyCursor {
+    pub day: u32,
+    pub timestamp: i64,
+    pub product_id: u64,
+}
+
+impl RecencyCursor {
+    pub fn start_of(day: u32) -> Self {
+        Self {
+            day,
+            timestamp: i64::MAX,
+            product_id: u64::MAX,
+        }
+    }
+}
+
+/// Recency feed page structure
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RecentProductsPage {
+    pub product_ids: Vec<u64>,              // Product ids, newest first
+    pub next_cursor: Option<RecencyCursor>, // Cursor of the next page, None at the end of the feed
+    pub total_entries: u64,                 // Entries recorded in the feed
+}
+
+// Walk the buckets backwards from `start`, buckets must follow the previous_day links
+pub fn collect_recent_products(
+    buckets: &[RecencyBucket],
+    start: RecencyCursor,
+    limit: usize,
+) -> Result<(Vec<u64>, Option<RecencyCursor>)> {
+    let mut product_ids: Vec<u64> = Vec::new();
+    let mut expected_day = Some(start.day);
+
+    for bucket in buckets {
+        let Some(day) = expected_day else {
+            break;
+        };
+        require!(bucket.day == day, ErrorCode::InvalidRecencyBucket);
+
+        let mut position = if day == start.day {
+            start
+        } else {
+            RecencyCursor::start_of(day)
+        };
+        for entry in bucket.entries.iter().rev() {
+            if (entry.timestamp, entry.product_id) >= (position.timestamp, position.product_id) {
+                continue;
+            }
+            if product_ids.len() >= limit {
+                return Ok((product_ids, Some(position)));
+            }
+            if !product_ids.contains(&entry.product_id) {
+                product_ids.push(entry.product_id);
+            }
+            position.timestamp = entry.timestamp;
+            position.product_id = entry.product_id;
+        }
+
+        expected_day = bucket.previous_day;
+    }
+
+    Ok((product_ids, expected_day.map(RecencyCursor::start_of)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucket(day: u32, previous_day: Option<u32>, entries: &[(i64, u64)]) -> RecencyBucket {
+        let mut bucket = RecencyBucket {
+            feed: RecencyFeed::NewArrivals,
+            keyword: String::new(),
+            day,
+            previous_day,
+            entries: Vec::new(),
+            bump: 255,
+        };
+        for &(timestamp, product_id) in entries {
+            bucket.add_entry(product_id, timestamp).unwrap();
+        }
+        bucket
+    }
+
+    #[test]
+    fn test_recent_products_page_backwards() {
+        let buckets = vec![
+            bucket(12, Some(10), &[(12 * 86_400 + 5, 7), (12 * 86_400 + 1, 6)]),
+            bucket(10, None, &[(10 * 86_400, 3), (10 * 86_400 + 9, 4)]),
+        ];
+
+        let (first, cursor) =
+            collect_recent_products(&buckets, RecencyCursor::start_of(12), 3).unwrap();
+        assert_eq!(first, vec![7, 6, 4]);
+        let cursor = cursor.unwrap();
+        assert_eq!((cursor.day, cursor.product_id), (10, 4));
+
+        // The next page resumes in the cursor bucket
+        let (second, cursor) = collect_recent_products(&buckets[1..], cursor, 3).unwrap();
+        assert_eq!(second, vec![3]);
+        assert!(cursor.is_none());
+
+        // Buckets must follow the previous_day chain
+        assert!(collect_recent_products(&buckets[1..], RecencyCursor::start_of(12), 3).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::error::ErrorCode;
+use crate::state::*;
+use crate::SystemConfig;
+use anchor_lang::prelude::*;
+
+/// Record a dispute the merchant of an order lost (system administrator)
+#[derive(Accounts)]
+pub struct RecordLostDispute<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"system_config"],
+        bump,
+        constraint = system_config.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub system_config: Account<'info, SystemConfig>,
+
+    // Order the dispute was about
+    pub order: Account<'info, Order>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MerchantReputation::INIT_SPACE,
+        seeds = [b"merchant_reputation", order.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_reputation: Account<'info, MerchantReputation>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Record a dispute ruled against the merchant of an order
+///
+/// Disputes are settled off-chain by the administrator, who records the outcome here.
+pub fn record_lost_dispute(ctx: Context<RecordLostDispute>, reason: String) -> Result<()> {
+    require!(!reason.is_empty(), ErrorCode::InvalidDisputeReason);
+
+    let order = &ctx.accounts.order;
+    let now = Clock::get()?.unix_timestamp;
+    let reputation = &mut ctx.accounts.merchant_reputation;
+    reputation.open(order.merchant, now, ctx.bumps.merchant_reputation);
+    reputation.record(ReputationEvent::LostDispute, now);
+
+    msg!(
+        "Lost dispute recorded: Merchant: {}, Order: {}, Reason: {}",
+        order.merchant,
+        order.key(),
+        reason
+    );
+
+    Ok(())
+}
+
+/// Query the reputation of a merchant
+#[derive(Accounts)]
+pub struct GetMerchantReputation<'info> {
+    /// CHECK: merchant reputation, does not exist before the merchant's first recorded event; read
+    /// when it does
+    #[account(
+        seeds = [b"merchant_reputation", owner.key().as_ref()],
+        bump
+    )]
+    pub merchant_reputation: UncheckedAccount<'info>,
+
+    /// CHECK: merchant owner, only used to derive the reputation address
+    pub owner: UncheckedAccount<'info>,
+}
+
+/// Reputation of a merchant decayed to the current time, neutral before any recorded event
+pub fn get_merchant_reputation(ctx: Context<GetMerchantReputation>) -> Result<ReputationScore> {
+    let reputation_info = &ctx.accounts.merchant_reputation;
+    let reputation = if reputation_info.owner == ctx.program_id && !reputation_info.data_is_empty()
+    {
+        let data = reputation_info.try_borrow_data()?;
+        MerchantReputation::try_deserialize(&mut &data[..])?
+    } else {
+        MerchantReputation {
+            merchant: ctx.accounts.owner.key(),
+            ..Default::default()
+        }
+    };
+
+    Ok(reputation.score(Clock::get()?.unix_timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF_LIFE: i64 = REPUTATION_HALF_LIFE_SECONDS as i64;
+
+    fn reputation(events: &[(ReputationEvent, u32)], now: i64) -> MerchantReputation {
+        let mut reputation = MerchantReputation::default();
+        reputation.open(Pubkey::new_unique(), now, 255);
+        for &(event, count) in events {
+            for _ in 0..count {
+                reputation.record(event, now);
+            }
+        }
+        reputation
+    }
+
+    #[test]
+    fn test_reputation_score() {
+        // No history is neutral
+        let neutral = reputation(&[], 1).score(1);
+        assert_eq!(neutral.fulfilment_score, 500);
+        assert_eq!(neutral.review_score, 500);
+        assert_eq!(neutral.score, 500);
+
+        // 45 settled orders: (45 + 2.5) / (45 + 5); ten 5 star reviews: (10 + 2.5) / (10 + 5)
+        let good = reputation(
+            &[
+                (ReputationEvent::Settled, 45),
+                (ReputationEvent::Reviewed(5), 10),
+            ],
+            1,
+        )
+        .score(1);
+        assert_eq!(good.fulfilment_score, 950);
+        assert_eq!(good.review_score, 833);
+        assert_eq!(good.score, (950 * 7 + 833 * 3) / 10);
+
+        // One lost dispute weighs as much as five refunds
+        let disputed = reputation(
+            &[
+                (ReputationEvent::Settled, 45),
+                (ReputationEvent::LostDispute, 1),
+            ],
+            1,
+        )
+        .score(1);
+        let refunded = reputation(
+            &[
+                (ReputationEvent::Settled, 45),
+                (ReputationEvent::Refunded, 5),
+            ],
+            1,
+        )
+        .score(1);
+        assert_eq!(disputed.fulfilment_score, 863);
+        assert_eq!(disputed.fulfilment_score, refunded.fulfilment_score);
+
+        // 1 star reviews score nothing, 3 stars are neutral
+        let poor = reputation(&[(ReputationEvent::Reviewed(1), 5)], 1).score(1);
+        assert_eq!(poor.review_score, 250);
+        let average = reputation(&[(ReputationEvent::Reviewed(3), 5)], 1).score(1);
+        assert_eq!(average.review_score, 500);
+    }
+
+    #[test]
+    fn test_reputation_decays_towards_neutral() {
+        let slashed = reputation(
+            &[
+                (ReputationEvent::Settled, 5),
+                (ReputationEvent::DepositSlashed, 2),
+            ],
+            1,
+        );
+        let fresh = slashed.score(1);
+        assert_eq!(fresh.deposit_slashes, 2 * REPUTATION_EVENT_WEIGHT);
+        assert_eq!(fresh.fulfilment_score, 375);
+
+        // Decay scales all events alike, the prior takes over
+        let later = slashed.score(1 + HALF_LIFE);
+        assert_eq!(later.deposit_slashes, REPUTATION_EVENT_WEIGHT);
+        assert!(later.fulfilment_score > fresh.fulfilment_score);
+        let much_later = slashed.score(1 + 40 * HALF_LIFE);
+        assert_eq!(much_later.fulfilment_score, 500);
+
+        // An old incident weighs less than a recent one
+        let mut recovering = slashed.clone();
+        recovering.record(ReputationEvent::Settled, 1 + HALF_LIFE);
+        let mut relapsing = reputation(&[(ReputationEvent::Settled, 6)], 1);
+        relapsing.record(ReputationEvent::DepositSlashed, 1 + HALF_LIFE);
+        relapsing.record(ReputationEvent::DepositSlashed, 1 + HALF_LIFE);
+        let now = 1 + HALF_LIFE;
+        assert!(recovering.score(now).score > relapsing.score(now).score);
+    }
+}
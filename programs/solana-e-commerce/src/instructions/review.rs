@@ -0,0 +1,130 @@
+use crate::error::ErrorCode;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+/// Review a delivered order as its buyer
+#[derive(Accounts)]
+pub struct CreateReview<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        constraint = order.buyer == buyer.key() @ ErrorCode::Unauthorized,
+        constraint = order.status == OrderManagementStatus::Delivered @ ErrorCode::OrderNotDelivered
+    )]
+    pub order: Account<'info, Order>,
+
+    // One review per order
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + Review::INIT_SPACE,
+        seeds = [b"review", order.key().as_ref()],
+        bump
+    )]
+    pub review: Account<'info, Review>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + ProductRating::INIT_SPACE,
+        seeds = [b"product_rating", order.product_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub product_rating: Account<'info, ProductRating>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + MerchantRating::INIT_SPACE,
+        seeds = [b"merchant_rating", order.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_rating: Account<'info, MerchantRating>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + MerchantReputation::INIT_SPACE,
+        seeds = [b"merchant_reputation", order.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_reputation: Account<'info, MerchantReputation>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Review a delivered order and add its rating to the product and merchant ratings and to the
+/// merchant reputation
+///
+/// The review holds a short text, the hash of content stored off-chain, or both. A delivered
+/// order refunded afterwards keeps its review.
+pub fn create_review(
+    ctx: Context<CreateReview>,
+    rating: u8,
+    content_hash: Option<[u8; 32]>,
+    text: String,
+) -> Result<()> {
+    let order = &ctx.accounts.order;
+    let review = &mut ctx.accounts.review;
+    review.initialize(
+        order.key(),
+        order,
+        rating,
+        content_hash,
+        text,
+        ctx.bumps.review,
+    )?;
+    ctx.accounts
+        .product_rating
+        .record(order.product_id, rating, ctx.bumps.product_rating);
+    ctx.accounts
+        .merchant_rating
+        .record(order.merchant, rating, ctx.bumps.merchant_rating);
+    let reputation = &mut ctx.accounts.merchant_reputation;
+    reputation.open(
+        order.merchant,
+        review.created_at,
+        ctx.bumps.merchant_reputation,
+    );
+    reputation.record(ReputationEvent::Reviewed(rating), review.created_at);
+
+    emit!(ReviewCreated {
+        review: review.key(),
+        order: order.key(),
+        product_id: order.product_id,
+        merchant: order.merchant,
+        rating,
+        timestamp: review.created_at,
+    });
+
+    Ok(())
+}
+
+/// Reply to a review as the merchant of the order
+#[derive(Accounts)]
+pub struct ReplyToReview<'info> {
+    pub merchant: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"review", review.order.as_ref()],
+        bump = review.bump,
+        constraint = review.merchant == merchant.key() @ ErrorCode::Unauthorized
+    )]
+    pub review: Account<'info, Review>,
+}
+
+pub fn reply_to_review(ctx: Context<ReplyToReview>, reply: String) -> Result<()> {
+    ctx.accounts.review.set_reply(reply)
+}
+
+#[event]
+pub struct ReviewCreated {
+    pub review: Pubkey,
+    pub order: Pubkey,
+    pub product_id: u64,
+    pub merchant: Pubkey,
+    pub rating: u8,
+    pub timestamp: i64,
+}
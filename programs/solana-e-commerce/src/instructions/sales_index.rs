@@ -1,4 +1,7 @@
 use crate::error::ErrorCode;
+use crate::instructions::index_shard::{
+    add_to_sales_shard, index_shard_header, remove_from_sales_shard,
+};
 use crate::state::*;
 use anchor_lang::prelude::*;
 
@@ -12,7 +15,16 @@ pub struct UpdateProductSalesIndex<'info> {
     /// CHECK: Node for new sales range, will be verified in instruction
     #[account(mut)]
     pub new_sales_node: AccountInfo<'info>,
-    // Remove authority account - completely unused in function implementation, permission verification through PDA seed mechanism
+
+    // Sales are maintained by order settlement, manual moves are reserved for the administrator
+    #[account(
+        seeds = [b"system_config"],
+        bump,
+        constraint = system_config.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub system_config: Account<'info, crate::SystemConfig>,
+
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
@@ -25,7 +37,8 @@ pub struct RemoveProductFromSalesIndex<'info> {
             sales_node.sales_range_start.to_le_bytes().as_ref(),
             sales_node.sales_range_end.to_le_bytes().as_ref()
         ],
-        bump
+        bump,
+        constraint = !is_frozen_legacy_shard::<SalesIndexNode>(&sales_node.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
     )]
     pub sales_node: Account<'info, SalesIndexNode>,
     // Remove authority account - completely unused in function implementation, permission verification through PDA seed mechanism
@@ -46,23 +59,61 @@ pub struct GetTopSellingProducts<'info> {
     pub sales_root: AccountInfo<'info>,
 }
 
+/// Administrator migration: move a product between sales index nodes
 pub fn update_product_sales_index(
     ctx: Context<UpdateProductSalesIndex>,
     product_id: u64,
     old_sales: u32,
     new_sales: u32,
 ) -> Result<()> {
-    // If sales range hasn't changed, only need to update within the same node
-    let old_range = find_sales_node_for_sales(old_sales);
-    let new_range = find_sales_node_for_sales(new_sales);
+    move_product_sales_index(
+        &ctx.accounts.old_sales_node,
+        &ctx.accounts.new_sales_node,
+        product_id,
+        old_sales,
+        new_sales,
+        ctx.program_id,
+    )?;
+
+    msg!(
+        "Product ID {} sales index update successful, updated from {} to {}",
+        product_id,
+        old_sales,
+        new_sales
+    );
+
+    Ok(())
+}
+
+/// Move a product to the sales index node matching its new sales count
+///
+/// `old_sales_node` must cover `old_sales` and `new_sales_node` must cover `new_sales`, both may
+/// be the same account. A product missing from the old node is simply added to the new one.
+pub fn move_product_sales_index(
+    old_sales_node: &AccountInfo,
+    new_sales_node: &AccountInfo,
+    product_id: u64,
+    old_sales: u32,
+    new_sales: u32,
+    program_id: &Pubkey,
+) -> Result<()> {
+    require!(
+        old_sales_node.owner == program_id && new_sales_node.owner == program_id,
+        ErrorCode::InvalidAccountOwner
+    );
+    require!(
+        !is_frozen_legacy_shard::<SalesIndexNode>(old_sales_node)
+            && !is_frozen_legacy_shard::<SalesIndexNode>(new_sales_node),
+        ErrorCode::AccountAlreadyMigrated
+    );
 
-    if old_range == new_range {
+    if old_sales_node.key() == new_sales_node.key() {
         // Update within the same node
-        let node_data = ctx.accounts.old_sales_node.try_borrow_data()?;
+        let node_data = old_sales_node.try_borrow_data()?;
         let mut sales_node = SalesIndexNode::try_deserialize(&mut &node_data[..])?;
         drop(node_data);
 
-        let mut node_data = ctx.accounts.old_sales_node.try_borrow_mut_data()?;
+        let mut node_data = old_sales_node.try_borrow_mut_data()?;
 
         // Verify this is the correct sales node
         require!(
@@ -70,7 +121,7 @@ pub fn update_product_sales_index(
             ErrorCode::InvalidSalesRange
         );
 
-        sales_node.update_product_sales(product_id, new_sales)?;
+        sales_node.add_product(product_id, new_sales)?;
         sales_node.update_top_items(product_id, new_sales)?;
 
         // Re-serialize
@@ -78,56 +129,113 @@ pub fn update_product_sales_index(
         sales_node.try_serialize(&mut cursor)?;
     } else {
         // Need to move product between different nodes
-        // Remove from old node
-        {
-            let old_node_data = ctx.accounts.old_sales_node.try_borrow_data()?;
-            let mut old_sales_node = SalesIndexNode::try_deserialize(&mut &old_node_data[..])?;
-            drop(old_node_data);
-
-            let mut old_node_data = ctx.accounts.old_sales_node.try_borrow_mut_data()?;
-
-            // Verify this is the correct old sales node
-            require!(
-                old_sales_node.contains_sales(old_sales),
-                ErrorCode::InvalidSalesRange
-            );
-
-            old_sales_node.remove_product(product_id)?;
-
-            // Re-serialize old node
-            let mut cursor = std::io::Cursor::new(&mut old_node_data[..]);
-            old_sales_node.try_serialize(&mut cursor)?;
-        }
-
-        // Add to new node
-        {
-            let new_node_data = ctx.accounts.new_sales_node.try_borrow_data()?;
-            let mut new_sales_node = SalesIndexNode::try_deserialize(&mut &new_node_data[..])?;
-            drop(new_node_data);
-
-            let mut new_node_data = ctx.accounts.new_sales_node.try_borrow_mut_data()?;
-
-            // Verify this is the correct new sales node
-            require!(
-                new_sales_node.contains_sales(new_sales),
-                ErrorCode::InvalidSalesRange
-            );
-
-            new_sales_node.add_product(product_id, new_sales)?;
-            new_sales_node.update_top_items(product_id, new_sales)?;
-
-            // Re-serialize new node
-            let mut cursor = std::io::Cursor::new(&mut new_node_data[..]);
-            new_sales_node.try_serialize(&mut cursor)?;
-        }
+        remove_from_sales_node(old_sales_node, product_id, old_sales)?;
+        add_to_sales_node(new_sales_node, product_id, new_sales)?;
     }
 
-    msg!(
-        "Product ID {} sales index update successful, updated from {} to {}",
-        product_id,
-        old_sales,
-        new_sales
+    Ok(())
+}
+
+// Remove a product from the legacy sales node covering `sales`
+fn remove_from_sales_node(sales_node: &AccountInfo, product_id: u64, sales: u32) -> Result<()> {
+    let node_data = sales_node.try_borrow_data()?;
+    let mut node = SalesIndexNode::try_deserialize(&mut &node_data[..])?;
+    drop(node_data);
+
+    let mut node_data = sales_node.try_borrow_mut_data()?;
+
+    // Verify this is the correct sales node
+    require!(node.contains_sales(sales), ErrorCode::InvalidSalesRange);
+
+    node.remove_product(product_id)?;
+
+    // Re-serialize
+    let mut cursor = std::io::Cursor::new(&mut node_data[..]);
+    node.try_serialize(&mut cursor)?;
+    Ok(())
+}
+
+// Add a product with its sales to the legacy sales node covering them
+fn add_to_sales_node(sales_node: &AccountInfo, product_id: u64, sales: u32) -> Result<()> {
+    let node_data = sales_node.try_borrow_data()?;
+    let mut node = SalesIndexNode::try_deserialize(&mut &node_data[..])?;
+    drop(node_data);
+
+    let mut node_data = sales_node.try_borrow_mut_data()?;
+
+    // Verify this is the correct sales node
+    require!(node.contains_sales(sales), ErrorCode::InvalidSalesRange);
+
+    node.add_product(product_id, sales)?;
+    node.update_top_items(product_id, sales)?;
+
+    // Re-serialize
+    let mut cursor = std::io::Cursor::new(&mut node_data[..]);
+    node.try_serialize(&mut cursor)?;
+    Ok(())
+}
+
+/// Move a product to the sales index account matching its sales after an order settles
+///
+/// Each side is the legacy sales node covering the sales, or the zero-copy sales shard of the
+/// range once its node has been migrated; both may be the same account. Frozen legacy nodes are
+/// rejected so migrated ranges cannot go stale.
+#[allow(clippy::too_many_arguments)]
+pub fn settle_product_sales_index<'info>(
+    old_sales_index: &AccountInfo<'info>,
+    new_sales_index: &AccountInfo<'info>,
+    product_id: u64,
+    old_sales: u32,
+    new_sales: u32,
+    shard_capacity: u32,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> Result<()> {
+    require!(
+        old_sales_index.owner == program_id && new_sales_index.owner == program_id,
+        ErrorCode::InvalidAccountOwner
     );
+    let old_is_shard = index_shard_header(old_sales_index)?.is_some();
+    let new_is_shard = index_shard_header(new_sales_index)?.is_some();
+    if !old_is_shard && !new_is_shard {
+        return move_product_sales_index(
+            old_sales_index,
+            new_sales_index,
+            product_id,
+            old_sales,
+            new_sales,
+            program_id,
+        );
+    }
+
+    for (is_shard, sales_index) in [
+        (old_is_shard, old_sales_index),
+        (new_is_shard, new_sales_index),
+    ] {
+        require!(
+            is_shard || !is_frozen_legacy_shard::<SalesIndexNode>(sales_index),
+            ErrorCode::AccountAlreadyMigrated
+        );
+    }
+
+    if old_is_shard {
+        remove_from_sales_shard(old_sales_index, product_id, old_sales)?;
+    } else {
+        remove_from_sales_node(old_sales_index, product_id, old_sales)?;
+    }
+    if new_is_shard {
+        add_to_sales_shard(
+            new_sales_index,
+            product_id,
+            new_sales,
+            shard_capacity,
+            payer,
+            system_program,
+        )?;
+    } else {
+        add_to_sales_node(new_sales_index, product_id, new_sales)?;
+    }
 
     Ok(())
 }
@@ -286,7 +394,8 @@ pub struct InitializeSalesIndexIfNeeded<'info> {
             sales_range_start.to_le_bytes().as_ref(),
             sales_range_end.to_le_bytes().as_ref()
         ],
-        bump
+        bump,
+        constraint = !is_frozen_legacy_shard::<SalesIndexNode>(&sales_index.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
     )]
     pub sales_index: Account<'info, SalesIndexNode>,
 
@@ -339,7 +448,8 @@ pub struct AddProductToSalesIndexIfNeeded<'info> {
             sales_range_start.to_le_bytes().as_ref(),
             sales_range_end.to_le_bytes().as_ref()
         ],
-        bump
+        bump,
+        constraint = !is_frozen_legacy_shard::<SalesIndexNode>(&sales_index.to_account_info()) @ ErrorCode::AccountAlreadyMigrated
     )]
     pub sales_index: Account<'info, SalesIndexNode>,
 
@@ -0,0 +1,451 @@
+use crate::error::ErrorCode;
+use crate::state::*;
+use crate::utils::{SearchFilter, SearchResult, SortKey, SortOrder, MAX_PAGE_SIZE};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::compute_units::sol_remaining_compute_units;
+
+// Stop evaluating products when fewer compute units than this remain
+pub const SEARCH_MIN_REMAINING_COMPUTE: u64 = 25_000;
+
+// Index used to drive a composite search
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SearchIndexKind {
+    Keyword,
+    Price,
+    Sales,
+}
+
+// Candidate product ids produced by one index
+#[derive(Clone, Debug)]
+pub struct IndexCandidates {
+    pub kind: SearchIndexKind,
+    pub product_ids: Vec<u64>,
+}
+
+// Candidate sets of a search and the payment token of its price index accounts
+struct SearchCandidates {
+    sets: Vec<IndexCandidates>,
+    price_mint: Option<Pubkey>,
+}
+
+#[derive(Accounts)]
+pub struct SearchProducts {
+    // remaining_accounts: `index_account_count` index accounts (keyword shards, price index
+    // nodes, sales index nodes or zero-copy index shards), followed by the ProductBase accounts of the candidates starting
+    // at `cursor`, in candidate order
+}
+
+/// Composite product search driven by a SearchFilter
+///
+/// Each index passed in remaining accounts produces a candidate set, the smallest one drives the
+/// query and the candidates are checked against their ProductBase accounts. SortKey::ProductId
+/// orders candidates by id; SortKey::Price needs price index accounts, which then drive the query
+/// in price order. Price index accounts must share one payment token and price bounds only match
+/// products priced in it. `total_count` is the number of candidates of the driving index, the
+/// matching products among them are only known once all are evaluated. Evaluation stops early
+/// when the product accounts or the compute budget run out; `next_offset` is the cursor to
+/// resume from.
+pub fn search_products<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SearchProducts>,
+    filter: SearchFilter,
+    sort_key: SortKey,
+    sort_order: SortOrder,
+    index_account_count: u8,
+    cursor: u32,
+    limit: u16,
+) -> Result<SearchResult> {
+    let index_account_count = index_account_count as usize;
+    require!(
+        index_account_count > 0 && ctx.remaining_accounts.len() >= index_account_count,
+        ErrorCode::InsufficientAccounts
+    );
+    if let (Some(min), Some(max)) = (filter.price_min, filter.price_max) {
+        require!(min <= max, ErrorCode::InvalidPriceRange);
+    }
+    if let (Some(min), Some(max)) = (filter.sales_min, filter.sales_max) {
+        require!(min <= max, ErrorCode::InvalidSalesRange);
+    }
+    let (index_accounts, product_accounts) = ctx.remaining_accounts.split_at(index_account_count);
+
+    // Plan: collect candidates from every index and drive with the most selective one
+    let candidates = collect_index_candidates(index_accounts, &filter, ctx.program_id)?;
+    // Prices of different tokens are not comparable, so price queries are tied to one mint
+    let price_query =
+        filter.price_min.is_some() || filter.price_max.is_some() || sort_key == SortKey::Price;
+    require!(
+        !price_query || candidates.price_mint.is_some(),
+        ErrorCode::InvalidPriceIndexAccounts
+    );
+    let driving =
+        plan_search(&candidates.sets, &sort_key).ok_or(ErrorCode::InsufficientAccounts)?;
+    let candidate_ids = order_candidates(driving, &sort_key, &sort_order);
+    let candidate_count = candidate_ids.len() as u32;
+
+    // Execute: evaluate candidates from the cursor against their ProductBase accounts
+    let limit = limit.min(MAX_PAGE_SIZE) as usize;
+    let mut matched: Vec<ProductBase> = Vec::new();
+    let mut position = cursor as usize;
+    let mut product_accounts = product_accounts.iter();
+
+    while position < candidate_ids.len() && matched.len() < limit {
+        if sol_remaining_compute_units() < SEARCH_MIN_REMAINING_COMPUTE {
+            msg!(
+                "Search paused at candidate {} to stay within compute budget",
+                position
+            );
+            break;
+        }
+        let Some(product_info) = product_accounts.next() else {
+            break;
+        };
+
+        require!(
+            product_info.owner == ctx.program_id,
+            ErrorCode::InvalidAccountOwner
+        );
+        let product_data = product_info.try_borrow_data()?;
+        let product = ProductBase::try_deserialize(&mut &product_data[..])?;
+        drop(product_data);
+
+        require!(
+            product.id == candidate_ids[position],
+            ErrorCode::SearchCursorMismatch
+        );
+        position += 1;
+
+        let priced_in_mint = candidates
+            .price_mint
+            .is_none_or(|mint| product.payment_token == mint);
+        if priced_in_mint && product_matches_filter(&product, &filter) {
+            matched.push(product);
+        }
+    }
+
+    sort_products(&mut matched, &sort_key, &sort_order);
+
+    let result = SearchResult {
+        product_ids: matched.iter().map(|product| product.id).collect(),
+        total_count: candidate_count,
+        has_more: position < candidate_ids.len(),
+        next_offset: position as u32,
+    };
+
+    msg!(
+        "Composite search completed, driving index: {:?}, candidates: {}, evaluated: {}, matched: {}",
+        driving.kind,
+        candidate_count,
+        position.saturating_sub(cursor as usize),
+        result.product_ids.len()
+    );
+
+    Ok(result)
+}
+
+// Build candidate sets from the index accounts, one per index kind
+fn collect_index_candidates<'info>(
+    index_accounts: &'info [AccountInfo<'info>],
+    filter: &SearchFilter,
+    program_id: &Pubkey,
+) -> Result<SearchCandidates> {
+    let price_min = filter.price_min.unwrap_or(0);
+    let price_max = filter.price_max.unwrap_or(u64::MAX);
+    let sales_min = filter.sales_min.unwrap_or(0);
+    let sales_max = filter.sales_max.unwrap_or(u32::MAX);
+
+    // Keyword shards are grouped per keyword and the keywords intersected
+    let mut keyword_sets: Vec<(String, Vec<u64>)> = Vec::new();
+    let mut price_ids: Option<Vec<(u64, u64)>> = None;
+    let mut price_mint: Option<Pubkey> = None;
+    let mut sales_ids: Option<Vec<u64>> = None;
+
+    for index_info in index_accounts {
+        require!(
+            index_info.owner == program_id,
+            ErrorCode::InvalidAccountOwner
+        );
+        let data = index_info.try_borrow_data()?;
+
+        if data.starts_with(KeywordShard::DISCRIMINATOR) {
+            let shard = KeywordShard::try_deserialize(&mut &data[..])?;
+            let wanted = filter
+                .keywords
+                .as_ref()
+                .is_some_and(|keywords| keywords.contains(&shard.keyword));
+            require!(wanted, ErrorCode::InvalidKeyword);
+
+            match keyword_sets
+                .iter_mut()
+                .find(|(keyword, _)| *keyword == shard.keyword)
+            {
+                Some((_, ids)) => ids.extend_from_slice(&shard.product_ids),
+                None => keyword_sets.push((shard.keyword.clone(), shard.product_ids.clone())),
+            }
+        } else if data.starts_with(PriceIndexNode::DISCRIMINATOR) {
+            let node = PriceIndexNode::try_deserialize(&mut &data[..])?;
+            check_price_mint(&mut price_mint, node.payment_token)?;
+            price_ids.get_or_insert_with(Vec::new).extend(
+                node.entries_in_range(price_min, price_max)
+                    .iter()
+                    .map(|entry| (entry.price, entry.product_id)),
+            );
+        } else if data.starts_with(SalesIndexNode::DISCRIMINATOR) {
+            let node = SalesIndexNode::try_deserialize(&mut &data[..])?;
+            // Sales nodes only know their range, exact sales are checked on ProductBase
+            let ids = sales_ids.get_or_insert_with(Vec::new);
+            if node.sales_range_start <= sales_max && node.sales_range_end >= sales_min {
+                ids.extend_from_slice(&node.product_ids);
+            }
+        } else if data.starts_with(IndexShard::DISCRIMINATOR) {
+            // Zero-copy shards are read in place
+            let loader = AccountLoader::<IndexShard>::try_from(index_info)?;
+            let shard = loader.load()?;
+            let entries = &data[IndexShard::ENTRIES_OFFSET..];
+            let len = shard.len as usize;
+
+            match shard.kind {
+                0 => {
+                    let keyword = shard.keyword();
+                    let wanted = filter
+                        .keywords
+                        .as_ref()
+                        .is_some_and(|keywords| keywords.contains(&keyword));
+                    require!(wanted, ErrorCode::InvalidKeyword);
+
+                    let ids = IndexEntries::product_ids(entries, len);
+                    match keyword_sets
+                        .iter_mut()
+                        .find(|(existing, _)| *existing == keyword)
+                    {
+                        Some((_, existing_ids)) => existing_ids.extend(ids),
+                        None => keyword_sets.push((keyword, ids)),
+                    }
+                }
+                1 => {
+                    check_price_mint(&mut price_mint, shard.payment_token())?;
+                    price_ids.get_or_insert_with(Vec::new).extend(
+                        IndexEntries::in_range(entries, len, price_min, price_max)
+                            .iter()
+                            .map(|entry| (entry.value, entry.product_id)),
+                    );
+                }
+                _ => {
+                    let ids = sales_ids.get_or_insert_with(Vec::new);
+                    if shard.range_start <= sales_max as u64 && shard.range_end >= sales_min as u64
+                    {
+                        ids.extend(IndexEntries::product_ids(entries, len));
+                    }
+                }
+            }
+        } else {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+    }
+
+    let mut candidates = Vec::new();
+
+    // Every requested keyword must be covered for the keyword index to be usable
+    let requested_keywords = filter
+        .keywords
+        .as_ref()
+        .map_or(0, |keywords| keywords.len());
+    if requested_keywords > 0 && keyword_sets.len() == requested_keywords {
+        let mut keyword_ids: Option<Vec<u64>> = None;
+        for (_, mut ids) in keyword_sets {
+            ids.sort_unstable();
+            ids.dedup();
+            keyword_ids = Some(match keyword_ids {
+                Some(current) => crate::utils::intersect_sorted_vecs(&current, &ids),
+                None => ids,
+            });
+        }
+        candidates.push(IndexCandidates {
+            kind: SearchIndexKind::Keyword,
+            product_ids: keyword_ids.unwrap_or_default(),
+        });
+    }
+
+    if let Some(mut entries) = price_ids {
+        // Keep price order so price sorted searches are ordered globally
+        entries.sort_unstable();
+        candidates.push(IndexCandidates {
+            kind: SearchIndexKind::Price,
+            product_ids: entries
+                .into_iter()
+                .map(|(_, product_id)| product_id)
+                .collect(),
+        });
+    }
+
+    if let Some(mut ids) = sales_ids {
+        ids.sort_unstable();
+        ids.dedup();
+        candidates.push(IndexCandidates {
+            kind: SearchIndexKind::Sales,
+            product_ids: ids,
+        });
+    }
+
+    Ok(SearchCandidates {
+        sets: candidates,
+        price_mint,
+    })
+}
+
+// Every price index account of a search must belong to the same payment token
+fn check_price_mint(price_mint: &mut Option<Pubkey>, payment_token: Pubkey) -> Result<()> {
+    let mint = *price_mint.get_or_insert(payment_token);
+    require_keys_eq!(mint, payment_token, ErrorCode::InvalidPriceIndexAccounts);
+    Ok(())
+}
+
+/// Pick the index that drives the search
+///
+/// SortKey::Price is driven by the price index, the only one ordered by the sort key. Otherwise
+/// the most selective index (fewest candidates) drives, keyword index first on ties.
+pub fn plan_search<'a>(
+    candidates: &'a [IndexCandidates],
+    sort_key: &SortKey,
+) -> Option<&'a IndexCandidates> {
+    if *sort_key == SortKey::Price {
+        return candidates
+            .iter()
+            .find(|candidate| candidate.kind == SearchIndexKind::Price);
+    }
+    candidates
+        .iter()
+        .min_by_key(|candidate| candidate.product_ids.len())
+}
+
+// Candidate order used by the cursor: price order for SortKey::Price, product id otherwise
+fn order_candidates(
+    driving: &IndexCandidates,
+    sort_key: &SortKey,
+    sort_order: &SortOrder,
+) -> Vec<u64> {
+    let mut ids = driving.product_ids.clone();
+    if *sort_key != SortKey::Price {
+        ids.sort_unstable();
+    }
+    if *sort_order == SortOrder::Descending {
+        ids.reverse();
+    }
+    ids
+}
+
+pub fn product_matches_filter(product: &ProductBase, filter: &SearchFilter) -> bool {
+    if filter.is_active_only && !product.is_active {
+        return false;
+    }
+    if filter.price_min.is_some_and(|min| product.price < min)
+        || filter.price_max.is_some_and(|max| product.price > max)
+    {
+        return false;
+    }
+    if filter.sales_min.is_some_and(|min| product.sales < min)
+        || filter.sales_max.is_some_and(|max| product.sales > max)
+    {
+        return false;
+    }
+    if filter
+        .merchant
+        .is_some_and(|merchant| product.merchant != merchant)
+    {
+        return false;
+    }
+    if let Some(keywords) = &filter.keywords {
+        let product_keywords = product.parse_keywords();
+        if !keywords
+            .iter()
+            .all(|keyword| product_keywords.contains(keyword))
+        {
+            return false;
+        }
+    }
+    true
+}
+
+fn sort_products(products: &mut [ProductBase], sort_key: &SortKey, sort_order: &SortOrder) {
+    match sort_key {
+        SortKey::ProductId => products.sort_by_key(|product| product.id),
+        SortKey::Price => products.sort_by_key(|product| (product.price, product.id)),
+    }
+    if *sort_order == SortOrder::Descending {
+        products.reverse();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_uses_most_selective_index() {
+        let candidates = vec![
+            IndexCandidates {
+                kind: SearchIndexKind::Keyword,
+                product_ids: vec![1, 2, 3, 4],
+            },
+            IndexCandidates {
+                kind: SearchIndexKind::Price,
+                product_ids: vec![9, 3],
+            },
+            IndexCandidates {
+                kind: SearchIndexKind::Sales,
+                product_ids: vec![1, 2, 3],
+            },
+        ];
+
+        let driving = plan_search(&candidates, &SortKey::ProductId).unwrap();
+        assert_eq!(driving.kind, SearchIndexKind::Price);
+
+        // Price sort keeps index order, product id sort falls back to id order
+        assert_eq!(
+            order_candidates(driving, &SortKey::Price, &SortOrder::Ascending),
+            vec![9, 3]
+        );
+        assert_eq!(
+            order_candidates(driving, &SortKey::ProductId, &SortOrder::Descending),
+            vec![9, 3]
+        );
+        assert_eq!(
+            order_candidates(driving, &SortKey::ProductId, &SortOrder::Ascending),
+            vec![3, 9]
+        );
+
+        assert!(plan_search(&[], &SortKey::ProductId).is_none());
+    }
+
+    #[test]
+    fn test_price_sort_is_driven_by_the_price_index() {
+        let mut candidates = vec![
+            IndexCandidates {
+                kind: SearchIndexKind::Keyword,
+                product_ids: vec![2],
+            },
+            IndexCandidates {
+                kind: SearchIndexKind::Price,
+                product_ids: vec![7, 2, 5],
+            },
+        ];
+
+        let driving = plan_search(&candidates, &SortKey::Price).unwrap();
+        assert_eq!(driving.kind, SearchIndexKind::Price);
+        assert_eq!(
+            order_candidates(driving, &SortKey::Price, &SortOrder::Descending),
+            vec![5, 2, 7]
+        );
+
+        candidates.pop();
+        assert!(plan_search(&candidates, &SortKey::Price).is_none());
+    }
+
+    #[test]
+    fn test_price_index_accounts_share_one_mint() {
+        let usdc = Pubkey::new_unique();
+        let mut price_mint = None;
+        assert!(check_price_mint(&mut price_mint, usdc).is_ok());
+        assert!(check_price_mint(&mut price_mint, usdc).is_ok());
+        assert!(check_price_mint(&mut price_mint, Pubkey::new_unique()).is_err());
+        assert_eq!(price_mint, Some(usdc));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::error::ErrorCode;
+use crate::state::*;
+use crate::utils::MAX_PAGE_SIZE;
+use crate::SystemConfig;
+use anchor_lang::prelude::*;
+
+/// Create the trending list of a keyword
+#[derive(Accounts)]
+#[instruction(keyword: String)]
+pub struct InitializeKeywordTrendingBoard<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + TrendingBoard::INIT_SPACE,
+        seeds = [b"trending_keyword", keyword.as_bytes()],
+        bump
+    )]
+    pub trending_board: Account<'info, TrendingBoard>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create the trending list of a keyword, updated on settlement when passed with the order
+pub fn initialize_keyword_trending_board(
+    ctx: Context<InitializeKeywordTrendingBoard>,
+    keyword: String,
+) -> Result<()> {
+    require!(
+        crate::utils::is_valid_keyword(&keyword),
+        ErrorCode::InvalidKeyword
+    );
+
+    ctx.accounts
+        .trending_board
+        .initialize(keyword.clone(), ctx.bumps.trending_board)?;
+
+    msg!("关键词热度榜单初始化完成，关键词: {}", keyword);
+
+    Ok(())
+}
+
+/// Query a trending leaderboard (global or keyword)
+#[derive(Accounts)]
+pub struct GetTrendingProducts<'info> {
+    pub trending_board: Account<'info, TrendingBoard>,
+
+    #[account(
+        seeds = [b"system_config"],
+        bump
+    )]
+    pub system_config: Account<'info, SystemConfig>,
+}
+
+/// Trending products with their scores decayed to the current time, hottest first
+pub fn get_trending_products(
+    ctx: Context<GetTrendingProducts>,
+    limit: u16,
+) -> Result<Vec<TrendingEntry>> {
+    let mut board = ctx.accounts.trending_board.clone().into_inner();
+    let now = Clock::get()?.unix_timestamp;
+
+    board.decay_to(now, ctx.accounts.system_config.trending_half_life_seconds);
+    board.entries.truncate(limit.min(MAX_PAGE_SIZE) as usize);
+
+    Ok(board.entries)
+}
+
+/// Update the trending half-life (system administrator)
+#[derive(Accounts)]
+pub struct UpdateTrendingHalfLife<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"system_config"],
+        bump,
+        constraint = system_config.authority == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub system_config: Account<'info, SystemConfig>,
+}
+
+/// Update the trending half-life, stored scores keep their value and decay at the new rate
+pub fn update_trending_half_life(
+    ctx: Context<UpdateTrendingHalfLife>,
+    half_life_seconds: u32,
+) -> Result<()> {
+    require!(half_life_seconds > 0, ErrorCode::InvalidTrendingHalfLife);
+
+    let system_config = &mut ctx.accounts.system_config;
+    let old_half_life = system_config.trending_half_life_seconds;
+    system_config.trending_half_life_seconds = half_life_seconds;
+
+    msg!(
+        "Trending half-life updated: {} -> {} seconds",
+        old_half_life,
+        half_life_seconds
+    );
+
+    Ok(())
+}
+
+// Apply a settled sale (or its refund) to the product's trending score and leaderboards
+//
+// `keyword_boards` are keyword trending lists of the product, passed in remaining accounts; they
+// are skipped when the product has been hard deleted. `sold_at` is the settlement time of the
+// sale being refunded.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn record_trending_sale<'info>(
+    trending_score: &mut TrendingScore,
+    trending_score_bump: u8,
+    global_board: &mut TrendingBoard,
+    global_board_bump: u8,
+    keyword_boards: &[AccountInfo<'info>],
+    product: Option<&ProductBase>,
+    product_id: u64,
+    quantity: u32,
+    sold_at: Option<i64>,
+    half_life_seconds: u32,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+
+    trending_score.initialize_if_needed(product_id, now, trending_score_bump);
+    match sold_at {
+        None => trending_score.record_sale(quantity, now, half_life_seconds),
+        Some(sold_at) => trending_score.revert_sale(quantity, sold_at, now, half_life_seconds),
+    }
+    let score = trending_score.score;
+
+    if global_board.updated_at == 0 {
+        global_board.initialize(String::new(), global_board_bump)?;
+    }
+    global_board.upsert(product_id, score, now, half_life_seconds);
+
+    let Some(product) = product else {
+        return Ok(());
+    };
+    let product_keywords = product.parse_keywords();
+
+    for board_info in keyword_boards {
+        require!(
+            board_info.owner == program_id,
+            ErrorCode::InvalidAccountOwner
+        );
+        let board_data = board_info.try_borrow_data()?;
+        let mut board = TrendingBoard::try_deserialize(&mut &board_data[..])?;
+        drop(board_data);
+
+        require!(
+            !board.keyword.is_empty() && product_keywords.contains(&board.keyword),
+            ErrorCode::InvalidKeyword
+        );
+        board.upsert(product_id, score, now, half_life_seconds);
+
+        let mut board_data = board_info.try_borrow_mut_data()?;
+        let mut cursor = std::io::Cursor::new(&mut board_data[..]);
+        board.try_serialize(&mut cursor)?;
+    }
+
+    msg!("Product {} trending score updated to {}", product_id, score);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF_LIFE: u32 = 86_400;
+
+    #[test]
+    fn test_decay_and_trending_order() {
+        let score = 8 * TRENDING_SCORE_SCALE;
+        assert_eq!(decay_score(score, 0, HALF_LIFE), score);
+        assert_eq!(decay_score(score, HALF_LIFE as i64, HALF_LIFE), score / 2);
+        assert_eq!(
+            decay_score(score, 3 * HALF_LIFE as i64, HALF_LIFE),
+            score / 8
+        );
+
+        // Half a half-life is within rounding of 1/sqrt(2)
+        let half = decay_score(score, HALF_LIFE as i64 / 2, HALF_LIFE);
+        let expected = (score as f64 / 2f64.sqrt()) as u64;
+        assert!(half.abs_diff(expected) <= score / 10_000);
+
+        // An old bestseller falls behind a recent hit
+        let mut board = TrendingBoard {
+            keyword: String::new(),
+            entries: Vec::new(),
+            updated_at: 0,
+            bump: 255,
+        };
+        board.upsert(1, 10 * TRENDING_SCORE_SCALE, 1, HALF_LIFE);
+        board.upsert(
+            2,
+            3 * TRENDING_SCORE_SCALE,
+            1 + 2 * HALF_LIFE as i64,
+            HALF_LIFE,
+        );
+        let ids: Vec<u64> = board.entries.iter().map(|entry| entry.product_id).collect();
+        assert_eq!(ids, vec![2, 1]);
+        assert_eq!(board.entries[1].score, 10 * TRENDING_SCORE_SCALE / 4);
+    }
+}
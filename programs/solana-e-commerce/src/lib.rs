@@ -6,7 +6,10 @@ pub mod state;
 pub mod utils;
 
 use instructions::*;
-use state::SupportedToken;
+use state::{
+    EscrowSolvency, IndexShardKey, LeaderboardScope, ProductSales, RecencyFeed, ReputationScore,
+    SupportedToken, TrendingEntry, VariantOption,
+};
 
 declare_id!("5XZ74thixMBX2tQN9P3yLTugUK4YMdRLznDNa2mRdGNT");
 
@@ -27,6 +30,11 @@ pub mod solana_e_commerce {
         instructions::initialize::initialize_system_config(ctx, config)
     }
 
+    // Grow a system configuration created before trending scores and index shards
+    pub fn migrate_system_config(ctx: Context<MigrateSystemConfig>) -> Result<()> {
+        instructions::initialize::migrate_system_config(ctx)
+    }
+
     // Close system configuration
     pub fn close_system_config(ctx: Context<CloseSystemConfig>, force: bool) -> Result<()> {
         instructions::initialize::close_system_config(ctx, force)
@@ -54,6 +62,18 @@ pub mod solana_e_commerce {
         instructions::id_generator::allocate_new_chunk(ctx)
     }
 
+    // Release an ID without a product so it is handed out again
+    pub fn release_id(ctx: Context<ReleaseId>, id: u64) -> Result<()> {
+        instructions::id_generator::release_id(ctx, id)
+    }
+
+    // Close inactive ID chunks without any ID in use
+    pub fn compact_id_chunks<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CompactIdChunks<'info>>,
+    ) -> Result<u32> {
+        instructions::id_generator::compact_id_chunks(ctx)
+    }
+
     // ==================== Merchant Management Instructions ====================
 
     // Atomic merchant registration instruction
@@ -75,7 +95,9 @@ pub mod solana_e_commerce {
     }
 
     // Get merchant statistics information
-    pub fn get_merchant_stats(ctx: Context<GetMerchantStats>) -> Result<state::MerchantStats> {
+    pub fn get_merchant_stats<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetMerchantStats<'info>>,
+    ) -> Result<state::MerchantStats> {
         instructions::merchant::get_merchant_stats(ctx)
     }
 
@@ -86,7 +108,8 @@ pub mod solana_e_commerce {
 
     // Product management instructions
 
-    // Create ProductBase (core business data)
+    // Create ProductBase (core business data), `day` is the current day (recency_day) of the
+    // NewArrivals feed
     pub fn create_product_base(
         ctx: Context<CreateProductBase>,
         name: String,
@@ -96,6 +119,7 @@ pub mod solana_e_commerce {
         inventory: u64,
         payment_token: Pubkey,
         shipping_location: String,
+        day: u32,
     ) -> Result<u64> {
         instructions::product::create_product_base(
             ctx,
@@ -106,6 +130,7 @@ pub mod solana_e_commerce {
             inventory,
             payment_token,
             shipping_location,
+            day,
         )
     }
 
@@ -126,7 +151,8 @@ pub mod solana_e_commerce {
         )
     }
 
-    // Product modification instruction
+    // Product modification instruction, `day` is the current day (recency_day) of the
+    // RecentlyUpdated feed
     pub fn update_product(
         ctx: Context<UpdateProduct>,
         product_id: u64,
@@ -140,6 +166,7 @@ pub mod solana_e_commerce {
         shipping_location: Option<String>,
         sales_regions: Option<Vec<String>>,
         logistics_methods: Option<Vec<String>>,
+        day: u32,
     ) -> Result<()> {
         instructions::product::update_product(
             ctx,
@@ -154,11 +181,12 @@ pub mod solana_e_commerce {
             shipping_location,
             sales_regions,
             logistics_methods,
+            day,
         )
     }
 
-    pub fn delete_product(
-        ctx: Context<DeleteProduct>,
+    pub fn delete_product<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DeleteProduct<'info>>,
         product_id: u64,
         hard_delete: bool,
         force: bool,
@@ -166,6 +194,18 @@ pub mod solana_e_commerce {
         instructions::product::delete_product(ctx, product_id, hard_delete, force)
     }
 
+    // Query one page of a merchant's active or inactive product catalog
+    pub fn get_merchant_products(
+        ctx: Context<GetMerchantProducts>,
+        merchant: Pubkey,
+        is_active: bool,
+        page_index: u64,
+    ) -> Result<MerchantProductPageInfo> {
+        instructions::merchant_product_list::get_merchant_products(
+            ctx, merchant, is_active, page_index,
+        )
+    }
+
     pub fn update_product_price(
         ctx: Context<UpdateProductPrice>,
         product_id: u64,
@@ -174,6 +214,50 @@ pub mod solana_e_commerce {
         instructions::product::update_product_price(ctx, product_id, new_price)
     }
 
+    // Product variants with their own options, SKU, price override and inventory
+    pub fn create_product_variant(
+        ctx: Context<CreateProductVariant>,
+        product_id: u64,
+        options: Vec<VariantOption>,
+        sku: String,
+        price: Option<u64>,
+        inventory: u64,
+    ) -> Result<()> {
+        instructions::product_variant::create_product_variant(
+            ctx, product_id, options, sku, price, inventory,
+        )
+    }
+
+    pub fn update_product_variant(
+        ctx: Context<UpdateProductVariant>,
+        product_id: u64,
+        variant_id: u32,
+        sku: Option<String>,
+        inventory: Option<u64>,
+        is_active: Option<bool>,
+    ) -> Result<()> {
+        instructions::product_variant::update_product_variant(
+            ctx, product_id, variant_id, sku, inventory, is_active,
+        )
+    }
+
+    pub fn update_product_variant_price(
+        ctx: Context<UpdateProductVariant>,
+        product_id: u64,
+        variant_id: u32,
+        price: Option<u64>,
+    ) -> Result<()> {
+        instructions::product_variant::update_product_variant_price(
+            ctx, product_id, variant_id, price,
+        )
+    }
+
+    // Migration of products created before variants, paid by any payer
+    pub fn migrate_product_base(ctx: Context<MigrateProductBase>, product_id: u64) -> Result<()> {
+        instructions::product::migrate_product_base(ctx, product_id)
+    }
+
+    // Administrator migration only, sales are recorded on order settlement
     pub fn update_sales_count(
         ctx: Context<UpdateSales>,
         product_id: u64,
@@ -182,6 +266,28 @@ pub mod solana_e_commerce {
         instructions::product::update_sales_count(ctx, product_id, sales_increment)
     }
 
+    // Record a product in the new arrivals or recently updated feed
+    pub fn record_recent_product(
+        ctx: Context<RecordRecentProduct>,
+        product_id: u64,
+        feed: RecencyFeed,
+        keyword: String,
+        day: u32,
+    ) -> Result<()> {
+        instructions::recency_index::record_recent_product(ctx, product_id, feed, keyword, day)
+    }
+
+    // Page backwards through a recency feed, newest first
+    pub fn get_recent_products<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetRecentProducts>,
+        feed: RecencyFeed,
+        keyword: String,
+        cursor: Option<RecencyCursor>,
+        limit: u16,
+    ) -> Result<RecentProductsPage> {
+        instructions::recency_index::get_recent_products(ctx, feed, keyword, cursor, limit)
+    }
+
     // Payment system instructions
     pub fn initialize_payment_system(
         ctx: Context<InitializePaymentSystem>,
@@ -228,6 +334,21 @@ pub mod solana_e_commerce {
         instructions::payment::purchase_product_escrow(ctx, product_id, amount)
     }
 
+    // Escrow reconciliation: liabilities of the program token account of a payment token
+    pub fn initialize_escrow_ledger(ctx: Context<InitializeEscrowLedger>) -> Result<()> {
+        instructions::payment::initialize_escrow_ledger(ctx)
+    }
+
+    // Compare escrow liabilities with the token balance (view)
+    pub fn verify_escrow_solvency(ctx: Context<VerifyEscrowSolvency>) -> Result<EscrowSolvency> {
+        instructions::payment::verify_escrow_solvency(ctx)
+    }
+
+    // Block escrow withdrawals while insolvent, unblock once solvent (system administrator)
+    pub fn enforce_escrow_solvency(ctx: Context<EnforceEscrowSolvency>) -> Result<EscrowSolvency> {
+        instructions::payment::enforce_escrow_solvency(ctx)
+    }
+
     // Keyword index management instructions (removed old functions, only keep if_needed versions)
 
     pub fn remove_product_from_keyword_index(
@@ -256,6 +377,14 @@ pub mod solana_e_commerce {
         )
     }
 
+    // Query the bloom filter statistics of a keyword index
+    pub fn get_keyword_bloom_summary(
+        ctx: Context<GetKeywordBloomSummary>,
+        keyword: String,
+    ) -> Result<KeywordBloomStats> {
+        instructions::keyword_index::get_keyword_bloom_summary(ctx, keyword)
+    }
+
     pub fn create_keyword_shard(
         ctx: Context<CreateKeywordShard>,
         keyword: String,
@@ -264,20 +393,33 @@ pub mod solana_e_commerce {
         instructions::keyword_index::create_keyword_shard(ctx, keyword, shard_index)
     }
 
+    // Merge a sparse keyword shard into its neighbor (permissionless)
+    pub fn merge_keyword_shards(
+        ctx: Context<MergeKeywordShards>,
+        keyword: String,
+        source_shard_index: u32,
+        target_shard_index: u32,
+    ) -> Result<()> {
+        instructions::keyword_index::merge_keyword_shards(
+            ctx,
+            keyword,
+            source_shard_index,
+            target_shard_index,
+        )
+    }
+
     // Price index management instructions
 
     // Smart price index instructions
     pub fn add_product_to_price_index(
         ctx: Context<AddProductToPriceIndex>,
         product_id: u64,
-        price: u64,
         price_range_start: u64,
         price_range_end: u64,
     ) -> Result<()> {
         instructions::price_index::add_product_to_price_index(
             ctx,
             product_id,
-            price,
             price_range_start,
             price_range_end,
         )
@@ -294,8 +436,74 @@ pub mod solana_e_commerce {
         ctx: Context<SplitPriceNode>,
         price_range_start: u64,
         price_range_end: u64,
+        split_price: u64,
     ) -> Result<()> {
-        instructions::price_index::split_price_node(ctx, price_range_start, price_range_end)
+        instructions::price_index::split_price_node(
+            ctx,
+            price_range_start,
+            price_range_end,
+            split_price,
+        )
+    }
+
+    // Move products of a legacy (unnamespaced) price node into the node of their payment mint
+    pub fn migrate_price_node<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MigratePriceNode<'info>>,
+        legacy_range_start: u64,
+        legacy_range_end: u64,
+        price_range_start: u64,
+        price_range_end: u64,
+    ) -> Result<()> {
+        instructions::price_index::migrate_price_node(
+            ctx,
+            legacy_range_start,
+            legacy_range_end,
+            price_range_start,
+            price_range_end,
+        )
+    }
+
+    // Price range search over adjacent leaf nodes (passed in remaining accounts)
+    // Without a payment token the range is normalized and spans all tokens
+    pub fn search_price_range<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SearchPriceRange<'info>>,
+        payment_token: Option<Pubkey>,
+        min_price: u64,
+        max_price: u64,
+        sort_order: utils::SortOrder,
+        offset: u32,
+        limit: u16,
+    ) -> Result<PriceRangePage> {
+        instructions::price_index::search_price_range(
+            ctx,
+            payment_token,
+            min_price,
+            max_price,
+            sort_order,
+            offset,
+            limit,
+        )
+    }
+
+    // Composite search over keyword, price and sales indexes (view)
+    pub fn search_products<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SearchProducts>,
+        filter: utils::SearchFilter,
+        sort_key: utils::SortKey,
+        sort_order: utils::SortOrder,
+        index_account_count: u8,
+        cursor: u32,
+        limit: u16,
+    ) -> Result<utils::SearchResult> {
+        instructions::search::search_products(
+            ctx,
+            filter,
+            sort_key,
+            sort_order,
+            index_account_count,
+            cursor,
+            limit,
+        )
     }
 
     // Sales index management instructions
@@ -334,6 +542,7 @@ pub mod solana_e_commerce {
         instructions::sales_index::remove_product_from_sales_index(ctx, product_id)
     }
 
+    // Administrator migration only, settlement moves products between sales nodes
     pub fn update_product_sales_index(
         ctx: Context<UpdateProductSalesIndex>,
         product_id: u64,
@@ -352,6 +561,14 @@ pub mod solana_e_commerce {
         instructions::keyword_index::close_keyword_root(ctx, keyword, force)
     }
 
+    // Administrator migration of keyword roots with the fixed 256-byte bloom filter
+    pub fn migrate_keyword_root<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MigrateKeywordRoot<'info>>,
+        keyword: String,
+    ) -> Result<()> {
+        instructions::keyword_index::migrate_keyword_root(ctx, keyword)
+    }
+
     pub fn close_keyword_shard(
         ctx: Context<CloseKeywordShard>,
         keyword: String,
@@ -383,6 +600,8 @@ pub mod solana_e_commerce {
         instructions::order::initialize_order_stats(ctx)
     }
 
+    // `day` of the order instructions is the current day (recency_day) of the daily order
+    // statistics
     pub fn create_order(
         ctx: Context<CreateOrder>,
         product_id: u64,
@@ -390,6 +609,7 @@ pub mod solana_e_commerce {
         shipping_address: String,
         notes: String,
         transaction_signature: String,
+        day: u32,
     ) -> Result<()> {
         instructions::order::create_order(
             ctx,
@@ -398,6 +618,7 @@ pub mod solana_e_commerce {
             shipping_address,
             notes,
             transaction_signature,
+            day,
         )
     }
 
@@ -406,23 +627,66 @@ pub mod solana_e_commerce {
     }
 
     // Buyer requests refund
-    pub fn refund_order(ctx: Context<RefundOrder>, refund_reason: String) -> Result<()> {
-        instructions::order::refund_order(ctx, refund_reason)
+    pub fn refund_order(
+        ctx: Context<RefundOrder>,
+        refund_reason: String,
+        day: u32,
+    ) -> Result<()> {
+        instructions::order::refund_order(ctx, refund_reason, day)
+    }
+
+    // Merchant approved refund of a delivered order, reverts the recorded sale
+    pub fn refund_delivered_order(
+        ctx: Context<RefundDeliveredOrder>,
+        refund_reason: String,
+        day: u32,
+    ) -> Result<()> {
+        instructions::order::refund_delivered_order(ctx, refund_reason, day)
     }
 
     // Merchant approve refund instruction removed, buyers can refund directly
 
+    // Migration of orders created before variants, paid by any payer
+    pub fn migrate_order(ctx: Context<MigrateOrder>) -> Result<()> {
+        instructions::order::migrate_order(ctx)
+    }
+
     pub fn get_order_stats(ctx: Context<GetOrderStats>) -> Result<()> {
         instructions::order::get_order_stats(ctx)
     }
 
-    pub fn confirm_delivery(ctx: Context<ConfirmDelivery>) -> Result<()> {
-        instructions::order::confirm_delivery(ctx)
+    pub fn confirm_delivery(ctx: Context<ConfirmDelivery>, day: u32) -> Result<()> {
+        instructions::order::confirm_delivery(ctx, day)
     }
 
     // Auto confirm delivery (system call)
-    pub fn auto_confirm_delivery(ctx: Context<AutoConfirmDelivery>) -> Result<()> {
-        instructions::order::auto_confirm_delivery(ctx)
+    pub fn auto_confirm_delivery(ctx: Context<AutoConfirmDelivery>, day: u32) -> Result<()> {
+        instructions::order::auto_confirm_delivery(ctx, day)
+    }
+
+    // Review a delivered order as its buyer
+    pub fn create_review(
+        ctx: Context<CreateReview>,
+        rating: u8,
+        content_hash: Option<[u8; 32]>,
+        text: String,
+    ) -> Result<()> {
+        instructions::review::create_review(ctx, rating, content_hash, text)
+    }
+
+    // Reply to a review as the merchant
+    pub fn reply_to_review(ctx: Context<ReplyToReview>, reply: String) -> Result<()> {
+        instructions::review::reply_to_review(ctx, reply)
+    }
+
+    // Record a dispute the merchant of an order lost (system administrator)
+    pub fn record_lost_dispute(ctx: Context<RecordLostDispute>, reason: String) -> Result<()> {
+        instructions::reputation::record_lost_dispute(ctx, reason)
+    }
+
+    // Query merchant reputation
+    pub fn get_merchant_reputation(ctx: Context<GetMerchantReputation>) -> Result<ReputationScore> {
+        instructions::reputation::get_merchant_reputation(ctx)
     }
 
     // ==================== 保证金管理指令 ====================
@@ -463,9 +727,90 @@ pub mod solana_e_commerce {
     ) -> Result<()> {
         instructions::deposit::update_deposit_requirement(ctx, new_requirement)
     }
+
+    // Create the trending list of a keyword
+    pub fn initialize_keyword_trending_board(
+        ctx: Context<InitializeKeywordTrendingBoard>,
+        keyword: String,
+    ) -> Result<()> {
+        instructions::trending::initialize_keyword_trending_board(ctx, keyword)
+    }
+
+    // Query a global or keyword trending leaderboard
+    pub fn get_trending_products(
+        ctx: Context<GetTrendingProducts>,
+        limit: u16,
+    ) -> Result<Vec<TrendingEntry>> {
+        instructions::trending::get_trending_products(ctx, limit)
+    }
+
+    // Update the trending half-life (system administrator)
+    pub fn update_trending_half_life(
+        ctx: Context<UpdateTrendingHalfLife>,
+        half_life_seconds: u32,
+    ) -> Result<()> {
+        instructions::trending::update_trending_half_life(ctx, half_life_seconds)
+    }
+
+    // Create a global, category or payment token bestseller leaderboard
+    pub fn initialize_leaderboard(
+        ctx: Context<InitializeLeaderboard>,
+        scope: LeaderboardScope,
+    ) -> Result<()> {
+        instructions::leaderboard::initialize_leaderboard(ctx, scope)
+    }
+
+    // Query a bestseller leaderboard
+    pub fn get_leaderboard(
+        ctx: Context<GetLeaderboard>,
+        scope: LeaderboardScope,
+        limit: u16,
+    ) -> Result<Vec<ProductSales>> {
+        instructions::leaderboard::get_leaderboard(ctx, scope, limit)
+    }
+
+    // Create a zero-copy keyword, price or sales index shard
+    pub fn initialize_index_shard(
+        ctx: Context<InitializeIndexShard>,
+        key: IndexShardKey,
+    ) -> Result<()> {
+        instructions::index_shard::initialize_index_shard(ctx, key)
+    }
+
+    // Add a product to a zero-copy index shard
+    pub fn add_product_to_index_shard(
+        ctx: Context<AddProductToIndexShard>,
+        key: IndexShardKey,
+        product_id: u64,
+    ) -> Result<()> {
+        instructions::index_shard::add_product_to_index_shard(ctx, key, product_id)
+    }
+
+    // Remove a product from a zero-copy index shard
+    pub fn remove_product_from_index_shard(
+        ctx: Context<RemoveProductFromIndexShard>,
+        key: IndexShardKey,
+        product_id: u64,
+    ) -> Result<()> {
+        instructions::index_shard::remove_product_from_index_shard(ctx, key, product_id)
+    }
+
+    // Copy a legacy Borsh index shard into its zero-copy shard
+    pub fn migrate_index_shard(ctx: Context<MigrateIndexShard>, key: IndexShardKey) -> Result<()> {
+        instructions::index_shard::migrate_index_shard(ctx, key)
+    }
+
+    // Update the maximum capacity of zero-copy index shards
+    pub fn update_index_shard_capacity(
+        ctx: Context<UpdateIndexShardCapacity>,
+        capacity: u32,
+    ) -> Result<()> {
+        instructions::index_shard::update_index_shard_capacity(ctx, capacity)
+    }
 }
 
 #[account]
+#[derive(InitSpace)]
 pub struct SystemConfig {
     pub authority: Pubkey, // System administrator address
     pub max_products_per_shard: u16,
@@ -487,6 +832,12 @@ pub struct SystemConfig {
     pub vault_account: Pubkey,          // Vault数据账户地址（PDA）
     pub vault_token_account: Pubkey,    // Vault的Token账户地址
     pub platform_token_account: Pubkey, // 平台Token账户地址
+
+    // Trending ranking configuration
+    pub trending_half_life_seconds: u32, // Half-life of trending scores (default 7 days)
+
+    // Zero-copy index configuration
+    pub index_shard_capacity: u32, // Maximum entries a zero-copy index shard can grow to
 }
 
 impl Default for SystemConfig {
@@ -512,11 +863,70 @@ impl Default for SystemConfig {
             vault_account: Pubkey::default(), // Needs to be set during initialization
             vault_token_account: Pubkey::default(), // Needs to be set during initialization
             platform_token_account: Pubkey::default(), // Needs to be set during initialization
+
+            // Default trending ranking configuration
+            trending_half_life_seconds: state::DEFAULT_TRENDING_HALF_LIFE_SECONDS,
+
+            // Default zero-copy index configuration
+            index_shard_capacity: state::DEFAULT_INDEX_SHARD_CAPACITY,
+        }
+    }
+}
+
+// System configuration layout before trending scores and zero-copy index shards
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacySystemConfig {
+    pub authority: Pubkey,
+    pub max_products_per_shard: u16,
+    pub max_keywords_per_product: u8,
+    pub chunk_size: u32,
+    pub bloom_filter_size: u16,
+    pub merchant_deposit_required: u64,
+    pub deposit_token_mint: Pubkey,
+    pub platform_fee_rate: u16,
+    pub platform_fee_recipient: Pubkey,
+    pub auto_confirm_days: u32,
+    pub vault_program_id: Pubkey,
+    pub vault_account: Pubkey,
+    pub vault_token_account: Pubkey,
+    pub platform_token_account: Pubkey,
+}
+
+impl LegacySystemConfig {
+    // Allocated as 8 + size_of::<SystemConfig>() of this layout: 7 pubkeys, one u64, two u32,
+    // three u16 and one u8, plus one byte of padding to the 8 byte alignment
+    pub const SPACE: usize = 8 + 7 * 32 + 8 + 2 * 4 + 3 * 2 + 1 + 1;
+}
+
+impl From<LegacySystemConfig> for SystemConfig {
+    fn from(legacy: LegacySystemConfig) -> Self {
+        Self {
+            authority: legacy.authority,
+            max_products_per_shard: legacy.max_products_per_shard,
+            max_keywords_per_product: legacy.max_keywords_per_product,
+            chunk_size: legacy.chunk_size,
+            bloom_filter_size: legacy.bloom_filter_size,
+            merchant_deposit_required: legacy.merchant_deposit_required,
+            deposit_token_mint: legacy.deposit_token_mint,
+            platform_fee_rate: legacy.platform_fee_rate,
+            platform_fee_recipient: legacy.platform_fee_recipient,
+            auto_confirm_days: legacy.auto_confirm_days,
+            vault_program_id: legacy.vault_program_id,
+            vault_account: legacy.vault_account,
+            vault_token_account: legacy.vault_token_account,
+            platform_token_account: legacy.platform_token_account,
+            ..Self::default()
         }
     }
 }
 
 impl SystemConfig {
+    pub const SPACE: usize = 8 + Self::INIT_SPACE;
+
+    pub fn seeds() -> &'static [&'static [u8]] {
+        &[b"system_config"]
+    }
+
     /// 获取保证金要求（以代币最小单位计算）
     ///
     /// # 参数
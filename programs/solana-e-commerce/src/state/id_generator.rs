@@ -3,6 +3,9 @@ use anchor_lang::prelude::*;
 pub const DEFAULT_CHUNK_SIZE: u32 = 10_000;
 pub const MAX_CHUNKS_PER_MERCHANT: u32 = 100;
 pub const ID_CHUNK_BITMAP_SIZE: usize = 1250; // 10,000 bits / 8 = 1250 bytes
+pub const CHUNK_PREALLOCATE_THRESHOLD: f32 = 0.8; // Preallocate the next chunk above 80% utilization
+pub const MERCHANT_ID_RANGE: u64 = 10_000; // IDs reserved per merchant for its initial chunk
+pub const CHUNK_POOL_START: u64 = (u32::MAX as u64 + 1) * MERCHANT_ID_RANGE; // Later chunks start here
 
 #[account]
 #[derive(InitSpace)]
@@ -22,6 +25,18 @@ impl GlobalIdRoot {
     pub fn seeds() -> &'static [&'static [u8]] {
         &[b"global_id_root"]
     }
+
+    // First ID of the next chunk reserved from the pool
+    pub fn next_chunk_start(&self) -> u64 {
+        self.last_global_id.max(CHUNK_POOL_START)
+    }
+
+    // Reserve the ID range of a chunk after a merchant's initial one, returns its first ID
+    pub fn reserve_chunk_range(&mut self) -> u64 {
+        let start_id = self.next_chunk_start();
+        self.last_global_id = start_id + self.chunk_size as u64;
+        start_id
+    }
 }
 
 #[account]
@@ -40,6 +55,22 @@ impl MerchantIdAccount {
     pub fn seeds(merchant: &Pubkey) -> Vec<Vec<u8>> {
         vec![b"merchant_id".to_vec(), merchant.as_ref().to_vec()]
     }
+
+    // Make a chunk active, the previous active chunk is kept in unused_chunks for reuse
+    pub fn activate_chunk(&mut self, chunk_key: Pubkey) -> Result<()> {
+        if self.active_chunk == chunk_key {
+            return Ok(());
+        }
+        self.unused_chunks.retain(|key| *key != chunk_key);
+        require!(
+            self.unused_chunks.len() < MAX_CHUNKS_PER_MERCHANT as usize,
+            crate::error::ErrorCode::InvalidShardIndex
+        );
+        self.unused_chunks.push(self.active_chunk);
+        self.active_chunk = chunk_key;
+
+        Ok(())
+    }
 }
 
 #[account]
@@ -56,14 +87,48 @@ pub struct IdChunk {
 }
 
 impl IdChunk {
-    pub fn seeds(merchant_id: u32, chunk_index: u32) -> Vec<Vec<u8>> {
+    pub fn seeds(merchant: &Pubkey, chunk_index: u32) -> Vec<Vec<u8>> {
         vec![
             b"id_chunk".to_vec(),
-            merchant_id.to_le_bytes().to_vec(),
-            chunk_index.to_le_bytes().to_vec(),
+            merchant.as_ref().to_vec(),
+            Self::chunk_index_seed(chunk_index),
         ]
     }
 
+    // Chunk index seed, the initial chunk of a merchant uses a single byte
+    pub fn chunk_index_seed(chunk_index: u32) -> Vec<u8> {
+        if chunk_index == 0 {
+            vec![0]
+        } else {
+            chunk_index.to_le_bytes().to_vec()
+        }
+    }
+
+    // First ID of a merchant's initial chunk, later chunks come from GlobalIdRoot::reserve_chunk_range
+    //
+    // IDs map to chunks through the stored start_id and end_id only: chunks allocated by earlier
+    // layouts keep their ranges.
+    pub fn initial_start_id(merchant_id: u32) -> u64 {
+        merchant_id as u64 * MERCHANT_ID_RANGE
+    }
+
+    pub fn initialize(
+        &mut self,
+        merchant_id: u32,
+        chunk_index: u32,
+        start_id: u64,
+        chunk_size: u32,
+        bump: u8,
+    ) {
+        self.merchant_id = merchant_id;
+        self.chunk_index = chunk_index;
+        self.start_id = start_id;
+        self.end_id = self.start_id + chunk_size as u64 - 1;
+        self.next_available = 0;
+        self.initialize_bitmap();
+        self.bump = bump;
+    }
+
     pub fn capacity(&self) -> u64 {
         (self.end_id - self.start_id) + 1
     }
@@ -77,10 +142,11 @@ impl IdChunk {
         (self.bitmap[byte_index] >> bit_index) & 1 == 1
     }
 
+    // Local IDs past the chunk end are ignored, the bitmap is sized for the largest chunk
     pub fn mark_id_used(&mut self, local_id: u64) {
         let byte_index = (local_id / 8) as usize;
         let bit_index = (local_id % 8) as u8;
-        if byte_index < self.bitmap.len() {
+        if local_id < self.capacity() && byte_index < self.bitmap.len() {
             self.bitmap[byte_index] |= 1 << bit_index;
         }
     }
@@ -98,11 +164,57 @@ impl IdChunk {
         self.bitmap = vec![0u8; ID_CHUNK_BITMAP_SIZE];
     }
 
+    pub fn contains_id(&self, id: u64) -> bool {
+        id >= self.start_id && id <= self.end_id
+    }
+
+    // next_available is the lowest unused local ID, every ID below it is in use
     pub fn is_full(&self) -> bool {
         self.next_available >= self.capacity()
     }
 
+    // Move next_available to the lowest unused local ID at or above it
+    pub fn advance_next_available(&mut self) {
+        while self.next_available < self.capacity() && self.is_id_used(self.next_available) {
+            self.next_available += 1;
+        }
+    }
+
+    // Allocate the lowest unused ID, returns the global ID
+    pub fn allocate_next(&mut self) -> Option<u64> {
+        self.advance_next_available();
+        if self.is_full() {
+            return None;
+        }
+        let local_id = self.next_available;
+        self.mark_id_used(local_id);
+        self.advance_next_available();
+        Some(self.start_id + local_id)
+    }
+
+    // Release a global ID so it is handed out again, returns false when it was not in use
+    //
+    // Callers release an ID only once no account keyed by it is left (see delete_product).
+    pub fn release(&mut self, id: u64) -> Result<bool> {
+        require!(self.contains_id(id), crate::error::ErrorCode::InvalidId);
+        let local_id = id - self.start_id;
+        if !self.is_id_used(local_id) {
+            return Ok(false);
+        }
+        self.clear_id(local_id);
+        self.next_available = self.next_available.min(local_id);
+        Ok(true)
+    }
+
+    // Number of IDs in use
+    pub fn used_count(&self) -> u64 {
+        self.bitmap
+            .iter()
+            .map(|byte| byte.count_ones() as u64)
+            .sum()
+    }
+
     pub fn utilization_rate(&self) -> f32 {
-        self.next_available as f32 / self.capacity() as f32
+        self.used_count() as f32 / self.capacity() as f32
     }
 }
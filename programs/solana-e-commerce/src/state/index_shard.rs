@@ -0,0 +1,332 @@
+use super::ProductBase;
+use crate::error::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_lang::Space;
+
+// Entries allocated when a zero-copy index shard is created
+pub const INDEX_SHARD_INITIAL_CAPACITY: u32 = 128;
+// Entries added by each realloc of a full shard (stays within the per-instruction realloc limit)
+pub const INDEX_SHARD_GROWTH: u32 = 256;
+// Default maximum entries of a zero-copy index shard
+pub const DEFAULT_INDEX_SHARD_CAPACITY: u32 = 4_096;
+// Size of one (value, product_id) entry
+pub const INDEX_ENTRY_SIZE: usize = 16;
+// Bytes appended to a legacy Borsh shard when its migration starts, marking it read-only
+pub const LEGACY_SHARD_FROZEN_BYTES: usize = 1;
+
+// Index a zero-copy shard belongs to, also its PDA key
+//
+// Seeds: [b"index_shard", [kind], primary_seed, secondary_seed]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum IndexShardKey {
+    Keyword {
+        keyword: String,
+        shard_index: u32,
+    },
+    Price {
+        payment_token: Pubkey,
+        price_range_start: u64,
+        price_range_end: u64,
+    },
+    Sales {
+        sales_range_start: u32,
+        sales_range_end: u32,
+    },
+}
+
+impl IndexShardKey {
+    pub fn kind(&self) -> u8 {
+        match self {
+            IndexShardKey::Keyword { .. } => 0,
+            IndexShardKey::Price { .. } => 1,
+            IndexShardKey::Sales { .. } => 2,
+        }
+    }
+
+    // Keyword bytes or payment token mint, empty for sales shards
+    pub fn primary_seed(&self) -> Vec<u8> {
+        match self {
+            IndexShardKey::Keyword { keyword, .. } => keyword.as_bytes().to_vec(),
+            IndexShardKey::Price { payment_token, .. } => payment_token.to_bytes().to_vec(),
+            IndexShardKey::Sales { .. } => Vec::new(),
+        }
+    }
+
+    // Shard index or value range
+    pub fn secondary_seed(&self) -> Vec<u8> {
+        match self {
+            IndexShardKey::Keyword { shard_index, .. } => shard_index.to_le_bytes().to_vec(),
+            IndexShardKey::Price {
+                price_range_start,
+                price_range_end,
+                ..
+            } => [
+                price_range_start.to_le_bytes(),
+                price_range_end.to_le_bytes(),
+            ]
+            .concat(),
+            IndexShardKey::Sales {
+                sales_range_start,
+                sales_range_end,
+            } => [
+                sales_range_start.to_le_bytes(),
+                sales_range_end.to_le_bytes(),
+            ]
+            .concat(),
+        }
+    }
+
+    // Inclusive range of entry values, keyword entries always have value 0
+    pub fn value_range(&self) -> (u64, u64) {
+        match self {
+            IndexShardKey::Keyword { .. } => (0, 0),
+            IndexShardKey::Price {
+                price_range_start,
+                price_range_end,
+                ..
+            } => (*price_range_start, *price_range_end),
+            IndexShardKey::Sales {
+                sales_range_start,
+                sales_range_end,
+            } => (*sales_range_start as u64, *sales_range_end as u64),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            IndexShardKey::Keyword { keyword, .. } => {
+                require!(
+                    crate::utils::is_valid_keyword(keyword),
+                    ErrorCode::InvalidKeyword
+                );
+            }
+            IndexShardKey::Price {
+                price_range_start,
+                price_range_end,
+                ..
+            } => {
+                require!(
+                    price_range_start <= price_range_end,
+                    ErrorCode::InvalidPriceRange
+                );
+            }
+            IndexShardKey::Sales {
+                sales_range_start,
+                sales_range_end,
+            } => {
+                require!(
+                    sales_range_start <= sales_range_end,
+                    ErrorCode::InvalidSalesRange
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    // Entry value of a product in this index, checked against the product itself
+    pub fn entry_value(&self, product: &ProductBase) -> Result<u64> {
+        match self {
+            IndexShardKey::Keyword { keyword, .. } => {
+                require!(
+                    product.parse_keywords().contains(keyword),
+                    ErrorCode::InvalidKeyword
+                );
+                Ok(0)
+            }
+            IndexShardKey::Price { payment_token, .. } => {
+                require_keys_eq!(
+                    product.payment_token,
+                    *payment_token,
+                    ErrorCode::UnsupportedToken
+                );
+                Ok(product.price)
+            }
+            IndexShardKey::Sales { .. } => Ok(product.sales as u64),
+        }
+    }
+}
+
+// Whether a legacy Borsh shard has been frozen by migrate_index_shard
+//
+// Legacy shards keep the 8 + INIT_SPACE bytes they were created with until their migration
+// starts, legacy instructions must not write them afterwards.
+pub fn is_frozen_legacy_shard<T: Space>(info: &AccountInfo) -> bool {
+    info.data_len() > 8 + T::INIT_SPACE
+}
+
+// Zero-copy index shard header, followed in the account by `capacity` entries
+//
+// Entries are 16 bytes (value, product_id) kept sorted, where value is the price for price
+// shards, the sales for sales shards (updated by order settlement) and 0 for keyword shards.
+// They are read and written in place, adding a product never deserializes the whole shard.
+#[account(zero_copy)]
+pub struct IndexShard {
+    pub key: [u8; 32], // Keyword bytes or payment token mint
+    pub range_start: u64,
+    pub range_end: u64,
+    pub shard_index: u32,
+    pub len: u32,              // Entries in use
+    pub capacity: u32,         // Entries allocated
+    pub migrated_entries: u32, // Entries copied from the legacy Borsh shard
+    pub kind: u8,              // 0 = keyword, 1 = price, 2 = sales
+    pub key_len: u8,           // Keyword length
+    pub bump: u8,
+    pub _padding: [u8; 5],
+}
+
+impl IndexShard {
+    // Offset of the first entry in the account data
+    pub const ENTRIES_OFFSET: usize = 8 + std::mem::size_of::<IndexShard>();
+
+    // Account space for `capacity` entries
+    pub fn space(capacity: u32) -> usize {
+        Self::ENTRIES_OFFSET + capacity as usize * INDEX_ENTRY_SIZE
+    }
+
+    pub fn seeds(key: &IndexShardKey) -> Vec<Vec<u8>> {
+        vec![
+            b"index_shard".to_vec(),
+            vec![key.kind()],
+            key.primary_seed(),
+            key.secondary_seed(),
+        ]
+    }
+
+    pub fn initialize(&mut self, key: &IndexShardKey, capacity: u32, bump: u8) -> Result<()> {
+        key.validate()?;
+
+        self.key = [0; 32];
+        let primary = key.primary_seed();
+        self.key[..primary.len()].copy_from_slice(&primary);
+        self.key_len = match key {
+            IndexShardKey::Keyword { .. } => primary.len() as u8,
+            _ => 0,
+        };
+        (self.range_start, self.range_end) = key.value_range();
+        self.shard_index = match key {
+            IndexShardKey::Keyword { shard_index, .. } => *shard_index,
+            _ => 0,
+        };
+        self.len = 0;
+        self.capacity = capacity;
+        self.migrated_entries = 0;
+        self.kind = key.kind();
+        self.bump = bump;
+
+        Ok(())
+    }
+
+    // Keyword of a keyword shard
+    pub fn keyword(&self) -> String {
+        String::from_utf8_lossy(&self.key[..self.key_len as usize]).into_owned()
+    }
+
+    // Payment token mint of a price shard
+    pub fn payment_token(&self) -> Pubkey {
+        Pubkey::new_from_array(self.key)
+    }
+
+    pub fn contains_value(&self, value: u64) -> bool {
+        value >= self.range_start && value <= self.range_end
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len >= self.capacity
+    }
+}
+
+// Entry of a zero-copy index shard
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug,
+)]
+pub struct IndexEntry {
+    pub value: u64,
+    pub product_id: u64,
+}
+
+// In-place operations on the entry region of a zero-copy index shard
+//
+// `entries` is the account data after IndexShard::ENTRIES_OFFSET and `len` the entries in use.
+pub struct IndexEntries;
+
+impl IndexEntries {
+    pub fn get(entries: &[u8], index: usize) -> IndexEntry {
+        let offset = index * INDEX_ENTRY_SIZE;
+        let mut value = [0u8; 8];
+        let mut product_id = [0u8; 8];
+        value.copy_from_slice(&entries[offset..offset + 8]);
+        product_id.copy_from_slice(&entries[offset + 8..offset + 16]);
+        IndexEntry {
+            value: u64::from_le_bytes(value),
+            product_id: u64::from_le_bytes(product_id),
+        }
+    }
+
+    fn set(entries: &mut [u8], index: usize, entry: IndexEntry) {
+        let offset = index * INDEX_ENTRY_SIZE;
+        entries[offset..offset + 8].copy_from_slice(&entry.value.to_le_bytes());
+        entries[offset + 8..offset + 16].copy_from_slice(&entry.product_id.to_le_bytes());
+    }
+
+    // Position of a product, whatever value it was indexed with
+    pub fn position(entries: &[u8], len: usize, product_id: u64) -> Option<usize> {
+        (0..len).find(|&index| Self::get(entries, index).product_id == product_id)
+    }
+
+    // Insert keeping (value, product_id) order, returns false when the product is already present
+    //
+    // The entry region must have room for len + 1 entries.
+    pub fn insert(entries: &mut [u8], len: usize, entry: IndexEntry) -> bool {
+        if Self::position(entries, len, entry.product_id).is_some() {
+            return false;
+        }
+
+        // Binary search for the insert position
+        let (mut low, mut high) = (0, len);
+        while low < high {
+            let middle = (low + high) / 2;
+            if Self::get(entries, middle) < entry {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+
+        entries.copy_within(
+            low * INDEX_ENTRY_SIZE..len * INDEX_ENTRY_SIZE,
+            (low + 1) * INDEX_ENTRY_SIZE,
+        );
+        Self::set(entries, low, entry);
+
+        true
+    }
+
+    // Remove a product, returns false when it is not present
+    pub fn remove(entries: &mut [u8], len: usize, product_id: u64) -> bool {
+        let Some(index) = Self::position(entries, len, product_id) else {
+            return false;
+        };
+
+        entries.copy_within(
+            (index + 1) * INDEX_ENTRY_SIZE..len * INDEX_ENTRY_SIZE,
+            index * INDEX_ENTRY_SIZE,
+        );
+
+        true
+    }
+
+    // Entries with a value in [min, max], in (value, product_id) order
+    pub fn in_range(entries: &[u8], len: usize, min: u64, max: u64) -> Vec<IndexEntry> {
+        (0..len)
+            .map(|index| Self::get(entries, index))
+            .filter(|entry| entry.value >= min && entry.value <= max)
+            .collect()
+    }
+
+    pub fn product_ids(entries: &[u8], len: usize) -> Vec<u64> {
+        (0..len)
+            .map(|index| Self::get(entries, index).product_id)
+            .collect()
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error::ErrorCode;
+use crate::utils::{CountingBloomFilter, MAX_BLOOM_FILTER_SIZE};
 use anchor_lang::prelude::*;
 
 #[account]
@@ -10,7 +11,8 @@ pub struct KeywordRoot {
     pub first_shard: Pubkey,
     pub last_shard: Pubkey,
     pub total_products: u32,
-    pub bloom_filter: [u8; super::BLOOM_FILTER_SIZE],
+    #[max_len(MAX_BLOOM_FILTER_SIZE)]
+    pub bloom_filter: Vec<u8>, // Counting bloom filter, two 4-bit counters per byte
     pub bump: u8,
 }
 
@@ -19,7 +21,13 @@ impl KeywordRoot {
         vec![b"keyword_root".to_vec(), keyword.as_bytes().to_vec()]
     }
 
-    pub fn initialize(&mut self, keyword: String, bump: u8) -> Result<()> {
+    // Account space with a bloom filter of the configured size
+    pub fn space(bloom_filter_size: u16) -> usize {
+        8 + Self::INIT_SPACE - MAX_BLOOM_FILTER_SIZE
+            + CountingBloomFilter::clamp_size(bloom_filter_size)
+    }
+
+    pub fn initialize(&mut self, keyword: String, bloom_filter_size: u16, bump: u8) -> Result<()> {
         require!(
             keyword.len() <= super::MAX_KEYWORD_LENGTH,
             ErrorCode::InvalidKeywordLength
@@ -30,7 +38,7 @@ impl KeywordRoot {
         self.first_shard = Pubkey::default();
         self.last_shard = Pubkey::default();
         self.total_products = 0;
-        self.bloom_filter = [0; super::BLOOM_FILTER_SIZE];
+        self.bloom_filter = vec![0; CountingBloomFilter::clamp_size(bloom_filter_size)];
         self.bump = bump;
 
         Ok(())
@@ -46,35 +54,59 @@ impl KeywordRoot {
         self.total_shards += 1;
     }
 
+    // Unlink a shard that was merged into its neighbor
+    pub fn remove_shard(&mut self, shard_key: Pubkey, neighbor_key: Pubkey) {
+        if self.first_shard == shard_key {
+            self.first_shard = neighbor_key;
+        }
+        if self.last_shard == shard_key {
+            self.last_shard = neighbor_key;
+        }
+        self.total_shards = self.total_shards.saturating_sub(1);
+    }
+
     pub fn update_bloom_filter(&mut self, product_id: u64, add: bool) {
-        let hash1 = (product_id as usize) % (super::BLOOM_FILTER_SIZE * 8);
-        let hash2 = ((product_id * 31) as usize) % (super::BLOOM_FILTER_SIZE * 8);
-        let hash3 = ((product_id * 37) as usize) % (super::BLOOM_FILTER_SIZE * 8);
-
-        for hash in [hash1, hash2, hash3] {
-            let byte_index = hash / 8;
-            let bit_index = hash % 8;
-            if add {
-                self.bloom_filter[byte_index] |= 1 << bit_index;
-            } else {
-                self.bloom_filter[byte_index] &= !(1 << bit_index);
-            }
+        if add {
+            CountingBloomFilter::add(&mut self.bloom_filter, product_id);
+        } else {
+            CountingBloomFilter::remove(&mut self.bloom_filter, product_id);
         }
     }
 
     pub fn might_contain(&self, product_id: u64) -> bool {
-        let hash1 = (product_id as usize) % (super::BLOOM_FILTER_SIZE * 8);
-        let hash2 = ((product_id * 31) as usize) % (super::BLOOM_FILTER_SIZE * 8);
-        let hash3 = ((product_id * 37) as usize) % (super::BLOOM_FILTER_SIZE * 8);
-
-        for hash in [hash1, hash2, hash3] {
-            let byte_index = hash / 8;
-            let bit_index = hash % 8;
-            if (self.bloom_filter[byte_index] >> bit_index) & 1 == 0 {
-                return false;
-            }
+        CountingBloomFilter::might_contain(&self.bloom_filter, product_id)
+    }
+}
+
+// Keyword root layout before the counting bloom filter, a fixed 256-byte bit filter
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyKeywordRoot {
+    pub keyword: String,
+    pub total_shards: u8,
+    pub first_shard: Pubkey,
+    pub last_shard: Pubkey,
+    pub total_products: u32,
+    pub bloom_filter: [u8; super::BLOOM_FILTER_SIZE],
+    pub bump: u8,
+}
+
+impl LegacyKeywordRoot {
+    pub const SPACE: usize = 8 + 4 + 128 + 1 + 32 + 32 + 4 + super::BLOOM_FILTER_SIZE + 1;
+
+    // Whether `data` holds a legacy root rather than a current one of the same size
+    pub fn is_legacy(data: &[u8]) -> bool {
+        if data.len() != Self::SPACE || data[..8] != *KeywordRoot::DISCRIMINATOR {
+            return false;
+        }
+        match KeywordRoot::try_deserialize(&mut &data[..]) {
+            Ok(root) => KeywordRoot::space(root.bloom_filter.len() as u16) != data.len(),
+            Err(_) => true,
         }
-        true
+    }
+
+    pub fn try_from_data(data: &[u8]) -> Result<Self> {
+        require!(Self::is_legacy(data), ErrorCode::AccountAlreadyMigrated);
+        Self::deserialize(&mut &data[8..]).map_err(|_| ErrorCode::InvalidLegacyAccount.into())
     }
 }
 
@@ -137,7 +169,7 @@ impl KeywordShard {
         if !self.product_ids.contains(&product_id) {
             self.product_ids.push(product_id);
             self.update_min_max(product_id);
-            self.update_bloom_summary(product_id, true);
+            self.update_bloom_summary(product_id);
         }
 
         Ok(())
@@ -174,26 +206,32 @@ impl KeywordShard {
         }
     }
 
-    fn update_bloom_summary(&mut self, product_id: u64, add: bool) {
+    // Summary bit positions of a product ID
+    fn bloom_summary_bits(product_id: u64) -> [usize; 2] {
         let hash1 = (product_id as usize) % (super::BLOOM_SUMMARY_SIZE * 8);
-        let hash2 = ((product_id * 31) as usize) % (super::BLOOM_SUMMARY_SIZE * 8);
-
-        for hash in [hash1, hash2] {
-            let byte_index = hash / 8;
-            let bit_index = hash % 8;
-            if add {
-                self.bloom_summary[byte_index] |= 1 << bit_index;
-            } else {
-                self.bloom_summary[byte_index] &= !(1 << bit_index);
-            }
+        let hash2 = (product_id.wrapping_mul(31) as usize) % (super::BLOOM_SUMMARY_SIZE * 8);
+        [hash1, hash2]
+    }
+
+    // Bits are only set here, removals rebuild the summary from the remaining products
+    fn update_bloom_summary(&mut self, product_id: u64) {
+        for hash in Self::bloom_summary_bits(product_id) {
+            self.bloom_summary[hash / 8] |= 1 << (hash % 8);
         }
     }
 
+    // False when the product is certainly not in this shard
+    pub fn might_contain(&self, product_id: u64) -> bool {
+        Self::bloom_summary_bits(product_id)
+            .iter()
+            .all(|&hash| self.bloom_summary[hash / 8] & (1 << (hash % 8)) != 0)
+    }
+
     fn recalculate_bloom_summary(&mut self) {
         self.bloom_summary = [0; super::BLOOM_SUMMARY_SIZE];
         let product_ids = self.product_ids.clone();
         for product_id in product_ids {
-            self.update_bloom_summary(product_id, true);
+            self.update_bloom_summary(product_id);
         }
     }
 
@@ -206,10 +244,30 @@ impl KeywordShard {
     }
 
     pub fn needs_split(&self) -> bool {
-        self.utilization_rate() > 0.8
+        self.utilization_rate() > crate::utils::SHARD_SPLIT_THRESHOLD
     }
 
     pub fn needs_merge(&self) -> bool {
-        self.utilization_rate() < 0.25
+        self.utilization_rate() < crate::utils::SHARD_MERGE_THRESHOLD
+    }
+
+    pub fn can_absorb(&self, other: &KeywordShard) -> bool {
+        self.product_ids.len() + other.product_ids.len() <= super::MAX_PRODUCTS_PER_SHARD
+    }
+
+    // Move all products of another shard into this one, rebuilding min/max and bloom summary
+    pub fn absorb(&mut self, other: &KeywordShard) -> Result<()> {
+        require!(self.can_absorb(other), ErrorCode::ShardIsFull);
+
+        for &product_id in &other.product_ids {
+            if !self.product_ids.contains(&product_id) {
+                self.product_ids.push(product_id);
+            }
+        }
+
+        self.recalculate_min_max();
+        self.recalculate_bloom_summary();
+
+        Ok(())
     }
 }
@@ -0,0 +1,156 @@
+use super::{ProductBase, ProductSales};
+use anchor_lang::prelude::*;
+
+/// 排行榜容量
+pub const LEADERBOARD_SIZE: usize = 50;
+/// 排行榜中商品名称的最大长度
+pub const LEADERBOARD_NAME_LENGTH: usize = 32;
+
+/// 排行榜范围
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum LeaderboardScope {
+    Global,               // 全局排行榜
+    Category(String),     // 分类（商品关键词）排行榜
+    PaymentToken(Pubkey), // 支付代币排行榜
+}
+
+impl LeaderboardScope {
+    /// 范围类型
+    pub fn kind(&self) -> u8 {
+        match self {
+            LeaderboardScope::Global => 0,
+            LeaderboardScope::Category(_) => 1,
+            LeaderboardScope::PaymentToken(_) => 2,
+        }
+    }
+
+    /// 范围键（全局为空，分类为关键词，支付代币为mint）
+    pub fn key_bytes(&self) -> Vec<u8> {
+        match self {
+            LeaderboardScope::Global => Vec::new(),
+            LeaderboardScope::Category(keyword) => keyword.as_bytes().to_vec(),
+            LeaderboardScope::PaymentToken(mint) => mint.to_bytes().to_vec(),
+        }
+    }
+
+    /// 商品是否属于该范围
+    pub fn includes(&self, product: &ProductBase) -> bool {
+        match self {
+            LeaderboardScope::Global => true,
+            LeaderboardScope::Category(keyword) => product.parse_keywords().contains(keyword),
+            LeaderboardScope::PaymentToken(mint) => product.payment_token == *mint,
+        }
+    }
+}
+
+/// 畅销商品排行榜 - 按销量降序保存前LEADERBOARD_SIZE个商品，销量变化时增量更新
+#[account]
+#[derive(InitSpace)]
+pub struct Leaderboard {
+    pub kind: u8, // 范围类型：0=全局, 1=分类, 2=支付代币
+    #[max_len(32)]
+    pub category: String, // 分类关键词（仅分类排行榜）
+    pub payment_token: Pubkey, // 支付代币（仅支付代币排行榜）
+    #[max_len(50)]
+    pub entries: Vec<ProductSales>, // 排行榜条目
+    pub updated_at: i64, // 更新时间
+    pub bump: u8, // PDA bump
+}
+
+impl Leaderboard {
+    /// 生成排行榜PDA种子
+    pub fn seeds(scope: &LeaderboardScope) -> Vec<Vec<u8>> {
+        vec![
+            b"leaderboard".to_vec(),
+            vec![scope.kind()],
+            scope.key_bytes(),
+        ]
+    }
+
+    /// 初始化排行榜
+    pub fn initialize(&mut self, scope: &LeaderboardScope, bump: u8) -> Result<()> {
+        self.kind = scope.kind();
+        self.category = String::new();
+        self.payment_token = Pubkey::default();
+        match scope {
+            LeaderboardScope::Global => {}
+            LeaderboardScope::Category(keyword) => {
+                require!(
+                    crate::utils::is_valid_keyword(keyword),
+                    crate::error::ErrorCode::InvalidKeyword
+                );
+                self.category = keyword.clone();
+            }
+            LeaderboardScope::PaymentToken(mint) => self.payment_token = *mint,
+        }
+        self.entries = Vec::new();
+        self.updated_at = Clock::get()?.unix_timestamp;
+        self.bump = bump;
+
+        Ok(())
+    }
+
+    /// 排行榜范围
+    pub fn scope(&self) -> LeaderboardScope {
+        match self.kind {
+            1 => LeaderboardScope::Category(self.category.clone()),
+            2 => LeaderboardScope::PaymentToken(self.payment_token),
+            _ => LeaderboardScope::Global,
+        }
+    }
+
+    /// 按商品当前销量更新排行榜，返回商品是否在榜
+    pub fn update_product(&mut self, product: &ProductBase, now: i64) -> bool {
+        self.remove_product(product.id);
+        self.updated_at = now;
+        if product.sales == 0 {
+            return false;
+        }
+
+        // 按销量降序，同销量按商品ID升序
+        let insert_pos = self
+            .entries
+            .partition_point(|item| (item.sales, product.id) > (product.sales, item.product_id));
+        if insert_pos >= LEADERBOARD_SIZE {
+            return false;
+        }
+
+        let mut name = product.name.clone();
+        if name.len() > LEADERBOARD_NAME_LENGTH {
+            let mut end = LEADERBOARD_NAME_LENGTH;
+            while !name.is_char_boundary(end) {
+                end -= 1;
+            }
+            name.truncate(end);
+        }
+
+        self.entries.insert(
+            insert_pos,
+            ProductSales {
+                product_id: product.id,
+                merchant: product.merchant,
+                name,
+                price: product.price,
+                sales: product.sales,
+                last_update: now,
+            },
+        );
+        self.entries.truncate(LEADERBOARD_SIZE);
+
+        true
+    }
+
+    /// 移除商品，返回是否存在
+    pub fn remove_product(&mut self, product_id: u64) -> bool {
+        if let Some(index) = self
+            .entries
+            .iter()
+            .position(|item| item.product_id == product_id)
+        {
+            self.entries.remove(index);
+            true
+        } else {
+            false
+        }
+    }
+}
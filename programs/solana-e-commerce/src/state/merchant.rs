@@ -1,4 +1,5 @@
 use crate::error::ErrorCode;
+use crate::state::{MerchantOrderStats, MerchantProductList, OrderCounters};
 use anchor_lang::prelude::*;
 
 #[account]
@@ -103,6 +104,12 @@ impl Merchant {
         Ok(())
     }
 
+    pub fn remove_sales(&mut self, sales: u64) -> Result<()> {
+        self.total_sales = self.total_sales.saturating_sub(sales);
+        self.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
     pub fn set_active(&mut self, active: bool) -> Result<()> {
         self.is_active = active;
         self.updated_at = Clock::get()?.unix_timestamp;
@@ -195,12 +202,96 @@ pub struct MerchantInfo {
     pub description: String,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+/// 商户统计中支付代币的数量上限，与支付配置支持的代币数量上限一致
+pub const MAX_MERCHANT_STATS_TOKENS: usize = 10;
+
+/// 商户在一种支付代币下的订单统计
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct MerchantTokenStats {
+    pub mint: Pubkey,            // 支付代币mint
+    pub counters: OrderCounters, // 订单计数与收入
+    pub avg_order_value: u64,    // 平均订单金额（已送达订单）
+    pub refund_rate_bps: u16,    // 退款率（基点）
+}
+
+/// 商户统计
+///
+/// 在售/下架商品数来自商家商品目录，目录建立前创建的商品只计入 product_count；
+/// 订单统计来自各支付代币的 MerchantOrderStats，开启统计前创建的订单不计入。
+/// 原有字段保持原来的顺序，新增字段追加在 last_updated 之后
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
 pub struct MerchantStats {
-    pub product_count: u64,
-    pub total_sales: u64,
-    pub active_products: u64,
+    pub product_count: u64,   // 商品总数
+    pub total_sales: u64,     // 已送达的商品件数（送达后退款扣回）
+    pub active_products: u64, // 在售商品数
+    /// 已弃用：始终为0，仅为兼容旧客户端保留，将在后续版本移除
     pub total_keywords: u64,
+    /// 已弃用：始终为0，商品以不同代币定价，没有统一的均价；请使用 tokens 中各代币的
+    /// avg_order_value，将在后续版本移除
     pub avg_product_price: u64,
-    pub last_updated: i64,
+    pub last_updated: i64,               // 商户信息更新时间
+    pub inactive_products: u64,          // 下架商品数
+    pub total_orders: u64,               // 总订单数
+    pub pending_orders: u64,             // 待处理订单数
+    pub shipped_orders: u64,             // 已发货订单数
+    pub delivered_orders: u64,           // 已送达订单数
+    pub refunded_orders: u64,            // 已退款订单数
+    pub refund_rate_bps: u16,            // 退款率（基点）
+    pub avg_ship_time: u64,              // 下单到发货的平均时长（秒）
+    pub tokens: Vec<MerchantTokenStats>, // 各支付代币的订单统计
+}
+
+impl MerchantStats {
+    pub fn new(
+        merchant: &Merchant,
+        catalog: Option<&MerchantProductList>,
+        order_stats: &[MerchantOrderStats],
+    ) -> Self {
+        let mut totals = OrderCounters::default();
+        let mut shipments: u64 = 0;
+        let mut total_ship_time: u64 = 0;
+        let mut tokens = Vec::with_capacity(order_stats.len());
+        for stats in order_stats {
+            let counters = &stats.counters;
+            totals.total_orders = totals.total_orders.saturating_add(counters.total_orders);
+            totals.pending_orders = totals
+                .pending_orders
+                .saturating_add(counters.pending_orders);
+            totals.shipped_orders = totals
+                .shipped_orders
+                .saturating_add(counters.shipped_orders);
+            totals.delivered_orders = totals
+                .delivered_orders
+                .saturating_add(counters.delivered_orders);
+            totals.refunded_orders = totals
+                .refunded_orders
+                .saturating_add(counters.refunded_orders);
+            shipments = shipments.saturating_add(stats.shipments);
+            total_ship_time = total_ship_time.saturating_add(stats.total_ship_time);
+            tokens.push(MerchantTokenStats {
+                mint: stats.mint,
+                counters: counters.clone(),
+                avg_order_value: counters.avg_order_value(),
+                refund_rate_bps: counters.refund_rate_bps(),
+            });
+        }
+
+        Self {
+            product_count: merchant.product_count,
+            total_sales: merchant.total_sales,
+            active_products: catalog.map_or(0, |catalog| catalog.active_count),
+            total_keywords: 0,
+            avg_product_price: 0,
+            last_updated: merchant.updated_at,
+            inactive_products: catalog.map_or(0, |catalog| catalog.inactive_count),
+            total_orders: totals.total_orders,
+            pending_orders: totals.pending_orders,
+            shipped_orders: totals.shipped_orders,
+            delivered_orders: totals.delivered_orders,
+            refunded_orders: totals.refunded_orders,
+            refund_rate_bps: totals.refund_rate_bps(),
+            avg_ship_time: total_ship_time.checked_div(shipments).unwrap_or_default(),
+            tokens,
+        }
+    }
 }
@@ -0,0 +1,142 @@
+use anchor_lang::prelude::*;
+
+/// 每个商品目录分页可容纳的商品数量
+pub const MERCHANT_PRODUCT_PAGE_SIZE: usize = 50;
+
+/// 商家商品目录 - 记录商家在售/下架商品数量，并为两条分页链分配追加位置
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantProductList {
+    pub merchant: Pubkey,       // 商家地址
+    pub active_count: u64,      // 在售商品数量
+    pub inactive_count: u64,    // 下架商品数量
+    pub active_appended: u64,   // 在售链累计追加次数（决定下一个追加分页）
+    pub inactive_appended: u64, // 下架链累计追加次数（决定下一个追加分页）
+    pub created_at: i64,        // 创建时间
+    pub updated_at: i64,        // 更新时间
+    pub bump: u8,               // PDA bump
+}
+
+impl MerchantProductList {
+    /// 生成商家商品目录PDA种子
+    pub fn seeds(merchant: &Pubkey) -> Vec<Vec<u8>> {
+        vec![
+            b"merchant_product_list".to_vec(),
+            merchant.to_bytes().to_vec(),
+        ]
+    }
+
+    /// 初始化商家商品目录
+    pub fn initialize(&mut self, merchant: Pubkey, bump: u8) -> Result<()> {
+        self.merchant = merchant;
+        self.active_count = 0;
+        self.inactive_count = 0;
+        self.active_appended = 0;
+        self.inactive_appended = 0;
+        self.created_at = Clock::get()?.unix_timestamp;
+        self.updated_at = Clock::get()?.unix_timestamp;
+        self.bump = bump;
+        Ok(())
+    }
+
+    /// 下一次追加所在的分页序号
+    pub fn next_page_index(&self, is_active: bool) -> u64 {
+        let appended = if is_active {
+            self.active_appended
+        } else {
+            self.inactive_appended
+        };
+        appended / MERCHANT_PRODUCT_PAGE_SIZE as u64
+    }
+
+    /// 记录一次追加
+    pub fn record_append(&mut self, is_active: bool) -> Result<()> {
+        if is_active {
+            self.active_appended = self.active_appended.saturating_add(1);
+            self.active_count = self.active_count.saturating_add(1);
+        } else {
+            self.inactive_appended = self.inactive_appended.saturating_add(1);
+            self.inactive_count = self.inactive_count.saturating_add(1);
+        }
+        self.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// 记录一次移除
+    pub fn record_remove(&mut self, is_active: bool) -> Result<()> {
+        if is_active {
+            self.active_count = self.active_count.saturating_sub(1);
+        } else {
+            self.inactive_count = self.inactive_count.saturating_sub(1);
+        }
+        self.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+}
+
+/// 商家商品分页 - 在售链与下架链各自按分页序号串联
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantProductPage {
+    pub merchant: Pubkey, // 商家地址
+    pub is_active: bool,  // true=在售链, false=下架链
+    pub page_index: u64,  // 分页序号
+    #[max_len(50)]
+    pub product_ids: Vec<u64>, // 商品ID列表
+    pub bump: u8,         // PDA bump
+}
+
+impl MerchantProductPage {
+    /// 生成商家商品分页PDA种子
+    pub fn seeds(merchant: &Pubkey, is_active: bool, page_index: u64) -> Vec<Vec<u8>> {
+        let prefix: &[u8] = if is_active {
+            b"merchant_active_products"
+        } else {
+            b"merchant_inactive_products"
+        };
+        vec![
+            prefix.to_vec(),
+            merchant.to_bytes().to_vec(),
+            page_index.to_le_bytes().to_vec(),
+        ]
+    }
+
+    /// 首次使用时初始化分页
+    pub fn initialize_if_needed(
+        &mut self,
+        merchant: Pubkey,
+        is_active: bool,
+        page_index: u64,
+        bump: u8,
+    ) {
+        if self.merchant == Pubkey::default() {
+            self.merchant = merchant;
+            self.is_active = is_active;
+            self.page_index = page_index;
+            self.product_ids = Vec::new();
+            self.bump = bump;
+        }
+    }
+
+    /// 追加商品ID
+    pub fn add_product(&mut self, product_id: u64) -> Result<()> {
+        require!(
+            self.product_ids.len() < MERCHANT_PRODUCT_PAGE_SIZE,
+            crate::error::ErrorCode::ShardIsFull
+        );
+        if !self.product_ids.contains(&product_id) {
+            self.product_ids.push(product_id);
+        }
+        Ok(())
+    }
+
+    /// 移除商品ID，返回是否存在
+    pub fn remove_product(&mut self, product_id: u64) -> bool {
+        if let Some(index) = self.product_ids.iter().position(|&id| id == product_id) {
+            self.product_ids.remove(index);
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -1,25 +1,43 @@
 pub mod id_generator;
+pub mod index_shard;
 pub mod keyword_index;
+pub mod leaderboard;
 pub mod merchant;
 pub mod merchant_order;
 pub mod merchant_order_count;
+pub mod merchant_product_list;
 pub mod order;
+pub mod order_stats;
 pub mod payment;
 pub mod price_index;
 pub mod product;
+pub mod product_variant;
+pub mod recency_index;
+pub mod reputation;
+pub mod review;
 pub mod sales_index;
+pub mod trending;
 pub mod user_purchase_count;
 
 pub use id_generator::*;
+pub use index_shard::*;
 pub use keyword_index::*;
+pub use leaderboard::*;
 pub use merchant::*;
 pub use merchant_order::*;
 pub use merchant_order_count::*;
+pub use merchant_product_list::*;
 pub use order::*;
+pub use order_stats::*;
 pub use payment::*;
 pub use price_index::*;
 pub use product::*;
+pub use product_variant::*;
+pub use recency_index::*;
+pub use reputation::*;
+pub use review::*;
 pub use sales_index::*;
+pub use trending::*;
 pub use user_purchase_count::*;
 
 // 系统常量
@@ -45,9 +45,75 @@ pub struct Order {
     pub transaction_signature: String, // 支付交易签名
     pub merchant_order_pda: Pubkey,    // 关联的商家订单PDA
     pub bump: u8,                      // PDA bump
+    pub variant_id: Option<u32>,       // 商品规格ID（商品没有规格时为None）
+}
+
+// 商品规格上线前的订单布局（没有variant_id）
+//
+// 订单的字符串字段只写入一次，旧订单末尾的空余字节为零，按当前布局读取时variant_id为None
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyOrder {
+    pub buyer: Pubkey,
+    pub merchant: Pubkey,
+    pub product_id: u64,
+    pub quantity: u32,
+    pub price: u64,
+    pub total_amount: u64,
+    pub payment_token: Pubkey,
+    pub status: OrderManagementStatus,
+    pub shipping_address: String,
+    pub notes: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub confirmed_at: Option<i64>,
+    pub shipped_at: Option<i64>,
+    pub delivered_at: Option<i64>,
+    pub refunded_at: Option<i64>,
+    pub refund_requested_at: Option<i64>,
+    pub refund_reason: String,
+    pub tracking_number: String,
+    pub transaction_signature: String,
+    pub merchant_order_pda: Pubkey,
+    pub bump: u8,
+}
+
+impl LegacyOrder {
+    pub const SPACE: usize = Order::SPACE - 5;
+}
+
+impl From<LegacyOrder> for Order {
+    fn from(legacy: LegacyOrder) -> Self {
+        Self {
+            buyer: legacy.buyer,
+            merchant: legacy.merchant,
+            product_id: legacy.product_id,
+            quantity: legacy.quantity,
+            price: legacy.price,
+            total_amount: legacy.total_amount,
+            payment_token: legacy.payment_token,
+            status: legacy.status,
+            shipping_address: legacy.shipping_address,
+            notes: legacy.notes,
+            created_at: legacy.created_at,
+            updated_at: legacy.updated_at,
+            confirmed_at: legacy.confirmed_at,
+            shipped_at: legacy.shipped_at,
+            delivered_at: legacy.delivered_at,
+            refunded_at: legacy.refunded_at,
+            refund_requested_at: legacy.refund_requested_at,
+            refund_reason: legacy.refund_reason,
+            tracking_number: legacy.tracking_number,
+            transaction_signature: legacy.transaction_signature,
+            merchant_order_pda: legacy.merchant_order_pda,
+            bump: legacy.bump,
+            variant_id: None,
+        }
+    }
 }
 
 impl Order {
+    pub const SPACE: usize = 8 + Self::INIT_SPACE;
+
     // PDA种子 - 使用买家地址和购买序列号确保唯一性
     pub fn seeds(buyer: &Pubkey, buyer_purchase_sequence: u64) -> Vec<Vec<u8>> {
         vec![
@@ -88,6 +154,11 @@ impl Order {
         self.status == OrderManagementStatus::Pending
     }
 
+    // 检查在at时刻发货（或at时刻仍未发货）是否超过发货期限
+    pub fn is_past_ship_deadline(&self, at: i64) -> bool {
+        at - self.created_at > super::SHIP_DEADLINE_SECONDS
+    }
+
     // 检查订单是否应该自动确认收货
     pub fn should_auto_confirm(&self, auto_confirm_days: u32, current_time: i64) -> bool {
         // 只有已发货状态的订单才能自动确认
@@ -119,6 +190,20 @@ impl Order {
         Ok(())
     }
 
+    // 已送达订单的售后退款（由商户同意）
+    pub fn refund_after_delivery(&mut self, current_time: i64) -> Result<()> {
+        require!(
+            self.status == OrderManagementStatus::Delivered,
+            ErrorCode::OrderCannotBeRefunded
+        );
+
+        self.status = OrderManagementStatus::Refunded;
+        self.refunded_at = Some(current_time);
+        self.updated_at = current_time;
+
+        Ok(())
+    }
+
     // 更新订单状态
     pub fn update_status(
         &mut self,
@@ -170,7 +255,7 @@ pub struct OrderStats {
     pub shipped_orders: u64,   // 已发货订单数
     pub delivered_orders: u64, // 已送达订单数
     pub refunded_orders: u64,  // 已退款订单数
-    pub total_revenue: u64,    // 总收入
+    pub total_revenue: u64,    // 总收入（跨支付代币累加，按代币的收入见 TokenOrderStats）
     pub bump: u8,
 }
 
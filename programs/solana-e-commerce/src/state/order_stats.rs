@@ -0,0 +1,206 @@
+use crate::error::ErrorCode;
+use crate::state::{recency_day, Order, OrderManagementStatus};
+use anchor_lang::prelude::*;
+
+/// 订单计数（同一支付代币内）
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq, InitSpace)]
+pub struct OrderCounters {
+    pub total_orders: u64,     // 总订单数
+    pub pending_orders: u64,   // 待处理订单数
+    pub shipped_orders: u64,   // 已发货订单数
+    pub delivered_orders: u64, // 已送达订单数
+    pub refunded_orders: u64,  // 已退款订单数
+    pub revenue: u64,          // 已送达订单总金额
+}
+
+impl OrderCounters {
+    fn status_count(&mut self, status: &OrderManagementStatus) -> &mut u64 {
+        match status {
+            OrderManagementStatus::Pending => &mut self.pending_orders,
+            OrderManagementStatus::Shipped => &mut self.shipped_orders,
+            OrderManagementStatus::Delivered => &mut self.delivered_orders,
+            OrderManagementStatus::Refunded => &mut self.refunded_orders,
+        }
+    }
+
+    pub fn record_new_order(&mut self, status: &OrderManagementStatus, amount: u64) {
+        self.total_orders = self.total_orders.saturating_add(1);
+        let count = self.status_count(status);
+        *count = count.saturating_add(1);
+        if *status == OrderManagementStatus::Delivered {
+            self.revenue = self.revenue.saturating_add(amount);
+        }
+    }
+
+    pub fn record_status_change(
+        &mut self,
+        old_status: &OrderManagementStatus,
+        new_status: &OrderManagementStatus,
+        amount: u64,
+    ) {
+        let count = self.status_count(old_status);
+        *count = count.saturating_sub(1);
+        if *old_status == OrderManagementStatus::Delivered {
+            self.revenue = self.revenue.saturating_sub(amount);
+        }
+
+        let count = self.status_count(new_status);
+        *count = count.saturating_add(1);
+        if *new_status == OrderManagementStatus::Delivered {
+            self.revenue = self.revenue.saturating_add(amount);
+        }
+    }
+
+    /// 退款率（基点），退款订单数占总订单数
+    pub fn refund_rate_bps(&self) -> u16 {
+        rate_bps(self.refunded_orders, self.total_orders)
+    }
+
+    /// 平均订单金额，按已送达订单计算
+    pub fn avg_order_value(&self) -> u64 {
+        self.revenue
+            .checked_div(self.delivered_orders)
+            .unwrap_or_default()
+    }
+}
+
+/// `part` 占 `total` 的基点数，`total` 为0时为0
+pub fn rate_bps(part: u64, total: u64) -> u16 {
+    (part as u128 * 10_000)
+        .checked_div(total as u128)
+        .map_or(0, |rate| rate.min(10_000) as u16)
+}
+
+/// 按支付代币的订单统计
+///
+/// 首个订单创建时开启，只统计开启之后创建的订单，各状态计数与订单状态保持一致
+#[account]
+#[derive(InitSpace)]
+pub struct TokenOrderStats {
+    pub mint: Pubkey,            // 支付代币mint
+    pub tracking_since: i64,     // 开启统计的时间
+    pub counters: OrderCounters, // 订单计数
+    pub bump: u8,
+}
+
+impl TokenOrderStats {
+    pub fn seeds(mint: &Pubkey) -> Vec<Vec<u8>> {
+        vec![b"token_order_stats".to_vec(), mint.as_ref().to_vec()]
+    }
+
+    /// 账户刚创建时记录代币与开启时间
+    pub fn open(&mut self, mint: Pubkey, now: i64, bump: u8) {
+        if self.mint == Pubkey::default() {
+            self.mint = mint;
+            self.tracking_since = now;
+            self.counters = OrderCounters::default();
+            self.bump = bump;
+        }
+    }
+
+    /// 订单的计数，开启统计前创建的订单不计入
+    pub fn counters_for(&mut self, order: &Order) -> Option<&mut OrderCounters> {
+        (order.created_at >= self.tracking_since).then_some(&mut self.counters)
+    }
+}
+
+/// 按商户与支付代币的订单统计
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantOrderStats {
+    pub merchant: Pubkey,        // 商户所有者
+    pub mint: Pubkey,            // 支付代币mint
+    pub tracking_since: i64,     // 开启统计的时间
+    pub counters: OrderCounters, // 订单计数
+    pub shipments: u64,          // 开启统计后的发货次数
+    pub total_ship_time: u64,    // 上述发货的下单到发货总时长（秒）
+    pub bump: u8,
+}
+
+impl MerchantOrderStats {
+    pub fn seeds(merchant: &Pubkey, mint: &Pubkey) -> Vec<Vec<u8>> {
+        vec![
+            b"merchant_order_stats".to_vec(),
+            merchant.as_ref().to_vec(),
+            mint.as_ref().to_vec(),
+        ]
+    }
+
+    /// 账户刚创建时记录商户、代币与开启时间
+    pub fn open(&mut self, merchant: Pubkey, mint: Pubkey, now: i64, bump: u8) {
+        if self.merchant == Pubkey::default() {
+            self.merchant = merchant;
+            self.mint = mint;
+            self.tracking_since = now;
+            self.counters = OrderCounters::default();
+            self.shipments = 0;
+            self.total_ship_time = 0;
+            self.bump = bump;
+        }
+    }
+
+    /// 订单的计数，开启统计前创建的订单不计入
+    pub fn counters_for(&mut self, order: &Order) -> Option<&mut OrderCounters> {
+        (order.created_at >= self.tracking_since).then_some(&mut self.counters)
+    }
+
+    /// 记录一次发货的下单到发货时长
+    pub fn record_shipment(&mut self, order: &Order, shipped_at: i64) {
+        let ship_time = shipped_at.saturating_sub(order.created_at).max(0) as u64;
+        self.shipments = self.shipments.saturating_add(1);
+        self.total_ship_time = self.total_ship_time.saturating_add(ship_time);
+    }
+}
+
+/// 按支付代币的每日订单统计（时间序列，天序号同 recency_day）
+///
+/// 记录当天发生的下单、送达与退款，不随之后的状态变化回改
+#[account]
+#[derive(InitSpace)]
+pub struct DailyOrderStats {
+    pub mint: Pubkey,          // 支付代币mint
+    pub day: u32,              // 天序号
+    pub orders_created: u64,   // 当天下单数
+    pub ordered_amount: u64,   // 当天下单金额
+    pub orders_delivered: u64, // 当天送达数
+    pub delivered_amount: u64, // 当天送达金额
+    pub orders_refunded: u64,  // 当天退款数
+    pub refunded_amount: u64,  // 当天退款金额
+    pub bump: u8,
+}
+
+impl DailyOrderStats {
+    pub fn seeds(mint: &Pubkey, day: u32) -> Vec<Vec<u8>> {
+        vec![
+            b"daily_order_stats".to_vec(),
+            mint.as_ref().to_vec(),
+            day.to_le_bytes().to_vec(),
+        ]
+    }
+
+    /// 校验天序号为当前时间所在的一天，账户刚创建时记录代币与天序号
+    pub fn open(&mut self, mint: Pubkey, day: u32, now: i64, bump: u8) -> Result<()> {
+        require!(recency_day(now) == day, ErrorCode::InvalidOrderStatsDay);
+        if self.mint == Pubkey::default() {
+            self.mint = mint;
+            self.day = day;
+            self.bump = bump;
+        }
+        Ok(())
+    }
+
+    pub fn record_created(&mut self, amount: u64) {
+        self.orders_created = self.orders_created.saturating_add(1);
+        self.ordered_amount = self.ordered_amount.saturating_add(amount);
+    }
+
+    pub fn record_delivered(&mut self, amount: u64) {
+        self.orders_delivered = self.orders_delivered.saturating_add(1);
+        self.delivered_amount = self.delivered_amount.saturating_add(amount);
+    }
+
+    pub fn record_refunded(&mut self, amount: u64) {
+        self.orders_refunded = self.orders_refunded.saturating_add(1);
+        self.refunded_amount = self.refunded_amount.saturating_add(amount);
+    }
+}
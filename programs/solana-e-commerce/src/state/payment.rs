@@ -1,6 +1,26 @@
 use crate::error::ErrorCode;
 use anchor_lang::prelude::*;
 
+/// 程序托管权限PDA种子，持有托管Token账户
+pub fn program_authority_seeds() -> &'static [&'static [u8]] {
+    &[b"program_authority"]
+}
+
+/// 程序托管Token账户PDA种子（每个支付代币一个）
+pub fn program_token_account_seeds(mint: &Pubkey) -> Vec<Vec<u8>> {
+    vec![b"program_token_account".to_vec(), mint.as_ref().to_vec()]
+}
+
+/// 保证金托管Token账户PDA种子（每个保证金代币一个）
+pub fn deposit_escrow_seeds(mint: &Pubkey) -> Vec<Vec<u8>> {
+    vec![b"deposit_escrow".to_vec(), mint.as_ref().to_vec()]
+}
+
+/// 托管对账账本PDA种子（每个支付代币一个）
+pub fn escrow_ledger_seeds(mint: &Pubkey) -> Vec<Vec<u8>> {
+    vec![b"escrow_ledger".to_vec(), mint.as_ref().to_vec()]
+}
+
 /// 系统级支付配置账户
 #[account]
 #[derive(InitSpace)]
@@ -185,6 +205,99 @@ impl EscrowAccount {
     }
 }
 
+/// 托管对账账本（每个支付代币一个）
+///
+/// 记录程序托管Token账户应持有的金额：未结算订单的托管金额与未转出的平台手续费。
+/// 购买、退款和确认收货都会更新账本，余额低于负债时管理员可冻结托管账户的转出。
+#[account]
+#[derive(InitSpace)]
+pub struct EscrowLedger {
+    pub mint: Pubkey,              // 支付代币mint
+    pub escrowed_amount: u64,      // 托管中的订单金额
+    pub accrued_fees: u64,         // 留在托管账户中的平台手续费
+    pub withdrawals_blocked: bool, // 是否冻结托管账户转出
+    pub bump: u8,                  // PDA bump
+}
+
+impl EscrowLedger {
+    pub fn initialize(&mut self, mint: Pubkey, escrowed_amount: u64, bump: u8) {
+        self.mint = mint;
+        self.escrowed_amount = escrowed_amount;
+        self.accrued_fees = 0;
+        self.withdrawals_blocked = false;
+        self.bump = bump;
+    }
+
+    /// 买家付款转入托管
+    pub fn record_payment(&mut self, amount: u64) -> Result<()> {
+        self.escrowed_amount = self
+            .escrowed_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+        Ok(())
+    }
+
+    /// 订单金额转出托管（退款或结算）
+    ///
+    /// 账本创建前支付的订单不在托管金额中，按饱和减法处理
+    pub fn record_release(&mut self, amount: u64) {
+        self.escrowed_amount = self.escrowed_amount.saturating_sub(amount);
+    }
+
+    /// 结算时未转出的平台手续费
+    pub fn record_fee(&mut self, amount: u64) -> Result<()> {
+        self.accrued_fees = self
+            .accrued_fees
+            .checked_add(amount)
+            .ok_or(ErrorCode::IntegerOverflow)?;
+        Ok(())
+    }
+
+    pub fn total_liabilities(&self) -> u64 {
+        self.escrowed_amount.saturating_add(self.accrued_fees)
+    }
+
+    /// 与托管Token账户余额对账
+    pub fn solvency(&self, token_balance: u64) -> EscrowSolvency {
+        let liabilities = self.total_liabilities();
+        EscrowSolvency {
+            mint: self.mint,
+            token_balance,
+            escrowed_amount: self.escrowed_amount,
+            accrued_fees: self.accrued_fees,
+            surplus: token_balance.saturating_sub(liabilities),
+            deficit: liabilities.saturating_sub(token_balance),
+            withdrawals_blocked: self.withdrawals_blocked,
+        }
+    }
+
+    pub fn check_withdrawals_allowed(&self) -> Result<()> {
+        require!(
+            !self.withdrawals_blocked,
+            ErrorCode::EscrowWithdrawalsBlocked
+        );
+        Ok(())
+    }
+}
+
+/// 托管对账结果
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct EscrowSolvency {
+    pub mint: Pubkey,              // 支付代币mint
+    pub token_balance: u64,        // 托管Token账户余额
+    pub escrowed_amount: u64,      // 托管中的订单金额
+    pub accrued_fees: u64,         // 未转出的平台手续费
+    pub surplus: u64,              // 余额超出负债的部分
+    pub deficit: u64,              // 余额不足负债的部分
+    pub withdrawals_blocked: bool, // 是否冻结托管账户转出
+}
+
+impl EscrowSolvency {
+    pub fn is_solvent(&self) -> bool {
+        self.deficit == 0
+    }
+}
+
 /// 支付方式枚举
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Debug)]
 pub enum PaymentMethod {
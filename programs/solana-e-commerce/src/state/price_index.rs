@@ -1,12 +1,40 @@
 use anchor_lang::prelude::*;
 
+// Maximum number of entries stored in a single price index node
+pub const MAX_PRICE_NODE_ENTRIES: usize = 500;
+
+// Common decimals used when comparing prices across payment tokens
+pub const NORMALIZED_PRICE_DECIMALS: u8 = 9;
+
+// Scale a raw token amount with `decimals` to NORMALIZED_PRICE_DECIMALS
+pub fn normalize_price(price: u64, decimals: u8) -> u128 {
+    if decimals <= NORMALIZED_PRICE_DECIMALS {
+        let scale = 10u128.pow((NORMALIZED_PRICE_DECIMALS - decimals) as u32);
+        price as u128 * scale
+    } else {
+        let scale = 10u128
+            .checked_pow((decimals - NORMALIZED_PRICE_DECIMALS) as u32)
+            .unwrap_or(u128::MAX);
+        price as u128 / scale
+    }
+}
+
+// Product entry in a price index node
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct PriceEntry {
+    pub price: u64,
+    pub product_id: u64,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct PriceIndexNode {
+    pub payment_token: Pubkey, // Payment token mint this index belongs to
+    pub token_decimals: u8,    // Decimals of the payment token mint
     pub price_range_start: u64,
     pub price_range_end: u64,
-    #[max_len(1000)]
-    pub product_ids: Vec<u64>,
+    #[max_len(500)]
+    pub entries: Vec<PriceEntry>,
     pub left_child: Option<Pubkey>,
     pub right_child: Option<Pubkey>,
     pub parent: Option<Pubkey>,
@@ -15,9 +43,14 @@ pub struct PriceIndexNode {
 }
 
 impl PriceIndexNode {
-    pub fn seeds(price_range_start: u64, price_range_end: u64) -> Vec<Vec<u8>> {
+    pub fn seeds(
+        payment_token: &Pubkey,
+        price_range_start: u64,
+        price_range_end: u64,
+    ) -> Vec<Vec<u8>> {
         vec![
             b"price_index".to_vec(),
+            payment_token.to_bytes().to_vec(),
             price_range_start.to_le_bytes().to_vec(),
             price_range_end.to_le_bytes().to_vec(),
         ]
@@ -25,13 +58,17 @@ impl PriceIndexNode {
 
     pub fn initialize(
         &mut self,
+        payment_token: Pubkey,
+        token_decimals: u8,
         price_range_start: u64,
         price_range_end: u64,
         bump: u8,
     ) -> Result<()> {
+        self.payment_token = payment_token;
+        self.token_decimals = token_decimals;
         self.price_range_start = price_range_start;
         self.price_range_end = price_range_end;
-        self.product_ids = Vec::new();
+        self.entries = Vec::new();
         self.left_child = None;
         self.right_child = None;
         self.parent = None;
@@ -43,40 +80,107 @@ impl PriceIndexNode {
 
     pub fn add_product(&mut self, product_id: u64, price: u64) -> Result<()> {
         require!(
-            price >= self.price_range_start && price <= self.price_range_end,
+            self.contains_price(price),
             crate::error::ErrorCode::InvalidPriceRange
         );
 
-        if !self.product_ids.contains(&product_id) {
-            self.product_ids.push(product_id);
+        if !self.contains_product(product_id) {
+            require!(
+                self.entries.len() < MAX_PRICE_NODE_ENTRIES,
+                crate::error::ErrorCode::ShardIsFull
+            );
+            // Keep entries sorted by (price, product_id)
+            let insert_pos = self
+                .entries
+                .partition_point(|entry| (entry.price, entry.product_id) < (price, product_id));
+            self.entries
+                .insert(insert_pos, PriceEntry { price, product_id });
         }
 
         Ok(())
     }
 
     pub fn remove_product(&mut self, product_id: u64) -> Result<bool> {
-        if let Some(index) = self.product_ids.iter().position(|&x| x == product_id) {
-            self.product_ids.remove(index);
+        if let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.product_id == product_id)
+        {
+            self.entries.remove(index);
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
+    pub fn contains_product(&self, product_id: u64) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.product_id == product_id)
+    }
+
+    pub fn product_ids(&self) -> Vec<u64> {
+        self.entries.iter().map(|entry| entry.product_id).collect()
+    }
+
     pub fn contains_price(&self, price: u64) -> bool {
         price >= self.price_range_start && price <= self.price_range_end
     }
 
+    // Stored price expressed in NORMALIZED_PRICE_DECIMALS, for cross-token comparison
+    pub fn normalized_price(&self, price: u64) -> u128 {
+        normalize_price(price, self.token_decimals)
+    }
+
     pub fn is_leaf(&self) -> bool {
         self.left_child.is_none() && self.right_child.is_none()
     }
 
     pub fn needs_split(&self) -> bool {
-        self.product_ids.len() > super::MAX_PRODUCTS_PER_SHARD
+        self.entries.len() > super::MAX_PRODUCTS_PER_SHARD
     }
 
     pub fn needs_merge(&self) -> bool {
-        self.product_ids.len() < super::MAX_PRODUCTS_PER_SHARD / 4
+        self.entries.len() < super::MAX_PRODUCTS_PER_SHARD / 4
+    }
+
+    /// Split point on the median stored price
+    ///
+    /// Entries priced below the returned price go to the left child, the rest to the right child.
+    /// Returns None when the entries cannot be divided (fewer than two distinct prices).
+    pub fn median_split_price(&self) -> Option<u64> {
+        if self.entries.len() < 2 {
+            return None;
+        }
+
+        let median = self.entries[self.entries.len() / 2].price;
+        if self.entries[0].price < median {
+            return Some(median);
+        }
+
+        // Lower half all share the median price, split above it instead
+        self.entries
+            .iter()
+            .map(|entry| entry.price)
+            .find(|&price| price > median)
+    }
+
+    /// Move entries into the two children produced by a split at `split_price`
+    pub fn split_entries_into(
+        &mut self,
+        split_price: u64,
+        left: &mut PriceIndexNode,
+        right: &mut PriceIndexNode,
+    ) -> Result<()> {
+        for entry in self.entries.drain(..) {
+            if entry.price < split_price {
+                left.add_product(entry.product_id, entry.price)?;
+            } else {
+                right.add_product(entry.product_id, entry.price)?;
+            }
+        }
+
+        Ok(())
     }
 
     pub fn balance_factor(&self) -> i8 {
@@ -97,11 +201,51 @@ impl PriceIndexNode {
         self.height = 1 + left_height.max(right_height);
     }
 
-    pub fn get_products_in_range(&self, min_price: u64, max_price: u64) -> Vec<u64> {
-        if min_price <= self.price_range_end && max_price >= self.price_range_start {
-            self.product_ids.clone()
-        } else {
-            Vec::new()
+    // Entries whose stored price is within [min_price, max_price], in ascending price order
+    pub fn entries_in_range(&self, min_price: u64, max_price: u64) -> &[PriceEntry] {
+        if min_price > max_price {
+            return &[];
         }
+        let start = self
+            .entries
+            .partition_point(|entry| entry.price < min_price);
+        let end = self
+            .entries
+            .partition_point(|entry| entry.price <= max_price);
+        &self.entries[start..end]
+    }
+
+    pub fn get_products_in_range(&self, min_price: u64, max_price: u64) -> Vec<u64> {
+        self.entries_in_range(min_price, max_price)
+            .iter()
+            .map(|entry| entry.product_id)
+            .collect()
+    }
+}
+
+// Price index node layout before nodes were namespaced by payment mint, without stored prices,
+// at [b"price_index", price_range_start, price_range_end]
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyPriceIndexNode {
+    pub price_range_start: u64,
+    pub price_range_end: u64,
+    pub product_ids: Vec<u64>,
+    pub left_child: Option<Pubkey>,
+    pub right_child: Option<Pubkey>,
+    pub parent: Option<Pubkey>,
+    pub height: u8,
+    pub bump: u8,
+}
+
+impl LegacyPriceIndexNode {
+    // Legacy nodes held up to 1000 product ids
+    pub const SPACE: usize = 8 + 8 + 8 + (4 + 1000 * 8) + 3 * 33 + 1 + 1;
+
+    pub fn seeds(price_range_start: u64, price_range_end: u64) -> Vec<Vec<u8>> {
+        vec![
+            b"price_index".to_vec(),
+            price_range_start.to_le_bytes().to_vec(),
+            price_range_end.to_le_bytes().to_vec(),
+        ]
     }
 }
@@ -23,6 +23,52 @@ pub struct ProductBase {
     #[max_len(128)]
     pub shipping_location: String, // Shipping address
     pub bump: u8,
+    pub variant_count: u32, // Number of variants, orders must name one when non-zero
+}
+
+// ProductBase layout before product variants, without variant_count
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyProductBase {
+    pub id: u64,
+    pub merchant: Pubkey,
+    pub name: String,
+    pub description: String,
+    pub price: u64,
+    pub keywords: String,
+    pub inventory: u64,
+    pub sales: u32,
+    pub is_active: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub payment_token: Pubkey,
+    pub shipping_location: String,
+    pub bump: u8,
+}
+
+impl LegacyProductBase {
+    pub const SPACE: usize = ProductBase::SPACE - 4;
+}
+
+impl From<LegacyProductBase> for ProductBase {
+    fn from(legacy: LegacyProductBase) -> Self {
+        Self {
+            id: legacy.id,
+            merchant: legacy.merchant,
+            name: legacy.name,
+            description: legacy.description,
+            price: legacy.price,
+            keywords: legacy.keywords,
+            inventory: legacy.inventory,
+            sales: legacy.sales,
+            is_active: legacy.is_active,
+            created_at: legacy.created_at,
+            updated_at: legacy.updated_at,
+            payment_token: legacy.payment_token,
+            shipping_location: legacy.shipping_location,
+            bump: legacy.bump,
+            variant_count: 0,
+        }
+    }
 }
 
 // 产品扩展信息账户（可选的营销和展示数据）
@@ -43,6 +89,13 @@ pub struct ProductExtended {
 pub type Product = ProductBase;
 
 impl ProductBase {
+    pub const SPACE: usize = 8 + Self::INIT_SPACE;
+
+    // Legacy products can leave stale bytes where variant_count now lives, until migrated
+    pub fn has_current_layout(info: &AccountInfo) -> bool {
+        info.data_len() == Self::SPACE
+    }
+
     pub fn seeds(&self) -> Vec<Vec<u8>> {
         vec![b"product".to_vec(), self.id.to_le_bytes().to_vec()]
     }
@@ -79,6 +132,12 @@ impl ProductBase {
         Ok(())
     }
 
+    pub fn decrease_sales(&mut self, sales_decrement: u32) -> Result<()> {
+        self.sales = self.sales.saturating_sub(sales_decrement);
+        self.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
     pub fn set_active(&mut self, active: bool) -> Result<()> {
         self.is_active = active;
         self.updated_at = Clock::get()?.unix_timestamp;
@@ -149,7 +208,13 @@ impl ProductExtended {
         vec![
             b"product_extended".to_vec(),
             self.product_id.to_le_bytes().to_vec(),
-            b"v1".to_vec(),
+        ]
+    }
+
+    pub fn seeds_static(product_id: u64) -> Vec<Vec<u8>> {
+        vec![
+            b"product_extended".to_vec(),
+            product_id.to_le_bytes().to_vec(),
         ]
     }
 
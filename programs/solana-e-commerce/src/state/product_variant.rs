@@ -0,0 +1,129 @@
+use crate::error::ErrorCode;
+use crate::state::ProductBase;
+use anchor_lang::prelude::*;
+
+/// 每个规格的选项数上限（如尺码、颜色）
+pub const MAX_VARIANT_OPTIONS: usize = 3;
+pub const MAX_VARIANT_OPTION_NAME_LENGTH: usize = 16;
+pub const MAX_VARIANT_OPTION_VALUE_LENGTH: usize = 32;
+pub const MAX_SKU_LENGTH: usize = 32;
+
+/// 规格选项，如 尺码=M
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct VariantOption {
+    #[max_len(16)]
+    pub name: String, // 选项名
+    #[max_len(32)]
+    pub value: String, // 选项值
+}
+
+/// 商品规格 - 有独立的选项值、SKU、价格和库存，搜索索引只收录父商品
+///
+/// 规格ID在商品内从0开始依次分配；商品有规格时订单必须指定规格
+#[account]
+#[derive(InitSpace)]
+pub struct ProductVariant {
+    pub product_id: u64, // 父商品ID
+    pub variant_id: u32, // 规格ID
+    #[max_len(3)]
+    pub options: Vec<VariantOption>, // 选项值
+    #[max_len(32)]
+    pub sku: String, // SKU
+    pub price: Option<u64>, // 价格（None时使用商品价格）
+    pub inventory: u64,  // 库存
+    pub is_active: bool, // 是否在售
+    pub created_at: i64, // 创建时间
+    pub updated_at: i64, // 更新时间
+    pub bump: u8,        // PDA bump
+}
+
+impl ProductVariant {
+    pub fn seeds(product_id: u64, variant_id: u32) -> Vec<Vec<u8>> {
+        vec![
+            b"product_variant".to_vec(),
+            product_id.to_le_bytes().to_vec(),
+            variant_id.to_le_bytes().to_vec(),
+        ]
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize(
+        &mut self,
+        product_id: u64,
+        variant_id: u32,
+        options: Vec<VariantOption>,
+        sku: String,
+        price: Option<u64>,
+        inventory: u64,
+        bump: u8,
+    ) -> Result<()> {
+        validate_variant_options(&options)?;
+        validate_sku(&sku)?;
+        require!(price != Some(0), ErrorCode::InvalidPrice);
+
+        let now = Clock::get()?.unix_timestamp;
+        self.product_id = product_id;
+        self.variant_id = variant_id;
+        self.options = options;
+        self.sku = sku;
+        self.price = price;
+        self.inventory = inventory;
+        self.is_active = true;
+        self.created_at = now;
+        self.updated_at = now;
+        self.bump = bump;
+        Ok(())
+    }
+
+    /// 规格的售价
+    pub fn effective_price(&self, product: &ProductBase) -> u64 {
+        self.price.unwrap_or(product.price)
+    }
+
+    /// 下单时扣减库存
+    pub fn reserve(&mut self, quantity: u32) -> Result<()> {
+        require!(self.is_active, ErrorCode::ProductVariantInactive);
+        self.inventory = self
+            .inventory
+            .checked_sub(quantity as u64)
+            .ok_or(ErrorCode::InsufficientVariantInventory)?;
+        self.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// 未发货订单退款时归还库存
+    pub fn release(&mut self, quantity: u32) -> Result<()> {
+        self.inventory = self.inventory.saturating_add(quantity as u64);
+        self.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+}
+
+/// 验证规格选项：1到MAX_VARIANT_OPTIONS个，选项名不为空且不重复
+pub fn validate_variant_options(options: &[VariantOption]) -> Result<()> {
+    require!(
+        !options.is_empty() && options.len() <= MAX_VARIANT_OPTIONS,
+        ErrorCode::InvalidVariantOptions
+    );
+    for (index, option) in options.iter().enumerate() {
+        require!(
+            !option.name.is_empty()
+                && option.name.len() <= MAX_VARIANT_OPTION_NAME_LENGTH
+                && !option.value.is_empty()
+                && option.value.len() <= MAX_VARIANT_OPTION_VALUE_LENGTH,
+            ErrorCode::InvalidVariantOptions
+        );
+        require!(
+            options[..index]
+                .iter()
+                .all(|other| other.name != option.name),
+            ErrorCode::InvalidVariantOptions
+        );
+    }
+    Ok(())
+}
+
+pub fn validate_sku(sku: &str) -> Result<()> {
+    require!(sku.len() <= MAX_SKU_LENGTH, ErrorCode::InvalidSkuLength);
+    Ok(())
+}
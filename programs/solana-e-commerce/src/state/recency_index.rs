@@ -0,0 +1,189 @@
+use anchor_lang::prelude::*;
+
+/// 每个时间桶覆盖的秒数（一天）
+pub const RECENCY_BUCKET_SECONDS: i64 = 86_400;
+/// 每个时间桶可记录的商品数量
+pub const MAX_PRODUCTS_PER_RECENCY_BUCKET: usize = 300;
+
+/// 时间索引类型
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum RecencyFeed {
+    NewArrivals,     // 按创建时间（新品上架）
+    RecentlyUpdated, // 按更新时间（最近更新）
+}
+
+impl RecencyFeed {
+    pub fn seed(&self) -> u8 {
+        match self {
+            RecencyFeed::NewArrivals => 0,
+            RecencyFeed::RecentlyUpdated => 1,
+        }
+    }
+}
+
+/// 时间戳所在的天序号
+pub fn recency_day(timestamp: i64) -> u32 {
+    timestamp.max(0).div_euclid(RECENCY_BUCKET_SECONDS) as u32
+}
+
+/// 时间索引头 - 指向最新的时间桶，关键词为空表示全局索引
+#[account]
+#[derive(InitSpace)]
+pub struct RecencyFeedHead {
+    pub feed: RecencyFeed,
+    #[max_len(32)]
+    pub keyword: String,
+    pub latest_day: Option<u32>, // 最新时间桶的天序号
+    pub total_entries: u64,      // 累计记录数
+    pub bump: u8,                // PDA bump
+}
+
+impl RecencyFeedHead {
+    /// 生成时间索引头PDA种子
+    pub fn seeds(feed: RecencyFeed, keyword: &str) -> Vec<Vec<u8>> {
+        vec![
+            b"recency_head".to_vec(),
+            vec![feed.seed()],
+            keyword.as_bytes().to_vec(),
+        ]
+    }
+
+    pub fn initialize(&mut self, feed: RecencyFeed, keyword: String, bump: u8) -> Result<()> {
+        require!(
+            keyword.len() <= super::MAX_KEYWORD_LENGTH,
+            crate::error::ErrorCode::InvalidKeywordLength
+        );
+
+        self.feed = feed;
+        self.keyword = keyword;
+        self.latest_day = None;
+        self.total_entries = 0;
+        self.bump = bump;
+
+        Ok(())
+    }
+
+    /// 记录商品，`opened`表示时间桶刚创建，需要接入previous_day链
+    ///
+    /// 早于最新一天的时间桶插入到链中紧随其后的时间桶`newer_bucket`之前
+    pub fn record(
+        &mut self,
+        bucket: &mut RecencyBucket,
+        opened: bool,
+        newer_bucket: Option<&mut RecencyBucket>,
+        product_id: u64,
+        timestamp: i64,
+    ) -> Result<bool> {
+        if opened {
+            self.link(bucket, newer_bucket)?;
+        }
+
+        let added = bucket.add_entry(product_id, timestamp)?;
+        if added {
+            self.total_entries = self.total_entries.saturating_add(1);
+        }
+        Ok(added)
+    }
+
+    fn link(
+        &mut self,
+        bucket: &mut RecencyBucket,
+        newer_bucket: Option<&mut RecencyBucket>,
+    ) -> Result<()> {
+        match self.latest_day {
+            Some(latest) if bucket.day < latest => {
+                let newer_bucket =
+                    newer_bucket.ok_or(crate::error::ErrorCode::InvalidRecencyBucket)?;
+                require!(
+                    newer_bucket.feed == self.feed
+                        && newer_bucket.keyword == self.keyword
+                        && newer_bucket.day > bucket.day
+                        && newer_bucket
+                            .previous_day
+                            .is_none_or(|previous| previous < bucket.day),
+                    crate::error::ErrorCode::InvalidRecencyBucket
+                );
+                bucket.previous_day = newer_bucket.previous_day;
+                newer_bucket.previous_day = Some(bucket.day);
+            }
+            _ => {
+                bucket.previous_day = self.latest_day;
+                self.latest_day = Some(bucket.day);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 时间桶中的商品记录
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct RecencyEntry {
+    pub timestamp: i64,
+    pub product_id: u64,
+}
+
+/// 时间桶 - 记录某一天的商品，通过previous_day向前串联
+#[account]
+#[derive(InitSpace)]
+pub struct RecencyBucket {
+    pub feed: RecencyFeed,
+    #[max_len(32)]
+    pub keyword: String,
+    pub day: u32,                  // 天序号
+    pub previous_day: Option<u32>, // 上一个非空时间桶的天序号
+    #[max_len(300)]
+    pub entries: Vec<RecencyEntry>,
+    pub bump: u8,
+}
+
+impl RecencyBucket {
+    /// 生成时间桶PDA种子
+    pub fn seeds(feed: RecencyFeed, keyword: &str, day: u32) -> Vec<Vec<u8>> {
+        vec![
+            b"recency_bucket".to_vec(),
+            vec![feed.seed()],
+            keyword.as_bytes().to_vec(),
+            day.to_le_bytes().to_vec(),
+        ]
+    }
+
+    pub fn initialize(&mut self, feed: RecencyFeed, keyword: String, day: u32, bump: u8) {
+        self.feed = feed;
+        self.keyword = keyword;
+        self.day = day;
+        self.previous_day = None;
+        self.entries = Vec::new();
+        self.bump = bump;
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.entries.len() >= MAX_PRODUCTS_PER_RECENCY_BUCKET
+    }
+
+    pub fn contains_product(&self, product_id: u64) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.product_id == product_id)
+    }
+
+    /// 按(时间戳, 商品ID)有序插入，已存在时返回false
+    pub fn add_entry(&mut self, product_id: u64, timestamp: i64) -> Result<bool> {
+        if self.contains_product(product_id) {
+            return Ok(false);
+        }
+        require!(!self.is_full(), crate::error::ErrorCode::ShardIsFull);
+
+        let insert_pos = self
+            .entries
+            .partition_point(|entry| (entry.timestamp, entry.product_id) < (timestamp, product_id));
+        self.entries.insert(
+            insert_pos,
+            RecencyEntry {
+                timestamp,
+                product_id,
+            },
+        );
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,183 @@
+use crate::state::{decay_score, MAX_RATING, MIN_RATING};
+use anchor_lang::prelude::*;
+
+/// 一次信誉事件的权重（定点数）
+pub const REPUTATION_EVENT_WEIGHT: u64 = 1_000_000;
+/// 信誉事件的半衰期（90天）
+pub const REPUTATION_HALF_LIFE_SECONDS: u32 = 90 * 24 * 60 * 60;
+/// 发货期限：下单后超过该时长才发货（或仍未发货被退款）计为一次发货超时
+pub const SHIP_DEADLINE_SECONDS: i64 = 7 * 24 * 60 * 60;
+/// 信誉分满分
+pub const MAX_REPUTATION_SCORE: u16 = 1000;
+/// 先验事件数，记录很少的商户接近中性分（满分的一半）
+pub const REPUTATION_PRIOR_EVENTS: u64 = 5;
+
+/// 各类负面事件相对一笔结算订单的权重
+pub const REFUND_PENALTY: u64 = 1;
+pub const MISSED_SHIP_DEADLINE_PENALTY: u64 = 2;
+pub const LOST_DISPUTE_PENALTY: u64 = 5;
+pub const DEPOSIT_SLASH_PENALTY: u64 = 5;
+
+/// 履约分与评价分在信誉分中的占比（十分之几）
+pub const FULFILMENT_SHARE: u64 = 7;
+pub const REVIEW_SHARE: u64 = 3;
+
+/// 影响商户信誉的事件
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReputationEvent {
+    Settled,            // 订单结算（确认收货）
+    Refunded,           // 已送达订单退款
+    MissedShipDeadline, // 发货超时
+    LostDispute,        // 纠纷败诉（管理员裁决）
+    DepositSlashed,     // 保证金被扣除
+    Reviewed(u8),       // 已送达订单的评价（1-5星）
+}
+
+/// 商户信誉 - 由订单结算、退款、发货超时、纠纷、保证金扣除和评价自动更新
+///
+/// 各计数为按 REPUTATION_HALF_LIFE_SECONDS 衰减到 last_update 时刻的事件权重，
+/// 一次事件记 REPUTATION_EVENT_WEIGHT
+#[account]
+#[derive(InitSpace, Default)]
+pub struct MerchantReputation {
+    pub merchant: Pubkey,           // 商户所有者
+    pub settled_orders: u64,        // 结算订单
+    pub refunds: u64,               // 已送达订单退款
+    pub missed_ship_deadlines: u64, // 发货超时
+    pub lost_disputes: u64,         // 纠纷败诉
+    pub deposit_slashes: u64,       // 保证金扣除
+    pub reviews: u64,               // 评价数
+    pub review_points: u64,         // 评价得分，1星为0，5星为一次事件的权重
+    pub last_update: i64,           // 最后更新时间
+    pub bump: u8,                   // PDA bump
+}
+
+impl MerchantReputation {
+    pub fn seeds(merchant: &Pubkey) -> Vec<Vec<u8>> {
+        vec![b"merchant_reputation".to_vec(), merchant.as_ref().to_vec()]
+    }
+
+    /// 首次使用时初始化
+    pub fn open(&mut self, merchant: Pubkey, now: i64, bump: u8) {
+        if self.last_update == 0 {
+            self.merchant = merchant;
+            self.last_update = now;
+            self.bump = bump;
+        }
+    }
+
+    /// 将所有计数衰减到now
+    pub fn decay_to(&mut self, now: i64) {
+        let elapsed = now - self.last_update;
+        if elapsed <= 0 {
+            return;
+        }
+        for weight in [
+            &mut self.settled_orders,
+            &mut self.refunds,
+            &mut self.missed_ship_deadlines,
+            &mut self.lost_disputes,
+            &mut self.deposit_slashes,
+            &mut self.reviews,
+            &mut self.review_points,
+        ] {
+            *weight = decay_score(*weight, elapsed, REPUTATION_HALF_LIFE_SECONDS);
+        }
+        self.last_update = now;
+    }
+
+    /// 记录一次事件
+    pub fn record(&mut self, event: ReputationEvent, now: i64) {
+        self.decay_to(now);
+        let weight = match event {
+            ReputationEvent::Settled => &mut self.settled_orders,
+            ReputationEvent::Refunded => &mut self.refunds,
+            ReputationEvent::MissedShipDeadline => &mut self.missed_ship_deadlines,
+            ReputationEvent::LostDispute => &mut self.lost_disputes,
+            ReputationEvent::DepositSlashed => &mut self.deposit_slashes,
+            ReputationEvent::Reviewed(rating) => {
+                let stars = rating.clamp(MIN_RATING, MAX_RATING) - MIN_RATING;
+                let points =
+                    REPUTATION_EVENT_WEIGHT * stars as u64 / (MAX_RATING - MIN_RATING) as u64;
+                self.review_points = self.review_points.saturating_add(points);
+                &mut self.reviews
+            }
+        };
+        *weight = weight.saturating_add(REPUTATION_EVENT_WEIGHT);
+    }
+
+    /// now时刻的信誉分
+    ///
+    /// 履约分 = 满分 × (结算 + 先验/2) / (结算 + 先验 + 退款×1 + 发货超时×2 + 纠纷败诉×5 + 保证金扣除×5)
+    /// 评价分 = 满分 × (评价得分 + 先验/2) / (评价数 + 先验)
+    /// 信誉分 = 履约分×0.7 + 评价分×0.3
+    ///
+    /// 先验为 REPUTATION_PRIOR_EVENTS 次事件，没有记录的商户为中性分，记录衰减后逐渐回到中性分
+    pub fn score(&self, now: i64) -> ReputationScore {
+        let mut reputation = self.clone();
+        reputation.decay_to(now);
+
+        let prior = REPUTATION_PRIOR_EVENTS * REPUTATION_EVENT_WEIGHT;
+        let penalties = [
+            (reputation.refunds, REFUND_PENALTY),
+            (
+                reputation.missed_ship_deadlines,
+                MISSED_SHIP_DEADLINE_PENALTY,
+            ),
+            (reputation.lost_disputes, LOST_DISPUTE_PENALTY),
+            (reputation.deposit_slashes, DEPOSIT_SLASH_PENALTY),
+        ]
+        .iter()
+        .fold(0u128, |total, &(weight, penalty)| {
+            total + weight as u128 * penalty as u128
+        });
+        let fulfilment_score = scaled(
+            reputation.settled_orders as u128 + prior as u128 / 2,
+            reputation.settled_orders as u128 + prior as u128 + penalties,
+        );
+        let review_score = scaled(
+            reputation.review_points as u128 + prior as u128 / 2,
+            reputation.reviews as u128 + prior as u128,
+        );
+        let score = (fulfilment_score as u64 * FULFILMENT_SHARE
+            + review_score as u64 * REVIEW_SHARE)
+            / (FULFILMENT_SHARE + REVIEW_SHARE);
+
+        ReputationScore {
+            merchant: reputation.merchant,
+            score: score as u16,
+            fulfilment_score,
+            review_score,
+            settled_orders: reputation.settled_orders,
+            refunds: reputation.refunds,
+            missed_ship_deadlines: reputation.missed_ship_deadlines,
+            lost_disputes: reputation.lost_disputes,
+            deposit_slashes: reputation.deposit_slashes,
+            reviews: reputation.reviews,
+            updated_at: now,
+        }
+    }
+}
+
+/// `part / total` 换算为信誉分
+fn scaled(part: u128, total: u128) -> u16 {
+    (part * MAX_REPUTATION_SCORE as u128)
+        .checked_div(total)
+        .map_or(0, |score| score.min(MAX_REPUTATION_SCORE as u128) as u16)
+}
+
+/// 商户信誉查询结果，事件计数为衰减到updated_at时刻的权重
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct ReputationScore {
+    pub merchant: Pubkey,           // 商户所有者
+    pub score: u16,                 // 信誉分（0-1000）
+    pub fulfilment_score: u16,      // 履约分（0-1000）
+    pub review_score: u16,          // 评价分（0-1000）
+    pub settled_orders: u64,        // 结算订单
+    pub refunds: u64,               // 已送达订单退款
+    pub missed_ship_deadlines: u64, // 发货超时
+    pub lost_disputes: u64,         // 纠纷败诉
+    pub deposit_slashes: u64,       // 保证金扣除
+    pub reviews: u64,               // 评价数
+    pub updated_at: i64,            // 计算时间
+}
@@ -0,0 +1,153 @@
+use crate::error::ErrorCode;
+use crate::state::Order;
+use crate::utils::validate_rating;
+use anchor_lang::prelude::*;
+
+/// 评分范围
+pub const MIN_RATING: u8 = 1;
+pub const MAX_RATING: u8 = 5;
+/// 评价文本与商家回复的最大长度
+pub const MAX_REVIEW_TEXT_LENGTH: usize = 280;
+pub const MAX_REVIEW_REPLY_LENGTH: usize = 280;
+
+/// 订单评价 - 已送达订单的买家每个订单可评价一次
+#[account]
+#[derive(InitSpace)]
+pub struct Review {
+    pub order: Pubkey,                  // 评价的订单
+    pub buyer: Pubkey,                  // 买家地址
+    pub merchant: Pubkey,               // 商户地址
+    pub product_id: u64,                // 商品ID
+    pub rating: u8,                     // 评分（1-5）
+    pub content_hash: Option<[u8; 32]>, // 链下评价内容的哈希
+    #[max_len(280)]
+    pub text: String, // 简短评价文本
+    #[max_len(280)]
+    pub reply: String, // 商家回复
+    pub created_at: i64,                // 评价时间
+    pub replied_at: Option<i64>,        // 回复时间
+    pub bump: u8,                       // PDA bump
+}
+
+impl Review {
+    pub fn seeds(order: &Pubkey) -> Vec<Vec<u8>> {
+        vec![b"review".to_vec(), order.as_ref().to_vec()]
+    }
+
+    pub fn initialize(
+        &mut self,
+        order_key: Pubkey,
+        order: &Order,
+        rating: u8,
+        content_hash: Option<[u8; 32]>,
+        text: String,
+        bump: u8,
+    ) -> Result<()> {
+        validate_rating(rating)?;
+        require!(
+            text.len() <= MAX_REVIEW_TEXT_LENGTH,
+            ErrorCode::InvalidReviewTextLength
+        );
+
+        self.order = order_key;
+        self.buyer = order.buyer;
+        self.merchant = order.merchant;
+        self.product_id = order.product_id;
+        self.rating = rating;
+        self.content_hash = content_hash;
+        self.text = text;
+        self.reply = String::new();
+        self.created_at = Clock::get()?.unix_timestamp;
+        self.replied_at = None;
+        self.bump = bump;
+        Ok(())
+    }
+
+    /// 商家回复，每条评价只能回复一次
+    pub fn set_reply(&mut self, reply: String) -> Result<()> {
+        require!(self.replied_at.is_none(), ErrorCode::ReviewAlreadyReplied);
+        require!(
+            !reply.is_empty() && reply.len() <= MAX_REVIEW_REPLY_LENGTH,
+            ErrorCode::InvalidReviewReplyLength
+        );
+        self.reply = reply;
+        self.replied_at = Some(Clock::get()?.unix_timestamp);
+        Ok(())
+    }
+}
+
+/// 评分汇总
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq, InitSpace)]
+pub struct RatingSummary {
+    pub rating_count: u64,                       // 评价数
+    pub rating_sum: u64,                         // 评分总和
+    pub star_counts: [u64; MAX_RATING as usize], // 各星级评价数，下标0为1星
+}
+
+impl RatingSummary {
+    pub fn record(&mut self, rating: u8) {
+        self.rating_count = self.rating_count.saturating_add(1);
+        self.rating_sum = self.rating_sum.saturating_add(rating as u64);
+        let stars = &mut self.star_counts[(rating - MIN_RATING) as usize];
+        *stars = stars.saturating_add(1);
+    }
+
+    /// 平均评分乘以100，没有评价时为0
+    pub fn average_x100(&self) -> u64 {
+        self.rating_sum
+            .saturating_mul(100)
+            .checked_div(self.rating_count)
+            .unwrap_or_default()
+    }
+}
+
+/// 商品评分汇总（只统计已送达订单的评价）
+#[account]
+#[derive(InitSpace)]
+pub struct ProductRating {
+    pub product_id: u64,        // 商品ID
+    pub summary: RatingSummary, // 评分汇总
+    pub bump: u8,
+}
+
+impl ProductRating {
+    pub fn seeds(product_id: u64) -> Vec<Vec<u8>> {
+        vec![
+            b"product_rating".to_vec(),
+            product_id.to_le_bytes().to_vec(),
+        ]
+    }
+
+    /// 记录一条评价，账户刚创建时记录商品ID
+    pub fn record(&mut self, product_id: u64, rating: u8, bump: u8) {
+        if self.summary.rating_count == 0 {
+            self.product_id = product_id;
+            self.bump = bump;
+        }
+        self.summary.record(rating);
+    }
+}
+
+/// 商户评分汇总（只统计已送达订单的评价）
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantRating {
+    pub merchant: Pubkey,       // 商户所有者
+    pub summary: RatingSummary, // 评分汇总
+    pub bump: u8,
+}
+
+impl MerchantRating {
+    pub fn seeds(merchant: &Pubkey) -> Vec<Vec<u8>> {
+        vec![b"merchant_rating".to_vec(), merchant.as_ref().to_vec()]
+    }
+
+    /// 记录一条评价，账户刚创建时记录商户
+    pub fn record(&mut self, merchant: Pubkey, rating: u8, bump: u8) {
+        if self.summary.rating_count == 0 {
+            self.merchant = merchant;
+            self.bump = bump;
+        }
+        self.summary.record(rating);
+    }
+}
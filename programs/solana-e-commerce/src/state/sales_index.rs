@@ -1,6 +1,9 @@
 use super::ProductSales;
 use anchor_lang::prelude::*;
 
+// Best sellers cached per node
+pub const MAX_TOP_ITEMS: usize = 10;
+
 #[account]
 #[derive(InitSpace)]
 pub struct SalesIndexNode {
@@ -8,7 +11,7 @@ pub struct SalesIndexNode {
     pub sales_range_end: u32,
     #[max_len(500)]
     pub product_ids: Vec<u64>,
-    #[max_len(10)]
+    #[max_len(MAX_TOP_ITEMS)]
     pub top_items: Vec<ProductSales>, // 缓存热销商品
     pub left_child: Option<Pubkey>,
     pub right_child: Option<Pubkey>,
@@ -98,10 +101,8 @@ impl SalesIndexNode {
 
         self.top_items.insert(insert_pos, product_sales);
 
-        // 限制top_items数量为20个
-        if self.top_items.len() > 20 {
-            self.top_items.truncate(20);
-        }
+        // 限制top_items数量，不超过账户空间
+        self.top_items.truncate(MAX_TOP_ITEMS);
 
         Ok(())
     }
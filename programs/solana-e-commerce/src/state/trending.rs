@@ -0,0 +1,191 @@
+use anchor_lang::prelude::*;
+
+/// 一件销量对应的热度分值（定点数）
+pub const TRENDING_SCORE_SCALE: u64 = 1_000_000;
+/// 热度榜单容量
+pub const TRENDING_BOARD_SIZE: usize = 50;
+/// 默认热度半衰期（7天）
+pub const DEFAULT_TRENDING_HALF_LIFE_SECONDS: u32 = 7 * 24 * 60 * 60;
+
+/// 2^(-k/16)，Q32定点数，k = 0..=16
+const DECAY_TABLE: [u64; 17] = [
+    4294967296, 4112874773, 3938502376, 3771522796, 3611622603, 3458501653, 3311872529, 3171459999,
+    3037000500, 2908241642, 2784941738, 2666869345, 2553802834, 2445529972, 2341847524, 2242560872,
+    2147483648,
+];
+
+/// 按半衰期衰减热度分值：score * 2^(-elapsed / half_life)
+///
+/// 整半衰期使用移位，余下部分在1/16半衰期的查表值之间线性插值
+pub fn decay_score(score: u64, elapsed: i64, half_life_seconds: u32) -> u64 {
+    if score == 0 || elapsed <= 0 || half_life_seconds == 0 {
+        return score;
+    }
+
+    let half_life = half_life_seconds as u128;
+    let elapsed = elapsed as u128;
+    let halvings = elapsed / half_life;
+    if halvings >= 64 {
+        return 0;
+    }
+    let value = (score >> halvings) as u128;
+
+    // 余下部分在表中的位置（Q32）
+    let position = ((elapsed % half_life) << 36) / half_life;
+    let index = (position >> 32) as usize;
+    let fraction = position & 0xFFFF_FFFF;
+    let upper = DECAY_TABLE[index] as u128;
+    let lower = DECAY_TABLE[index + 1] as u128;
+    let factor = upper - (((upper - lower) * fraction) >> 32);
+
+    ((value * factor) >> 32) as u64
+}
+
+/// 商品热度分值 - 在订单结算时更新
+#[account]
+#[derive(InitSpace)]
+pub struct TrendingScore {
+    pub product_id: u64,  // 商品ID
+    pub score: u64,       // last_update时刻的热度分值
+    pub last_update: i64, // 最后更新时间
+    pub bump: u8,         // PDA bump
+}
+
+impl TrendingScore {
+    /// 生成商品热度PDA种子
+    pub fn seeds(product_id: u64) -> Vec<Vec<u8>> {
+        vec![
+            b"trending_score".to_vec(),
+            product_id.to_le_bytes().to_vec(),
+        ]
+    }
+
+    /// 首次使用时初始化
+    pub fn initialize_if_needed(&mut self, product_id: u64, now: i64, bump: u8) {
+        if self.last_update == 0 {
+            self.product_id = product_id;
+            self.score = 0;
+            self.last_update = now;
+            self.bump = bump;
+        }
+    }
+
+    /// 当前时刻的热度分值
+    pub fn current_score(&self, now: i64, half_life_seconds: u32) -> u64 {
+        decay_score(self.score, now - self.last_update, half_life_seconds)
+    }
+
+    /// 记录一次结算销量
+    pub fn record_sale(&mut self, quantity: u32, now: i64, half_life_seconds: u32) {
+        let weight = (quantity as u64).saturating_mul(TRENDING_SCORE_SCALE);
+        self.score = self
+            .current_score(now, half_life_seconds)
+            .saturating_add(weight);
+        self.last_update = now;
+    }
+
+    /// 撤销一次销量，按该笔销量从sold_at至今的衰减值扣除
+    pub fn revert_sale(&mut self, quantity: u32, sold_at: i64, now: i64, half_life_seconds: u32) {
+        let weight = decay_score(
+            (quantity as u64).saturating_mul(TRENDING_SCORE_SCALE),
+            now - sold_at,
+            half_life_seconds,
+        );
+        self.score = self
+            .current_score(now, half_life_seconds)
+            .saturating_sub(weight);
+        self.last_update = now;
+    }
+}
+
+/// 热度榜单条目
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct TrendingEntry {
+    pub product_id: u64,
+    pub score: u64, // 榜单updated_at时刻的热度分值
+}
+
+/// 热度榜单 - 关键词为空表示全局榜单，按热度降序保存
+#[account]
+#[derive(InitSpace)]
+pub struct TrendingBoard {
+    #[max_len(32)]
+    pub keyword: String, // 关键词（全局榜单为空）
+    #[max_len(50)]
+    pub entries: Vec<TrendingEntry>, // 榜单条目
+    pub updated_at: i64, // 条目分值对应的时间
+    pub bump: u8,        // PDA bump
+}
+
+impl TrendingBoard {
+    /// 生成全局热度榜单PDA种子
+    pub fn global_seeds() -> Vec<Vec<u8>> {
+        vec![b"trending_global".to_vec()]
+    }
+
+    /// 生成关键词热度榜单PDA种子
+    pub fn keyword_seeds(keyword: &str) -> Vec<Vec<u8>> {
+        vec![b"trending_keyword".to_vec(), keyword.as_bytes().to_vec()]
+    }
+
+    /// 初始化热度榜单
+    pub fn initialize(&mut self, keyword: String, bump: u8) -> Result<()> {
+        require!(
+            keyword.len() <= super::MAX_KEYWORD_LENGTH,
+            crate::error::ErrorCode::InvalidKeywordLength
+        );
+
+        self.keyword = keyword;
+        self.entries = Vec::new();
+        self.updated_at = Clock::get()?.unix_timestamp;
+        self.bump = bump;
+
+        Ok(())
+    }
+
+    /// 将所有条目衰减到now（同一衰减不改变相对顺序）
+    pub fn decay_to(&mut self, now: i64, half_life_seconds: u32) {
+        let elapsed = now - self.updated_at;
+        if elapsed > 0 {
+            for entry in self.entries.iter_mut() {
+                entry.score = decay_score(entry.score, elapsed, half_life_seconds);
+            }
+            self.entries.retain(|entry| entry.score > 0);
+            self.updated_at = now;
+        }
+    }
+
+    /// 更新商品的热度分值（score为now时刻的值），返回商品是否在榜
+    pub fn upsert(
+        &mut self,
+        product_id: u64,
+        score: u64,
+        now: i64,
+        half_life_seconds: u32,
+    ) -> bool {
+        self.decay_to(now, half_life_seconds);
+
+        if let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.product_id == product_id)
+        {
+            self.entries.remove(index);
+        }
+        if score == 0 {
+            return false;
+        }
+
+        let insert_pos = self
+            .entries
+            .partition_point(|entry| (entry.score, entry.product_id) > (score, product_id));
+        if insert_pos >= TRENDING_BOARD_SIZE {
+            return false;
+        }
+        self.entries
+            .insert(insert_pos, TrendingEntry { product_id, score });
+        self.entries.truncate(TRENDING_BOARD_SIZE);
+
+        true
+    }
+}
@@ -4,6 +4,11 @@ use anchor_lang::prelude::*;
 pub const BLOOM_FILTER_SIZE: usize = 256;
 pub const BLOOM_HASH_COUNT: u8 = 3;
 
+// 计数布隆过滤器：每字节两个4位计数器
+pub const MIN_BLOOM_FILTER_SIZE: usize = 32;
+pub const MAX_BLOOM_FILTER_SIZE: usize = 1024;
+pub const BLOOM_COUNTER_MAX: u8 = 15;
+
 // 布隆过滤器操作
 pub struct BloomFilter;
 
@@ -70,6 +75,103 @@ impl BloomFilter {
     }
 }
 
+// 计数布隆过滤器操作（支持删除）
+//
+// 每个槽位是一个4位饱和计数器，计数器达到上限后不再增减，避免删除时产生假阴性
+pub struct CountingBloomFilter;
+
+impl CountingBloomFilter {
+    // 配置的过滤器字节数（限制在允许范围内）
+    pub fn clamp_size(bloom_filter_size: u16) -> usize {
+        (bloom_filter_size as usize).clamp(MIN_BLOOM_FILTER_SIZE, MAX_BLOOM_FILTER_SIZE)
+    }
+
+    // 槽位数量
+    pub fn slot_count(filter: &[u8]) -> usize {
+        filter.len() * 2
+    }
+
+    fn slot_index(filter: &[u8], value: u64, seed: u8) -> usize {
+        (multi_hash(value, seed) % Self::slot_count(filter) as u64) as usize
+    }
+
+    fn counter(filter: &[u8], slot: usize) -> u8 {
+        (filter[slot / 2] >> ((slot % 2) * 4)) & 0x0F
+    }
+
+    fn set_counter(filter: &mut [u8], slot: usize, counter: u8) {
+        let shift = (slot % 2) * 4;
+        filter[slot / 2] = (filter[slot / 2] & !(0x0F << shift)) | (counter << shift);
+    }
+
+    // 添加元素
+    pub fn add(filter: &mut [u8], value: u64) {
+        if filter.is_empty() {
+            return;
+        }
+        for i in 0..BLOOM_HASH_COUNT {
+            let slot = Self::slot_index(filter, value, i);
+            let counter = Self::counter(filter, slot);
+            if counter < BLOOM_COUNTER_MAX {
+                Self::set_counter(filter, slot, counter + 1);
+            }
+        }
+    }
+
+    // 删除元素（调用方需确认元素确实存在），饱和计数器保持不变
+    pub fn remove(filter: &mut [u8], value: u64) {
+        if !Self::might_contain(filter, value) {
+            return;
+        }
+        for i in 0..BLOOM_HASH_COUNT {
+            let slot = Self::slot_index(filter, value, i);
+            let counter = Self::counter(filter, slot);
+            if counter < BLOOM_COUNTER_MAX {
+                Self::set_counter(filter, slot, counter - 1);
+            }
+        }
+    }
+
+    // 检查元素是否可能存在（空过滤器视为未启用）
+    pub fn might_contain(filter: &[u8], value: u64) -> bool {
+        if filter.is_empty() {
+            return true;
+        }
+        (0..BLOOM_HASH_COUNT).all(|i| Self::counter(filter, Self::slot_index(filter, value, i)) > 0)
+    }
+
+    // 非零槽位数量
+    pub fn occupied_slots(filter: &[u8]) -> u32 {
+        (0..Self::slot_count(filter))
+            .filter(|&slot| Self::counter(filter, slot) > 0)
+            .count() as u32
+    }
+
+    // 饱和槽位数量
+    pub fn saturated_slots(filter: &[u8]) -> u32 {
+        (0..Self::slot_count(filter))
+            .filter(|&slot| Self::counter(filter, slot) == BLOOM_COUNTER_MAX)
+            .count() as u32
+    }
+
+    // 估算元素数量
+    pub fn estimate_count(filter: &[u8]) -> u32 {
+        let slots = Self::slot_count(filter);
+        if slots == 0 {
+            return 0;
+        }
+        let fill_rate = Self::occupied_slots(filter) as f32 / slots as f32;
+        if fill_rate >= 1.0 {
+            return u32::MAX;
+        }
+
+        let m = slots as f32;
+        let k = BLOOM_HASH_COUNT as f32;
+        let estimated = -(m / k) * (1.0 - fill_rate).ln();
+        estimated.max(0.0) as u32
+    }
+}
+
 // 布隆过滤器摘要（用于快速比较）
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
 pub struct BloomSummary {
@@ -97,7 +199,58 @@ impl BloomSummary {
         }
     }
 
+    pub fn from_counting_filter(filter: &[u8]) -> Self {
+        let mut checksum = 0u64;
+        for chunk in filter.chunks(8) {
+            let mut bytes = [0u8; 8];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            checksum ^= u64::from_le_bytes(bytes);
+        }
+
+        let slots = CountingBloomFilter::slot_count(filter).max(1);
+        let fill_rate =
+            (CountingBloomFilter::occupied_slots(filter) as u64 * 10000 / slots as u64) as u16;
+        let estimated_count = CountingBloomFilter::estimate_count(filter);
+
+        Self {
+            checksum,
+            fill_rate,
+            estimated_count,
+        }
+    }
+
     pub fn get_fill_rate(&self) -> f32 {
         self.fill_rate as f32 / 10000.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counting_bloom_filter_removal() {
+        let mut filter = vec![0u8; CountingBloomFilter::clamp_size(0)];
+        assert_eq!(CountingBloomFilter::slot_count(&filter), 64);
+
+        for product_id in 1..=40u64 {
+            CountingBloomFilter::add(&mut filter, product_id);
+        }
+        for product_id in 1..=20u64 {
+            CountingBloomFilter::remove(&mut filter, product_id);
+        }
+
+        // Removing products never hides the ones that remain
+        for product_id in 21..=40u64 {
+            assert!(CountingBloomFilter::might_contain(&filter, product_id));
+        }
+
+        for product_id in 21..=40u64 {
+            CountingBloomFilter::remove(&mut filter, product_id);
+        }
+        assert_eq!(
+            CountingBloomFilter::occupied_slots(&filter),
+            CountingBloomFilter::saturated_slots(&filter)
+        );
+    }
+}
@@ -30,6 +30,13 @@ pub enum SortOrder {
     Descending,
 }
 
+// Search result sort key
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum SortKey {
+    ProductId,
+    Price,
+}
+
 // Search result filter
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct SearchFilter {
@@ -60,7 +67,7 @@ impl Default for SearchFilter {
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct SearchResult {
     pub product_ids: Vec<u64>,
-    pub total_count: u32,
+    pub total_count: u32, // Candidates to evaluate, not the number of products matching the filter
     pub has_more: bool,
     pub next_offset: u32,
 }
@@ -121,6 +128,90 @@ pub fn calculate_rent(size: usize, rent: &Rent) -> u64 {
     rent.minimum_balance(size)
 }
 
+// Resize a program-owned account, the payer funds any additional rent
+pub fn resize_account<'info>(
+    account: &AccountInfo<'info>,
+    new_space: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let rent_due = Rent::get()?
+        .minimum_balance(new_space)
+        .saturating_sub(account.lamports());
+    if rent_due > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+    account.realloc(new_space, false)?;
+    Ok(())
+}
+
+// Close a program owned account, its rent goes to `beneficiary`
+pub fn close_program_account<'info>(
+    account: &AccountInfo<'info>,
+    beneficiary: &AccountInfo<'info>,
+) -> Result<()> {
+    require!(
+        account.owner == &crate::ID,
+        crate::error::ErrorCode::InvalidAccountOwner
+    );
+    let lamports = account.lamports();
+    **account.try_borrow_mut_lamports()? = 0;
+    **beneficiary.try_borrow_mut_lamports()? = beneficiary
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(crate::error::ErrorCode::ArithmeticOverflow)?;
+    account.assign(&anchor_lang::system_program::ID);
+    account.realloc(0, false)?;
+    Ok(())
+}
+
+// Rewrite an account holding the legacy layout `L` as `T`, resized to `space`
+//
+// Legacy accounts are recognised by their size, the payer funds any additional rent.
+pub fn migrate_account<'info, L, T>(
+    account: &AccountInfo<'info>,
+    legacy_space: usize,
+    space: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<T>
+where
+    L: AnchorDeserialize,
+    T: AccountSerialize + Discriminator + From<L>,
+{
+    require!(
+        account.owner == &crate::ID,
+        crate::error::ErrorCode::InvalidAccountOwner
+    );
+    require!(
+        account.data_len() < space,
+        crate::error::ErrorCode::AccountAlreadyMigrated
+    );
+    let state: T = {
+        let data = account.try_borrow_data()?;
+        require!(
+            data.len() == legacy_space && data[..8] == *T::DISCRIMINATOR,
+            crate::error::ErrorCode::InvalidLegacyAccount
+        );
+        L::deserialize(&mut &data[8..])
+            .map_err(|_| crate::error::ErrorCode::InvalidLegacyAccount)?
+            .into()
+    };
+
+    resize_account(account, space, payer, system_program)?;
+    state.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+    Ok(state)
+}
+
 // Verify PDA seeds
 pub fn verify_pda(expected_key: &Pubkey, seeds: &[&[u8]], program_id: &Pubkey) -> Result<u8> {
     let (derived_key, bump) = Pubkey::find_program_address(seeds, program_id);
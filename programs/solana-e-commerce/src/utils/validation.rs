@@ -102,9 +102,12 @@ pub fn validate_sales_count(sales: u32) -> Result<()> {
     Ok(())
 }
 
-// 验证评分
+// 验证评分（评价使用，返回程序错误码）
 pub fn validate_rating(rating: u8) -> Result<()> {
-    require!(rating <= 5, ErrorCode::InvalidRating);
+    require!(
+        (crate::state::MIN_RATING..=crate::state::MAX_RATING).contains(&rating),
+        crate::error::ErrorCode::InvalidRating
+    );
     Ok(())
 }
 
@@ -0,0 +1,573 @@
+//! Property tests for the index and ID generator state machines
+//!
+//! Random add/remove/update sequences run against a plain model of each account and the
+//! invariants are checked after every step. Shrunk failures are persisted by proptest under
+//! `tests/proptest-regressions/` and the fixed ones are pinned as regular tests at the bottom.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Once;
+
+use anchor_lang::prelude::{Clock, Pubkey};
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use proptest::prelude::*;
+use proptest::test_runner::FileFailurePersistence;
+use solana_e_commerce::state::{
+    GlobalIdRoot, IdChunk, KeywordShard, PriceIndexNode, SalesIndexNode, BLOOM_SUMMARY_SIZE,
+    DEFAULT_CHUNK_SIZE, MAX_PRODUCTS_PER_SHARD, MAX_TOP_ITEMS,
+};
+use solana_e_commerce::utils::{
+    BloomFilter, CountingBloomFilter, BLOOM_FILTER_SIZE, MAX_BLOOM_FILTER_SIZE,
+};
+
+const CLOCK_TIMESTAMP: i64 = 1_700_000_000;
+
+struct ClockStubs;
+
+impl SyscallStubs for ClockStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            unix_timestamp: CLOCK_TIMESTAMP,
+            ..Clock::default()
+        };
+        unsafe { (var_addr as *mut Clock).write(clock) };
+        0
+    }
+}
+
+// SalesIndexNode reads the Clock sysvar, which needs a stub off-chain
+fn install_clock() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        set_syscall_stubs(Box::new(ClockStubs));
+    });
+}
+
+fn keyword_shard() -> KeywordShard {
+    let mut shard = KeywordShard {
+        keyword: String::new(),
+        shard_index: 0,
+        prev_shard: Pubkey::default(),
+        next_shard: None,
+        product_ids: Vec::new(),
+        min_id: 0,
+        max_id: 0,
+        bloom_summary: [0; BLOOM_SUMMARY_SIZE],
+        bump: 0,
+    };
+    shard
+        .initialize("lamp".to_string(), 0, Pubkey::default(), 255)
+        .unwrap();
+    shard
+}
+
+fn price_node(price_range_start: u64, price_range_end: u64) -> PriceIndexNode {
+    let mut node = PriceIndexNode {
+        payment_token: Pubkey::default(),
+        token_decimals: 0,
+        price_range_start: 0,
+        price_range_end: 0,
+        entries: Vec::new(),
+        left_child: None,
+        right_child: None,
+        parent: None,
+        height: 0,
+        bump: 0,
+    };
+    node.initialize(
+        Pubkey::default(),
+        6,
+        price_range_start,
+        price_range_end,
+        255,
+    )
+    .unwrap();
+    node
+}
+
+fn sales_node(sales_range_start: u32, sales_range_end: u32) -> SalesIndexNode {
+    let mut node = SalesIndexNode {
+        sales_range_start: 0,
+        sales_range_end: 0,
+        product_ids: Vec::new(),
+        top_items: Vec::new(),
+        left_child: None,
+        right_child: None,
+        parent: None,
+        height: 0,
+        bump: 0,
+    };
+    node.initialize(sales_range_start, sales_range_end, 255)
+        .unwrap();
+    node
+}
+
+fn id_chunk(merchant_id: u32, chunk_index: u32, start_id: u64, chunk_size: u32) -> IdChunk {
+    let mut chunk = IdChunk {
+        merchant_id: 0,
+        chunk_index: 0,
+        start_id: 0,
+        end_id: 0,
+        next_available: 0,
+        bitmap: Vec::new(),
+        bump: 0,
+    };
+    chunk.initialize(merchant_id, chunk_index, start_id, chunk_size, 255);
+    chunk
+}
+
+fn initial_chunk(merchant_id: u32, chunk_size: u32) -> IdChunk {
+    id_chunk(
+        merchant_id,
+        0,
+        IdChunk::initial_start_id(merchant_id),
+        chunk_size,
+    )
+}
+
+fn global_root(chunk_size: u32) -> GlobalIdRoot {
+    GlobalIdRoot {
+        last_merchant_id: 0,
+        last_global_id: 0,
+        chunk_size,
+        merchants: Vec::new(),
+        max_products_per_shard: MAX_PRODUCTS_PER_SHARD as u16,
+        max_keywords_per_product: 10,
+        bloom_filter_size: BLOOM_FILTER_SIZE as u16,
+        bump: 255,
+    }
+}
+
+// Small IDs collide often, arbitrary ones exercise the arithmetic
+fn product_id() -> impl Strategy<Value = u64> {
+    prop_oneof![3 => 0u64..64, 1 => any::<u64>()]
+}
+
+#[derive(Clone, Debug)]
+enum ShardOp {
+    Add(u64),
+    Remove(u64),
+    Absorb(Vec<u64>),
+}
+
+fn shard_op() -> impl Strategy<Value = ShardOp> {
+    prop_oneof![
+        6 => product_id().prop_map(ShardOp::Add),
+        3 => product_id().prop_map(ShardOp::Remove),
+        1 => prop::collection::vec(product_id(), 0..8).prop_map(ShardOp::Absorb),
+    ]
+}
+
+fn check_shard(shard: &KeywordShard, model: &BTreeSet<u64>) {
+    let ids: BTreeSet<u64> = shard.product_ids.iter().copied().collect();
+    assert_eq!(ids.len(), shard.product_ids.len(), "duplicate product IDs");
+    assert_eq!(&ids, model);
+    assert!(shard.product_ids.len() <= MAX_PRODUCTS_PER_SHARD);
+
+    match (model.first(), model.last()) {
+        (Some(&min_id), Some(&max_id)) => {
+            assert_eq!((shard.min_id, shard.max_id), (min_id, max_id));
+        }
+        _ => assert_eq!((shard.min_id, shard.max_id), (u64::MAX, 0)),
+    }
+    for &product_id in model {
+        assert!(shard.might_contain(product_id), "bloom false negative");
+    }
+}
+
+#[derive(Clone, Debug)]
+enum PriceOp {
+    Add(u64, u64),
+    Remove(u64),
+}
+
+fn price_op() -> impl Strategy<Value = PriceOp> {
+    prop_oneof![
+        3 => (0u64..48, 0u64..1_200).prop_map(|(id, price)| PriceOp::Add(id, price)),
+        1 => (0u64..48).prop_map(PriceOp::Remove),
+    ]
+}
+
+fn check_price_node(node: &PriceIndexNode, model: &BTreeMap<u64, u64>) {
+    assert!(
+        node.entries
+            .windows(2)
+            .all(|pair| (pair[0].price, pair[0].product_id) < (pair[1].price, pair[1].product_id)),
+        "entries not strictly sorted by (price, product_id)"
+    );
+    let stored: BTreeMap<u64, u64> = node
+        .entries
+        .iter()
+        .map(|entry| (entry.product_id, entry.price))
+        .collect();
+    assert_eq!(stored.len(), node.entries.len(), "duplicate product IDs");
+    assert_eq!(&stored, model);
+    assert!(node
+        .entries
+        .iter()
+        .all(|entry| node.contains_price(entry.price)));
+}
+
+#[derive(Clone, Debug)]
+enum SalesOp {
+    Add(u64, u32),
+    Remove(u64),
+    Update(u64, u32),
+}
+
+fn sales_op() -> impl Strategy<Value = SalesOp> {
+    prop_oneof![
+        3 => (0u64..32, 0u32..150).prop_map(|(id, sales)| SalesOp::Add(id, sales)),
+        1 => (0u64..32).prop_map(SalesOp::Remove),
+        3 => (0u64..32, 0u32..150).prop_map(|(id, sales)| SalesOp::Update(id, sales)),
+    ]
+}
+
+fn check_sales_node(node: &SalesIndexNode, model: &BTreeMap<u64, u32>) {
+    let ids: BTreeSet<u64> = node.product_ids.iter().copied().collect();
+    assert_eq!(ids.len(), node.product_ids.len(), "duplicate product IDs");
+    assert!(ids.iter().eq(model.keys()));
+
+    assert!(node.top_items.len() <= MAX_TOP_ITEMS, "top_items overflow");
+    assert!(
+        node.top_items
+            .windows(2)
+            .all(|pair| pair[0].sales >= pair[1].sales),
+        "top_items not ordered by sales"
+    );
+    let top_ids: BTreeSet<u64> = node.top_items.iter().map(|item| item.product_id).collect();
+    assert_eq!(top_ids.len(), node.top_items.len(), "duplicate top item");
+    for item in &node.top_items {
+        assert_eq!(model.get(&item.product_id), Some(&item.sales));
+        assert_eq!(item.last_update, CLOCK_TIMESTAMP);
+    }
+}
+
+#[derive(Clone, Debug)]
+enum ChunkOp {
+    Allocate,
+    Release(u64),
+    Mark(u64),
+}
+
+// Local IDs run a little past the chunk end to cover out of range calls
+fn chunk_op() -> impl Strategy<Value = ChunkOp> {
+    prop_oneof![
+        4 => Just(ChunkOp::Allocate),
+        2 => (0u64..80).prop_map(ChunkOp::Release),
+        1 => (0u64..80).prop_map(ChunkOp::Mark),
+    ]
+}
+
+fn check_chunk(chunk: &IdChunk, model: &BTreeSet<u64>) {
+    assert_eq!(chunk.used_count(), model.len() as u64, "popcount drift");
+    for local_id in 0..chunk.capacity() {
+        assert_eq!(chunk.is_id_used(local_id), model.contains(&local_id));
+    }
+    // Every local ID below next_available is in use
+    assert!((0..chunk.next_available.min(chunk.capacity())).all(|id| model.contains(&id)));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        failure_persistence: Some(Box::new(FileFailurePersistence::Direct(
+            "tests/proptest-regressions/state_properties.txt",
+        ))),
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn keyword_shard_matches_model(ops in prop::collection::vec(shard_op(), 1..200)) {
+        let mut shard = keyword_shard();
+        let mut model = BTreeSet::new();
+
+        for op in ops {
+            match op {
+                ShardOp::Add(product_id) => {
+                    let result = shard.add_product(product_id);
+                    if model.len() >= MAX_PRODUCTS_PER_SHARD {
+                        prop_assert!(result.is_err());
+                    } else {
+                        prop_assert!(result.is_ok());
+                        model.insert(product_id);
+                    }
+                }
+                ShardOp::Remove(product_id) => {
+                    prop_assert_eq!(shard.remove_product(product_id).unwrap(), model.remove(&product_id));
+                }
+                ShardOp::Absorb(product_ids) => {
+                    let mut other = keyword_shard();
+                    for &product_id in &product_ids {
+                        other.add_product(product_id).unwrap();
+                    }
+                    let result = shard.absorb(&other);
+                    if model.len() + other.product_ids.len() > MAX_PRODUCTS_PER_SHARD {
+                        prop_assert!(result.is_err());
+                    } else {
+                        prop_assert!(result.is_ok());
+                        model.extend(product_ids);
+                    }
+                }
+            }
+            check_shard(&shard, &model);
+        }
+    }
+
+    #[test]
+    fn price_node_matches_model(
+        ops in prop::collection::vec(price_op(), 1..200),
+        (range_start, range_end) in (0u64..500).prop_flat_map(|start| (Just(start), start..1_000)),
+    ) {
+        let mut node = price_node(range_start, range_end);
+        let mut model = BTreeMap::new();
+
+        for op in ops {
+            match op {
+                PriceOp::Add(product_id, price) => {
+                    let result = node.add_product(product_id, price);
+                    if !(range_start..=range_end).contains(&price) {
+                        prop_assert!(result.is_err());
+                    } else {
+                        prop_assert!(result.is_ok());
+                        model.entry(product_id).or_insert(price);
+                    }
+                }
+                PriceOp::Remove(product_id) => {
+                    prop_assert_eq!(node.remove_product(product_id).unwrap(), model.remove(&product_id).is_some());
+                }
+            }
+            check_price_node(&node, &model);
+        }
+    }
+
+    #[test]
+    fn price_node_range_queries_and_split(
+        entries in prop::collection::btree_map(0u64..1_000, 0u64..64, 0..120),
+        (min_price, max_price) in (0u64..70, 0u64..70),
+    ) {
+        let mut node = price_node(0, 63);
+        for (&product_id, &price) in &entries {
+            node.add_product(product_id, price).unwrap();
+        }
+
+        let expected: BTreeSet<u64> = entries
+            .iter()
+            .filter(|(_, &price)| price >= min_price && price <= max_price)
+            .map(|(&product_id, _)| product_id)
+            .collect();
+        let found = node.get_products_in_range(min_price, max_price);
+        prop_assert_eq!(found.len(), expected.len());
+        prop_assert_eq!(found.into_iter().collect::<BTreeSet<u64>>(), expected);
+
+        let distinct_prices: BTreeSet<u64> = entries.values().copied().collect();
+        let Some(split_price) = node.median_split_price() else {
+            prop_assert!(distinct_prices.len() < 2);
+            return Ok(());
+        };
+        let mut left = price_node(0, split_price - 1);
+        let mut right = price_node(split_price, 63);
+        node.split_entries_into(split_price, &mut left, &mut right).unwrap();
+
+        // Both children get entries and together hold exactly the original ones
+        prop_assert!(node.entries.is_empty());
+        prop_assert!(!left.entries.is_empty() && !right.entries.is_empty());
+        let mut moved: BTreeMap<u64, u64> = BTreeMap::new();
+        for entry in left.entries.iter().chain(&right.entries) {
+            moved.insert(entry.product_id, entry.price);
+        }
+        prop_assert_eq!(moved, entries);
+    }
+
+    #[test]
+    fn sales_node_matches_model(ops in prop::collection::vec(sales_op(), 1..200)) {
+        install_clock();
+        let mut node = sales_node(0, 99);
+        let mut model = BTreeMap::new();
+
+        for op in ops {
+            match op {
+                SalesOp::Add(product_id, sales) => {
+                    let result = node.add_product(product_id, sales);
+                    if sales > 99 {
+                        prop_assert!(result.is_err());
+                    } else {
+                        prop_assert!(result.is_ok());
+                        model.entry(product_id).or_insert(sales);
+                    }
+                }
+                SalesOp::Remove(product_id) => {
+                    prop_assert_eq!(node.remove_product(product_id).unwrap(), model.remove(&product_id).is_some());
+                }
+                SalesOp::Update(product_id, sales) => {
+                    node.update_product_sales(product_id, sales).unwrap();
+                    if let Some(stored) = model.get_mut(&product_id) {
+                        *stored = sales;
+                    }
+                }
+            }
+            check_sales_node(&node, &model);
+        }
+    }
+
+    #[test]
+    fn id_chunk_matches_model(
+        ops in prop::collection::vec(chunk_op(), 1..300),
+        merchant_id in 0u32..1_000,
+        chunk_size in 1u32..64,
+    ) {
+        let mut chunk = initial_chunk(merchant_id, chunk_size);
+        let capacity = chunk_size as u64;
+        let mut model = BTreeSet::new();
+
+        for op in ops {
+            match op {
+                ChunkOp::Allocate => {
+                    let lowest_unused = (0..capacity).find(|id| !model.contains(id));
+                    let allocated = chunk.allocate_next();
+                    prop_assert_eq!(allocated, lowest_unused.map(|id| chunk.start_id + id));
+                    if let Some(local_id) = lowest_unused {
+                        model.insert(local_id);
+                    }
+                    prop_assert_eq!(chunk.is_full(), model.len() as u64 == capacity);
+                }
+                ChunkOp::Release(local_id) => {
+                    let result = chunk.release(chunk.start_id + local_id);
+                    if local_id >= capacity {
+                        prop_assert!(result.is_err());
+                    } else {
+                        prop_assert_eq!(result.unwrap(), model.remove(&local_id));
+                    }
+                }
+                ChunkOp::Mark(local_id) => {
+                    chunk.mark_id_used(local_id);
+                    if local_id < capacity {
+                        model.insert(local_id);
+                    }
+                }
+            }
+            check_chunk(&chunk, &model);
+        }
+    }
+
+    #[test]
+    fn id_chunks_do_not_overlap(
+        merchant_ids in prop::collection::btree_set(any::<u32>(), 1..20),
+        merchants_with_later_chunks in prop::collection::vec(any::<u32>(), 0..20),
+        chunk_size in prop_oneof![Just(DEFAULT_CHUNK_SIZE), 1u32..=DEFAULT_CHUNK_SIZE],
+    ) {
+        // Initial chunks of any merchants, later chunks reserved from the pool in any order
+        let mut root = global_root(chunk_size);
+        let mut chunks: Vec<IdChunk> = merchant_ids
+            .iter()
+            .map(|&merchant_id| initial_chunk(merchant_id, chunk_size))
+            .collect();
+        for (index, &merchant_id) in merchants_with_later_chunks.iter().enumerate() {
+            let start_id = root.reserve_chunk_range();
+            chunks.push(id_chunk(merchant_id, index as u32 + 1, start_id, chunk_size));
+        }
+
+        chunks.sort_by_key(|chunk| chunk.start_id);
+        for chunk in &chunks {
+            prop_assert_eq!(chunk.capacity(), chunk_size as u64);
+        }
+        for pair in chunks.windows(2) {
+            prop_assert!(pair[0].end_id < pair[1].start_id);
+            prop_assert!(!pair[0].contains_id(pair[1].start_id));
+        }
+    }
+
+    #[test]
+    fn bloom_filter_has_no_false_negatives(
+        values in prop::collection::vec(any::<u64>(), 0..300),
+        other in prop::collection::vec(any::<u64>(), 0..50),
+    ) {
+        let mut filter = [0u8; BLOOM_FILTER_SIZE];
+        for &value in &values {
+            BloomFilter::add(&mut filter, value);
+        }
+        let mut merged = [0u8; BLOOM_FILTER_SIZE];
+        for &value in &other {
+            BloomFilter::add(&mut merged, value);
+        }
+        BloomFilter::merge(&mut merged, &filter);
+
+        for value in &values {
+            prop_assert!(BloomFilter::might_contain(&filter, *value));
+        }
+        for value in values.iter().chain(&other) {
+            prop_assert!(BloomFilter::might_contain(&merged, *value));
+        }
+    }
+
+    #[test]
+    fn counting_bloom_filter_removal_keeps_remaining(
+        size in 0u16..(MAX_BLOOM_FILTER_SIZE as u16 + 64),
+        values in prop::collection::vec(prop_oneof![0u64..32, any::<u64>()], 0..400),
+        removals in prop::collection::vec(any::<prop::sample::Index>(), 0..400),
+    ) {
+        let mut filter = vec![0u8; CountingBloomFilter::clamp_size(size)];
+        let mut present = values.clone();
+        for &value in &values {
+            CountingBloomFilter::add(&mut filter, value);
+        }
+
+        // Only elements that were added are removed, as the keyword index does
+        for index in removals {
+            if present.is_empty() {
+                break;
+            }
+            let value = present.swap_remove(index.index(present.len()));
+            CountingBloomFilter::remove(&mut filter, value);
+            for remaining in &present {
+                prop_assert!(CountingBloomFilter::might_contain(&filter, *remaining));
+            }
+        }
+    }
+}
+
+// Minimized failures found by the properties above
+
+#[test]
+fn test_bloom_summary_of_large_product_id() {
+    // product_id * 31 overflowed u64 and panicked
+    let mut shard = keyword_shard();
+    let mut other = keyword_shard();
+    other.add_product(595_056_260_442_243_601).unwrap();
+    shard.absorb(&other).unwrap();
+    shard.add_product(u64::MAX).unwrap();
+
+    assert!(shard.might_contain(595_056_260_442_243_601));
+    assert!(shard.might_contain(u64::MAX));
+    assert_eq!(
+        (shard.min_id, shard.max_id),
+        (595_056_260_442_243_601, u64::MAX)
+    );
+}
+
+#[test]
+fn test_top_items_fit_account_space() {
+    // top_items was truncated to 20 while the account only has room for MAX_TOP_ITEMS
+    install_clock();
+    let mut node = sales_node(0, 99);
+    for product_id in 0..=MAX_TOP_ITEMS as u64 {
+        node.add_product(product_id, 0).unwrap();
+    }
+    assert_eq!(node.product_ids.len(), MAX_TOP_ITEMS + 1);
+    assert_eq!(node.top_items.len(), MAX_TOP_ITEMS);
+
+    // A better seller still enters the cache at the front
+    node.update_product_sales(MAX_TOP_ITEMS as u64, 42).unwrap();
+    assert_eq!(node.top_items.len(), MAX_TOP_ITEMS);
+    assert_eq!(node.top_items[0].product_id, MAX_TOP_ITEMS as u64);
+    assert_eq!(node.top_items[0].sales, 42);
+}
+
+#[test]
+fn test_mark_id_past_chunk_end() {
+    // Bits past the chunk end were set and counted as used IDs
+    let mut chunk = initial_chunk(0, 1);
+    chunk.mark_id_used(16);
+    assert_eq!(chunk.used_count(), 0);
+    assert_eq!(chunk.allocate_next(), Some(0));
+    assert_eq!(chunk.used_count(), 1);
+    assert!(chunk.is_full());
+}
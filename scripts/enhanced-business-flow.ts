@@ -572,6 +572,12 @@ export class EnhancedBusinessFlowExecutor {
       vaultAccount: new PublicKey("8hDcWvDXvZHcqneLAPBQMjCY9Bpwatdyv16fx7Pf3fys"), // Vault数据账户 (修正为用户提供的正确地址)
       vaultTokenAccount: new PublicKey("GSzHB4ZRdA26yZRXRnSvTx41YJFQnBivifaNn6XKHQy1"), // Vault Token账户
       platformTokenAccount: new PublicKey("HKSDubsoppVK9tyPBonLZbfu4z16Pb4qQimugnFgARdq"), // 平台Token账户 (使用您提供的地址)
+
+      // 热度半衰期（秒）- 商品热度按此半衰期随时间衰减
+      trendingHalfLifeSeconds: 7 * 24 * 60 * 60,
+
+      // 零拷贝索引分片最大容量（条目数）
+      indexShardCapacity: 4096,
     };
 
     // 调用 initialize_system 指令
@@ -662,6 +668,12 @@ export class EnhancedBusinessFlowExecutor {
       vaultAccount: new PublicKey("8hDcWvDXvZHcqneLAPBQMjCY9Bpwatdyv16fx7Pf3fys"), // Vault数据账户 (修正为用户提供的正确地址)
       vaultTokenAccount: new PublicKey("GSzHB4ZRdA26yZRXRnSvTx41YJFQnBivifaNn6XKHQy1"), // Vault Token账户
       platformTokenAccount: new PublicKey("HKSDubsoppVK9tyPBonLZbfu4z16Pb4qQimugnFgARdq"), // 平台Token账户 (使用您提供的地址)
+
+      // 热度半衰期（秒）- 商品热度按此半衰期随时间衰减
+      trendingHalfLifeSeconds: 7 * 24 * 60 * 60,
+
+      // 零拷贝索引分片最大容量（条目数）
+      indexShardCapacity: 4096,
     };
 
     // 调用 initialize_system_config 指令
@@ -1285,6 +1297,7 @@ export class EnhancedBusinessFlowExecutor {
       const priceRangeEnd = this.calculatePriceRangeEnd(priceValue);
       const [priceIndexPDA] = this.calculatePDA([
         "price_index",
+        this.tokenMint!.toBuffer(),
         new anchor.BN(priceRangeStart).toArrayLike(Buffer, "le", 8),
         new anchor.BN(priceRangeEnd).toArrayLike(Buffer, "le", 8),
       ]);
@@ -1325,8 +1338,10 @@ export class EnhancedBusinessFlowExecutor {
           merchantIdAccount: merchantIdAccountPDA,
           merchantInfo: merchantInfoPDA,
           activeChunk: activeChunkPDA,
+          nextChunk: await this.getNextChunkPDA(merchantPubkey),
           paymentConfig: paymentConfigPDA,
           productAccount: productAccountPDA,
+          ...(await this.getMerchantCatalogAccounts(merchantPubkey)),
           systemProgram: SystemProgram.programId,
         } as any)
         .instruction();
@@ -1339,6 +1354,7 @@ export class EnhancedBusinessFlowExecutor {
         const keywordIndexInstruction = await this.program.methods
           .addProductToKeywordIndex(keyword, new anchor.BN(nextProductId))
           .accounts({
+            globalRoot: this.calculatePDA(["global_id_root"])[0],
             keywordRoot: keywordRootPDA,
             targetShard: keywordShardPDA, // 使用正确的分片PDA
             payer: merchantPubkey,
@@ -1364,6 +1380,7 @@ export class EnhancedBusinessFlowExecutor {
           .accounts({
             payer: merchantPubkey,
             priceIndex: priceIndexPDA,
+            paymentMint: this.tokenMint!,
             systemProgram: SystemProgram.programId,
           } as any)
           .instruction();
@@ -1609,6 +1626,7 @@ export class EnhancedBusinessFlowExecutor {
       const priceRangeEndBytes = new anchor.BN(priceRangeEnd).toArray("le", 8);
       const [priceIndexPDA] = this.calculatePDA([
         "price_index",
+        this.tokenMint!.toBuffer(),
         Buffer.from(priceRangeStartBytes),
         Buffer.from(priceRangeEndBytes),
       ]);
@@ -1671,8 +1689,10 @@ export class EnhancedBusinessFlowExecutor {
           merchantIdAccount: merchantIdAccountPDA,
           merchantInfo: merchantInfoPDA,
           activeChunk: activeChunkPDA,
+          nextChunk: await this.getNextChunkPDA(merchantKeypair.publicKey),
           paymentConfig: paymentConfigPDA,
           productAccount: productBasePDA,
+          ...(await this.getMerchantCatalogAccounts(merchantKeypair.publicKey)),
           systemProgram: SystemProgram.programId,
         } as any)
         .instruction();
@@ -1704,6 +1724,7 @@ export class EnhancedBusinessFlowExecutor {
           const keywordIx = await this.program.methods
             .addProductToKeywordIndex(keyword, new anchor.BN(actualProductId))
             .accounts({
+              globalRoot: this.calculatePDA(["global_id_root"])[0],
               keywordRoot: keywordRootPDA,
               targetShard: targetShardPDA,
               payer: merchantKeypair.publicKey,
@@ -1729,6 +1750,7 @@ export class EnhancedBusinessFlowExecutor {
         .accounts({
           payer: merchantKeypair.publicKey,
           priceIndex: priceIndexPDA,
+          paymentMint: this.tokenMint!,
           systemProgram: SystemProgram.programId,
         } as any)
         .instruction();
@@ -1796,6 +1818,7 @@ export class EnhancedBusinessFlowExecutor {
       const priceRangeEndBytes = new anchor.BN(priceRangeEnd).toArray("le", 8);
       const [priceIndexPDA] = this.calculatePDA([
         "price_index",
+        this.tokenMint!.toBuffer(),
         Buffer.from(priceRangeStartBytes),
         Buffer.from(priceRangeEndBytes),
       ]);
@@ -1882,6 +1905,7 @@ export class EnhancedBusinessFlowExecutor {
         const signature = await this.program.methods
           .addProductToKeywordIndex(keyword, new anchor.BN(productId))
           .accounts({
+            globalRoot: this.calculatePDA(["global_id_root"])[0],
             keywordRoot: keywordRootPDA,
             targetShard: targetShardPDA,
             payer: merchantKeypair.publicKey,
@@ -1925,6 +1949,7 @@ export class EnhancedBusinessFlowExecutor {
         .accounts({
           payer: merchantKeypair.publicKey,
           priceIndex: priceIndexPDA,
+          paymentMint: this.tokenMint!,
           systemProgram: SystemProgram.programId,
         } as any)
         .signers([merchantKeypair])
@@ -2037,6 +2062,7 @@ export class EnhancedBusinessFlowExecutor {
           const signature = await this.program.methods
             .addProductToKeywordIndex(keyword, new anchor.BN(productId))
             .accounts({
+              globalRoot: this.calculatePDA(["global_id_root"])[0],
               keywordRoot: keywordRootPDA,
               targetShard: targetShardPDA,
               payer: merchantKeypair.publicKey,
@@ -2061,6 +2087,56 @@ export class EnhancedBusinessFlowExecutor {
     }
   }
 
+  /**
+   * 计算商家商品目录及下一个在售分页PDA（创建产品时追加到该分页）
+   */
+  private async getMerchantCatalogAccounts(
+    merchant: PublicKey
+  ): Promise<{ merchantProductList: PublicKey; activeProductPage: PublicKey }> {
+    const [merchantProductList] = this.calculatePDA(["merchant_product_list", merchant.toBuffer()]);
+
+    let activeAppended = 0;
+    try {
+      const productList = await this.program.account.merchantProductList.fetch(merchantProductList);
+      activeAppended = productList.activeAppended.toNumber();
+    } catch (error) {
+      // 目录尚未创建，首个商品写入第0页
+    }
+
+    const pageIndex = Math.floor(activeAppended / 50); // MERCHANT_PRODUCT_PAGE_SIZE
+    const [activeProductPage] = this.calculatePDA([
+      "merchant_active_products",
+      merchant.toBuffer(),
+      new anchor.BN(pageIndex).toArrayLike(Buffer, "le", 8),
+    ]);
+
+    return { merchantProductList, activeProductPage };
+  }
+
+  /**
+   * 计算商户的下一个ID块PDA（活跃块使用率超过80%时自动预分配，活跃块用尽时自动切换）
+   */
+  private async getNextChunkPDA(merchant: PublicKey): Promise<PublicKey> {
+    const [merchantIdAccountPDA] = this.calculatePDA(["merchant_id", merchant.toBuffer()]);
+
+    let lastChunkIndex = 0;
+    try {
+      const merchantIdAccount = await this.program.account.merchantIdAccount.fetch(
+        merchantIdAccountPDA
+      );
+      lastChunkIndex = merchantIdAccount.lastChunkIndex;
+    } catch (error) {
+      // 商户ID账户尚未创建，仅有第0块
+    }
+
+    const [nextChunk] = this.calculatePDA([
+      "id_chunk",
+      merchant.toBuffer(),
+      new anchor.BN(lastChunkIndex + 1).toArrayLike(Buffer, "le", 4),
+    ]);
+    return nextChunk;
+  }
+
   /**
    * 添加产品到价格索引
    */
@@ -2079,6 +2155,7 @@ export class EnhancedBusinessFlowExecutor {
       const priceRangeEndBytes = new anchor.BN(priceRangeEnd).toArray("le", 8);
       const [priceIndexPDA] = this.calculatePDA([
         "price_index",
+        this.tokenMint!.toBuffer(),
         Buffer.from(priceRangeStartBytes),
         Buffer.from(priceRangeEndBytes),
       ]);
@@ -2097,6 +2174,7 @@ export class EnhancedBusinessFlowExecutor {
         .accounts({
           payer: merchantKeypair.publicKey,
           priceIndex: priceIndexPDA,
+          paymentMint: this.tokenMint!,
           systemProgram: SystemProgram.programId,
         } as any)
         .signers([merchantKeypair])
@@ -2188,6 +2266,7 @@ export class EnhancedBusinessFlowExecutor {
       const signature = await this.program.methods
         .initializeKeywordIndex(keyword)
         .accounts({
+          globalRoot: this.calculatePDA(["global_id_root"])[0],
           keywordRoot: keywordRootPDA,
           firstShard: firstShardPDA,
           payer: this.merchantKeypair.publicKey,
@@ -3138,6 +3217,19 @@ export class EnhancedBusinessFlowExecutor {
       ]);
       const [programAuthorityPDA] = this.calculatePDA(["program_authority"]);
 
+      // 确认收货时记录销量：产品账户及其所在的销量索引节点（与创建产品时的销量范围一致）
+      const productIdBytes = new anchor.BN(orderData.productId).toArray("le", 8);
+      const [productPDA] = this.calculatePDA(["product", Buffer.from(productIdBytes)]);
+      const [salesIndexPDA] = this.calculatePDA([
+        "sales_index",
+        Buffer.from(new anchor.BN(0).toArray("le", 4)),
+        Buffer.from(new anchor.BN(1000).toArray("le", 4)),
+      ]);
+      // 结算时更新商品热度与全局热度榜单
+      const [trendingScorePDA] = this.calculatePDA(["trending_score", Buffer.from(productIdBytes)]);
+      const [trendingBoardPDA] = this.calculatePDA(["trending_global"]);
+      const [leaderboardPDA] = this.calculatePDA(["leaderboard", Buffer.from([0])]);
+
       console.log(`   🏪 Merchant public key: ${merchantPubkey.toString()}`);
       console.log(`   🏪 Merchant info PDA: ${merchantInfoPDA.toString()}`);
 
@@ -3163,6 +3255,12 @@ export class EnhancedBusinessFlowExecutor {
           order: orderPDA,
           orderStats: orderStatsPDA,
           merchantInfo: merchantInfoPDA,
+          product: productPDA,
+          oldSalesNode: salesIndexPDA,
+          newSalesNode: salesIndexPDA,
+          trendingScore: trendingScorePDA,
+          trendingBoard: trendingBoardPDA,
+          leaderboard: leaderboardPDA,
           systemConfig: systemConfigPDA,
           depositEscrowAccount: depositEscrowPDA,
           programTokenAccount: programTokenAccountPDA,
@@ -86,19 +86,17 @@ class MerchantProductQueryService {
     console.log(`   Sort: ${sortBy} ${sortOrder}`);
 
     try {
-      // 1. First get merchant info to find product_count
-      const merchantInfoPDA = this.calculatePDA(["merchant_info", merchant.toBuffer()])[0];
+      // 1. Read the merchant product catalog (active and inactive chains are kept separately)
+      const isActive = params.isActive ?? true;
+      const [productListPDA] = this.calculatePDA(["merchant_product_list", merchant.toBuffer()]);
 
-      console.log(`🏪 Merchant info PDA: ${merchantInfoPDA.toString()}`);
+      console.log(`🏪 Merchant product catalog PDA: ${productListPDA.toString()}`);
 
-      let merchantInfo: any;
+      let productList: any;
       try {
-        merchantInfo = await this.program.account.merchant.fetch(merchantInfoPDA);
-        console.log(
-          `✅ Found merchant info, product_count: ${merchantInfo.productCount.toNumber()}`
-        );
+        productList = await this.program.account.merchantProductList.fetch(productListPDA);
       } catch (error) {
-        console.log(`⚠️ Merchant info not found, assuming no products`);
+        console.log(`⚠️ Merchant product catalog not found, assuming no products`);
         return {
           products: [],
           totalCount: 0,
@@ -111,37 +109,50 @@ class MerchantProductQueryService {
         };
       }
 
-      const productCount = merchantInfo.productCount.toNumber();
-      console.log(`📊 Total products for merchant: ${productCount}`);
+      const appended = (isActive ? productList.activeAppended : productList.inactiveAppended).toNumber();
+      const catalogPageSize = 50; // MERCHANT_PRODUCT_PAGE_SIZE
+      const catalogPageCount = Math.ceil(appended / catalogPageSize);
+      const pagePrefix = isActive ? "merchant_active_products" : "merchant_inactive_products";
+
+      const productIds: number[] = [];
+      for (let pageIndex = 0; pageIndex < catalogPageCount; pageIndex++) {
+        const [catalogPagePDA] = this.calculatePDA([
+          pagePrefix,
+          merchant.toBuffer(),
+          Buffer.from(new anchor.BN(pageIndex).toArray("le", 8)),
+        ]);
+        try {
+          const catalogPage = await this.program.account.merchantProductPage.fetch(catalogPagePDA);
+          catalogPage.productIds.forEach((id: anchor.BN) => productIds.push(id.toNumber()));
+        } catch (error) {
+          console.warn(`⚠️ Failed to load catalog page ${pageIndex}:`, error);
+        }
+      }
+
+      console.log(`📊 Catalog products for merchant (${isActive ? "active" : "inactive"}): ${productIds.length}`);
 
-      // 2. Query each product using new PDA calculation
+      // 2. Load each product account by id
       const products: ProductWithDetails[] = [];
 
-      for (let i = 0; i < productCount; i++) {
+      for (const productId of productIds) {
         try {
-          // Calculate product PDA using new seed rules: ["product", merchant_pubkey, product_count]
-          const productCountBytes = new anchor.BN(i).toArray("le", 8);
           const [productPDA] = this.calculatePDA([
             "product",
-            merchant.toBuffer(),
-            Buffer.from(productCountBytes),
+            Buffer.from(new anchor.BN(productId).toArray("le", 8)),
           ]);
 
-          console.log(`🔍 Querying product ${i}: ${productPDA.toString()}`);
-
-          // Try to fetch the product account
           const productData = await this.program.account.productBase.fetch(productPDA);
           const formattedProduct = this.formatProductData(productData, productPDA);
           products.push(formattedProduct);
 
-          console.log(`✅ Successfully loaded product ${i}: ${productData.name}`);
+          console.log(`✅ Successfully loaded product ${productId}: ${productData.name}`);
         } catch (error) {
-          console.warn(`⚠️ Failed to load product ${i}:`, error);
+          console.warn(`⚠️ Failed to load product ${productId}:`, error);
           // Continue to next product instead of failing completely
         }
       }
 
-      console.log(`✅ Successfully loaded ${products.length} out of ${productCount} products`);
+      console.log(`✅ Successfully loaded ${products.length} out of ${productIds.length} products`);
 
       // 3. Apply filter conditions
       let filteredProducts = this.applyFilters(products, params);
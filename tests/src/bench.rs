@@ -0,0 +1,159 @@
+//! Compute unit and account size budgets
+//!
+//! Benchmarks collect the compute units of each instruction and the data length of each account
+//! type in a [`Report`], which is checked against the budgets committed in [`BUDGET_FILE`].
+//! Compute units are only metered when the program runs from its shared object
+//! ([`crate::runs_sbf`]); a native run checks account sizes alone.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Committed budgets
+pub const BUDGET_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/compute-budgets.json");
+
+/// Measurements of the last run, for comparing against the budgets by hand
+pub const REPORT_FILE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../target/bench/compute-units.json"
+);
+
+/// Set to rewrite [`BUDGET_FILE`] from a run instead of checking it
+pub const UPDATE_BUDGETS_ENV: &str = "UPDATE_BUDGETS";
+
+/// Headroom over measured compute units when budgets are rewritten, in percent
+///
+/// PDA bump searches depend on the random keys of a run, so compute units vary slightly between
+/// runs of the same code.
+pub const COMPUTE_UNIT_HEADROOM: u64 = 10;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    /// Highest compute units per instruction
+    pub compute_units: BTreeMap<String, u64>,
+    /// Largest data length per account type
+    pub account_sizes: BTreeMap<String, usize>,
+}
+
+impl Report {
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("reading {}: {}", path.display(), err));
+        serde_json::from_str(&json)
+            .unwrap_or_else(|err| panic!("parsing {}: {}", path.display(), err))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .unwrap_or_else(|err| panic!("creating {}: {}", dir.display(), err));
+        }
+        let json = serde_json::to_string_pretty(self).expect("serializing report");
+        fs::write(path, json + "\n")
+            .unwrap_or_else(|err| panic!("writing {}: {}", path.display(), err));
+    }
+
+    pub fn record_compute_units(&mut self, instruction: &str, units: u64) {
+        let entry = self
+            .compute_units
+            .entry(instruction.to_string())
+            .or_default();
+        *entry = (*entry).max(units);
+    }
+
+    pub fn record_account_size(&mut self, account: &str, size: usize) {
+        let entry = self.account_sizes.entry(account.to_string()).or_default();
+        *entry = (*entry).max(size);
+    }
+
+    /// Measurements over their budget or without one, compute units only when `metered`
+    pub fn violations(&self, budgets: &Report, metered: bool) -> Vec<String> {
+        let mut violations = Vec::new();
+        if metered {
+            for (instruction, &units) in &self.compute_units {
+                match budgets.compute_units.get(instruction) {
+                    None => violations.push(format!("{}: no compute unit budget", instruction)),
+                    Some(&budget) if units > budget => violations.push(format!(
+                        "{}: {} compute units, budget {}",
+                        instruction, units, budget
+                    )),
+                    Some(_) => {}
+                }
+            }
+        }
+        for (account, &size) in &self.account_sizes {
+            match budgets.account_sizes.get(account) {
+                None => violations.push(format!("{}: no account size budget", account)),
+                Some(&budget) if size > budget => {
+                    violations.push(format!("{}: {} bytes, budget {}", account, size, budget))
+                }
+                Some(_) => {}
+            }
+        }
+        violations
+    }
+
+    /// Budgets matching this report, keeping the compute unit budgets of `previous` when
+    /// compute units were not metered
+    pub fn to_budgets(&self, previous: &Report, metered: bool) -> Report {
+        let compute_units = if metered {
+            self.compute_units
+                .iter()
+                .map(|(instruction, &units)| {
+                    let budget = units + units * COMPUTE_UNIT_HEADROOM / 100;
+                    // Round up to a multiple of 1000
+                    (instruction.clone(), budget.div_ceil(1_000) * 1_000)
+                })
+                .collect()
+        } else {
+            previous.compute_units.clone()
+        };
+        Report {
+            compute_units,
+            account_sizes: self.account_sizes.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_violations_and_budgets() {
+        let mut report = Report::default();
+        report.record_compute_units("ship_order", 9_000);
+        report.record_compute_units("ship_order", 12_345);
+        report.record_compute_units("confirm_delivery", 40_000);
+        report.record_account_size("Order", 600);
+
+        let mut budgets = Report::default();
+        budgets
+            .compute_units
+            .insert("ship_order".to_string(), 12_000);
+        budgets.account_sizes.insert("Order".to_string(), 600);
+
+        assert_eq!(
+            report.violations(&budgets, true),
+            vec![
+                "confirm_delivery: no compute unit budget".to_string(),
+                "ship_order: 12345 compute units, budget 12000".to_string(),
+            ]
+        );
+        // Unmetered runs only check account sizes
+        assert!(report.violations(&budgets, false).is_empty());
+
+        let updated = report.to_budgets(&budgets, true);
+        assert_eq!(updated.compute_units["ship_order"], 14_000);
+        assert_eq!(updated.compute_units["confirm_delivery"], 44_000);
+        assert!(report.violations(&updated, true).is_empty());
+        assert_eq!(
+            report.to_budgets(&budgets, false).compute_units,
+            budgets.compute_units
+        );
+    }
+}
@@ -0,0 +1,804 @@
+//! In-process test harness for the solana-e-commerce program
+//!
+//! [`TestShop`] runs the program in solana-program-test next to the SPL token programs and a
+//! [`mock_vault`] standing in for the external vault program. Under `cargo test` the program runs
+//! natively; under `cargo test-sbf` (or with `SBF_OUT_DIR` pointing at `solana_e_commerce.so`) the
+//! compiled program is loaded instead.
+//!
+//! Flow helpers issue the same instruction sequences a client does, building every instruction
+//! with [`shop_client::instructions`].
+
+pub mod bench;
+pub mod mock_vault;
+
+use anchor_lang::prelude::{AccountInfo, Clock, Pubkey, Rent};
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::instruction::{Instruction, InstructionError};
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::{AccountDeserialize, AccountSerialize, AnchorDeserialize, Space};
+use anchor_spl::token::spl_token;
+use shop_client::{instructions as ix, pda};
+use solana_account::Account;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::instructions::price_index::find_price_node_for_price;
+use solana_e_commerce::state::{
+    recency_day, IdChunk, IndexShardKey, MerchantIdAccount, MerchantProductList, Order,
+    ProductBase, SalesIndexNode, SupportedToken, UserPurchaseCount,
+};
+use solana_e_commerce::SystemConfig;
+use solana_keypair::Keypair;
+use solana_program_runtime::invoke_context::BuiltinFunctionWithContext;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+use solana_transaction_error::TransactionError;
+
+pub const DECIMALS: u8 = 6;
+/// One whole token in base units
+pub const TOKEN: u64 = 10u64.pow(DECIMALS as u32);
+/// Merchant deposit requirement in whole tokens, as configured by [`TestShop::initialize`]
+pub const DEPOSIT_REQUIREMENT: u64 = 1_000;
+pub const PLATFORM_FEE_RATE: u16 = 40;
+pub const AUTO_CONFIRM_DAYS: u32 = 7;
+/// Sales index node every product is added to
+pub const SALES_RANGE: (u32, u32) = (0, 999);
+
+const SOL: u64 = 1_000_000_000;
+
+// Anchor's entrypoint wants the accounts for 'info, which a builtin cannot give it
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    solana_e_commerce::entry(program_id, accounts, data)
+}
+
+/// The program and the mock vault, on top of the default SPL programs
+pub fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::new(
+        "solana_e_commerce",
+        solana_e_commerce::ID,
+        processor!(process_instruction),
+    );
+    // Never loaded from a shared object, even when the program is
+    let mock_vault: Option<BuiltinFunctionWithContext> =
+        processor!(mock_vault::process_instruction);
+    program_test.add_builtin_program("mock_vault", mock_vault::ID, mock_vault.unwrap());
+    program_test
+}
+
+/// Whether the program runs from its shared object, the only mode that meters compute units
+///
+/// solana-program-test loads `solana_e_commerce.so` whenever `SBF_OUT_DIR` or `BPF_OUT_DIR` is set.
+pub fn runs_sbf() -> bool {
+    std::env::var("SBF_OUT_DIR").is_ok() || std::env::var("BPF_OUT_DIR").is_ok()
+}
+
+/// Custom error code of a failed transaction
+pub fn custom_error(err: BanksClientError) -> Option<u32> {
+    match err {
+        BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        ))
+        | BanksClientError::SimulationError {
+            err: TransactionError::InstructionError(_, InstructionError::Custom(code)),
+            ..
+        } => Some(code),
+        _ => None,
+    }
+}
+
+/// Anchor error code of a program error
+pub fn error_code(error: solana_e_commerce::error::ErrorCode) -> u32 {
+    anchor_lang::error::ERROR_CODE_OFFSET + error as u32
+}
+
+/// Accounts the platform fee is paid to, configured in SystemConfig
+pub struct Vault {
+    pub account: Pubkey,
+    pub token_account: Pubkey,
+    pub platform_token_account: Pubkey,
+}
+
+pub struct Merchant {
+    pub keypair: Keypair,
+    pub token_account: Pubkey,
+}
+
+pub struct Buyer {
+    pub keypair: Keypair,
+    pub token_account: Pubkey,
+}
+
+pub struct TestShop {
+    pub context: ProgramTestContext,
+    pub mint: Pubkey,
+    pub vault: Vault,
+    /// Compute units consumed by the last transaction sent with [`TestShop::process`], only
+    /// metered when [`runs_sbf`]
+    pub last_compute_units: u64,
+}
+
+impl TestShop {
+    /// Start a bank with the deposit token mint and the vault accounts, the program uninitialized
+    pub async fn start() -> Self {
+        Self::start_with(program_test()).await
+    }
+
+    pub async fn start_with(program_test: ProgramTest) -> Self {
+        let context = program_test.start_with_context().await;
+        let mut shop = Self {
+            context,
+            mint: Pubkey::new_unique(),
+            vault: Vault {
+                account: Pubkey::new_unique(),
+                token_account: Pubkey::new_unique(),
+                platform_token_account: Pubkey::new_unique(),
+            },
+            last_compute_units: 0,
+        };
+
+        let authority = shop.authority();
+        shop.set_packed(
+            shop.mint,
+            spl_token::state::Mint {
+                mint_authority: Some(authority).into(),
+                supply: 0,
+                decimals: DECIMALS,
+                is_initialized: true,
+                freeze_authority: None.into(),
+            },
+        );
+        shop.context.set_account(
+            &shop.vault.account,
+            &Account {
+                lamports: SOL,
+                data: vec![0; mock_vault::VAULT_ACCOUNT_SPACE],
+                owner: mock_vault::ID,
+                executable: false,
+                rent_epoch: 0,
+            }
+            .into(),
+        );
+        let vault_authority = shop.vault.account;
+        shop.set_token_account(shop.vault.token_account, vault_authority, 0);
+        shop.set_token_account(shop.vault.platform_token_account, authority, 0);
+        shop
+    }
+
+    /// System administrator and fee payer
+    pub fn authority(&self) -> Pubkey {
+        self.context.payer.pubkey()
+    }
+
+    pub fn system_config(&self) -> SystemConfig {
+        SystemConfig {
+            authority: self.authority(),
+            merchant_deposit_required: DEPOSIT_REQUIREMENT,
+            deposit_token_mint: self.mint,
+            platform_fee_rate: PLATFORM_FEE_RATE,
+            platform_fee_recipient: self.authority(),
+            auto_confirm_days: AUTO_CONFIRM_DAYS,
+            vault_program_id: mock_vault::ID,
+            vault_account: self.vault.account,
+            vault_token_account: self.vault.token_account,
+            platform_token_account: self.vault.platform_token_account,
+            ..SystemConfig::default()
+        }
+    }
+
+    /// Send a transaction paid by the authority, signed by it and `signers`
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let blockhash = self.context.banks_client.get_latest_blockhash().await?;
+        let mut keypairs = vec![&self.context.payer];
+        keypairs.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &keypairs,
+            blockhash,
+        );
+        let processed = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await?;
+        self.last_compute_units = processed
+            .metadata
+            .map_or(0, |metadata| metadata.compute_units_consumed);
+        Ok(processed.result?)
+    }
+
+    /// Simulate a view instruction signed by the authority and `signers`, and decode its return
+    /// data
+    pub async fn view<T: AnchorDeserialize>(
+        &mut self,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Result<T, BanksClientError> {
+        let blockhash = self.context.banks_client.get_latest_blockhash().await?;
+        let mut keypairs = vec![&self.context.payer];
+        keypairs.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.context.payer.pubkey()),
+            &keypairs,
+            blockhash,
+        );
+        let simulated = self
+            .context
+            .banks_client
+            .simulate_transaction(transaction)
+            .await?;
+        if let Some(result) = simulated.result {
+            result?;
+        }
+        let return_data = simulated
+            .simulation_details
+            .and_then(|details| details.return_data)
+            .expect("view instruction returns data");
+        Ok(T::deserialize(&mut &return_data.data[..]).expect("decoding return data"))
+    }
+
+    pub async fn try_account<T: AccountDeserialize>(&mut self, address: &Pubkey) -> Option<T> {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*address)
+            .await
+            .expect("fetching account")?;
+        Some(
+            shop_client::accounts::decode(&account.data)
+                .unwrap_or_else(|err| panic!("decoding account {}: {}", address, err)),
+        )
+    }
+
+    pub async fn account<T: AccountDeserialize>(&mut self, address: &Pubkey) -> T {
+        self.try_account(address)
+            .await
+            .unwrap_or_else(|| panic!("account {} does not exist", address))
+    }
+
+    pub async fn exists(&mut self, address: &Pubkey) -> bool {
+        self.context
+            .banks_client
+            .get_account(*address)
+            .await
+            .expect("fetching account")
+            .is_some()
+    }
+
+    /// Data length of an existing account
+    pub async fn account_size(&mut self, address: &Pubkey) -> usize {
+        self.context
+            .banks_client
+            .get_account(*address)
+            .await
+            .expect("fetching account")
+            .unwrap_or_else(|| panic!("account {} does not exist", address))
+            .data
+            .len()
+    }
+
+    /// Rewrite a program account in place, keeping its size and lamports
+    pub async fn update_account<T: AccountSerialize + AccountDeserialize>(
+        &mut self,
+        address: &Pubkey,
+        update: impl FnOnce(&mut T),
+    ) {
+        let mut account = self
+            .context
+            .banks_client
+            .get_account(*address)
+            .await
+            .expect("fetching account")
+            .unwrap_or_else(|| panic!("account {} does not exist", address));
+        let mut state: T = shop_client::accounts::decode(&account.data)
+            .unwrap_or_else(|err| panic!("decoding account {}: {}", address, err));
+        update(&mut state);
+        state
+            .try_serialize(&mut &mut account.data[..])
+            .expect("account data fits the account");
+        self.context.set_account(address, &account.into());
+    }
+
+    pub async fn token_balance(&mut self, address: &Pubkey) -> u64 {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*address)
+            .await
+            .expect("fetching account")
+            .unwrap_or_else(|| panic!("token account {} does not exist", address));
+        spl_token::state::Account::unpack(&account.data)
+            .expect("unpacking token account")
+            .amount
+    }
+
+    /// Recorded add_rewards calls of the mock vault
+    pub async fn vault_calls(&mut self) -> Vec<mock_vault::AddRewardsCall> {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.vault.account)
+            .await
+            .expect("fetching account")
+            .expect("vault account exists");
+        mock_vault::recorded_calls(&account.data)
+    }
+
+    pub async fn clock(&mut self) -> Clock {
+        self.context
+            .banks_client
+            .get_sysvar()
+            .await
+            .expect("fetching the clock")
+    }
+
+    /// Day of the bank clock, as the daily order statistics take it
+    pub async fn today(&mut self) -> u32 {
+        recency_day(self.clock().await.unix_timestamp)
+    }
+
+    /// Move the bank clock forward
+    ///
+    /// Warps to the next slot first, so a transaction retried after the jump gets a new blockhash
+    /// instead of the recorded status of its earlier attempt.
+    pub async fn advance_clock(&mut self, seconds: i64) {
+        let mut clock = self.clock().await;
+        clock.slot += 1;
+        self.context
+            .warp_to_slot(clock.slot)
+            .expect("warping to the next slot");
+        clock.unix_timestamp += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    /// Write raw data to a program account, as left behind by an earlier program version
+    pub fn set_program_account(&mut self, address: Pubkey, data: Vec<u8>) {
+        self.context.set_account(
+            &address,
+            &Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: solana_e_commerce::ID,
+                executable: false,
+                rent_epoch: 0,
+            }
+            .into(),
+        );
+    }
+
+    fn set_packed<T: Pack>(&mut self, address: Pubkey, state: T) {
+        let mut data = vec![0; T::LEN];
+        T::pack(state, &mut data).expect("packing token state");
+        self.context.set_account(
+            &address,
+            &Account {
+                lamports: SOL,
+                data,
+                owner: spl_token::ID,
+                executable: false,
+                rent_epoch: 0,
+            }
+            .into(),
+        );
+    }
+
+    /// Write a token account of the deposit mint holding `amount`
+    pub fn set_token_account(&mut self, address: Pubkey, owner: Pubkey, amount: u64) {
+        self.set_packed(
+            address,
+            spl_token::state::Account {
+                mint: self.mint,
+                owner,
+                amount,
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// A funded wallet with a token account holding `tokens` base units
+    pub fn wallet(&mut self, tokens: u64) -> (Keypair, Pubkey) {
+        let keypair = Keypair::new();
+        self.context.set_account(
+            &keypair.pubkey(),
+            &Account {
+                lamports: 10 * SOL,
+                data: Vec::new(),
+                owner: anchor_lang::system_program::ID,
+                executable: false,
+                rent_epoch: 0,
+            }
+            .into(),
+        );
+        let token_account = Pubkey::new_unique();
+        self.set_token_account(token_account, keypair.pubkey(), tokens);
+        (keypair, token_account)
+    }
+
+    pub fn buyer(&mut self, tokens: u64) -> Buyer {
+        let (keypair, token_account) = self.wallet(tokens);
+        Buyer {
+            keypair,
+            token_account,
+        }
+    }
+
+    // ==================== Flows ====================
+
+    /// Initialize the ID generator, system and payment configuration, order statistics and the
+    /// program's escrow token account with its ledger
+    pub async fn initialize(&mut self) -> Result<(), BanksClientError> {
+        let authority = self.authority();
+        let config = self.system_config();
+        self.process(
+            &[
+                ix::initialize_system(
+                    authority,
+                    args::InitializeSystem {
+                        config: config.clone(),
+                    },
+                ),
+                ix::initialize_system_config(authority, args::InitializeSystemConfig { config }),
+                ix::initialize_payment_system(
+                    authority,
+                    args::InitializePaymentSystem {
+                        supported_tokens: vec![SupportedToken {
+                            mint: self.mint,
+                            symbol: "USDC".to_string(),
+                            is_active: true,
+                        }],
+                        fee_rate: PLATFORM_FEE_RATE,
+                        fee_recipient: authority,
+                    },
+                ),
+                ix::initialize_order_stats(authority),
+                ix::initialize_program_token_account(authority, self.mint),
+                ix::initialize_escrow_ledger(authority, self.mint),
+            ],
+            &[],
+        )
+        .await
+    }
+
+    /// Register a merchant and pay `deposit` into the deposit escrow
+    pub async fn register_merchant(
+        &mut self,
+        name: &str,
+        deposit: u64,
+    ) -> Result<Merchant, BanksClientError> {
+        let (keypair, token_account) = self.wallet(deposit);
+        let owner = keypair.pubkey();
+        self.process(
+            &[
+                ix::register_merchant_atomic(
+                    owner,
+                    owner,
+                    args::RegisterMerchantAtomic {
+                        name: name.to_string(),
+                        description: format!("{} test store", name),
+                    },
+                ),
+                ix::manage_deposit(
+                    owner,
+                    token_account,
+                    self.mint,
+                    args::ManageDeposit { amount: deposit },
+                ),
+            ],
+            &[&keypair],
+        )
+        .await?;
+        Ok(Merchant {
+            keypair,
+            token_account,
+        })
+    }
+
+    /// Create a product priced in the deposit token and add it to the keyword, price and sales
+    /// indexes, returning its ID
+    pub async fn create_product(
+        &mut self,
+        merchant: &Merchant,
+        name: &str,
+        price: u64,
+        keywords: &[&str],
+    ) -> Result<u64, BanksClientError> {
+        let (instructions, product_id) = self
+            .create_product_instructions(merchant, name, price, keywords)
+            .await;
+        self.process(&instructions, &[&merchant.keypair]).await?;
+        Ok(product_id)
+    }
+
+    /// Instructions of [`TestShop::create_product`] and the ID the product gets, signed by the
+    /// merchant
+    pub async fn create_product_instructions(
+        &mut self,
+        merchant: &Merchant,
+        name: &str,
+        price: u64,
+        keywords: &[&str],
+    ) -> (Vec<Instruction>, u64) {
+        let owner = merchant.keypair.pubkey();
+        let (active_chunk, last_chunk_index, product_id) = self.next_product_id(&owner).await;
+        let day = self.today().await;
+        let page = self
+            .try_account::<MerchantProductList>(&pda::merchant_product_list(&owner).0)
+            .await
+            .map_or(0, |list| list.next_page_index(true));
+
+        let mut instructions = vec![ix::create_product_base(
+            owner,
+            active_chunk,
+            last_chunk_index,
+            product_id,
+            page,
+            args::CreateProductBase {
+                name: name.to_string(),
+                description: format!("{} description", name),
+                price,
+                keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+                inventory: 100,
+                payment_token: self.mint,
+                shipping_location: "Warehouse 1".to_string(),
+                day,
+            },
+        )];
+        for keyword in keywords {
+            instructions.push(ix::initialize_keyword_index(
+                owner,
+                args::InitializeKeywordIndex {
+                    keyword: keyword.to_string(),
+                },
+            ));
+            instructions.push(ix::add_product_to_keyword_index(
+                owner,
+                args::AddProductToKeywordIndex {
+                    keyword: keyword.to_string(),
+                    product_id,
+                },
+            ));
+        }
+        let (price_range_start, price_range_end) = find_price_node_for_price(price);
+        instructions.push(ix::add_product_to_price_index(
+            owner,
+            self.mint,
+            args::AddProductToPriceIndex {
+                product_id,
+                price_range_start,
+                price_range_end,
+            },
+        ));
+        instructions.push(ix::add_product_to_sales_index(
+            owner,
+            args::AddProductToSalesIndex {
+                sales_range_start: SALES_RANGE.0,
+                sales_range_end: SALES_RANGE.1,
+                product_id,
+                sales: 0,
+            },
+        ));
+        (instructions, product_id)
+    }
+
+    // Active chunk, last chunk index and the ID the program generates next
+    async fn next_product_id(&mut self, merchant: &Pubkey) -> (Pubkey, u32, u64) {
+        let id_account: MerchantIdAccount =
+            self.account(&pda::merchant_id_account(merchant).0).await;
+        let mut chunk: IdChunk = self.account(&id_account.active_chunk).await;
+        let product_id = match chunk.allocate_next() {
+            Some(product_id) => product_id,
+            // A full active chunk is only left for the preallocated next one
+            None => {
+                let next_chunk = pda::id_chunk(merchant, id_account.last_chunk_index + 1).0;
+                let mut next_chunk: IdChunk = self.account(&next_chunk).await;
+                next_chunk.allocate_next().expect("next ID chunk is full")
+            }
+        };
+        (
+            id_account.active_chunk,
+            id_account.last_chunk_index,
+            product_id,
+        )
+    }
+
+    /// Pay for `quantity` of a product into the program escrow and create the order, returning
+    /// the order address
+    pub async fn place_order(
+        &mut self,
+        buyer: &Buyer,
+        product_id: u64,
+        quantity: u32,
+    ) -> Result<Pubkey, BanksClientError> {
+        self.place_variant_order(buyer, product_id, None, quantity)
+            .await
+    }
+
+    /// [`TestShop::place_order`] of a variant of the product
+    pub async fn place_variant_order(
+        &mut self,
+        buyer: &Buyer,
+        product_id: u64,
+        variant_id: Option<u32>,
+        quantity: u32,
+    ) -> Result<Pubkey, BanksClientError> {
+        let (instructions, order) = self
+            .place_order_instructions(buyer, product_id, variant_id, quantity)
+            .await;
+        self.process(&instructions, &[&buyer.keypair]).await?;
+        Ok(order)
+    }
+
+    /// Instructions of [`TestShop::place_variant_order`] and the order address, signed by the
+    /// buyer
+    pub async fn place_order_instructions(
+        &mut self,
+        buyer: &Buyer,
+        product_id: u64,
+        variant_id: Option<u32>,
+        quantity: u32,
+    ) -> (Vec<Instruction>, Pubkey) {
+        let buyer_key = buyer.keypair.pubkey();
+        let product: ProductBase = self.account(&pda::product(product_id).0).await;
+        let purchase_sequence = self
+            .try_account::<UserPurchaseCount>(&pda::user_purchase_count(&buyer_key).0)
+            .await
+            .map_or(0, |count| count.purchase_count)
+            + 1;
+        let merchant_order_sequence = self
+            .try_account::<solana_e_commerce::state::MerchantOrderCount>(
+                &pda::merchant_order_count(&product.merchant).0,
+            )
+            .await
+            .map_or(0, |count| count.total_orders)
+            + 1;
+        let day = self.today().await;
+
+        let instructions = vec![
+            ix::purchase_product_escrow(
+                buyer_key,
+                buyer.token_account,
+                self.mint,
+                variant_id,
+                args::PurchaseProductEscrow {
+                    product_id,
+                    amount: quantity as u64,
+                },
+            ),
+            ix::create_order(
+                buyer_key,
+                product.merchant,
+                product.payment_token,
+                purchase_sequence,
+                merchant_order_sequence,
+                variant_id,
+                args::CreateOrder {
+                    product_id,
+                    quantity,
+                    shipping_address: "1 Harbour Road".to_string(),
+                    notes: String::new(),
+                    transaction_signature: String::new(),
+                    day,
+                },
+            ),
+        ];
+        (
+            instructions,
+            pda::buyer_order(&buyer_key, purchase_sequence).0,
+        )
+    }
+
+    pub async fn ship_order(
+        &mut self,
+        merchant: &Merchant,
+        order: Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let order_account: Order = self.account(&order).await;
+        self.process(
+            &[ix::ship_order(
+                order,
+                &order_account,
+                args::ShipOrder {
+                    tracking_number: "TRACK-0001".to_string(),
+                },
+            )],
+            &[&merchant.keypair],
+        )
+        .await
+    }
+
+    /// Sales index account of SALES_RANGE settlement writes: the legacy sales node, or the
+    /// zero-copy sales shard once the node has been frozen by its migration. None without a node.
+    pub async fn sales_index_account(&mut self) -> Option<Pubkey> {
+        let legacy_node = pda::sales_index(SALES_RANGE.0, SALES_RANGE.1).0;
+        if !self.exists(&legacy_node).await {
+            return None;
+        }
+        Some(
+            if self.account_size(&legacy_node).await > 8 + SalesIndexNode::INIT_SPACE {
+                pda::index_shard(&IndexShardKey::Sales {
+                    sales_range_start: SALES_RANGE.0,
+                    sales_range_end: SALES_RANGE.1,
+                })
+                .0
+            } else {
+                legacy_node
+            },
+        )
+    }
+
+    pub async fn confirm_delivery(
+        &mut self,
+        buyer: &Buyer,
+        order: Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let order_account: Order = self.account(&order).await;
+        let system_config: SystemConfig = self.account(&pda::system_config().0).await;
+        let sales_node = self.sales_index_account().await;
+        let day = self.today().await;
+        self.process(
+            &[ix::confirm_delivery(
+                order,
+                &order_account,
+                &system_config,
+                sales_node,
+                sales_node,
+                args::ConfirmDelivery { day },
+            )],
+            &[&buyer.keypair],
+        )
+        .await
+    }
+
+    /// Auto confirm a shipped order as the system administrator
+    pub async fn auto_confirm_delivery(&mut self, order: Pubkey) -> Result<(), BanksClientError> {
+        let order_account: Order = self.account(&order).await;
+        let sales_node = self.sales_index_account().await;
+        let authority = self.authority();
+        let day = self.today().await;
+        self.process(
+            &[ix::auto_confirm_delivery(
+                authority,
+                order,
+                &order_account,
+                sales_node,
+                sales_node,
+                args::AutoConfirmDelivery { day },
+            )],
+            &[],
+        )
+        .await
+    }
+
+    pub async fn refund_order(
+        &mut self,
+        buyer: &Buyer,
+        order: Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let order_account: Order = self.account(&order).await;
+        let day = self.today().await;
+        self.process(
+            &[ix::refund_order(
+                order,
+                &order_account,
+                buyer.token_account,
+                args::RefundOrder {
+                    refund_reason: "Changed my mind".to_string(),
+                    day,
+                },
+            )],
+            &[&buyer.keypair],
+        )
+        .await
+    }
+}
@@ -0,0 +1,82 @@
+//! Stand-in for the external vault program that receives platform fees
+//!
+//! Implements `add_rewards` as confirm_delivery calls it: the fee moves from the reward source to
+//! the vault token account under the reward source authority's signature, and every call is
+//! appended to the vault account's data so tests can assert on the CPI payload.
+
+use anchor_lang::prelude::{borsh, AccountInfo, ProgramError, Pubkey};
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::pubkey;
+use anchor_lang::{AnchorDeserialize, AnchorSerialize};
+use anchor_spl::token::spl_token;
+
+pub const ID: Pubkey = pubkey!("MockVau1t1111111111111111111111111111111111");
+
+/// Discriminator of the vault's add_rewards instruction
+pub const ADD_REWARDS_DISCRIMINATOR: [u8; 8] = [88, 186, 25, 227, 38, 137, 81, 23];
+
+/// Data size of the vault account, room for the recorded calls
+pub const VAULT_ACCOUNT_SPACE: usize = 4_096;
+
+/// One add_rewards invocation as received
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct AddRewardsCall {
+    pub amount: u64,
+    /// Instruction accounts in order
+    pub accounts: Vec<Pubkey>,
+    pub writable: Vec<bool>,
+    pub signers: Vec<bool>,
+}
+
+pub fn recorded_calls(data: &[u8]) -> Vec<AddRewardsCall> {
+    Vec::<AddRewardsCall>::deserialize(&mut &data[..]).expect("decoding recorded vault calls")
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if data.len() != 16 || data[..8] != ADD_REWARDS_DISCRIMINATOR {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let amount = u64::from_le_bytes(data[8..].try_into().unwrap());
+
+    let [vault, vault_token_account, reward_source, _platform_token_account, reward_source_authority, token_program, ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if !reward_source_authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    invoke(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            reward_source.key,
+            vault_token_account.key,
+            reward_source_authority.key,
+            &[],
+            amount,
+        )?,
+        &[
+            reward_source.clone(),
+            vault_token_account.clone(),
+            reward_source_authority.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    let mut calls = recorded_calls(&vault.try_borrow_data()?);
+    calls.push(AddRewardsCall {
+        amount,
+        accounts: accounts.iter().map(|account| *account.key).collect(),
+        writable: accounts.iter().map(|account| account.is_writable).collect(),
+        signers: accounts.iter().map(|account| account.is_signer).collect(),
+    });
+    calls
+        .serialize(&mut &mut vault.try_borrow_mut_data()?[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
@@ -0,0 +1,325 @@
+//! Compute unit and account size budgets at full index nodes
+//!
+//! The keyword shards, price node and sales node of an indexed product get filler entries until
+//! they hold MAX_PRODUCTS_PER_SHARD - 1 products, so the product added next fills them, and every
+//! instruction is sent in a transaction of its own. Under
+//! `cargo test-sbf` the compute units are checked too; `UPDATE_BUDGETS=1` rewrites the budget file
+//! from the run instead.
+
+use anchor_lang::prelude::Pubkey;
+use shop_client::{instructions as ix, pda};
+use shop_tests::bench::{Report, BUDGET_FILE, REPORT_FILE, UPDATE_BUDGETS_ENV};
+use shop_tests::{runs_sbf, TestShop, SALES_RANGE, TOKEN};
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::instructions::price_index::find_price_node_for_price;
+use solana_e_commerce::state::{
+    KeywordRoot, KeywordShard, Order, PriceIndexNode, ProductSales, SalesIndexNode, VariantOption,
+    MAX_KEYWORDS_PER_PRODUCT_CREATE, MAX_PRODUCTS_PER_SHARD, MAX_REVIEW_REPLY_LENGTH,
+    MAX_REVIEW_TEXT_LENGTH, MAX_SKU_LENGTH, MAX_TOP_ITEMS, MAX_VARIANT_OPTIONS,
+    MAX_VARIANT_OPTION_NAME_LENGTH, MAX_VARIANT_OPTION_VALUE_LENGTH,
+};
+use solana_e_commerce::utils::SortOrder;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+const KEYWORDS: [&str; MAX_KEYWORDS_PER_PRODUCT_CREATE] = ["lamp", "home", "desk"];
+
+/// Products per index node before the measured instructions run, the indexed product included
+const FILL: usize = MAX_PRODUCTS_PER_SHARD - 1;
+/// IDs of the filler products, far from the IDs merchants get
+const FILLER_ID_START: u64 = 1 << 40;
+
+struct Bench {
+    shop: TestShop,
+    report: Report,
+    metered: bool,
+}
+
+impl Bench {
+    // Compute units of the last transaction, a native run has none worth recording
+    fn record_compute_units(&mut self, name: &str) {
+        if self.metered {
+            self.report
+                .record_compute_units(name, self.shop.last_compute_units);
+        }
+    }
+
+    async fn send(
+        &mut self,
+        name: &str,
+        instruction: anchor_lang::solana_program::instruction::Instruction,
+        signers: &[&Keypair],
+    ) {
+        self.shop
+            .process(&[instruction], signers)
+            .await
+            .unwrap_or_else(|err| panic!("{} failed: {}", name, err));
+        self.record_compute_units(name);
+    }
+
+    async fn record_size(&mut self, account: &str, address: Pubkey) {
+        let size = self.shop.account_size(&address).await;
+        self.report.record_account_size(account, size);
+    }
+}
+
+// Fill the nodes of an indexed product up to FILL, FILL - 1 fillers next to the product
+async fn fill_indexes(shop: &mut TestShop) {
+    let fillers = || FILLER_ID_START..FILLER_ID_START + FILL as u64 - 1;
+
+    for keyword in KEYWORDS {
+        shop.update_account(
+            &pda::keyword_shard(keyword, 0).0,
+            |shard: &mut KeywordShard| {
+                for product_id in fillers() {
+                    shard.add_product(product_id).unwrap();
+                }
+            },
+        )
+        .await;
+        shop.update_account(&pda::keyword_root(keyword).0, |root: &mut KeywordRoot| {
+            for product_id in fillers() {
+                root.update_bloom_filter(product_id, true);
+                root.total_products += 1;
+            }
+        })
+        .await;
+    }
+
+    let (price_range_start, price_range_end) = find_price_node_for_price(PRICE);
+    let price_node = pda::price_index(&shop.mint, price_range_start, price_range_end).0;
+    shop.update_account(&price_node, |node: &mut PriceIndexNode| {
+        let span = price_range_end - price_range_start + 1;
+        for product_id in fillers() {
+            let price = price_range_start + product_id % span;
+            node.add_product(product_id, price).unwrap();
+        }
+    })
+    .await;
+
+    // Set directly, adding through the node reads the Clock sysvar
+    let sales_node = pda::sales_index(SALES_RANGE.0, SALES_RANGE.1).0;
+    shop.update_account(&sales_node, |node: &mut SalesIndexNode| {
+        node.product_ids.extend(fillers());
+        node.top_items = fillers()
+            .take(MAX_TOP_ITEMS)
+            .map(|product_id| ProductSales {
+                product_id,
+                merchant: Pubkey::default(),
+                name: String::new(),
+                price: 0,
+                sales: 1,
+                last_update: 0,
+            })
+            .collect();
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_compute_budgets() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let product_id = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &KEYWORDS)
+        .await
+        .unwrap();
+    fill_indexes(&mut shop).await;
+    let mut bench = Bench {
+        shop,
+        report: Report::default(),
+        metered: runs_sbf(),
+    };
+
+    // Product creation with the most keywords allowed, into full nodes
+    let (instructions, new_product_id) = bench
+        .shop
+        .create_product_instructions(&merchant, "Floor lamp", PRICE, &KEYWORDS)
+        .await;
+    let names = std::iter::once("create_product_base")
+        .chain(
+            KEYWORDS
+                .iter()
+                .flat_map(|_| ["initialize_keyword_index", "add_product_to_keyword_index"]),
+        )
+        .chain(["add_product_to_price_index", "add_product_to_sales_index"]);
+    for (name, instruction) in names.zip(instructions) {
+        bench.send(name, instruction, &[&merchant.keypair]).await;
+    }
+
+    // Order flow
+    let buyer = bench.shop.buyer(200 * TOKEN);
+    let (instructions, order) = bench
+        .shop
+        .place_order_instructions(&buyer, product_id, None, 2)
+        .await;
+    for (name, instruction) in ["purchase_product_escrow", "create_order"]
+        .into_iter()
+        .zip(instructions)
+    {
+        bench.send(name, instruction, &[&buyer.keypair]).await;
+    }
+    bench.shop.ship_order(&merchant, order).await.unwrap();
+    bench.record_compute_units("ship_order");
+    bench.shop.confirm_delivery(&buyer, order).await.unwrap();
+    bench.record_compute_units("confirm_delivery");
+    let order_account: Order = bench.shop.account(&order).await;
+    let review = ix::create_review(
+        order,
+        &order_account,
+        args::CreateReview {
+            rating: 4,
+            content_hash: Some([1; 32]),
+            text: "x".repeat(MAX_REVIEW_TEXT_LENGTH),
+        },
+    );
+    bench.send("create_review", review, &[&buyer.keypair]).await;
+    let reply = ix::reply_to_review(
+        merchant.keypair.pubkey(),
+        order,
+        args::ReplyToReview {
+            reply: "x".repeat(MAX_REVIEW_REPLY_LENGTH),
+        },
+    );
+    bench
+        .send("reply_to_review", reply, &[&merchant.keypair])
+        .await;
+
+    let refunded = bench.shop.place_order(&buyer, product_id, 1).await.unwrap();
+    bench.shop.refund_order(&buyer, refunded).await.unwrap();
+    bench.record_compute_units("refund_order");
+
+    // Search and removal over full nodes
+    let (price_range_start, price_range_end) = find_price_node_for_price(PRICE);
+    let price_node = pda::price_index(&bench.shop.mint, price_range_start, price_range_end).0;
+    let search = ix::search_price_range(
+        price_node,
+        args::SearchPriceRange {
+            payment_token: Some(bench.shop.mint),
+            min_price: price_range_start,
+            max_price: price_range_end,
+            sort_order: SortOrder::Descending,
+            offset: 0,
+            limit: 20,
+        },
+    );
+    bench.send("search_price_range", search, &[]).await;
+
+    let remove = ix::remove_product_from_keyword_index(args::RemoveProductFromKeywordIndex {
+        keyword: KEYWORDS[0].to_string(),
+        product_id: new_product_id,
+    });
+    bench
+        .send("remove_product_from_keyword_index", remove, &[])
+        .await;
+    let mint = bench.shop.mint;
+    let remove = ix::remove_product_from_price_index(
+        mint,
+        price_range_start,
+        price_range_end,
+        args::RemoveProductFromPriceIndex {
+            product_id: new_product_id,
+        },
+    );
+    bench
+        .send("remove_product_from_price_index", remove, &[])
+        .await;
+    let remove = ix::remove_product_from_sales_index(
+        SALES_RANGE.0,
+        SALES_RANGE.1,
+        args::RemoveProductFromSalesIndex {
+            product_id: new_product_id,
+        },
+    );
+    bench
+        .send("remove_product_from_sales_index", remove, &[])
+        .await;
+
+    // Variant with every option, after the orders so they need none
+    let options = (0..MAX_VARIANT_OPTIONS)
+        .map(|index| VariantOption {
+            name: format!("{}", index).repeat(MAX_VARIANT_OPTION_NAME_LENGTH),
+            value: "x".repeat(MAX_VARIANT_OPTION_VALUE_LENGTH),
+        })
+        .collect();
+    let variant = ix::create_product_variant(
+        merchant.keypair.pubkey(),
+        0,
+        args::CreateProductVariant {
+            product_id,
+            options,
+            sku: "x".repeat(MAX_SKU_LENGTH),
+            price: Some(PRICE),
+            inventory: 10,
+        },
+    );
+    bench
+        .send("create_product_variant", variant, &[&merchant.keypair])
+        .await;
+
+    // Account sizes
+    let owner = merchant.keypair.pubkey();
+    let today = bench.shop.today().await;
+    let buyer_key = buyer.keypair.pubkey();
+    let accounts = [
+        ("SystemConfig", pda::system_config().0),
+        ("OrderStats", pda::order_stats().0),
+        ("Merchant", pda::merchant_info(&owner).0),
+        ("MerchantIdAccount", pda::merchant_id_account(&owner).0),
+        ("IdChunk", pda::id_chunk(&owner, 0).0),
+        ("MerchantProductList", pda::merchant_product_list(&owner).0),
+        ("ProductBase", pda::product(product_id).0),
+        ("ProductVariant", pda::product_variant(product_id, 0).0),
+        ("KeywordRoot", pda::keyword_root(KEYWORDS[0]).0),
+        ("KeywordShard", pda::keyword_shard(KEYWORDS[0], 0).0),
+        ("PriceIndexNode", price_node),
+        (
+            "SalesIndexNode",
+            pda::sales_index(SALES_RANGE.0, SALES_RANGE.1).0,
+        ),
+        ("Order", order),
+        ("UserPurchaseCount", pda::user_purchase_count(&buyer_key).0),
+        ("MerchantOrder", pda::merchant_order(&owner, 1).0),
+        ("MerchantOrderCount", pda::merchant_order_count(&owner).0),
+        ("EscrowLedger", pda::escrow_ledger(&mint).0),
+        ("TokenOrderStats", pda::token_order_stats(&mint).0),
+        (
+            "MerchantOrderStats",
+            pda::merchant_order_stats(&owner, &mint).0,
+        ),
+        ("DailyOrderStats", pda::daily_order_stats(&mint, today).0),
+        ("Review", pda::review(&order).0),
+        ("ProductRating", pda::product_rating(product_id).0),
+        ("MerchantRating", pda::merchant_rating(&owner).0),
+        ("MerchantReputation", pda::merchant_reputation(&owner).0),
+    ];
+    for (account, address) in accounts {
+        bench.record_size(account, address).await;
+    }
+
+    let Bench {
+        report, metered, ..
+    } = bench;
+    report.save(REPORT_FILE);
+    let budgets = Report::load(BUDGET_FILE);
+    if std::env::var(UPDATE_BUDGETS_ENV).is_ok() {
+        report.to_budgets(&budgets, metered).save(BUDGET_FILE);
+        return;
+    }
+    if !metered {
+        eprintln!("compute units are not metered natively, run cargo test-sbf to check them");
+    }
+    let violations = report.violations(&budgets, metered);
+    assert!(
+        violations.is_empty(),
+        "over budget (see {}):\n{}",
+        REPORT_FILE,
+        violations.join("\n")
+    );
+}
@@ -0,0 +1,104 @@
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, TestShop, DEPOSIT_REQUIREMENT, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::state::Merchant;
+use solana_signer::Signer;
+
+#[tokio::test]
+async fn test_deposit_below_requirement_fails() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+
+    let err = shop
+        .register_merchant("Harbor Goods", DEPOSIT_REQUIREMENT * TOKEN - 1)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::MerchantDepositInsufficient))
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_and_deduct_deposit() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", 1_500 * TOKEN)
+        .await
+        .unwrap();
+    let owner = merchant.keypair.pubkey();
+    let mint = shop.mint;
+    let escrow = pda::deposit_escrow(&shop.mint).0;
+    assert_eq!(shop.token_balance(&escrow).await, 1_500 * TOKEN);
+    assert_eq!(shop.token_balance(&merchant.token_account).await, 0);
+
+    // Withdrawals keep the requirement in the deposit
+    let withdraw = |amount| {
+        ix::withdraw_merchant_deposit(
+            owner,
+            owner,
+            merchant.token_account,
+            mint,
+            args::WithdrawMerchantDeposit { amount },
+        )
+    };
+    let over_requirement = withdraw(500 * TOKEN + 1);
+    let err = shop
+        .process(&[over_requirement], &[&merchant.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::MerchantDepositInsufficient))
+    );
+    let instruction = withdraw(500 * TOKEN);
+    shop.process(&[instruction], &[&merchant.keypair])
+        .await
+        .unwrap();
+    assert_eq!(
+        shop.token_balance(&merchant.token_account).await,
+        500 * TOKEN
+    );
+    assert_eq!(shop.token_balance(&escrow).await, 1_000 * TOKEN);
+
+    // The administrator deducts to its own token account, below the requirement if need be
+    let (_, admin_token_account) = shop.wallet(0);
+    let authority = shop.authority();
+    let deduct = |amount| {
+        ix::deduct_merchant_deposit(
+            authority,
+            owner,
+            mint,
+            admin_token_account,
+            args::DeductMerchantDeposit {
+                amount,
+                reason: "Counterfeit goods".to_string(),
+            },
+        )
+    };
+    let instruction = deduct(200 * TOKEN);
+    shop.process(&[instruction], &[]).await.unwrap();
+    assert_eq!(shop.token_balance(&admin_token_account).await, 200 * TOKEN);
+    assert_eq!(shop.token_balance(&escrow).await, 800 * TOKEN);
+    let merchant_info: Merchant = shop.account(&pda::merchant_info(&owner).0).await;
+    assert_eq!(merchant_info.deposit_amount, 800 * TOKEN);
+
+    let instruction = deduct(800 * TOKEN + 1);
+    let err = shop.process(&[instruction], &[]).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InsufficientDeposit))
+    );
+
+    // Only the system administrator deducts
+    let mut instruction = deduct(TOKEN);
+    instruction.accounts[0].pubkey = owner;
+    let err = shop
+        .process(&[instruction], &[&merchant.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+}
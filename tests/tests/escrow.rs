@@ -0,0 +1,177 @@
+use anchor_lang::system_program;
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, TestShop, TOKEN};
+use solana_account::Account;
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::state::{EscrowLedger, EscrowSolvency};
+use solana_e_commerce::SystemConfig;
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+
+async fn open_shop() -> (TestShop, shop_tests::Merchant, u64) {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let product_id = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    (shop, merchant, product_id)
+}
+
+async fn ledger(shop: &mut TestShop) -> EscrowLedger {
+    let mint = shop.mint;
+    shop.account(&pda::escrow_ledger(&mint).0).await
+}
+
+async fn verify_solvency(shop: &mut TestShop) -> EscrowSolvency {
+    let mint = shop.mint;
+    shop.view(ix::verify_escrow_solvency(mint), &[])
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_ledger_follows_orders_refunds_and_settlement() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(500 * TOKEN);
+
+    let settled = shop.place_order(&buyer, product_id, 2).await.unwrap();
+    let refunded = shop.place_order(&buyer, product_id, 1).await.unwrap();
+    assert_eq!(ledger(&mut shop).await.escrowed_amount, 150 * TOKEN);
+
+    shop.refund_order(&buyer, refunded).await.unwrap();
+    assert_eq!(ledger(&mut shop).await.escrowed_amount, 100 * TOKEN);
+
+    shop.ship_order(&merchant, settled).await.unwrap();
+    shop.confirm_delivery(&buyer, settled).await.unwrap();
+
+    // The vault took the platform fee, nothing is left in escrow
+    let ledger = ledger(&mut shop).await;
+    assert_eq!(ledger.escrowed_amount, 0);
+    assert_eq!(ledger.accrued_fees, 0);
+    assert_eq!(
+        verify_solvency(&mut shop).await,
+        EscrowSolvency {
+            mint: shop.mint,
+            token_balance: 0,
+            escrowed_amount: 0,
+            accrued_fees: 0,
+            surplus: 0,
+            deficit: 0,
+            withdrawals_blocked: false,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_purchases_need_an_initialized_ledger() {
+    let (mut shop, _merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(200 * TOKEN);
+    let order = shop.place_order(&buyer, product_id, 1).await.unwrap();
+
+    // Without a ledger the escrowed balance would go unrecorded
+    let mint = shop.mint;
+    let ledger_address = pda::escrow_ledger(&mint).0;
+    shop.context
+        .set_account(&ledger_address, &Account::default().into());
+    let err = shop.place_order(&buyer, product_id, 1).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(anchor_lang::error::ErrorCode::AccountNotInitialized as u32)
+    );
+
+    // The administrator reopens it from the escrow balance
+    let authority = shop.authority();
+    shop.process(&[ix::initialize_escrow_ledger(authority, mint)], &[])
+        .await
+        .unwrap();
+    assert_eq!(ledger(&mut shop).await.escrowed_amount, PRICE);
+    shop.place_order(&buyer, product_id, 1).await.unwrap();
+    assert_eq!(ledger(&mut shop).await.escrowed_amount, 2 * PRICE);
+    shop.refund_order(&buyer, order).await.unwrap();
+    assert_eq!(ledger(&mut shop).await.escrowed_amount, PRICE);
+}
+
+#[tokio::test]
+async fn test_unforwarded_platform_fee_accrues() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    // Without a vault program the fee stays in escrow
+    shop.update_account(&pda::system_config().0, |config: &mut SystemConfig| {
+        config.vault_program_id = system_program::ID;
+    })
+    .await;
+    let buyer = shop.buyer(200 * TOKEN);
+
+    let order = shop.place_order(&buyer, product_id, 2).await.unwrap();
+    shop.ship_order(&merchant, order).await.unwrap();
+    shop.confirm_delivery(&buyer, order).await.unwrap();
+
+    let platform_fee = 400_000;
+    let solvency = verify_solvency(&mut shop).await;
+    assert_eq!(solvency.token_balance, platform_fee);
+    assert_eq!(solvency.escrowed_amount, 0);
+    assert_eq!(solvency.accrued_fees, platform_fee);
+    assert!(solvency.is_solvent());
+    assert_eq!(solvency.surplus, 0);
+}
+
+#[tokio::test]
+async fn test_insolvent_escrow_blocks_withdrawals() {
+    let (mut shop, _merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(200 * TOKEN);
+    let order = shop.place_order(&buyer, product_id, 2).await.unwrap();
+
+    // Lose 30 tokens out of the escrow account
+    let program_token_account = pda::program_token_account(&shop.mint).0;
+    let program_authority = pda::program_authority().0;
+    shop.set_token_account(program_token_account, program_authority, 70 * TOKEN);
+    let solvency = verify_solvency(&mut shop).await;
+    assert_eq!(solvency.escrowed_amount, 100 * TOKEN);
+    assert_eq!(solvency.deficit, 30 * TOKEN);
+    assert_eq!(solvency.surplus, 0);
+
+    // Only the system administrator enforces
+    let intruder = shop.buyer(0);
+    let err = shop
+        .process(
+            &[ix::enforce_escrow_solvency(
+                intruder.keypair.pubkey(),
+                shop.mint,
+            )],
+            &[&intruder.keypair],
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+
+    let authority = shop.authority();
+    let mint = shop.mint;
+    shop.process(&[ix::enforce_escrow_solvency(authority, mint)], &[])
+        .await
+        .unwrap();
+    assert!(ledger(&mut shop).await.withdrawals_blocked);
+    let err = shop.refund_order(&buyer, order).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::EscrowWithdrawalsBlocked))
+    );
+
+    // Topped up past the liabilities, withdrawals resume
+    shop.set_token_account(program_token_account, program_authority, 105 * TOKEN);
+    // Next slot, the same enforce transaction would otherwise be a duplicate
+    shop.advance_clock(0).await;
+    shop.process(&[ix::enforce_escrow_solvency(authority, mint)], &[])
+        .await
+        .unwrap();
+    let solvency = verify_solvency(&mut shop).await;
+    assert!(!solvency.withdrawals_blocked);
+    assert_eq!(solvency.surplus, 5 * TOKEN);
+    shop.refund_order(&buyer, order).await.unwrap();
+    assert_eq!(shop.token_balance(&buyer.token_account).await, 200 * TOKEN);
+}
@@ -0,0 +1,82 @@
+use shop_client::{instructions as ix, pda};
+use shop_tests::{TestShop, TOKEN};
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::state::{IdChunk, MerchantIdAccount, CHUNK_POOL_START, MERCHANT_ID_RANGE};
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 20 * TOKEN;
+
+#[tokio::test]
+async fn test_later_chunks_do_not_overlap_initial_chunks() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let first = shop.register_merchant("Tide Shop", DEPOSIT).await.unwrap();
+    let second = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let (first_key, second_key) = (first.keypair.pubkey(), second.keypair.pubkey());
+    let first_id: MerchantIdAccount = shop.account(&pda::merchant_id_account(&first_key).0).await;
+    let second_id: MerchantIdAccount = shop.account(&pda::merchant_id_account(&second_key).0).await;
+
+    // Initial chunks keep the range the earlier layout gave them
+    let second_initial: IdChunk = shop.account(&pda::id_chunk(&second_key, 0).0).await;
+    assert_eq!(
+        second_initial.start_id,
+        second_id.merchant_id as u64 * MERCHANT_ID_RANGE
+    );
+
+    // The first merchant's next chunk no longer lands in the second merchant's initial range
+    let payer = shop.context.payer.pubkey();
+    shop.process(
+        &[ix::allocate_new_chunk(first_key, payer, 0)],
+        &[&first.keypair],
+    )
+    .await
+    .unwrap();
+    shop.process(
+        &[ix::allocate_new_chunk(second_key, payer, 0)],
+        &[&second.keypair],
+    )
+    .await
+    .unwrap();
+    let first_next: IdChunk = shop.account(&pda::id_chunk(&first_key, 1).0).await;
+    let second_next: IdChunk = shop.account(&pda::id_chunk(&second_key, 1).0).await;
+    assert_eq!(first_next.start_id, CHUNK_POOL_START);
+    assert_eq!(second_next.start_id, first_next.end_id + 1);
+    assert!(!first_next.contains_id(second_initial.start_id));
+    assert!(first_id.merchant_id < second_id.merchant_id);
+
+    // Products come from the active chunks and are found through their stored ranges
+    let first_product = shop
+        .create_product(&first, "Kayak paddle", PRICE, &["paddle"])
+        .await
+        .unwrap();
+    let second_product = shop
+        .create_product(&second, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    assert_eq!(first_product, first_next.start_id);
+    assert_eq!(second_product, second_next.start_id);
+
+    let exists = |merchant, chunk_index, id| {
+        ix::is_id_exists(
+            merchant,
+            pda::id_chunk(&merchant, chunk_index).0,
+            args::IsIdExists { id },
+        )
+    };
+    assert!(shop
+        .view::<bool>(exists(first_key, 1, first_product), &[&first.keypair])
+        .await
+        .unwrap());
+    assert!(!shop
+        .view::<bool>(exists(first_key, 1, second_product), &[&first.keypair])
+        .await
+        .unwrap());
+    assert!(!shop
+        .view::<bool>(exists(second_key, 0, second_product), &[&second.keypair])
+        .await
+        .unwrap());
+}
@@ -0,0 +1,436 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Space;
+use shop_client::accounts::IndexShardAccount;
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, TestShop, SALES_RANGE, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::state::{
+    IndexEntry, IndexShard, IndexShardKey, KeywordShard, Order, ProductBase, SalesIndexNode,
+    INDEX_ENTRY_SIZE, INDEX_SHARD_GROWTH, INDEX_SHARD_INITIAL_CAPACITY, LEGACY_SHARD_FROZEN_BYTES,
+};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+
+fn keyword_key(keyword: &str) -> IndexShardKey {
+    IndexShardKey::Keyword {
+        keyword: keyword.to_string(),
+        shard_index: 0,
+    }
+}
+
+fn sales_key() -> IndexShardKey {
+    IndexShardKey::Sales {
+        sales_range_start: SALES_RANGE.0,
+        sales_range_end: SALES_RANGE.1,
+    }
+}
+
+async fn raw_data(shop: &mut TestShop, address: &Pubkey) -> Vec<u8> {
+    shop.context
+        .banks_client
+        .get_account(*address)
+        .await
+        .expect("fetching account")
+        .unwrap_or_else(|| panic!("account {} does not exist", address))
+        .data
+}
+
+async fn index_shard(shop: &mut TestShop, key: &IndexShardKey) -> IndexShardAccount {
+    let data = raw_data(shop, &pda::index_shard(key).0).await;
+    IndexShardAccount::decode(&data).unwrap()
+}
+
+async fn initialize(shop: &mut TestShop, key: &IndexShardKey) {
+    let init = ix::initialize_index_shard(
+        shop.authority(),
+        args::InitializeIndexShard { key: key.clone() },
+    );
+    shop.process(&[init], &[]).await.unwrap();
+}
+
+async fn add(
+    shop: &mut TestShop,
+    authority: &Keypair,
+    key: &IndexShardKey,
+    product_id: u64,
+) -> Result<(), solana_program_test::BanksClientError> {
+    let add = ix::add_product_to_index_shard(
+        authority.pubkey(),
+        shop.authority(),
+        args::AddProductToIndexShard {
+            key: key.clone(),
+            product_id,
+        },
+    );
+    shop.process(&[add], &[authority]).await
+}
+
+async fn remove(
+    shop: &mut TestShop,
+    authority: &Keypair,
+    key: &IndexShardKey,
+    product_id: u64,
+) -> Result<(), solana_program_test::BanksClientError> {
+    let remove = ix::remove_product_from_index_shard(
+        authority.pubkey(),
+        args::RemoveProductFromIndexShard {
+            key: key.clone(),
+            product_id,
+        },
+    );
+    shop.process(&[remove], &[authority]).await
+}
+
+#[tokio::test]
+async fn test_entries_are_read_from_the_product() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let shade = shop
+        .create_product(&merchant, "Lamp shade", 10 * TOKEN, &["shade"])
+        .await
+        .unwrap();
+    let price_key = IndexShardKey::Price {
+        payment_token: shop.mint,
+        price_range_start: 0,
+        price_range_end: 100 * TOKEN,
+    };
+    let other_mint_key = IndexShardKey::Price {
+        payment_token: Pubkey::new_unique(),
+        price_range_start: 0,
+        price_range_end: 100 * TOKEN,
+    };
+    for key in [&price_key, &other_mint_key, &keyword_key("lamp")] {
+        initialize(&mut shop, key).await;
+    }
+
+    // Only the merchant of the product or the administrator index it
+    let stranger = Keypair::new();
+    let err = add(&mut shop, &stranger, &price_key, lamp)
+        .await
+        .unwrap_err();
+    assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+
+    add(&mut shop, &merchant.keypair, &price_key, lamp)
+        .await
+        .unwrap();
+    add(&mut shop, &merchant.keypair, &price_key, shade)
+        .await
+        .unwrap();
+    add(&mut shop, &merchant.keypair, &keyword_key("lamp"), lamp)
+        .await
+        .unwrap();
+    let shard = index_shard(&mut shop, &price_key).await;
+    assert_eq!(
+        shard.entries,
+        vec![
+            IndexEntry {
+                value: 10 * TOKEN,
+                product_id: shade,
+            },
+            IndexEntry {
+                value: PRICE,
+                product_id: lamp,
+            },
+        ]
+    );
+
+    let err = add(&mut shop, &merchant.keypair, &other_mint_key, lamp)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::UnsupportedToken))
+    );
+    let err = add(&mut shop, &merchant.keypair, &keyword_key("lamp"), shade)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidKeyword))
+    );
+
+    let err = remove(&mut shop, &stranger, &price_key, lamp)
+        .await
+        .unwrap_err();
+    assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+    remove(&mut shop, &merchant.keypair, &price_key, lamp)
+        .await
+        .unwrap();
+    assert_eq!(
+        index_shard(&mut shop, &price_key).await.product_ids(),
+        vec![shade]
+    );
+
+    // Entries of a deleted product can be cleaned up by anyone
+    let owner = merchant.keypair.pubkey();
+    let delete = ix::delete_product(
+        owner,
+        owner,
+        0,
+        Some((true, 0)),
+        None,
+        args::DeleteProduct {
+            product_id: shade,
+            hard_delete: true,
+            force: false,
+        },
+    );
+    shop.process(&[delete], &[&merchant.keypair]).await.unwrap();
+    remove(&mut shop, &stranger, &price_key, shade)
+        .await
+        .unwrap();
+    assert!(index_shard(&mut shop, &price_key).await.entries.is_empty());
+}
+
+#[tokio::test]
+async fn test_full_shards_grow() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let key = sales_key();
+    initialize(&mut shop, &key).await;
+
+    // Fill the initial capacity with entries of products sold before the lamp
+    let address = pda::index_shard(&key).0;
+    let mut data = raw_data(&mut shop, &address).await;
+    let capacity = INDEX_SHARD_INITIAL_CAPACITY as usize;
+    let len_offset = 8 + std::mem::offset_of!(IndexShard, len);
+    data[len_offset..len_offset + 4].copy_from_slice(&INDEX_SHARD_INITIAL_CAPACITY.to_le_bytes());
+    for index in 0..capacity {
+        let offset = IndexShard::ENTRIES_OFFSET + index * INDEX_ENTRY_SIZE;
+        data[offset..offset + 8].copy_from_slice(&0u64.to_le_bytes());
+        data[offset + 8..offset + 16].copy_from_slice(&(index as u64 + 1).to_le_bytes());
+    }
+    shop.set_program_account(address, data);
+
+    add(&mut shop, &merchant.keypair, &key, lamp).await.unwrap();
+
+    let grown = INDEX_SHARD_INITIAL_CAPACITY + INDEX_SHARD_GROWTH;
+    assert_eq!(shop.account_size(&address).await, IndexShard::space(grown));
+    let shard = index_shard(&mut shop, &key).await;
+    assert_eq!(shard.header.capacity, grown);
+    assert_eq!(shard.entries.len(), capacity + 1);
+    assert_eq!(shard.entries.last().unwrap().product_id, lamp);
+}
+
+#[tokio::test]
+async fn test_migration_freezes_the_legacy_shard() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let owner = merchant.keypair.pubkey();
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let floor_lamp = shop
+        .create_product(&merchant, "Floor lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let key = keyword_key("lamp");
+    initialize(&mut shop, &key).await;
+
+    let migrate = ix::migrate_index_shard(
+        shop.authority(),
+        args::MigrateIndexShard { key: key.clone() },
+    );
+    shop.process(&[migrate], &[]).await.unwrap();
+
+    let shard = index_shard(&mut shop, &key).await;
+    assert_eq!(shard.product_ids(), vec![lamp, floor_lamp]);
+    assert_eq!(shard.header.migrated_entries, 2);
+
+    // The legacy shard stays readable but no longer takes writes
+    let legacy_address = pda::keyword_shard("lamp", 0).0;
+    assert_eq!(
+        shop.account_size(&legacy_address).await,
+        8 + KeywordShard::INIT_SPACE + LEGACY_SHARD_FROZEN_BYTES
+    );
+    let legacy: KeywordShard = shop.account(&legacy_address).await;
+    assert_eq!(legacy.product_ids, vec![lamp, floor_lamp]);
+
+    let bulb = shop
+        .create_product(&merchant, "Bulb", 5 * TOKEN, &["bulb"])
+        .await
+        .unwrap();
+    let legacy_add = ix::add_product_to_keyword_index(
+        owner,
+        args::AddProductToKeywordIndex {
+            keyword: "lamp".to_string(),
+            product_id: bulb,
+        },
+    );
+    // `init_if_needed` already rejects the resized account on its space check
+    assert!(shop
+        .process(&[legacy_add], &[&merchant.keypair])
+        .await
+        .is_err());
+    let legacy_remove =
+        ix::remove_product_from_keyword_index(args::RemoveProductFromKeywordIndex {
+            keyword: "lamp".to_string(),
+            product_id: lamp,
+        });
+    let err = shop.process(&[legacy_remove], &[]).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::AccountAlreadyMigrated))
+    );
+
+    // The zero-copy shard carries on
+    remove(&mut shop, &merchant.keypair, &key, lamp)
+        .await
+        .unwrap();
+    assert_eq!(
+        index_shard(&mut shop, &key).await.product_ids(),
+        vec![floor_lamp]
+    );
+}
+
+#[tokio::test]
+async fn test_only_the_authority_migrates_index_shards() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    shop.create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let key = keyword_key("lamp");
+    initialize(&mut shop, &key).await;
+    let (outsider, _) = shop.wallet(0);
+
+    // Neither the merchant nor anyone else can freeze the legacy shard
+    for signer in [&merchant.keypair, &outsider] {
+        let migrate = ix::migrate_index_shard(
+            signer.pubkey(),
+            args::MigrateIndexShard { key: key.clone() },
+        );
+        let err = shop.process(&[migrate], &[signer]).await.unwrap_err();
+        assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+    }
+
+    assert_eq!(
+        shop.account_size(&pda::keyword_shard("lamp", 0).0).await,
+        8 + KeywordShard::INIT_SPACE
+    );
+    assert_eq!(
+        index_shard(&mut shop, &key).await.header.migrated_entries,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_orders_settle_after_the_sales_node_migrates() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let key = sales_key();
+    initialize(&mut shop, &key).await;
+    let migrate = ix::migrate_index_shard(
+        shop.authority(),
+        args::MigrateIndexShard { key: key.clone() },
+    );
+    shop.process(&[migrate], &[]).await.unwrap();
+
+    let legacy_size = shop
+        .account_size(&pda::sales_index(SALES_RANGE.0, SALES_RANGE.1).0)
+        .await;
+
+    let buyer = shop.buyer(500 * TOKEN);
+    let order = shop.place_order(&buyer, lamp, 2).await.unwrap();
+    shop.ship_order(&merchant, order).await.unwrap();
+    shop.confirm_delivery(&buyer, order).await.unwrap();
+
+    // Settlement moves the product in the zero-copy shard, the frozen node is left alone
+    let product: ProductBase = shop.account(&pda::product(lamp).0).await;
+    assert_eq!(product.sales, 2);
+    let legacy_address = pda::sales_index(SALES_RANGE.0, SALES_RANGE.1).0;
+    let legacy: SalesIndexNode = shop.account(&legacy_address).await;
+    assert_eq!(legacy.product_ids, vec![lamp]);
+    assert_eq!(
+        index_shard(&mut shop, &key).await.entries,
+        vec![IndexEntry {
+            value: 2,
+            product_id: lamp,
+        }]
+    );
+
+    // So does a post-delivery refund
+    let order_account: Order = shop.account(&order).await;
+    let shard = pda::index_shard(&key).0;
+    let refund = ix::refund_delivered_order(
+        order,
+        &order_account,
+        Some(shard),
+        Some(shard),
+        buyer.token_account,
+        args::RefundDeliveredOrder {
+            refund_reason: "Arrived broken".to_string(),
+            day: shop.today().await,
+        },
+    );
+    shop.process(&[refund], &[&merchant.keypair]).await.unwrap();
+    assert_eq!(
+        index_shard(&mut shop, &key).await.entries,
+        vec![IndexEntry {
+            value: 0,
+            product_id: lamp,
+        }]
+    );
+
+    // The frozen legacy node is no longer accepted in its place
+    let order = shop.place_order(&buyer, lamp, 1).await.unwrap();
+    shop.ship_order(&merchant, order).await.unwrap();
+    let order_account: Order = shop.account(&order).await;
+    let system_config = shop.system_config();
+    let confirm = ix::confirm_delivery(
+        order,
+        &order_account,
+        &system_config,
+        Some(legacy_address),
+        Some(legacy_address),
+        args::ConfirmDelivery {
+            day: shop.today().await,
+        },
+    );
+    let err = shop
+        .process(&[confirm], &[&buyer.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::AccountAlreadyMigrated))
+    );
+    assert_eq!(shop.account_size(&legacy_address).await, legacy_size);
+}
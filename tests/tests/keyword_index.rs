@@ -0,0 +1,149 @@
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, TestShop, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::state::{KeywordRoot, KeywordShard};
+use solana_program_test::BanksClientError;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+const KEYWORD: &str = "lamp";
+
+async fn open_shop() -> (TestShop, u64) {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let product_id = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &[KEYWORD])
+        .await
+        .unwrap();
+    (shop, product_id)
+}
+
+async fn create_shard(shop: &mut TestShop, shard_index: u32) -> Result<(), BanksClientError> {
+    let instruction = ix::create_keyword_shard(
+        shop.authority(),
+        args::CreateKeywordShard {
+            keyword: KEYWORD.to_string(),
+            shard_index,
+        },
+    );
+    shop.process(&[instruction], &[]).await
+}
+
+async fn merge(
+    shop: &mut TestShop,
+    source_shard_index: u32,
+    target_shard_index: u32,
+    outer_shard_index: Option<u32>,
+) -> Result<(), BanksClientError> {
+    let instruction = ix::merge_keyword_shards(
+        shop.authority(),
+        outer_shard_index,
+        args::MergeKeywordShards {
+            keyword: KEYWORD.to_string(),
+            source_shard_index,
+            target_shard_index,
+        },
+    );
+    shop.process(&[instruction], &[]).await
+}
+
+async fn shard(shop: &mut TestShop, shard_index: u32) -> KeywordShard {
+    shop.account(&pda::keyword_shard(KEYWORD, shard_index).0)
+        .await
+}
+
+async fn root(shop: &mut TestShop) -> KeywordRoot {
+    shop.account(&pda::keyword_root(KEYWORD).0).await
+}
+
+#[tokio::test]
+async fn test_new_shards_extend_the_tail_of_the_chain() {
+    let (mut shop, _) = open_shop().await;
+    let first = pda::keyword_shard(KEYWORD, 0).0;
+    let second = pda::keyword_shard(KEYWORD, 1).0;
+    let third = pda::keyword_shard(KEYWORD, 2).0;
+
+    create_shard(&mut shop, 1).await.unwrap();
+    create_shard(&mut shop, 2).await.unwrap();
+    assert_eq!(shard(&mut shop, 1).await.next_shard, Some(third));
+    assert_eq!(root(&mut shop).await.last_shard, third);
+
+    // Merging the middle shard forward links shard 0 straight to shard 2
+    merge(&mut shop, 1, 2, Some(0)).await.unwrap();
+    assert!(!shop.exists(&second).await);
+    assert_eq!(shard(&mut shop, 0).await.next_shard, Some(third));
+    assert_eq!(shard(&mut shop, 2).await.prev_shard, first);
+    let keyword_root = root(&mut shop).await;
+    assert_eq!(
+        (keyword_root.total_shards, keyword_root.last_shard),
+        (2, third)
+    );
+
+    // Recreating the merged shard would orphan shard 2
+    shop.advance_clock(1).await;
+    let err = create_shard(&mut shop, 1).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidShardLink))
+    );
+    assert_eq!(shard(&mut shop, 0).await.next_shard, Some(third));
+
+    create_shard(&mut shop, 3).await.unwrap();
+    let fourth = pda::keyword_shard(KEYWORD, 3).0;
+    assert_eq!(shard(&mut shop, 2).await.next_shard, Some(fourth));
+    assert_eq!(shard(&mut shop, 3).await.prev_shard, third);
+    let keyword_root = root(&mut shop).await;
+    assert_eq!(
+        (keyword_root.total_shards, keyword_root.last_shard),
+        (3, fourth)
+    );
+}
+
+#[tokio::test]
+async fn test_merged_products_move_to_the_neighbour() {
+    let (mut shop, product_id) = open_shop().await;
+    create_shard(&mut shop, 1).await.unwrap();
+    create_shard(&mut shop, 2).await.unwrap();
+    shop.update_account(
+        &pda::keyword_shard(KEYWORD, 2).0,
+        |shard: &mut KeywordShard| shard.product_ids.push(product_id + 1),
+    )
+    .await;
+
+    // The next shard of a merged shard must be passed so its back link is updated
+    let err = merge(&mut shop, 1, 0, None).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InsufficientAccounts))
+    );
+
+    // Merging the tail backward makes its neighbour the new tail
+    merge(&mut shop, 2, 1, None).await.unwrap();
+    let second = pda::keyword_shard(KEYWORD, 1).0;
+    let merged = shard(&mut shop, 1).await;
+    assert_eq!(merged.product_ids, vec![product_id + 1]);
+    assert_eq!(merged.next_shard, None);
+    assert_eq!(root(&mut shop).await.last_shard, second);
+
+    // Same instruction as the rejected merge above, sent in a new slot
+    shop.advance_clock(1).await;
+    merge(&mut shop, 1, 0, None).await.unwrap();
+    let first = shard(&mut shop, 0).await;
+    assert_eq!(first.product_ids, vec![product_id, product_id + 1]);
+    assert_eq!(first.next_shard, None);
+    let keyword_root = root(&mut shop).await;
+    assert_eq!(
+        (keyword_root.total_shards, keyword_root.last_shard),
+        (1, pda::keyword_shard(KEYWORD, 0).0)
+    );
+
+    // The chain grows again from the new tail
+    shop.advance_clock(1).await;
+    create_shard(&mut shop, 1).await.unwrap();
+    assert_eq!(shard(&mut shop, 0).await.next_shard, Some(second));
+}
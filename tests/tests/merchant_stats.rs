@@ -0,0 +1,150 @@
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, TestShop, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::state::{MerchantStats, MerchantTokenStats, OrderCounters};
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+const HOUR: i64 = 60 * 60;
+
+async fn merchant_stats(
+    shop: &mut TestShop,
+    merchant: &shop_tests::Merchant,
+    order_stats: &[Pubkey],
+) -> Result<MerchantStats, solana_program_test::BanksClientError> {
+    let owner = merchant.keypair.pubkey();
+    let instruction = ix::with_remaining_accounts(
+        ix::get_merchant_stats(owner),
+        order_stats
+            .iter()
+            .map(|address| AccountMeta::new_readonly(*address, false)),
+    );
+    shop.view(instruction, &[&merchant.keypair]).await
+}
+
+#[tokio::test]
+async fn test_stats_follow_products_and_orders() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let owner = merchant.keypair.pubkey();
+
+    // Before the first product there is no catalog
+    let stats = merchant_stats(&mut shop, &merchant, &[]).await.unwrap();
+    assert_eq!(stats.product_count, 0);
+    assert_eq!(stats.active_products, 0);
+    assert!(stats.tokens.is_empty());
+
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let shade = shop
+        .create_product(&merchant, "Lamp shade", 10 * TOKEN, &["shade"])
+        .await
+        .unwrap();
+    let delete = ix::delete_product(
+        owner,
+        owner,
+        0,
+        Some((true, 0)),
+        None,
+        args::DeleteProduct {
+            product_id: shade,
+            hard_delete: false,
+            force: false,
+        },
+    );
+    shop.process(&[delete], &[&merchant.keypair]).await.unwrap();
+
+    let buyer = shop.buyer(500 * TOKEN);
+    let delivered = shop.place_order(&buyer, lamp, 2).await.unwrap();
+    let settled = shop.place_order(&buyer, lamp, 4).await.unwrap();
+    let refunded = shop.place_order(&buyer, lamp, 1).await.unwrap();
+    // Left pending
+    shop.place_order(&buyer, lamp, 1).await.unwrap();
+
+    // Shipped one and three hours after the orders
+    shop.advance_clock(HOUR).await;
+    shop.ship_order(&merchant, delivered).await.unwrap();
+    shop.advance_clock(2 * HOUR).await;
+    shop.ship_order(&merchant, settled).await.unwrap();
+    shop.confirm_delivery(&buyer, delivered).await.unwrap();
+    shop.confirm_delivery(&buyer, settled).await.unwrap();
+    shop.refund_order(&buyer, refunded).await.unwrap();
+
+    let order_stats = pda::merchant_order_stats(&owner, &shop.mint).0;
+    let stats = merchant_stats(&mut shop, &merchant, &[order_stats])
+        .await
+        .unwrap();
+    assert_eq!(stats.product_count, 2);
+    assert_eq!(stats.active_products, 1);
+    assert_eq!(stats.inactive_products, 1);
+    assert_eq!(stats.total_sales, 6);
+    // Deprecated fields keep their place in the layout but are never computed
+    assert_eq!((stats.total_keywords, stats.avg_product_price), (0, 0));
+    assert_eq!(stats.total_orders, 4);
+    assert_eq!(stats.pending_orders, 1);
+    assert_eq!(stats.shipped_orders, 0);
+    assert_eq!(stats.delivered_orders, 2);
+    assert_eq!(stats.refunded_orders, 1);
+    assert_eq!(stats.refund_rate_bps, 2_500);
+    assert_eq!(stats.avg_ship_time, 2 * HOUR as u64);
+    assert_eq!(
+        stats.tokens,
+        vec![MerchantTokenStats {
+            mint: shop.mint,
+            counters: OrderCounters {
+                total_orders: 4,
+                pending_orders: 1,
+                shipped_orders: 0,
+                delivered_orders: 2,
+                refunded_orders: 1,
+                revenue: 300 * TOKEN,
+            },
+            avg_order_value: 150 * TOKEN,
+            refund_rate_bps: 2_500,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_order_stats_must_be_the_merchants_own() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let harbor = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let pier = shop
+        .register_merchant("Pier Supply", DEPOSIT)
+        .await
+        .unwrap();
+    for merchant in [&harbor, &pier] {
+        let product_id = shop
+            .create_product(merchant, "Rope", PRICE, &["rope"])
+            .await
+            .unwrap();
+        let buyer = shop.buyer(100 * TOKEN);
+        shop.place_order(&buyer, product_id, 1).await.unwrap();
+    }
+
+    let mint = shop.mint;
+    let harbor_stats = pda::merchant_order_stats(&harbor.keypair.pubkey(), &mint).0;
+    let pier_stats = pda::merchant_order_stats(&pier.keypair.pubkey(), &mint).0;
+    for order_stats in [vec![pier_stats], vec![harbor_stats, harbor_stats]] {
+        let err = merchant_stats(&mut shop, &harbor, &order_stats)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            custom_error(err),
+            Some(error_code(ErrorCode::InvalidMerchantOrderStats))
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use anchor_lang::prelude::AccountMeta;
+use anchor_lang::{AccountSerialize, AnchorDeserialize, AnchorSerialize, Discriminator};
+use shop_client::instructions::with_remaining_accounts;
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, TestShop, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::instructions::price_index::find_price_node_for_price;
+use solana_e_commerce::state::{
+    GlobalIdRoot, KeywordRoot, LegacyKeywordRoot, LegacyOrder, LegacyPriceIndexNode,
+    LegacyProductBase, Order, PriceEntry, PriceIndexNode, ProductBase, BLOOM_FILTER_SIZE,
+};
+use solana_e_commerce::state::{DEFAULT_INDEX_SHARD_CAPACITY, DEFAULT_TRENDING_HALF_LIFE_SECONDS};
+use solana_e_commerce::utils::CountingBloomFilter;
+use solana_e_commerce::{LegacySystemConfig, SystemConfig};
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+
+// Account data written by an earlier program version whose layout lacked the trailing
+// `appended` bytes of `state`, with `stale` left in the unused tail
+fn without_appended_fields<T: AccountSerialize>(
+    state: &T,
+    appended: usize,
+    space: usize,
+    stale: u8,
+) -> Vec<u8> {
+    let mut data = Vec::new();
+    state.try_serialize(&mut data).unwrap();
+    data.truncate(data.len() - appended);
+    data.resize(space, stale);
+    data
+}
+
+fn legacy_data<T: AnchorSerialize>(discriminator: &[u8], state: &T, space: usize) -> Vec<u8> {
+    let mut data = discriminator.to_vec();
+    state.serialize(&mut data).unwrap();
+    data.resize(space, 0);
+    data
+}
+
+#[tokio::test]
+async fn test_legacy_keyword_root_is_rebuilt_from_its_shards() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let floor_lamp = shop
+        .create_product(&merchant, "Floor lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+
+    // Roots written before the counting filter held a fixed 256-byte bit filter
+    let root_address = pda::keyword_root("lamp").0;
+    let root: KeywordRoot = shop.account(&root_address).await;
+    let legacy = LegacyKeywordRoot {
+        keyword: root.keyword.clone(),
+        total_shards: root.total_shards,
+        first_shard: root.first_shard,
+        last_shard: root.last_shard,
+        total_products: root.total_products,
+        bloom_filter: [0xFF; BLOOM_FILTER_SIZE],
+        bump: root.bump,
+    };
+    shop.set_program_account(
+        root_address,
+        legacy_data(
+            KeywordRoot::DISCRIMINATOR,
+            &legacy,
+            LegacyKeywordRoot::SPACE,
+        ),
+    );
+
+    let migrate = ix::migrate_keyword_root(
+        shop.authority(),
+        args::MigrateKeywordRoot {
+            keyword: "lamp".to_string(),
+        },
+    );
+    let shard = pda::keyword_shard("lamp", 0).0;
+    let with_shards = with_remaining_accounts(
+        migrate.clone(),
+        [anchor_lang::prelude::AccountMeta::new_readonly(
+            shard, false,
+        )],
+    );
+
+    // Only the administrator migrates, and every shard must be listed
+    let (stranger, _) = shop.wallet(0);
+    let err = shop
+        .process(
+            &[ix::migrate_keyword_root(
+                stranger.pubkey(),
+                args::MigrateKeywordRoot {
+                    keyword: "lamp".to_string(),
+                },
+            )],
+            &[&stranger],
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+    let err = shop
+        .process(std::slice::from_ref(&migrate), &[])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InsufficientAccounts))
+    );
+
+    shop.process(&[with_shards], &[]).await.unwrap();
+    let global_root: GlobalIdRoot = shop.account(&pda::global_id_root().0).await;
+    assert_eq!(
+        shop.account_size(&root_address).await,
+        KeywordRoot::space(global_root.bloom_filter_size)
+    );
+    let migrated: KeywordRoot = shop.account(&root_address).await;
+    assert_eq!(
+        (
+            migrated.total_shards,
+            migrated.first_shard,
+            migrated.total_products
+        ),
+        (root.total_shards, root.first_shard, root.total_products)
+    );
+    assert_eq!(migrated.bloom_filter, root.bloom_filter);
+    assert!(migrated.might_contain(lamp) && migrated.might_contain(floor_lamp));
+    assert!(CountingBloomFilter::occupied_slots(&migrated.bloom_filter) > 0);
+
+    // A migrated root is left alone
+    shop.advance_clock(1).await;
+    let err = shop.process(&[migrate], &[]).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::AccountAlreadyMigrated))
+    );
+}
+
+#[tokio::test]
+async fn test_products_and_orders_created_before_variants_migrate() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let product_id = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let buyer = shop.buyer(500 * TOKEN);
+    let order = shop.place_order(&buyer, product_id, 1).await.unwrap();
+
+    // A legacy product may hold stale bytes where variant_count now lives
+    let product_address = pda::product(product_id).0;
+    let product: ProductBase = shop.account(&product_address).await;
+    shop.set_program_account(
+        product_address,
+        without_appended_fields(&product, 4, LegacyProductBase::SPACE, 0xFF),
+    );
+    let err = shop.place_order(&buyer, product_id, 1).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::AccountNotMigrated))
+    );
+
+    let migrate_product =
+        ix::migrate_product_base(shop.authority(), args::MigrateProductBase { product_id });
+    shop.process(std::slice::from_ref(&migrate_product), &[])
+        .await
+        .unwrap();
+    assert_eq!(
+        shop.account_size(&product_address).await,
+        ProductBase::SPACE
+    );
+    let migrated: ProductBase = shop.account(&product_address).await;
+    assert_eq!(migrated.variant_count, 0);
+    assert_eq!(
+        (migrated.name.as_str(), migrated.price, migrated.sales),
+        (product.name.as_str(), product.price, product.sales)
+    );
+    // Same order as the rejected one, sent in a new slot
+    shop.advance_clock(1).await;
+    shop.place_order(&buyer, product_id, 1).await.unwrap();
+
+    shop.advance_clock(1).await;
+    let err = shop.process(&[migrate_product], &[]).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::AccountAlreadyMigrated))
+    );
+
+    // Legacy orders read variant_id as None from their zeroed tail until migrated
+    let order_account: Order = shop.account(&order).await;
+    shop.set_program_account(
+        order,
+        without_appended_fields(&order_account, 1, LegacyOrder::SPACE, 0),
+    );
+    let legacy: Order = shop.account(&order).await;
+    assert_eq!(legacy.variant_id, None);
+    shop.process(&[ix::migrate_order(shop.authority(), order)], &[])
+        .await
+        .unwrap();
+    assert_eq!(shop.account_size(&order).await, Order::SPACE);
+    let migrated: Order = shop.account(&order).await;
+    assert_eq!(
+        (migrated.buyer, migrated.total_amount, migrated.variant_id),
+        (order_account.buyer, order_account.total_amount, None)
+    );
+    shop.refund_order(&buyer, order).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_system_config_created_before_trending_scores_migrates() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+
+    // Allocated as 8 + size_of::<SystemConfig>() before the trending and index shard settings
+    let config_address = pda::system_config().0;
+    let config: SystemConfig = shop.account(&config_address).await;
+    shop.set_program_account(
+        config_address,
+        without_appended_fields(&config, 8, LegacySystemConfig::SPACE, 0),
+    );
+
+    let (stranger, _) = shop.wallet(0);
+    let err = shop
+        .process(
+            &[ix::migrate_system_config(stranger.pubkey())],
+            &[&stranger],
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+
+    let migrate = ix::migrate_system_config(shop.authority());
+    shop.process(std::slice::from_ref(&migrate), &[])
+        .await
+        .unwrap();
+    assert_eq!(
+        shop.account_size(&config_address).await,
+        SystemConfig::SPACE
+    );
+    let migrated: SystemConfig = shop.account(&config_address).await;
+    assert_eq!(
+        (migrated.authority, migrated.platform_token_account),
+        (config.authority, config.platform_token_account)
+    );
+    assert_eq!(
+        (
+            migrated.trending_half_life_seconds,
+            migrated.index_shard_capacity
+        ),
+        (
+            DEFAULT_TRENDING_HALF_LIFE_SECONDS,
+            DEFAULT_INDEX_SHARD_CAPACITY
+        )
+    );
+
+    shop.advance_clock(1).await;
+    let err = shop.process(&[migrate], &[]).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::AccountAlreadyMigrated))
+    );
+}
+
+#[tokio::test]
+async fn test_legacy_price_nodes_move_to_the_node_of_their_mint() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let shade = shop
+        .create_product(&merchant, "Lamp shade", PRICE + TOKEN, &["lamp"])
+        .await
+        .unwrap();
+    let (start, end) = find_price_node_for_price(PRICE);
+    for product_id in [lamp, shade] {
+        let remove = ix::remove_product_from_price_index(
+            shop.mint,
+            start,
+            end,
+            args::RemoveProductFromPriceIndex { product_id },
+        );
+        shop.process(&[remove], &[]).await.unwrap();
+    }
+
+    // Nodes written before the mint namespace were shared by all tokens and stored ids only
+    let unknown_product = 999_999;
+    let legacy_address = pda::legacy_price_index(start, end).0;
+    let legacy = LegacyPriceIndexNode {
+        price_range_start: start,
+        price_range_end: end,
+        product_ids: vec![lamp, unknown_product, shade],
+        left_child: None,
+        right_child: None,
+        parent: None,
+        height: 1,
+        bump: pda::legacy_price_index(start, end).1,
+    };
+    shop.set_program_account(
+        legacy_address,
+        legacy_data(
+            PriceIndexNode::DISCRIMINATOR,
+            &legacy,
+            LegacyPriceIndexNode::SPACE,
+        ),
+    );
+
+    let (payer, mint) = (shop.authority(), shop.mint);
+    let migrate = |node: (u64, u64), products: &[u64]| {
+        with_remaining_accounts(
+            ix::migrate_price_node(
+                payer,
+                mint,
+                args::MigratePriceNode {
+                    legacy_range_start: start,
+                    legacy_range_end: end,
+                    price_range_start: node.0,
+                    price_range_end: node.1,
+                },
+            ),
+            products
+                .iter()
+                .map(|&product_id| AccountMeta::new_readonly(pda::product(product_id).0, false)),
+        )
+    };
+    let lamp_migration = migrate((start, end), &[lamp]);
+    shop.process(std::slice::from_ref(&lamp_migration), &[])
+        .await
+        .unwrap();
+
+    // Prices outside the receiving node are rejected
+    let err = shop
+        .process(&[migrate((end, 2 * end), &[shade])], &[])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidPriceRange))
+    );
+    shop.process(&[migrate((start, end), &[shade])], &[])
+        .await
+        .unwrap();
+
+    // Moved products leave the legacy node
+    shop.advance_clock(1).await;
+    let err = shop.process(&[lamp_migration], &[]).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::ProductNotFound))
+    );
+
+    let node: PriceIndexNode = shop
+        .account(&pda::price_index(&shop.mint, start, end).0)
+        .await;
+    assert_eq!(
+        node.entries,
+        vec![
+            PriceEntry {
+                price: PRICE,
+                product_id: lamp,
+            },
+            PriceEntry {
+                price: PRICE + TOKEN,
+                product_id: shade,
+            },
+        ]
+    );
+    let data = shop
+        .context
+        .banks_client
+        .get_account(legacy_address)
+        .await
+        .unwrap()
+        .unwrap()
+        .data;
+    let legacy = LegacyPriceIndexNode::deserialize(&mut &data[8..]).unwrap();
+    assert_eq!(legacy.product_ids, vec![unknown_product]);
+}
@@ -0,0 +1,312 @@
+use anchor_lang::prelude::Pubkey;
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, mock_vault, TestShop, SALES_RANGE, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::instructions::price_index::find_price_node_for_price;
+use solana_e_commerce::state::{
+    KeywordShard, Merchant, Order, OrderManagementStatus, OrderStats, PriceIndexNode, ProductBase,
+    SalesIndexNode,
+};
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+
+async fn open_shop() -> (TestShop, shop_tests::Merchant, u64) {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let product_id = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp", "home"])
+        .await
+        .unwrap();
+    (shop, merchant, product_id)
+}
+
+#[tokio::test]
+async fn test_product_creation_updates_indexes() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+
+    let product: ProductBase = shop.account(&pda::product(product_id).0).await;
+    assert_eq!(product.merchant, merchant.keypair.pubkey());
+    assert_eq!(product.price, PRICE);
+    assert_eq!(product.keywords, "lamp,home");
+
+    for keyword in ["lamp", "home"] {
+        let shard: KeywordShard = shop.account(&pda::keyword_shard(keyword, 0).0).await;
+        assert_eq!(shard.product_ids, vec![product_id]);
+    }
+
+    let (start, end) = find_price_node_for_price(PRICE);
+    let price_node: PriceIndexNode = shop
+        .account(&pda::price_index(&shop.mint, start, end).0)
+        .await;
+    assert_eq!(price_node.entries.len(), 1);
+    assert_eq!(price_node.entries[0].product_id, product_id);
+
+    let sales_node: SalesIndexNode = shop
+        .account(&pda::sales_index(SALES_RANGE.0, SALES_RANGE.1).0)
+        .await;
+    assert_eq!(sales_node.product_ids, vec![product_id]);
+}
+
+#[tokio::test]
+async fn test_confirm_delivery_splits_platform_fee() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(200 * TOKEN);
+
+    let order = shop.place_order(&buyer, product_id, 2).await.unwrap();
+    let program_token_account = pda::program_token_account(&shop.mint).0;
+    assert_eq!(shop.token_balance(&buyer.token_account).await, 100 * TOKEN);
+    assert_eq!(
+        shop.token_balance(&program_token_account).await,
+        100 * TOKEN
+    );
+
+    shop.ship_order(&merchant, order).await.unwrap();
+    let order_account: Order = shop.account(&order).await;
+    assert_eq!(order_account.status, OrderManagementStatus::Shipped);
+
+    shop.confirm_delivery(&buyer, order).await.unwrap();
+
+    // 0.4% of 100 tokens to the vault, the rest credited to the merchant deposit
+    let platform_fee = 400_000;
+    let merchant_amount = 100 * TOKEN - platform_fee;
+    assert_eq!(shop.token_balance(&program_token_account).await, 0);
+    let vault_token_account = shop.vault.token_account;
+    assert_eq!(shop.token_balance(&vault_token_account).await, platform_fee);
+    assert_eq!(
+        shop.token_balance(&pda::deposit_escrow(&shop.mint).0).await,
+        DEPOSIT + merchant_amount
+    );
+    let merchant_info: Merchant = shop
+        .account(&pda::merchant_info(&merchant.keypair.pubkey()).0)
+        .await;
+    assert_eq!(merchant_info.deposit_amount, DEPOSIT + merchant_amount);
+    assert_eq!(merchant_info.total_sales, 2);
+
+    let calls = shop.vault_calls().await;
+    assert_eq!(
+        calls,
+        vec![mock_vault::AddRewardsCall {
+            amount: platform_fee,
+            accounts: vec![
+                shop.vault.account,
+                shop.vault.token_account,
+                program_token_account,
+                shop.vault.platform_token_account,
+                pda::program_authority().0,
+                anchor_spl::token::ID,
+            ],
+            writable: vec![true, true, true, true, false, false],
+            signers: vec![false, false, false, false, true, false],
+        }]
+    );
+
+    let order_account: Order = shop.account(&order).await;
+    assert_eq!(order_account.status, OrderManagementStatus::Delivered);
+    assert!(order_account.delivered_at.is_some());
+
+    let product: ProductBase = shop.account(&pda::product(product_id).0).await;
+    assert_eq!(product.sales, 2);
+    let sales_node: SalesIndexNode = shop
+        .account(&pda::sales_index(SALES_RANGE.0, SALES_RANGE.1).0)
+        .await;
+    assert_eq!(sales_node.top_items[0].product_id, product_id);
+    assert_eq!(sales_node.top_items[0].sales, 2);
+
+    let stats: OrderStats = shop.account(&pda::order_stats().0).await;
+    assert_eq!(stats.total_orders, 1);
+    assert_eq!(stats.delivered_orders, 1);
+    assert_eq!(stats.shipped_orders, 0);
+}
+
+#[tokio::test]
+async fn test_refund_pending_order() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(200 * TOKEN);
+
+    let order = shop.place_order(&buyer, product_id, 1).await.unwrap();
+    shop.refund_order(&buyer, order).await.unwrap();
+
+    assert_eq!(shop.token_balance(&buyer.token_account).await, 200 * TOKEN);
+    assert_eq!(
+        shop.token_balance(&pda::program_token_account(&shop.mint).0)
+            .await,
+        0
+    );
+    let order_account: Order = shop.account(&order).await;
+    assert_eq!(order_account.status, OrderManagementStatus::Refunded);
+    assert_eq!(order_account.refund_reason, "Changed my mind");
+
+    // Only pending orders are refunded by the buyer
+    let order = shop.place_order(&buyer, product_id, 1).await.unwrap();
+    shop.ship_order(&merchant, order).await.unwrap();
+    let err = shop.refund_order(&buyer, order).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::OrderCannotBeRefunded))
+    );
+}
+
+#[tokio::test]
+async fn test_refund_delivered_order_from_deposit() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(200 * TOKEN);
+    let order = shop.place_order(&buyer, product_id, 2).await.unwrap();
+    shop.ship_order(&merchant, order).await.unwrap();
+    shop.confirm_delivery(&buyer, order).await.unwrap();
+
+    // The settled amount went to the deposit, the full price is paid back from there
+    let order_account: Order = shop.account(&order).await;
+    let sales_node = Some(pda::sales_index(SALES_RANGE.0, SALES_RANGE.1).0);
+    let instruction = ix::refund_delivered_order(
+        order,
+        &order_account,
+        sales_node,
+        sales_node,
+        buyer.token_account,
+        args::RefundDeliveredOrder {
+            refund_reason: "Arrived broken".to_string(),
+            day: shop.today().await,
+        },
+    );
+    shop.process(&[instruction], &[&merchant.keypair])
+        .await
+        .unwrap();
+
+    assert_eq!(shop.token_balance(&buyer.token_account).await, 200 * TOKEN);
+    let merchant_info: Merchant = shop
+        .account(&pda::merchant_info(&merchant.keypair.pubkey()).0)
+        .await;
+    assert_eq!(merchant_info.deposit_amount, DEPOSIT - 400_000);
+    assert_eq!(merchant_info.total_sales, 0);
+    let product: ProductBase = shop.account(&pda::product(product_id).0).await;
+    assert_eq!(product.sales, 0);
+    let order_account: Order = shop.account(&order).await;
+    assert_eq!(order_account.status, OrderManagementStatus::Refunded);
+}
+
+#[tokio::test]
+async fn test_auto_confirm_after_period() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(200 * TOKEN);
+
+    let order = shop.place_order(&buyer, product_id, 3).await.unwrap();
+    shop.ship_order(&merchant, order).await.unwrap();
+
+    let err = shop.auto_confirm_delivery(order).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidOrderStatusTransition))
+    );
+
+    shop.advance_clock(shop_tests::AUTO_CONFIRM_DAYS as i64 * 24 * 60 * 60)
+        .await;
+    shop.auto_confirm_delivery(order).await.unwrap();
+
+    let order_account: Order = shop.account(&order).await;
+    assert_eq!(order_account.status, OrderManagementStatus::Delivered);
+    let product: ProductBase = shop.account(&pda::product(product_id).0).await;
+    assert_eq!(product.sales, 3);
+
+    // Auto confirmation settles no tokens, the payment stays in escrow
+    assert!(shop.vault_calls().await.is_empty());
+    assert_eq!(
+        shop.token_balance(&pda::program_token_account(&shop.mint).0)
+            .await,
+        150 * TOKEN
+    );
+}
+
+#[tokio::test]
+async fn test_delivery_settles_without_sales_index_nodes() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    // Only the product base, the product never enters the sales index
+    let (instructions, product_id) = shop
+        .create_product_instructions(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await;
+    shop.process(&instructions[..1], &[&merchant.keypair])
+        .await
+        .unwrap();
+    assert!(shop.sales_index_account().await.is_none());
+
+    let buyer = shop.buyer(200 * TOKEN);
+    let confirmed = shop.place_order(&buyer, product_id, 1).await.unwrap();
+    let auto_confirmed = shop.place_order(&buyer, product_id, 2).await.unwrap();
+    shop.ship_order(&merchant, confirmed).await.unwrap();
+    shop.ship_order(&merchant, auto_confirmed).await.unwrap();
+
+    shop.confirm_delivery(&buyer, confirmed).await.unwrap();
+    shop.advance_clock(shop_tests::AUTO_CONFIRM_DAYS as i64 * 24 * 60 * 60)
+        .await;
+    shop.auto_confirm_delivery(auto_confirmed).await.unwrap();
+
+    for order in [confirmed, auto_confirmed] {
+        let order_account: Order = shop.account(&order).await;
+        assert_eq!(order_account.status, OrderManagementStatus::Delivered);
+    }
+    let product: ProductBase = shop.account(&pda::product(product_id).0).await;
+    assert_eq!(product.sales, 3);
+    assert!(
+        !shop
+            .exists(&pda::sales_index(SALES_RANGE.0, SALES_RANGE.1).0)
+            .await
+    );
+}
+
+#[tokio::test]
+async fn test_confirm_delivery_by_other_signer_fails() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(200 * TOKEN);
+    let order = shop.place_order(&buyer, product_id, 1).await.unwrap();
+    shop.ship_order(&merchant, order).await.unwrap();
+
+    let intruder = shop.buyer(0);
+    let order_account: Order = shop.account(&order).await;
+    let system_config = shop.account(&pda::system_config().0).await;
+    let sales_node = Some(pda::sales_index(SALES_RANGE.0, SALES_RANGE.1).0);
+    let mut instruction = ix::confirm_delivery(
+        order,
+        &order_account,
+        &system_config,
+        sales_node,
+        sales_node,
+        args::ConfirmDelivery {
+            day: shop.today().await,
+        },
+    );
+    replace_account(
+        &mut instruction,
+        order_account.buyer,
+        intruder.keypair.pubkey(),
+    );
+
+    let err = shop
+        .process(&[instruction], &[&intruder.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+    assert!(shop.vault_calls().await.is_empty());
+}
+
+fn replace_account(
+    instruction: &mut anchor_lang::solana_program::instruction::Instruction,
+    from: Pubkey,
+    to: Pubkey,
+) {
+    for meta in &mut instruction.accounts {
+        if meta.pubkey == from {
+            meta.pubkey = to;
+        }
+    }
+}
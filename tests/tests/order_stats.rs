@@ -0,0 +1,244 @@
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, TestShop, SALES_RANGE, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::state::{
+    DailyOrderStats, MerchantOrderStats, Order, OrderCounters, OrderStats, TokenOrderStats,
+};
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+
+async fn open_shop() -> (TestShop, shop_tests::Merchant, u64) {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let product_id = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    (shop, merchant, product_id)
+}
+
+async fn token_counters(shop: &mut TestShop) -> OrderCounters {
+    let mint = shop.mint;
+    let stats: TokenOrderStats = shop.account(&pda::token_order_stats(&mint).0).await;
+    stats.counters
+}
+
+async fn merchant_counters(shop: &mut TestShop, merchant: &shop_tests::Merchant) -> OrderCounters {
+    let address = pda::merchant_order_stats(&merchant.keypair.pubkey(), &shop.mint).0;
+    let stats: MerchantOrderStats = shop.account(&address).await;
+    stats.counters
+}
+
+async fn daily(shop: &mut TestShop, day: u32) -> DailyOrderStats {
+    let mint = shop.mint;
+    shop.account(&pda::daily_order_stats(&mint, day).0).await
+}
+
+#[tokio::test]
+async fn test_counters_follow_the_order_flow() {
+    let (mut shop, harbor, lamp) = open_shop().await;
+    let pier = shop
+        .register_merchant("Pier Supply", DEPOSIT)
+        .await
+        .unwrap();
+    let rope = shop
+        .create_product(&pier, "Rope", 20 * TOKEN, &["rope"])
+        .await
+        .unwrap();
+    let buyer = shop.buyer(500 * TOKEN);
+    let today = shop.today().await;
+
+    let delivered = shop.place_order(&buyer, lamp, 2).await.unwrap();
+    let refunded = shop.place_order(&buyer, lamp, 1).await.unwrap();
+    let auto_confirmed = shop.place_order(&buyer, rope, 3).await.unwrap();
+    assert_eq!(
+        token_counters(&mut shop).await,
+        OrderCounters {
+            total_orders: 3,
+            pending_orders: 3,
+            ..OrderCounters::default()
+        }
+    );
+
+    shop.ship_order(&harbor, delivered).await.unwrap();
+    shop.confirm_delivery(&buyer, delivered).await.unwrap();
+    shop.refund_order(&buyer, refunded).await.unwrap();
+    shop.ship_order(&pier, auto_confirmed).await.unwrap();
+    assert_eq!(
+        token_counters(&mut shop).await,
+        OrderCounters {
+            total_orders: 3,
+            pending_orders: 0,
+            shipped_orders: 1,
+            delivered_orders: 1,
+            refunded_orders: 1,
+            revenue: 100 * TOKEN,
+        }
+    );
+
+    // Auto confirmed on a later day
+    shop.advance_clock(shop_tests::AUTO_CONFIRM_DAYS as i64 * 24 * 60 * 60)
+        .await;
+    shop.auto_confirm_delivery(auto_confirmed).await.unwrap();
+    let later = shop.today().await;
+
+    assert_eq!(
+        token_counters(&mut shop).await,
+        OrderCounters {
+            total_orders: 3,
+            pending_orders: 0,
+            shipped_orders: 0,
+            delivered_orders: 2,
+            refunded_orders: 1,
+            revenue: 160 * TOKEN,
+        }
+    );
+    assert_eq!(
+        merchant_counters(&mut shop, &harbor).await,
+        OrderCounters {
+            total_orders: 2,
+            pending_orders: 0,
+            shipped_orders: 0,
+            delivered_orders: 1,
+            refunded_orders: 1,
+            revenue: 100 * TOKEN,
+        }
+    );
+    assert_eq!(
+        merchant_counters(&mut shop, &pier).await,
+        OrderCounters {
+            total_orders: 1,
+            delivered_orders: 1,
+            revenue: 60 * TOKEN,
+            ..OrderCounters::default()
+        }
+    );
+
+    // Refunds before delivery count in the global statistics as well
+    let global: OrderStats = shop.account(&pda::order_stats().0).await;
+    assert_eq!(global.total_orders, 3);
+    assert_eq!(global.pending_orders, 0);
+    assert_eq!(global.shipped_orders, 0);
+    assert_eq!(global.delivered_orders, 2);
+    assert_eq!(global.refunded_orders, 1);
+    assert_eq!(global.total_revenue, 160 * TOKEN);
+
+    let first_day = daily(&mut shop, today).await;
+    assert_eq!(first_day.mint, shop.mint);
+    assert_eq!(first_day.day, today);
+    assert_eq!(first_day.orders_created, 3);
+    assert_eq!(first_day.ordered_amount, 210 * TOKEN);
+    assert_eq!(first_day.orders_delivered, 1);
+    assert_eq!(first_day.delivered_amount, 100 * TOKEN);
+    assert_eq!(first_day.orders_refunded, 1);
+    assert_eq!(first_day.refunded_amount, 50 * TOKEN);
+    let second_day = daily(&mut shop, later).await;
+    assert_eq!(second_day.orders_created, 0);
+    assert_eq!(second_day.orders_delivered, 1);
+    assert_eq!(second_day.delivered_amount, 60 * TOKEN);
+}
+
+#[tokio::test]
+async fn test_refund_of_delivered_order_reverts_revenue() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(200 * TOKEN);
+    let order = shop.place_order(&buyer, product_id, 2).await.unwrap();
+    shop.ship_order(&merchant, order).await.unwrap();
+    shop.confirm_delivery(&buyer, order).await.unwrap();
+    assert_eq!(token_counters(&mut shop).await.revenue, 100 * TOKEN);
+
+    let order_account: Order = shop.account(&order).await;
+    let sales_node = Some(pda::sales_index(SALES_RANGE.0, SALES_RANGE.1).0);
+    let refund = |day| {
+        ix::refund_delivered_order(
+            order,
+            &order_account,
+            sales_node,
+            sales_node,
+            buyer.token_account,
+            args::RefundDeliveredOrder {
+                refund_reason: "Arrived broken".to_string(),
+                day,
+            },
+        )
+    };
+
+    // The daily bucket must be the current day
+    let today = shop.today().await;
+    let err = shop
+        .process(&[refund(today + 1)], &[&merchant.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidOrderStatsDay))
+    );
+
+    shop.process(&[refund(today)], &[&merchant.keypair])
+        .await
+        .unwrap();
+    let expected = OrderCounters {
+        total_orders: 1,
+        refunded_orders: 1,
+        ..OrderCounters::default()
+    };
+    assert_eq!(token_counters(&mut shop).await, expected);
+    assert_eq!(merchant_counters(&mut shop, &merchant).await, expected);
+    let bucket = daily(&mut shop, today).await;
+    assert_eq!(bucket.orders_delivered, 1);
+    assert_eq!(bucket.orders_refunded, 1);
+    assert_eq!(bucket.refunded_amount, 100 * TOKEN);
+}
+
+#[tokio::test]
+async fn test_orders_before_tracking_are_not_counted() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(200 * TOKEN);
+    let earlier = shop.place_order(&buyer, product_id, 1).await.unwrap();
+
+    // As if the statistics were opened after the order was created
+    let created_at = shop.account::<Order>(&earlier).await.created_at;
+    let mint = shop.mint;
+    shop.update_account(
+        &pda::token_order_stats(&mint).0,
+        |stats: &mut TokenOrderStats| {
+            stats.tracking_since = created_at + 1;
+            stats.counters = OrderCounters::default();
+        },
+    )
+    .await;
+    shop.advance_clock(1).await;
+
+    shop.ship_order(&merchant, earlier).await.unwrap();
+    shop.confirm_delivery(&buyer, earlier).await.unwrap();
+    assert_eq!(token_counters(&mut shop).await, OrderCounters::default());
+
+    let later = shop.place_order(&buyer, product_id, 2).await.unwrap();
+    shop.refund_order(&buyer, later).await.unwrap();
+    assert_eq!(
+        token_counters(&mut shop).await,
+        OrderCounters {
+            total_orders: 1,
+            refunded_orders: 1,
+            ..OrderCounters::default()
+        }
+    );
+    // The merchant statistics were open from the start
+    assert_eq!(
+        merchant_counters(&mut shop, &merchant).await,
+        OrderCounters {
+            total_orders: 2,
+            delivered_orders: 1,
+            refunded_orders: 1,
+            revenue: 50 * TOKEN,
+            ..OrderCounters::default()
+        }
+    );
+}
@@ -0,0 +1,188 @@
+use anchor_lang::prelude::AccountMeta;
+use shop_client::instructions::with_remaining_accounts;
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, TestShop, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::instructions::price_index::{find_price_node_for_price, PriceRangePage};
+use solana_e_commerce::utils::SortOrder;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+
+#[tokio::test]
+async fn test_search_needs_contiguous_leaves() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", 50 * TOKEN, &["lamp"])
+        .await
+        .unwrap();
+    let chandelier = shop
+        .create_product(&merchant, "Chandelier", 300 * TOKEN, &["lamp"])
+        .await
+        .unwrap();
+
+    // Two empty buckets lie between the products
+    let mint = shop.mint;
+    let bucket = |price: u64| {
+        let (start, end) = find_price_node_for_price(price);
+        pda::price_index(&mint, start, end).0
+    };
+    let (first_start, first_end) = find_price_node_for_price(50 * TOKEN);
+    let (_, second_end) = find_price_node_for_price(first_end);
+    let (third_start, _) = find_price_node_for_price(second_end);
+    let search = |nodes: &[_]| {
+        with_remaining_accounts(
+            ix::search_price_range(
+                bucket(50 * TOKEN),
+                args::SearchPriceRange {
+                    payment_token: Some(mint),
+                    min_price: first_start,
+                    max_price: 400 * TOKEN,
+                    sort_order: SortOrder::Ascending,
+                    offset: 0,
+                    limit: 20,
+                },
+            ),
+            nodes
+                .iter()
+                .map(|&node| AccountMeta::new_readonly(node, false)),
+        )
+    };
+
+    let err = shop
+        .view::<PriceRangePage>(search(&[bucket(300 * TOKEN)]), &[])
+        .await
+        .err()
+        .expect("search should fail");
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidPriceNodeLink))
+    );
+
+    // Only the address of the bucket itself stands in for a missing one
+    let err = shop
+        .view::<PriceRangePage>(
+            search(&[bucket(third_start), bucket(first_end), bucket(300 * TOKEN)]),
+            &[],
+        )
+        .await
+        .err()
+        .expect("search should fail");
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidPriceNodeLink))
+    );
+
+    let page: PriceRangePage = shop
+        .view(
+            search(&[bucket(first_end), bucket(third_start), bucket(300 * TOKEN)]),
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.product_ids, vec![lamp, chandelier]);
+    assert_eq!((page.total_count, page.has_more), (2, false));
+    assert_eq!(page.next_range_start, None);
+}
+
+#[tokio::test]
+async fn test_search_reports_the_unsearched_part_of_the_range() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", 50 * TOKEN, &["lamp"])
+        .await
+        .unwrap();
+    let chandelier = shop
+        .create_product(&merchant, "Chandelier", 60 * TOKEN, &["lamp"])
+        .await
+        .unwrap();
+
+    let mint = shop.mint;
+    let bucket = |price: u64| {
+        let (start, end) = find_price_node_for_price(price);
+        pda::price_index(&mint, start, end).0
+    };
+    let (bucket_start, bucket_end) = find_price_node_for_price(50 * TOKEN);
+    let (_, next_bucket_end) = find_price_node_for_price(bucket_end);
+    let search = |price_node, min_price, max_price, limit, nodes: &[_]| {
+        with_remaining_accounts(
+            ix::search_price_range(
+                price_node,
+                args::SearchPriceRange {
+                    payment_token: Some(mint),
+                    min_price,
+                    max_price,
+                    sort_order: SortOrder::Ascending,
+                    offset: 0,
+                    limit,
+                },
+            ),
+            nodes
+                .iter()
+                .map(|&node| AccountMeta::new_readonly(node, false)),
+        )
+    };
+
+    // The first leaf has to cover min_price, otherwise cheaper products would be skipped
+    let err = shop
+        .view::<PriceRangePage>(
+            search(bucket(50 * TOKEN), bucket_start - 1, 70 * TOKEN, 20, &[]),
+            &[],
+        )
+        .await
+        .err()
+        .expect("search should fail");
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidPriceNodeLink))
+    );
+
+    // A bucket that was never created stands in for the first leaf too
+    let page: PriceRangePage = shop
+        .view(
+            search(
+                bucket(bucket_start - 1),
+                bucket_start - 1,
+                60 * TOKEN,
+                20,
+                &[bucket(50 * TOKEN)],
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.product_ids, vec![lamp, chandelier]);
+    assert_eq!(page.next_range_start, None);
+
+    // Leaves ending before max_price report where the search continues
+    let page: PriceRangePage = shop
+        .view(
+            search(bucket(50 * TOKEN), bucket_start, next_bucket_end, 1, &[]),
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.product_ids, vec![lamp]);
+    assert_eq!((page.total_count, page.has_more), (2, true));
+    assert_eq!(page.next_range_start, Some(bucket_end));
+
+    let page: PriceRangePage = shop
+        .view(
+            search(bucket(bucket_end), bucket_end, next_bucket_end, 20, &[]),
+            &[],
+        )
+        .await
+        .unwrap();
+    assert!(page.product_ids.is_empty());
+    assert_eq!(page.next_range_start, Some(next_bucket_end));
+}
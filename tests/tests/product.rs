@@ -0,0 +1,229 @@
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, TestShop, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::state::{
+    IdChunk, MerchantProductList, MerchantProductPage, Order, ProductBase, ProductRating,
+    VariantOption,
+};
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 20 * TOKEN;
+
+fn delete(
+    merchant: &shop_tests::Merchant,
+    product_id: u64,
+    current_page: Option<(bool, u64)>,
+    hard_delete: bool,
+) -> anchor_lang::solana_program::instruction::Instruction {
+    let owner = merchant.keypair.pubkey();
+    ix::delete_product(
+        owner,
+        owner,
+        0,
+        current_page,
+        None,
+        args::DeleteProduct {
+            product_id,
+            hard_delete,
+            force: false,
+        },
+    )
+}
+
+#[tokio::test]
+async fn test_soft_delete_keeps_the_product() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop.register_merchant("Tide Shop", DEPOSIT).await.unwrap();
+    let owner = merchant.keypair.pubkey();
+    let product_id = shop
+        .create_product(&merchant, "Kayak paddle", PRICE, &["paddle"])
+        .await
+        .unwrap();
+    let product = pda::product(product_id).0;
+
+    shop.process(
+        &[delete(&merchant, product_id, Some((true, 0)), false)],
+        &[&merchant.keypair],
+    )
+    .await
+    .unwrap();
+
+    let delisted: ProductBase = shop.account(&product).await;
+    assert!(!delisted.is_active);
+    let inactive: MerchantProductPage = shop
+        .account(&pda::merchant_product_page(&owner, false, 0).0)
+        .await;
+    assert_eq!(inactive.product_ids, vec![product_id]);
+
+    // A hard delete takes the product off the inactive page and closes it
+    shop.process(
+        &[delete(&merchant, product_id, Some((false, 0)), true)],
+        &[&merchant.keypair],
+    )
+    .await
+    .unwrap();
+
+    assert!(!shop.exists(&product).await);
+    let catalog: MerchantProductList = shop.account(&pda::merchant_product_list(&owner).0).await;
+    assert_eq!((catalog.active_count, catalog.inactive_count), (0, 0));
+}
+
+#[tokio::test]
+async fn test_tracked_products_need_their_catalog_page() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop.register_merchant("Tide Shop", DEPOSIT).await.unwrap();
+    let owner = merchant.keypair.pubkey();
+    let product_id = shop
+        .create_product(&merchant, "Dry bag", PRICE, &["bag"])
+        .await
+        .unwrap();
+
+    for hard_delete in [false, true] {
+        let err = shop
+            .process(
+                &[delete(&merchant, product_id, None, hard_delete)],
+                &[&merchant.keypair],
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            custom_error(err),
+            Some(error_code(ErrorCode::InvalidProductCatalogPage))
+        );
+    }
+
+    let catalog: MerchantProductList = shop.account(&pda::merchant_product_list(&owner).0).await;
+    assert_eq!(catalog.active_count, 1);
+    assert!(shop.exists(&pda::product(product_id).0).await);
+}
+
+#[tokio::test]
+async fn test_hard_delete_closes_product_accounts_and_reuses_the_id() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop.register_merchant("Tide Shop", DEPOSIT).await.unwrap();
+    let owner = merchant.keypair.pubkey();
+    let product_id = shop
+        .create_product(&merchant, "Kayak paddle", PRICE, &["paddle"])
+        .await
+        .unwrap();
+
+    // Extended info, a variant, a rating and a trending score all keyed by the product ID
+    let extended = ix::create_product_extended(
+        owner,
+        args::CreateProductExtended {
+            product_id,
+            image_video_urls: vec![],
+            sales_regions: vec!["EU".to_string()],
+            logistics_methods: vec![],
+        },
+    );
+    let variant = ix::create_product_variant(
+        owner,
+        0,
+        args::CreateProductVariant {
+            product_id,
+            options: vec![VariantOption {
+                name: "blade".to_string(),
+                value: "carbon".to_string(),
+            }],
+            sku: "SKU-0".to_string(),
+            price: None,
+            inventory: 5,
+        },
+    );
+    shop.process(&[extended, variant], &[&merchant.keypair])
+        .await
+        .unwrap();
+    let buyer = shop.buyer(100 * TOKEN);
+    let order = shop
+        .place_variant_order(&buyer, product_id, Some(0), 1)
+        .await
+        .unwrap();
+    shop.ship_order(&merchant, order).await.unwrap();
+    shop.confirm_delivery(&buyer, order).await.unwrap();
+    let order_account: Order = shop.account(&order).await;
+    let review = ix::create_review(
+        order,
+        &order_account,
+        args::CreateReview {
+            rating: 4,
+            content_hash: None,
+            text: "Light".to_string(),
+        },
+    );
+    shop.process(&[review], &[&buyer.keypair]).await.unwrap();
+
+    let keyed = [
+        pda::product_extended(product_id).0,
+        pda::product_variant(product_id, 0).0,
+        pda::product_rating(product_id).0,
+        pda::trending_score(product_id).0,
+    ];
+    for address in keyed {
+        assert!(shop.exists(&address).await);
+    }
+
+    let hard_delete = |variants: &[Pubkey]| {
+        ix::with_remaining_accounts(
+            ix::delete_product(
+                owner,
+                owner,
+                0,
+                Some((true, 0)),
+                Some(0),
+                args::DeleteProduct {
+                    product_id,
+                    hard_delete: true,
+                    force: false,
+                },
+            ),
+            variants
+                .iter()
+                .map(|variant| AccountMeta::new(*variant, false)),
+        )
+    };
+
+    // Every variant has to be closed with the product
+    let err = shop
+        .process(&[hard_delete(&[])], &[&merchant.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidProductVariant))
+    );
+
+    shop.process(&[hard_delete(&keyed[1..2])], &[&merchant.keypair])
+        .await
+        .unwrap();
+    assert!(!shop.exists(&pda::product(product_id).0).await);
+    for address in keyed {
+        assert!(!shop.exists(&address).await);
+    }
+    let chunk: IdChunk = shop.account(&pda::id_chunk(&owner, 0).0).await;
+    assert!(!chunk.is_id_used(product_id - chunk.start_id));
+
+    // The next product gets the released ID (index entries are left to the index instructions)
+    let (instructions, next_id) = shop
+        .create_product_instructions(&merchant, "Dry bag", PRICE, &["bag"])
+        .await;
+    assert_eq!(next_id, product_id);
+    shop.process(&instructions[..1], &[&merchant.keypair])
+        .await
+        .unwrap();
+    let product: ProductBase = shop.account(&pda::product(next_id).0).await;
+    assert_eq!(
+        (product.name.as_str(), product.variant_count),
+        ("Dry bag", 0)
+    );
+    assert!(shop
+        .try_account::<ProductRating>(&pda::product_rating(next_id).0)
+        .await
+        .is_none());
+}
@@ -0,0 +1,310 @@
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, Merchant, TestShop, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::state::{KeywordShard, Order, ProductBase, ProductVariant, VariantOption};
+use solana_program_test::BanksClientError;
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 2_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+
+fn options(pairs: &[(&str, &str)]) -> Vec<VariantOption> {
+    pairs
+        .iter()
+        .map(|(name, value)| VariantOption {
+            name: name.to_string(),
+            value: value.to_string(),
+        })
+        .collect()
+}
+
+async fn create_variant(
+    shop: &mut TestShop,
+    merchant: &Merchant,
+    product_id: u64,
+    options: Vec<VariantOption>,
+    price: Option<u64>,
+    inventory: u64,
+) -> Result<u32, BanksClientError> {
+    let product: ProductBase = shop.account(&pda::product(product_id).0).await;
+    let instruction = ix::create_product_variant(
+        merchant.keypair.pubkey(),
+        product.variant_count,
+        args::CreateProductVariant {
+            product_id,
+            options,
+            sku: format!("SKU-{}", product.variant_count),
+            price,
+            inventory,
+        },
+    );
+    shop.process(&[instruction], &[&merchant.keypair]).await?;
+    Ok(product.variant_count)
+}
+
+async fn variant(shop: &mut TestShop, product_id: u64, variant_id: u32) -> ProductVariant {
+    shop.account(&pda::product_variant(product_id, variant_id).0)
+        .await
+}
+
+#[tokio::test]
+async fn test_variant_orders_use_variant_price_and_stock() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let product_id = shop
+        .create_product(&merchant, "Linen shirt", PRICE, &["shirt"])
+        .await
+        .unwrap();
+    let buyer = shop.buyer(500 * TOKEN);
+
+    let small = create_variant(
+        &mut shop,
+        &merchant,
+        product_id,
+        options(&[("size", "S"), ("color", "white")]),
+        None,
+        5,
+    )
+    .await
+    .unwrap();
+    let large = create_variant(
+        &mut shop,
+        &merchant,
+        product_id,
+        options(&[("size", "L"), ("color", "white")]),
+        Some(60 * TOKEN),
+        2,
+    )
+    .await
+    .unwrap();
+    assert_eq!((small, large), (0, 1));
+    let product: ProductBase = shop.account(&pda::product(product_id).0).await;
+    assert_eq!(product.variant_count, 2);
+    let stored = variant(&mut shop, product_id, large).await;
+    assert_eq!(stored.product_id, product_id);
+    assert_eq!(stored.sku, "SKU-1");
+    assert_eq!(stored.effective_price(&product), 60 * TOKEN);
+
+    // The variant price is charged and its stock reserved
+    let balance = shop.token_balance(&buyer.token_account).await;
+    let order = shop
+        .place_variant_order(&buyer, product_id, Some(large), 2)
+        .await
+        .unwrap();
+    let order_account: Order = shop.account(&order).await;
+    assert_eq!(order_account.variant_id, Some(large));
+    assert_eq!(order_account.price, 60 * TOKEN);
+    assert_eq!(order_account.total_amount, 120 * TOKEN);
+    assert_eq!(
+        shop.token_balance(&buyer.token_account).await,
+        balance - 120 * TOKEN
+    );
+    assert_eq!(variant(&mut shop, product_id, large).await.inventory, 0);
+
+    // Sold out until the order is refunded
+    let err = shop
+        .place_variant_order(&buyer, product_id, Some(large), 1)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InsufficientVariantInventory))
+    );
+    shop.refund_order(&buyer, order).await.unwrap();
+    assert_eq!(variant(&mut shop, product_id, large).await.inventory, 2);
+
+    // Without an override the variant sells at the product price
+    let order = shop
+        .place_variant_order(&buyer, product_id, Some(small), 1)
+        .await
+        .unwrap();
+    let order_account: Order = shop.account(&order).await;
+    assert_eq!(order_account.price, PRICE);
+    assert_eq!(variant(&mut shop, product_id, small).await.inventory, 4);
+
+    // Clearing the override falls back to the product price
+    let instruction = ix::update_product_variant_price(
+        merchant.keypair.pubkey(),
+        args::UpdateProductVariantPrice {
+            product_id,
+            variant_id: large,
+            price: None,
+        },
+    );
+    shop.process(&[instruction], &[&merchant.keypair])
+        .await
+        .unwrap();
+    let order = shop
+        .place_variant_order(&buyer, product_id, Some(large), 1)
+        .await
+        .unwrap();
+    let order_account: Order = shop.account(&order).await;
+    assert_eq!(order_account.price, PRICE);
+}
+
+#[tokio::test]
+async fn test_products_with_variants_require_an_active_variant() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let product_id = shop
+        .create_product(&merchant, "Linen shirt", PRICE, &["shirt"])
+        .await
+        .unwrap();
+    let other_product_id = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let buyer = shop.buyer(500 * TOKEN);
+
+    // Options need distinct names
+    let err = create_variant(
+        &mut shop,
+        &merchant,
+        product_id,
+        options(&[("size", "S"), ("size", "M")]),
+        None,
+        5,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidVariantOptions))
+    );
+
+    // Only the product owner adds variants
+    let stranger = shop
+        .register_merchant("Other Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let err = create_variant(
+        &mut shop,
+        &stranger,
+        product_id,
+        options(&[("size", "S")]),
+        None,
+        5,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+
+    let variant_id = create_variant(
+        &mut shop,
+        &merchant,
+        product_id,
+        options(&[("size", "S")]),
+        None,
+        5,
+    )
+    .await
+    .unwrap();
+
+    // The product can no longer be ordered without a variant
+    let err = shop.place_order(&buyer, product_id, 1).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::ProductVariantRequired))
+    );
+
+    // Nor with the variant of another product
+    let other_variant_id = create_variant(
+        &mut shop,
+        &merchant,
+        other_product_id,
+        options(&[("shade", "green")]),
+        None,
+        5,
+    )
+    .await
+    .unwrap();
+    let (instructions, _) = shop
+        .place_order_instructions(&buyer, product_id, None, 1)
+        .await;
+    let other_variant = pda::product_variant(other_product_id, other_variant_id).0;
+    let instructions: Vec<_> = instructions
+        .into_iter()
+        .map(|mut instruction| {
+            let product_variant = instruction
+                .accounts
+                .iter_mut()
+                .find(|meta| meta.pubkey == solana_e_commerce::ID)
+                .unwrap();
+            product_variant.pubkey = other_variant;
+            instruction
+        })
+        .collect();
+    let err = shop
+        .process(&instructions, &[&buyer.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidProductVariant))
+    );
+
+    // Deactivated variants are not sold
+    let instruction = ix::update_product_variant(
+        merchant.keypair.pubkey(),
+        args::UpdateProductVariant {
+            product_id,
+            variant_id,
+            sku: Some("SHIRT-S".to_string()),
+            inventory: Some(8),
+            is_active: Some(false),
+        },
+    );
+    shop.process(&[instruction], &[&merchant.keypair])
+        .await
+        .unwrap();
+    let stored = variant(&mut shop, product_id, variant_id).await;
+    assert_eq!((stored.sku.as_str(), stored.inventory), ("SHIRT-S", 8));
+    let err = shop
+        .place_variant_order(&buyer, product_id, Some(variant_id), 1)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::ProductVariantInactive))
+    );
+}
+
+#[tokio::test]
+async fn test_search_indexes_the_parent_product_once() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let product_id = shop
+        .create_product(&merchant, "Linen shirt", PRICE, &["shirt"])
+        .await
+        .unwrap();
+    for size in ["S", "M", "L"] {
+        create_variant(
+            &mut shop,
+            &merchant,
+            product_id,
+            options(&[("size", size)]),
+            None,
+            5,
+        )
+        .await
+        .unwrap();
+    }
+
+    // Variants live in their own accounts, the keyword index holds the product once
+    assert!(shop.exists(&pda::product_variant(product_id, 2).0).await);
+    let shard: KeywordShard = shop.account(&pda::keyword_shard("shirt", 0).0).await;
+    assert_eq!(shard.product_ids, vec![product_id]);
+}
@@ -0,0 +1,208 @@
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, TestShop, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::instructions::RecentProductsPage;
+use solana_e_commerce::state::{RecencyBucket, RecencyFeed, RECENCY_BUCKET_SECONDS};
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+
+async fn recent_products(
+    shop: &mut TestShop,
+    feed: RecencyFeed,
+    keyword: &str,
+    days: &[u32],
+) -> Vec<u64> {
+    let mut get = ix::get_recent_products(args::GetRecentProducts {
+        feed,
+        keyword: keyword.to_string(),
+        cursor: None,
+        limit: 20,
+    });
+    get.accounts.extend(
+        days.iter().map(|&day| {
+            AccountMeta::new_readonly(pda::recency_bucket(feed, keyword, day).0, false)
+        }),
+    );
+    let page: RecentProductsPage = shop.view(get, &[]).await.unwrap();
+    page.product_ids
+}
+
+fn record(payer: Pubkey, newer_day: Option<u32>, product_id: u64, day: u32) -> Instruction {
+    ix::record_recent_product(
+        payer,
+        newer_day,
+        args::RecordRecentProduct {
+            product_id,
+            feed: RecencyFeed::NewArrivals,
+            keyword: "lamp".to_string(),
+            day,
+        },
+    )
+}
+
+#[tokio::test]
+async fn test_products_are_recorded_on_create_and_update() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let today = shop.today().await;
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let shade = shop
+        .create_product(&merchant, "Lamp shade", PRICE, &["shade"])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        recent_products(&mut shop, RecencyFeed::NewArrivals, "", &[today]).await,
+        vec![shade, lamp]
+    );
+
+    shop.advance_clock(RECENCY_BUCKET_SECONDS).await;
+    let tomorrow = shop.today().await;
+    let update = ix::update_product(
+        merchant.keypair.pubkey(),
+        false,
+        args::UpdateProduct {
+            product_id: lamp,
+            name: None,
+            description: None,
+            price: Some(2 * PRICE),
+            keywords: None,
+            inventory: None,
+            payment_token: None,
+            image_video_urls: None,
+            shipping_location: None,
+            sales_regions: None,
+            logistics_methods: None,
+            day: tomorrow,
+        },
+    );
+    shop.process(&[update], &[&merchant.keypair]).await.unwrap();
+
+    assert_eq!(
+        recent_products(&mut shop, RecencyFeed::RecentlyUpdated, "", &[tomorrow]).await,
+        vec![lamp]
+    );
+
+    // The day of the feed bucket is the current day
+    let stale = ix::update_product(
+        merchant.keypair.pubkey(),
+        false,
+        args::UpdateProduct {
+            product_id: shade,
+            name: None,
+            description: None,
+            price: Some(2 * PRICE),
+            keywords: None,
+            inventory: None,
+            payment_token: None,
+            image_video_urls: None,
+            shipping_location: None,
+            sales_regions: None,
+            logistics_methods: None,
+            day: today,
+        },
+    );
+    let err = shop
+        .process(&[stale], &[&merchant.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidRecencyBucket))
+    );
+}
+
+#[tokio::test]
+async fn test_older_days_are_inserted_into_the_chain() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let payer = shop.authority();
+
+    let first_day = shop.today().await;
+    let desk_lamp = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    shop.advance_clock(RECENCY_BUCKET_SECONDS).await;
+    let second_day = shop.today().await;
+    let floor_lamp = shop
+        .create_product(&merchant, "Floor lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    shop.advance_clock(RECENCY_BUCKET_SECONDS).await;
+    let third_day = shop.today().await;
+    let wall_lamp = shop
+        .create_product(&merchant, "Wall lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+
+    // The keyword feed is backfilled newest first
+    shop.process(&[record(payer, None, wall_lamp, third_day)], &[])
+        .await
+        .unwrap();
+    let err = shop
+        .process(&[record(payer, None, desk_lamp, first_day)], &[])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidRecencyBucket))
+    );
+    shop.process(&[record(payer, Some(third_day), desk_lamp, first_day)], &[])
+        .await
+        .unwrap();
+
+    // The newer bucket must be the one the chain passes through right after the day
+    let err = shop
+        .process(
+            &[record(payer, Some(first_day), floor_lamp, second_day)],
+            &[],
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidRecencyBucket))
+    );
+    shop.process(
+        &[record(payer, Some(third_day), floor_lamp, second_day)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let third: RecencyBucket = shop
+        .account(&pda::recency_bucket(RecencyFeed::NewArrivals, "lamp", third_day).0)
+        .await;
+    let second: RecencyBucket = shop
+        .account(&pda::recency_bucket(RecencyFeed::NewArrivals, "lamp", second_day).0)
+        .await;
+    assert_eq!(third.previous_day, Some(second_day));
+    assert_eq!(second.previous_day, Some(first_day));
+    assert_eq!(
+        recent_products(
+            &mut shop,
+            RecencyFeed::NewArrivals,
+            "lamp",
+            &[third_day, second_day, first_day]
+        )
+        .await,
+        vec![wall_lamp, floor_lamp, desk_lamp]
+    );
+}
@@ -0,0 +1,181 @@
+use anchor_lang::prelude::Pubkey;
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, Buyer, TestShop, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::state::{
+    MerchantReputation, Order, ReputationScore, REPUTATION_EVENT_WEIGHT, SHIP_DEADLINE_SECONDS,
+};
+use solana_program_test::BanksClientError;
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 2_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+const WEIGHT: u64 = REPUTATION_EVENT_WEIGHT;
+
+async fn reputation(shop: &mut TestShop, owner: Pubkey) -> ReputationScore {
+    shop.view(ix::get_merchant_reputation(owner), &[])
+        .await
+        .unwrap()
+}
+
+async fn review(shop: &mut TestShop, buyer: &Buyer, order: Pubkey, rating: u8) {
+    let order_account: Order = shop.account(&order).await;
+    let instruction = ix::create_review(
+        order,
+        &order_account,
+        args::CreateReview {
+            rating,
+            content_hash: None,
+            text: String::new(),
+        },
+    );
+    shop.process(&[instruction], &[&buyer.keypair])
+        .await
+        .unwrap();
+}
+
+async fn record_lost_dispute(
+    shop: &mut TestShop,
+    authority: Pubkey,
+    order: Pubkey,
+    reason: &str,
+) -> Result<(), BanksClientError> {
+    let order_account: Order = shop.account(&order).await;
+    let instruction = ix::record_lost_dispute(
+        authority,
+        order,
+        &order_account,
+        args::RecordLostDispute {
+            reason: reason.to_string(),
+        },
+    );
+    shop.process(&[instruction], &[]).await
+}
+
+#[tokio::test]
+async fn test_reputation_follows_orders_reviews_and_penalties() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let owner = merchant.keypair.pubkey();
+    let product_id = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let buyer = shop.buyer(500 * TOKEN);
+
+    // Neutral before any recorded event
+    let neutral = reputation(&mut shop, owner).await;
+    assert_eq!(neutral.merchant, owner);
+    assert_eq!(neutral.score, 500);
+    assert!(!shop.exists(&pda::merchant_reputation(&owner).0).await);
+
+    // A late shipment, then two settled orders and a 5 star review
+    let late = shop.place_order(&buyer, product_id, 1).await.unwrap();
+    shop.advance_clock(SHIP_DEADLINE_SECONDS + 1).await;
+    shop.ship_order(&merchant, late).await.unwrap();
+    shop.confirm_delivery(&buyer, late).await.unwrap();
+    let prompt = shop.place_order(&buyer, product_id, 1).await.unwrap();
+    shop.ship_order(&merchant, prompt).await.unwrap();
+    shop.confirm_delivery(&buyer, prompt).await.unwrap();
+    review(&mut shop, &buyer, prompt, 5).await;
+
+    let stored: MerchantReputation = shop.account(&pda::merchant_reputation(&owner).0).await;
+    assert_eq!(stored.merchant, owner);
+    assert_eq!(stored.settled_orders, 2 * WEIGHT);
+    assert_eq!(stored.missed_ship_deadlines, WEIGHT);
+    assert_eq!(stored.reviews, WEIGHT);
+    assert_eq!(stored.review_points, WEIGHT);
+    let score = reputation(&mut shop, owner).await;
+    assert_eq!(score, stored.score(score.updated_at));
+    // (2 + 2.5) / (2 + 5 + 1 * 2) and (1 + 2.5) / (1 + 5)
+    assert_eq!(score.fulfilment_score, 500);
+    assert_eq!(score.review_score, 583);
+
+    // An order still pending past the deadline is cancelled, the deposit is slashed and the
+    // merchant loses a dispute
+    let abandoned = shop.place_order(&buyer, product_id, 1).await.unwrap();
+    shop.advance_clock(SHIP_DEADLINE_SECONDS + 1).await;
+    shop.refund_order(&buyer, abandoned).await.unwrap();
+    let (_, admin_token_account) = shop.wallet(0);
+    let authority = shop.authority();
+    let deduct = ix::deduct_merchant_deposit(
+        authority,
+        owner,
+        shop.mint,
+        admin_token_account,
+        args::DeductMerchantDeposit {
+            amount: 100 * TOKEN,
+            reason: "Counterfeit goods".to_string(),
+        },
+    );
+    shop.process(&[deduct], &[]).await.unwrap();
+    record_lost_dispute(&mut shop, authority, late, "Item not as described")
+        .await
+        .unwrap();
+
+    let stored: MerchantReputation = shop.account(&pda::merchant_reputation(&owner).0).await;
+    assert_eq!(stored.deposit_slashes, WEIGHT);
+    assert_eq!(stored.lost_disputes, WEIGHT);
+    // The earlier late shipment has decayed a little
+    assert!(stored.missed_ship_deadlines > WEIGHT);
+    assert!(stored.missed_ship_deadlines < 2 * WEIGHT);
+    assert!(stored.settled_orders < 2 * WEIGHT);
+    let penalized = reputation(&mut shop, owner).await;
+    assert_eq!(penalized, stored.score(penalized.updated_at));
+    assert!(penalized.fulfilment_score < score.fulfilment_score);
+    assert!(penalized.score < score.score);
+}
+
+#[tokio::test]
+async fn test_only_the_administrator_records_lost_disputes() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let product_id = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    let buyer = shop.buyer(100 * TOKEN);
+    let order = shop.place_order(&buyer, product_id, 1).await.unwrap();
+
+    let order_account: Order = shop.account(&order).await;
+    let instruction = ix::record_lost_dispute(
+        buyer.keypair.pubkey(),
+        order,
+        &order_account,
+        args::RecordLostDispute {
+            reason: "Never arrived".to_string(),
+        },
+    );
+    let err = shop
+        .process(&[instruction], &[&buyer.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+
+    let authority = shop.authority();
+    let err = record_lost_dispute(&mut shop, authority, order, "")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidDisputeReason))
+    );
+
+    record_lost_dispute(&mut shop, authority, order, "Never arrived")
+        .await
+        .unwrap();
+    let owner = merchant.keypair.pubkey();
+    let score = reputation(&mut shop, owner).await;
+    assert_eq!(score.lost_disputes, WEIGHT);
+    // (0 + 2.5) / (0 + 5 + 1 * 5)
+    assert_eq!(score.fulfilment_score, 250);
+}
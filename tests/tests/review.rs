@@ -0,0 +1,200 @@
+use anchor_lang::prelude::Pubkey;
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, Buyer, TestShop, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::state::{MerchantRating, Order, ProductRating, RatingSummary, Review};
+use solana_program_test::BanksClientError;
+use solana_signer::Signer;
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+const PRICE: u64 = 50 * TOKEN;
+
+async fn open_shop() -> (TestShop, shop_tests::Merchant, u64) {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let product_id = shop
+        .create_product(&merchant, "Desk lamp", PRICE, &["lamp"])
+        .await
+        .unwrap();
+    (shop, merchant, product_id)
+}
+
+async fn delivered_order(
+    shop: &mut TestShop,
+    merchant: &shop_tests::Merchant,
+    buyer: &Buyer,
+    product_id: u64,
+) -> Pubkey {
+    let order = shop.place_order(buyer, product_id, 1).await.unwrap();
+    shop.ship_order(merchant, order).await.unwrap();
+    shop.confirm_delivery(buyer, order).await.unwrap();
+    order
+}
+
+async fn review(
+    shop: &mut TestShop,
+    buyer: &Buyer,
+    order: Pubkey,
+    rating: u8,
+) -> Result<(), BanksClientError> {
+    let order_account: Order = shop.account(&order).await;
+    let instruction = ix::create_review(
+        order,
+        &order_account,
+        args::CreateReview {
+            rating,
+            content_hash: None,
+            text: format!("{} stars", rating),
+        },
+    );
+    shop.process(&[instruction], &[&buyer.keypair]).await
+}
+
+#[tokio::test]
+async fn test_reviews_aggregate_onto_product_and_merchant() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    let alice = shop.buyer(100 * TOKEN);
+    let bob = shop.buyer(100 * TOKEN);
+    let first = delivered_order(&mut shop, &merchant, &alice, product_id).await;
+    let second = delivered_order(&mut shop, &merchant, &bob, product_id).await;
+
+    review(&mut shop, &alice, first, 5).await.unwrap();
+    let content_hash = [7u8; 32];
+    let order_account: Order = shop.account(&second).await;
+    let instruction = ix::create_review(
+        second,
+        &order_account,
+        args::CreateReview {
+            rating: 2,
+            content_hash: Some(content_hash),
+            text: String::new(),
+        },
+    );
+    shop.process(&[instruction], &[&bob.keypair]).await.unwrap();
+
+    let stored: Review = shop.account(&pda::review(&first).0).await;
+    assert_eq!(stored.order, first);
+    assert_eq!(stored.buyer, alice.keypair.pubkey());
+    assert_eq!(stored.merchant, merchant.keypair.pubkey());
+    assert_eq!(stored.product_id, product_id);
+    assert_eq!(stored.rating, 5);
+    assert_eq!(stored.text, "5 stars");
+    assert_eq!(stored.replied_at, None);
+    let stored: Review = shop.account(&pda::review(&second).0).await;
+    assert_eq!(stored.content_hash, Some(content_hash));
+
+    let expected = RatingSummary {
+        rating_count: 2,
+        rating_sum: 7,
+        star_counts: [0, 1, 0, 0, 1],
+    };
+    let product: ProductRating = shop.account(&pda::product_rating(product_id).0).await;
+    assert_eq!(product.product_id, product_id);
+    assert_eq!(product.summary, expected);
+    assert_eq!(product.summary.average_x100(), 350);
+    let owner = merchant.keypair.pubkey();
+    let merchant_rating: MerchantRating = shop.account(&pda::merchant_rating(&owner).0).await;
+    assert_eq!(merchant_rating.merchant, owner);
+    assert_eq!(merchant_rating.summary, expected);
+
+    // One review per order
+    assert!(review(&mut shop, &alice, first, 4).await.is_err());
+}
+
+#[tokio::test]
+async fn test_only_the_buyer_of_a_delivered_order_reviews() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(100 * TOKEN);
+
+    let order = shop.place_order(&buyer, product_id, 1).await.unwrap();
+    let err = review(&mut shop, &buyer, order, 4).await.unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::OrderNotDelivered))
+    );
+    shop.ship_order(&merchant, order).await.unwrap();
+    shop.confirm_delivery(&buyer, order).await.unwrap();
+
+    let stranger = shop.buyer(0);
+    let order_account: Order = shop.account(&order).await;
+    let mut instruction = ix::create_review(
+        order,
+        &order_account,
+        args::CreateReview {
+            rating: 4,
+            content_hash: None,
+            text: String::new(),
+        },
+    );
+    instruction.accounts[0].pubkey = stranger.keypair.pubkey();
+    let err = shop
+        .process(&[instruction], &[&stranger.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+
+    for rating in [0, 6] {
+        let err = review(&mut shop, &buyer, order, rating).await.unwrap_err();
+        assert_eq!(
+            custom_error(err),
+            Some(error_code(ErrorCode::InvalidRating))
+        );
+    }
+    review(&mut shop, &buyer, order, 1).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_merchant_replies_once() {
+    let (mut shop, merchant, product_id) = open_shop().await;
+    let buyer = shop.buyer(100 * TOKEN);
+    let order = delivered_order(&mut shop, &merchant, &buyer, product_id).await;
+    review(&mut shop, &buyer, order, 3).await.unwrap();
+
+    let reply = |merchant: Pubkey, reply: &str| {
+        ix::reply_to_review(
+            merchant,
+            order,
+            args::ReplyToReview {
+                reply: reply.to_string(),
+            },
+        )
+    };
+
+    let other = shop
+        .register_merchant("Pier Supply", DEPOSIT)
+        .await
+        .unwrap();
+    let err = shop
+        .process(
+            &[reply(other.keypair.pubkey(), "Not ours")],
+            &[&other.keypair],
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(custom_error(err), Some(error_code(ErrorCode::Unauthorized)));
+
+    let owner = merchant.keypair.pubkey();
+    shop.process(
+        &[reply(owner, "Sorry, a new one is on the way")],
+        &[&merchant.keypair],
+    )
+    .await
+    .unwrap();
+    let stored: Review = shop.account(&pda::review(&order).0).await;
+    assert_eq!(stored.reply, "Sorry, a new one is on the way");
+    assert!(stored.replied_at.is_some());
+
+    let err = shop
+        .process(&[reply(owner, "Update")], &[&merchant.keypair])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::ReviewAlreadyReplied))
+    );
+}
@@ -0,0 +1,113 @@
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::AccountSerialize;
+use shop_client::instructions::with_remaining_accounts;
+use shop_client::{instructions as ix, pda};
+use shop_tests::{custom_error, error_code, TestShop, TOKEN};
+use solana_e_commerce::error::ErrorCode;
+use solana_e_commerce::instruction as args;
+use solana_e_commerce::instructions::price_index::find_price_node_for_price;
+use solana_e_commerce::state::PriceIndexNode;
+use solana_e_commerce::utils::{SearchFilter, SearchResult, SortKey, SortOrder};
+
+const DEPOSIT: u64 = 1_000 * TOKEN;
+
+fn search(index_accounts: &[Pubkey], product_ids: &[u64], sort_key: SortKey) -> Instruction {
+    let filter = SearchFilter {
+        price_min: Some(10 * TOKEN),
+        price_max: Some(500 * TOKEN),
+        ..SearchFilter::default()
+    };
+    with_remaining_accounts(
+        ix::search_products(args::SearchProducts {
+            filter,
+            sort_key,
+            sort_order: SortOrder::Descending,
+            index_account_count: index_accounts.len() as u8,
+            cursor: 0,
+            limit: 20,
+        }),
+        index_accounts
+            .iter()
+            .copied()
+            .chain(product_ids.iter().map(|&id| pda::product(id).0))
+            .map(|address| AccountMeta::new_readonly(address, false)),
+    )
+}
+
+#[tokio::test]
+async fn test_search_counts_the_candidates_of_the_driving_index() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let chandelier = shop
+        .create_product(&merchant, "Chandelier", 300 * TOKEN, &["lamp"])
+        .await
+        .unwrap();
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", 50 * TOKEN, &["lamp"])
+        .await
+        .unwrap();
+
+    let mint = shop.mint;
+    let bucket = |price: u64| {
+        let (start, end) = find_price_node_for_price(price);
+        pda::price_index(&mint, start, end).0
+    };
+
+    // total_count is known before any candidate is evaluated against its ProductBase
+    for (nodes, product_ids) in [
+        (vec![bucket(50 * TOKEN)], vec![lamp]),
+        (
+            vec![bucket(50 * TOKEN), bucket(300 * TOKEN)],
+            vec![chandelier, lamp],
+        ),
+    ] {
+        let result: SearchResult = shop
+            .view(search(&nodes, &product_ids, SortKey::Price), &[])
+            .await
+            .unwrap();
+        assert_eq!(result.total_count, product_ids.len() as u32);
+    }
+}
+
+#[tokio::test]
+async fn test_search_rejects_price_nodes_of_another_mint() {
+    let mut shop = TestShop::start().await;
+    shop.initialize().await.unwrap();
+    let merchant = shop
+        .register_merchant("Harbor Goods", DEPOSIT)
+        .await
+        .unwrap();
+    let lamp = shop
+        .create_product(&merchant, "Desk lamp", 50 * TOKEN, &["lamp"])
+        .await
+        .unwrap();
+
+    // Same bucket, priced in another token
+    let (start, end) = find_price_node_for_price(50 * TOKEN);
+    let node_address = pda::price_index(&shop.mint, start, end).0;
+    let mut node: PriceIndexNode = shop.account(&node_address).await;
+    node.payment_token = Pubkey::new_unique();
+    let mut data = Vec::new();
+    node.try_serialize(&mut data).unwrap();
+    data.resize(shop.account_size(&node_address).await, 0);
+    let foreign = Pubkey::new_unique();
+    shop.set_program_account(foreign, data);
+
+    let err = shop
+        .view::<SearchResult>(
+            search(&[node_address, foreign], &[lamp], SortKey::ProductId),
+            &[],
+        )
+        .await
+        .err()
+        .expect("search should fail");
+    assert_eq!(
+        custom_error(err),
+        Some(error_code(ErrorCode::InvalidPriceIndexAccounts))
+    );
+}